@@ -0,0 +1,84 @@
+//! Decodes the input into a sequence of signed oumuamua calls, dispatches them against the mock
+//! runtime and panics as soon as a ledger invariant is broken or a failed call left writes behind.
+
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+
+use parity_codec::Decode;
+use project_runtime::oumuamua::invariants::{assert_ledger_invariants, ACCOUNTS};
+use project_runtime::oumuamua::mock::{new_market_ext, Origin, System, Test, TestEvent};
+use project_runtime::oumuamua::{Call, RawEvent};
+use runtime_io::with_externalities;
+use support::dispatch::Dispatchable;
+
+fn track(accounts: &mut Vec<u64>, who: u64) {
+    if !accounts.contains(&who) {
+        accounts.push(who);
+    }
+}
+
+// 调用参数里的账户，包括闪电贷和提案里嵌套的调用
+fn track_call(accounts: &mut Vec<u64>, call: &Call<Test>) {
+    match call {
+        Call::init(owner, ..) => track(accounts, *owner),
+        Call::issue(_, to, _) => track(accounts, *to),
+        Call::transfer(_, to, _) => track(accounts, *to),
+        Call::approve(_, spender, _) => track(accounts, *spender),
+        Call::transfer_from(_, from, to, _) => {
+            track(accounts, *from);
+            track(accounts, *to);
+        }
+        Call::liquidate_pool_loan(_, borrower) => track(accounts, *borrower),
+        Call::transfer_loan_claim(_, to) => track(accounts, *to),
+        Call::set_admin_signatories(signatories, _) => {
+            for who in signatories {
+                track(accounts, *who);
+            }
+        }
+        Call::flash_loan(_, _, calls) => {
+            for call in calls {
+                track_call(accounts, call);
+            }
+        }
+        Call::propose(call) | Call::propose_admin_call(call) => track_call(accounts, call),
+        _ => {}
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    // 每个调用前面的一个字节选择签名账户，后面是 SCALE 编码的 Call
+    let mut input = data;
+    let mut calls = Vec::new();
+    while let Some((&who, rest)) = input.split_first() {
+        input = rest;
+        match Call::<Test>::decode(&mut input) {
+            Some(call) => calls.push((ACCOUNTS[who as usize % ACCOUNTS.len()], call)),
+            None => break,
+        }
+    }
+
+    with_externalities(&mut new_market_ext(), || {
+        // 调用参数里可以出现任意账户，出现过的和收到过代币的账户都要参与余额检查
+        let mut accounts = ACCOUNTS.to_vec();
+
+        for (who, call) in calls {
+            track_call(&mut accounts, &call);
+            let root = runtime_io::storage_root();
+            if let Err(e) = call.dispatch(Origin::signed(who)) {
+                assert_eq!(runtime_io::storage_root(), root, "failed call left writes behind: {}", e);
+            }
+
+            // 转账之外，铸币和从资金池付款（_mint/_give）不发 Transfer 事件
+            for record in System::events() {
+                match record.event {
+                    TestEvent::oumuamua(RawEvent::Transfer(_, _, to, _))
+                    | TestEvent::oumuamua(RawEvent::NewToken(_, to, _))
+                    | TestEvent::oumuamua(RawEvent::Issue(_, to, _)) => track(&mut accounts, to),
+                    _ => {}
+                }
+            }
+            assert_ledger_invariants(&accounts);
+        }
+    });
+});
@@ -0,0 +1,32 @@
+//! Replays a lending scenario against the oumuamua module and prints the report as JSON.
+//!
+//!     cargo run -- scenarios/btc-crash.json
+//!
+//! See `project_runtime::oumuamua::simulation` for the scenario format and the settlement rules.
+
+use std::{env, fs, process};
+
+use project_runtime::oumuamua::simulation::{run, Scenario};
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: oumuamua-sim <SCENARIO.json>");
+            process::exit(2);
+        },
+    };
+    let result = fs::read_to_string(&path)
+        .map_err(|e| format!("Error reading {}: {}", path, e))
+        .and_then(|json| serde_json::from_str::<Scenario>(&json).map_err(|e| format!("Error parsing {}: {}", path, e)))
+        .and_then(|scenario| run(&scenario))
+        .and_then(|report| serde_json::to_string_pretty(&report).map_err(|e| e.to_string()));
+
+    match result {
+        Ok(report) => println!("{}", report),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        },
+    }
+}
@@ -6,7 +6,7 @@
 
 use client::{
     block_builder::api::{self as block_builder_api, CheckInherentsResult, InherentData},
-    impl_runtime_apis, runtime_api,
+    decl_runtime_apis, impl_runtime_apis, runtime_api,
 };
 use parity_codec::{Decode, Encode};
 #[cfg(feature = "std")]
@@ -17,7 +17,7 @@ use runtime_primitives::{
     create_runtime_str, generic,
     traits::{self, BlakeTwo256, Block as BlockT, NumberFor, StaticLookup, Verify},
     transaction_validity::TransactionValidity,
-    ApplyResult,
+    ApplyError, ApplyResult,
 };
 #[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
@@ -57,7 +57,7 @@ pub type BlockNumber = u64;
 pub type Nonce = u64;
 
 /// Used for the module oumuamua in `./oumuamua.rs`
-mod oumuamua;
+pub mod oumuamua;
 
 
 /// Opaque types. These are used by the CLI to instantiate machinery that don't need to know
@@ -101,8 +101,8 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
     spec_name: create_runtime_str!("project"),
     impl_name: create_runtime_str!("project"),
     authoring_version: 3,
-    spec_version: 4,
-    impl_version: 4,
+    spec_version: 23,
+    impl_version: 23,
     apis: RUNTIME_API_VERSIONS,
 };
 
@@ -198,7 +198,8 @@ impl oumuamua::Trait for Runtime {
     type Event = Event;
     type AssetId = u32;
     type TokenBalance = u64;
-
+    type Currency = Balances;
+    type TransactionPayment = ();
 }
 
 
@@ -238,6 +239,21 @@ pub type CheckedExtrinsic = generic::CheckedExtrinsic<AccountId, Nonce, Call>;
 /// Executive: handles dispatch to the various modules.
 pub type Executive = executive::Executive<Runtime, Block, Context, Balances, AllModules>;
 
+/// The lending history entry of this runtime, as returned by `OumuamuaApi`.
+pub type HistoryEntry = oumuamua::HistoryEntry<u64, AccountId, u32, Hash, BlockNumber>;
+
+decl_runtime_apis! {
+    /// Read-only queries of the oumuamua module for RPC clients, indexers and wallets. Without a
+    /// dedicated RPC they are reached through `state_call`, e.g. `OumuamuaApi_history_page`.
+    pub trait OumuamuaApi {
+        /// Up to `limit` history entries of `who` from sequence number `from`, skipping pruned ones.
+        /// The next page starts at the sequence number after the last entry returned.
+        fn history_page(who: AccountId, from: u64, limit: u64) -> Vec<HistoryEntry>;
+        /// The sequence numbers of the oldest kept and the next history entry of `who`.
+        fn history_range(who: AccountId) -> (u64, u64);
+    }
+}
+
 // Implement our runtime API endpoints. This is just a bunch of proxying.
 impl_runtime_apis! {
     impl runtime_api::Core<Block> for Runtime {
@@ -288,6 +304,16 @@ impl_runtime_apis! {
 
     impl runtime_api::TaggedTransactionQueue<Block> for Runtime {
         fn validate_transaction(tx: <Block as BlockT>::Extrinsic) -> TransactionValidity {
+            // Executive 只检查 balances 按字节收取的手续费，oumuamua 的调用还要付按权重计算的手续费
+            if let (Some((address, ..)), Call::OumuamuaModule(call)) = (&tx.signature, &tx.function) {
+                if let Ok(who) = Indices::lookup(address.clone()) {
+                    let payment = Balances::transaction_base_fee()
+                        .saturating_add(Balances::transaction_byte_fee().saturating_mul(tx.encode().len() as u128));
+                    if OumuamuaModule::can_pay(&who, call, payment).is_err() {
+                        return TransactionValidity::Invalid(ApplyError::CantPay as i8);
+                    }
+                }
+            }
             Executive::validate_transaction(tx)
         }
     }
@@ -309,4 +335,14 @@ impl_runtime_apis! {
             Consensus::authorities()
         }
     }
+
+    impl self::OumuamuaApi<Block> for Runtime {
+        fn history_page(who: AccountId, from: u64, limit: u64) -> Vec<HistoryEntry> {
+            OumuamuaModule::history_page(&who, from, limit)
+        }
+
+        fn history_range(who: AccountId) -> (u64, u64) {
+            (OumuamuaModule::history_start(&who), OumuamuaModule::history_count(&who))
+        }
+    }
 }
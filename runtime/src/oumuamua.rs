@@ -1,47 +1,429 @@
 use parity_codec::{Codec, Decode, Encode};
+use rstd::borrow::Borrow;
 use rstd::cmp;
 use rstd::prelude::*;
+use runtime_io::blake2_256;
 use runtime_primitives::traits::One;
 use runtime_primitives::traits::{
-    As, CheckedAdd, CheckedSub, Hash, Member, SimpleArithmetic, Zero,
+    As, CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, Hash, Member, Saturating, SimpleArithmetic, Zero,
 };
 use support::{
     decl_event, decl_module, decl_storage,
-    dispatch::Result,
+    dispatch::{Dispatchable, Result},
     ensure,
-    traits::{Currency, ReservableCurrency},
+    traits::{Currency, ExistenceRequirement, OnUnbalanced, ReservableCurrency, WithdrawReason},
     Parameter, StorageMap, StorageValue,
 };
 use system::{self, ensure_signed};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+pub mod interest;
+mod journal;
+mod migration;
+mod weights;
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod invariants;
+#[cfg(any(test, feature = "fuzzing", feature = "simulation"))]
+pub mod mock;
+#[cfg(any(test, feature = "simulation"))]
+pub mod simulation;
+#[cfg(test)]
+mod tests;
 
 const yiwan: u64 = 10000;
 
+// 当前的存储版本，每次修改已有存储项的编码格式时加一，并在 migration.rs 中增加对应的迁移。
+pub const CURRENT_STORAGE_VERSION: u32 = 1;
+
+// 每次追加历史记录时最多清理的旧记录条数，避免调小 HistoryCap 后单个区块删除过多数据。
+const MAX_HISTORY_PRUNE: u64 = 8;
+
+#[derive(Encode, Decode, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub enum OrderStatus {
+    Open,
+    Cancelled,
+    Taken,
+    // 从旧版本迁移过来的已结束订单，无法区分是被取消还是被接单
+    Closed,
+    // 抵押不足被清算
+    Liquidated,
+    // 借款人已连本带息还清
+    Repaid,
+    // 到期未还，出借人拿走了抵押物
+    Defaulted,
+}
+
+impl Default for OrderStatus {
+    fn default() -> Self {
+        OrderStatus::Open
+    }
+}
+
 #[derive(Encode, Decode, Default, Clone, PartialEq)]
-#[cfg_attr(feature = "std", derive(Debug))]
-pub struct BorrowOrder<TokenBalance, AccountId, AssetId, Hash> {
-    id: Hash,
-    owner: AccountId,
-    btotal: TokenBalance,  // 借款总额
-    btoken_id: AssetId,    // 借款币种
-    already: TokenBalance, // 已经借到
-    duration: u64,         // 借款时长
-    stotal: TokenBalance,  // 抵押总额，为 0 时，表示它是 已完成/已取消 状态。
-    stoken_id: AssetId,    // 抵押币种
-    interest: u32,         // 年利率，万分之 x
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub struct BorrowOrder<TokenBalance, AccountId, AssetId, Hash, BlockNumber> {
+    pub id: Hash,
+    pub owner: AccountId,
+    pub btotal: TokenBalance,  // 借款总额
+    pub btoken_id: AssetId,    // 借款币种
+    pub already: TokenBalance, // 已经借到
+    pub duration: u64,         // 借款时长
+    pub stotal: TokenBalance,  // 抵押总额
+    pub stoken_id: AssetId,    // 抵押币种
+    pub interest: u32,         // 年利率，万分之 x
+    pub status: OrderStatus,
+    pub taker: Option<AccountId>, // 出借人
+    pub created_at: BlockNumber,
+    pub updated_at: BlockNumber,
+}
+
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub struct SupplyOrder<TokenBalance, AccountId, AssetId, Hash, BlockNumber> {
+    pub id: Hash,
+    pub owner: AccountId,
+    pub total: TokenBalance,  // 提供资金
+    pub stoken: AssetId,      // 提供的资金种类（默认是 USDT）
+    pub tokens: Vec<AssetId>, // 接受抵押的资金种类
+    pub amortgage: u32,       // 接受抵押率，万分之 x 借款方能拿到的钱/借款方抵押的钱
+    pub duration: u64,        // 这部分资金的 free time
+    pub interest: u32,        // 接受最小的年利率，万分之 x
+    pub status: OrderStatus,
+    pub taker: Option<AccountId>,  // 借款人
+    pub btoken: AssetId,           // 借款人实际使用的抵押币种
+    pub btotal: TokenBalance,      // 借款人实际抵押的数量
+    pub created_at: BlockNumber,
+    pub updated_at: BlockNumber,
+}
+
+// 某个币种的风险参数，比例都是万分之 x
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct RiskParams<TokenBalance> {
+    pub max_ltv: u32,               // 作为抵押物时，借款价值最多为抵押物价值的万分之 x，为 0 时不能作为抵押物
+    pub liquidation_threshold: u32, // 借款价值超过抵押物价值的万分之 x 时可以被清算
+    pub liquidation_bonus: u32,     // 清算人额外获得的抵押物，为借款价值的万分之 x
+    pub borrow_cap: TokenBalance,   // 该币种被借出的总量上限，0 表示不限制
+    pub supply_cap: TokenBalance,   // 该币种挂单提供和已借出的总量上限，0 表示不限制
+}
+
+// 没有设置风险参数的币种沿用原来的规则：抵押物价值不低于借款价值即可，不限总量
+impl<TokenBalance: Zero> Default for RiskParams<TokenBalance> {
+    fn default() -> Self {
+        RiskParams {
+            max_ltv: 10000,
+            liquidation_threshold: 10000,
+            liquidation_bonus: 0,
+            borrow_cap: Zero::zero(),
+            supply_cap: Zero::zero(),
+        }
+    }
+}
+
+// 已被接单的借贷，不论来自借款订单还是提供资金的订单
+struct Loan<T: Trait> {
+    id: T::Hash,
+    is_borrow_order: bool,
+    lender: T::AccountId,
+    borrower: T::AccountId,
+    asset: T::AssetId,
+    principal: T::TokenBalance,
+    debt: T::TokenBalance, // 本金加上到当前区块为止的利息
+    collateral_asset: T::AssetId,
+    collateral: T::TokenBalance,
+    interest: u32,
+    duration: u64,
+    taken_at: T::BlockNumber,
+}
+
+// 借款人请求的展期：到期区块延后 blocks 个区块，此后按新的年利率 interest 计息
+#[derive(Encode, Decode, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub struct Extension {
+    pub blocks: u64,
+    pub interest: u32,
+}
+
+// 订单的还款条款。挂单时由订单所有者设置提前还款费用，双方分别选择是否在到期时自动续期。
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct LoanTerms<BlockNumber> {
+    pub prepayment_fee: u32,              // 到期前还款时额外支付本金的万分之 x，归出借人
+    pub maturity: Option<BlockNumber>,    // 展期或续期后的到期区块，为 None 时是 接单区块 + duration
+    pub borrower_rollover: bool,          // 借款人同意到期续期
+    pub lender_rollover: bool,            // 出借人同意到期续期
+    pub extension: Option<Extension>,     // 等待出借人接受的展期请求
+}
+
+// 一笔借贷的累计利率指数：债务 = 本金 × index / INDEX_ONE。index 从 accrued_at 开始按 rate_per_block 每个区块复利增长，
+// 任何区块的债务都可以直接算出来，不需要逐块累计。
+#[derive(Encode, Decode, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct LoanIndex<BlockNumber> {
+    pub index: u128,
+    pub rate_per_block: u128,
+    pub accrued_at: BlockNumber,
+}
+
+impl<BlockNumber: SimpleArithmetic + Copy> LoanIndex<BlockNumber> {
+    /// A new index at `INDEX_ONE` for a loan taken at block `now` at `annual` interest, in 1/10000.
+    pub fn new(annual: u32, blocks_per_year: u64, now: BlockNumber) -> Self {
+        LoanIndex {
+            index: interest::INDEX_ONE,
+            rate_per_block: interest::rate_per_block(annual, blocks_per_year),
+            accrued_at: now,
+        }
+    }
+
+    /// The index at block `now`.
+    pub fn at(&self, now: BlockNumber) -> u128 {
+        let blocks = if now > self.accrued_at { (now - self.accrued_at).as_() } else { 0 };
+        interest::accrue(self.index, self.rate_per_block, blocks)
+    }
+}
+
+// 资金池的利率模型，利率都是年化万分之 x。利用率不超过 kink 时借款利率从 base_rate 线性增加 slope1，
+// 超过 kink 之后再线性增加 slope2，利用率 100% 时为 base_rate + slope1 + slope2。
+#[derive(Encode, Decode, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct RateModel {
+    pub base_rate: u32,
+    pub slope1: u32,
+    pub kink: u32, // 最优利用率，万分之 x，不能为 0
+    pub slope2: u32,
+}
 
+impl RateModel {
+    /// Annual borrow rate in 1/10000 at `utilization`, also in 1/10000.
+    pub fn borrow_rate(&self, utilization: u32) -> u32 {
+        let utilization = u64::from(cmp::min(utilization, yiwan as u32));
+        let kink = cmp::max(cmp::min(u64::from(self.kink), yiwan), 1);
+        let rate = if utilization <= kink {
+            u64::from(self.base_rate) + u64::from(self.slope1) * utilization / kink
+        } else {
+            let excess = (utilization - kink) * u64::from(self.slope2) / cmp::max(yiwan - kink, 1);
+            u64::from(self.base_rate) + u64::from(self.slope1) + excess
+        };
+        cmp::min(rate, u64::from(u32::max_value())) as u32
+    }
+}
+
+// 某个币种的资金池。存入的资金不属于任何账户，记在 cash 中；存款人得到份额币种 share_token。
+// 借款都按 borrow_index 折算后记账，index 按当前利用率下的借款利率逐块复利增长，借款人的债务随之增长，
+// 利息最终归入 cash，份额的价值随之上升。利息中 InsuranceFeeShare 的部分计入 reserves，不属于存款人，
+// 池中有资金时转入保险基金。
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct Pool<AssetId, TokenBalance, BlockNumber> {
+    pub share_token: AssetId,
+    pub cash: TokenBalance,            // 池中尚未借出的资金
+    pub scaled_borrowed: TokenBalance, // 所有借款折算到 index 为 INDEX_ONE 时的数量
+    pub rate_model: RateModel,
+    pub borrow_index: u128,            // 累计借款利率指数，从 INDEX_ONE 开始
+    pub accrued_at: BlockNumber,       // borrow_index 最后一次累计的区块
+    #[cfg_attr(feature = "std", serde(default))]
+    pub reserves: TokenBalance,        // 归保险基金、尚未从 cash 转出的利息
+}
+
+impl<AssetId, TokenBalance, BlockNumber> Pool<AssetId, TokenBalance, BlockNumber>
+where
+    TokenBalance: SimpleArithmetic + Copy,
+    BlockNumber: SimpleArithmetic + Copy,
+{
+    /// Debt of a loan of `scaled` at the current borrow index, rounded up.
+    pub fn debt(&self, scaled: TokenBalance) -> TokenBalance {
+        TokenBalance::sa(interest::grow(scaled.as_(), self.borrow_index))
+    }
+
+    /// `amount` scaled to the index `INDEX_ONE`, rounded down.
+    pub fn scaled(&self, amount: TokenBalance) -> TokenBalance {
+        TokenBalance::sa(interest::shrink(amount.as_(), self.borrow_index))
+    }
+
+    /// `amount` scaled to the index `INDEX_ONE`, rounded up.
+    pub fn scaled_up(&self, amount: TokenBalance) -> TokenBalance {
+        TokenBalance::sa(interest::shrink_up(amount.as_(), self.borrow_index))
+    }
+
+    /// Outstanding debt of all loans from the pool, interest included.
+    pub fn borrowed(&self) -> TokenBalance {
+        self.debt(self.scaled_borrowed)
+    }
+
+    /// Borrowed part of the pool, in 1/10000.
+    pub fn utilization(&self) -> u32 {
+        let borrowed = u128::from(self.borrowed().as_());
+        let total = borrowed + u128::from(self.cash.as_());
+        if total == 0 {
+            return 0;
+        }
+        (borrowed * u128::from(yiwan) / total) as u32
+    }
+
+    /// What the pool is worth to its depositors: cash and loans, less the reserves.
+    pub fn value(&self) -> TokenBalance {
+        let value = self.cash.as_().saturating_add(self.borrowed().as_());
+        TokenBalance::sa(value.saturating_sub(self.reserves.as_()))
+    }
+
+    /// Accrues the borrow index up to block `now` at the borrow rate of the current utilization,
+    /// setting `reserve_share`, in 1/10000, of the interest aside as reserves.
+    pub fn accrue(&mut self, now: BlockNumber, blocks_per_year: u64, reserve_share: u32) {
+        if now <= self.accrued_at {
+            return;
+        }
+        let before = self.borrowed().as_();
+        let rate = interest::rate_per_block(self.rate_model.borrow_rate(self.utilization()), blocks_per_year);
+        self.borrow_index = interest::accrue(self.borrow_index, rate, (now - self.accrued_at).as_());
+        self.accrued_at = now;
+        let interest = self.borrowed().as_().saturating_sub(before);
+        let reserved = (u128::from(interest) * u128::from(reserve_share) / u128::from(yiwan)) as u64;
+        self.reserves = TokenBalance::sa(self.reserves.as_().saturating_add(reserved));
+    }
 }
 
+// 某个账户从资金池的借款，抵押物冻结在借款人账户中。债务 = scaled_debt × borrow_index / INDEX_ONE。
 #[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct PoolLoan<AssetId, TokenBalance> {
+    pub scaled_debt: TokenBalance,
+    pub collateral_asset: AssetId,
+    pub collateral: TokenBalance,
+}
+
+// 某个币种的坏账统计：抵押物不够还债时保险基金补上的和没能补上的差额，只增不减
+#[derive(Encode, Decode, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub struct BadDebt<TokenBalance> {
+    pub covered: TokenBalance,
+    pub uncovered: TokenBalance,
+}
+
+// 治理参数。提案按提出时的参数投票和执行：投票期结束后，投票数达到治理币种总发行量的 quorum（万分之 x）
+// 并且赞成票超过投票数的 threshold（万分之 x）即为通过，再经过 enactment_delay 个区块后可以执行。
+#[derive(Encode, Decode, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct GovernanceParams<BlockNumber> {
+    pub voting_period: BlockNumber,
+    pub enactment_delay: BlockNumber,
+    pub quorum: u32,
+    pub threshold: u32,
+}
+
+#[derive(Encode, Decode, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum ProposalStatus {
+    Voting,
+    // 已通过并执行，执行结果见 ProposalEnacted 事件
+    Enacted,
+    Rejected,
+}
+
+// 治理提案，通过后 call 以 Root 身份执行
+#[derive(Encode, Decode, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct GovernanceProposal<AccountId, Call, AssetId, BlockNumber, TokenBalance> {
+    pub proposer: AccountId,
+    pub call: Call,
+    pub asset: AssetId, // 提案时的治理币种，投票锁定的就是这个币种
+    pub params: GovernanceParams<BlockNumber>,
+    pub created_at: BlockNumber,
+    pub ayes: TokenBalance,
+    pub nays: TokenBalance,
+    pub status: ProposalStatus,
+}
+
+// 账户在一个提案上的投票，投票的数量一直冻结到投票期结束后解锁
+#[derive(Encode, Decode, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct GovernanceVote<TokenBalance> {
+    pub aye: bool,
+    pub balance: TokenBalance,
+}
+
+#[derive(Encode, Decode, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum AdminProposalStatus {
+    Pending,
+    // 已达到门槛并执行，执行结果见 AdminCallExecuted 事件
+    Executed,
+    // 被提案人取消，或者在更换签名人时作废
+    Cancelled,
+}
+
+// 特权调用的来源。治理提案和多签调用都以 Root 身份执行，执行期间 DispatchingAs 记下来源，和 sudo 等其他 Root 调用区分开
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum AdminOrigin {
+    // 不是由本模块发起的 Root 调用，例如 sudo
+    Root,
+    Governance,
+    AdminMultisig,
+    // 过渡期内的 Admin 账户
+    LegacyAdmin,
+}
+
+// 多签管理员的提案，当前签名人中批准的人数达到门槛后 call 以 Root 身份执行
+#[derive(Encode, Decode, Clone, PartialEq)]
 #[cfg_attr(feature = "std", derive(Debug))]
-pub struct SupplyOrder<TokenBalance, AccountId, AssetId, Hash> {
-    id: Hash,
-    owner: AccountId,
-    total: TokenBalance,  // 提供资金，为 0 时表示 它是 已完成/已取消 状态。
-    stoken: AssetId,      // 提供的资金种类（默认是 USDT）
-    tokens: Vec<AssetId>, // 接受抵押的资金种类
-    amortgage: u32,       // 接受抵押率，万分之 x 借款方能拿到的钱/借款方抵押的钱
-    duration: u64,        // 这部分资金的 free time
-    interest: u32,        // 接受最小的年利率，万分之 x
+pub struct AdminProposal<AccountId, Call, BlockNumber> {
+    pub proposer: AccountId,
+    pub call: Call,
+    pub approvals: Vec<AccountId>,
+    pub created_at: BlockNumber,
+    pub status: AdminProposalStatus,
+}
+
+// 债权持有人挂出的卖单，按 price_asset 计价。持有人变化后卖单作废。
+#[derive(Encode, Decode, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct ClaimListing<AssetId, TokenBalance> {
+    pub price_asset: AssetId,
+    pub price: TokenBalance,
+}
+
+#[derive(Encode, Decode, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub enum HistoryKind {
+    OrderCreated,
+    OrderCancelled,
+    OrderFilled,
+    Repaid,
+    Liquidated,
+    InterestPaid,
+    InterestEarned,
+    // 资金池的记录没有订单，order_id 为零哈希
+    PoolBorrowed,
+    PoolRepaid,
+    PoolLiquidated,
+    ClaimSold,
+    ClaimBought,
+    Defaulted,
+    FlashLoan,
+}
+
+/// One entry of an account's lending history, stored in `HistoryOf` under the account and its
+/// sequence number. Off-chain callers page through it with the `OumuamuaApi` runtime API.
+#[derive(Encode, Decode, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct HistoryEntry<TokenBalance, AccountId, AssetId, Hash, BlockNumber> {
+    pub kind: HistoryKind,
+    pub order_id: Hash,
+    pub counterparty: Option<AccountId>, // 对手方，创建/取消订单时为 None
+    pub block: BlockNumber,
+    pub asset_id: AssetId,
+    pub amount: TokenBalance,
 }
 
 /*
@@ -58,9 +440,10 @@ pub struct IngOrder<Hash> {
 
 #[derive(Encode, Decode, Default, Clone, PartialEq, Debug)]
 pub struct Erc20Token<U> {
-    name: Vec<u8>,
-    ticker: Vec<u8>,
-    total_supply: U,
+    pub name: Vec<u8>,
+    pub ticker: Vec<u8>,
+    pub decimals: u8,
+    pub total_supply: U,
 }
 
 pub trait Trait: system::Trait {
@@ -75,8 +458,16 @@ pub trait Trait: system::Trait {
         + As<usize>
         + As<u64>
         + From<u64>;
+    /// The currency oumuamua call fees are paid in.
+    type Currency: Currency<Self::AccountId>;
+    /// Handler for the fees of oumuamua calls, like `TransactionPayment` of the balances module.
+    type TransactionPayment: OnUnbalanced<NegativeImbalanceOf<Self>>;
 }
 
+type FeeBalanceOf<T> = <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+type NegativeImbalanceOf<T> =
+    <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::NegativeImbalance;
+
 decl_event!(
     pub enum Event<T>
     where
@@ -84,22 +475,120 @@ decl_event!(
         <T as system::Trait>::Hash,
         <T as self::Trait>::TokenBalance,
         <T as self::Trait>::AssetId,
+        <T as system::Trait>::BlockNumber,
     {
-        CreateBorrow(AccountId, TokenBalance,  u64, TokenBalance, u32),
-        CancelBorrow(AccountId, Hash),
-        TakeBorrow(AccountId),
-        CreateSupply(AccountId),
-        CancelSupply(AccountId, Hash),
-        TakeSupply(AccountId),
-
-        FinishBorrow(AccountId),
-        FinishSupply(AccountId),
+        // 借款人, 订单 id, 借款币种, 借款总额, 抵押币种, 抵押总额, 借款时长, 年利率
+        CreateBorrow(AccountId, Hash, AssetId, TokenBalance, AssetId, TokenBalance, u64, u32),
+        // 借款人, 订单 id, 解冻的抵押币种, 抵押总额
+        CancelBorrow(AccountId, Hash, AssetId, TokenBalance),
+        // 订单 id, 出借人, 借款人, 借款币种, 借款总额
+        TakeBorrow(Hash, AccountId, AccountId, AssetId, TokenBalance),
+        // 出借人, 订单 id, 提供币种, 提供总额, 接受的抵押币种, 抵押率, 时长, 年利率
+        CreateSupply(AccountId, Hash, AssetId, TokenBalance, Vec<AssetId>, u32, u64, u32),
+        // 出借人, 订单 id, 解冻的提供币种, 提供总额
+        CancelSupply(AccountId, Hash, AssetId, TokenBalance),
+        // 订单 id, 出借人, 借款人, 提供币种, 提供总额, 抵押币种, 抵押总额
+        TakeSupply(Hash, AccountId, AccountId, AssetId, TokenBalance, AssetId, TokenBalance),
+
+        // 借贷结束（还清、清算或违约）时借款人和债权持有人各一条: 账户, 订单 id
+        FinishBorrow(AccountId, Hash),
+        FinishSupply(AccountId, Hash),
 
         Transfer(AssetId, AccountId, AccountId, TokenBalance),
         Approval(AssetId, AccountId, AccountId, TokenBalance),
+        NewToken(AssetId, AccountId, TokenBalance),
 		Issue(AssetId, AccountId, TokenBalance),
 		Destroyed(AssetId, AccountId, TokenBalance),
 
+        PriceSet(AssetId, u64),
+        // 币种, max_ltv, liquidation_threshold, liquidation_bonus, borrow_cap, supply_cap
+        RiskParamsSet(AssetId, u32, u32, u32, TokenBalance, TokenBalance),
+        // 订单 id, 清算人, 借款人, 抵押币种, 清算人获得的抵押物数量
+        Liquidated(Hash, AccountId, AccountId, AssetId, TokenBalance),
+        // 订单 id, 借款人, 出借人, 借款币种, 归还的本金加利息和提前还款费用
+        Repaid(Hash, AccountId, AccountId, AssetId, TokenBalance),
+        // 订单 id, 提前还款费用
+        PrepaymentFeeSet(Hash, u32),
+        // 订单 id, 延长的区块数, 新的年利率
+        ExtensionRequested(Hash, u64, u32),
+        // 订单 id, 延长的区块数, 新的年利率, 新的到期区块
+        ExtensionAccepted(Hash, u64, u32, BlockNumber),
+        // 订单 id, 账户, 是否同意续期
+        RolloverSet(Hash, AccountId, bool),
+        // 订单 id, 新的到期区块
+        RolledOver(Hash, BlockNumber),
+        // 订单 id, 抵押币种, 追加的数量, 追加后的抵押总额
+        CollateralAdded(Hash, AssetId, TokenBalance, TokenBalance),
+        // 订单 id, 抵押币种, 取回的数量, 取回后的抵押总额
+        CollateralWithdrawn(Hash, AssetId, TokenBalance, TokenBalance),
+        // 订单 id, 原抵押币种, 原抵押总额, 新抵押币种, 新抵押总额
+        CollateralSubstituted(Hash, AssetId, TokenBalance, AssetId, TokenBalance),
+        // 订单 id, 原债权持有人, 新债权持有人
+        LoanClaimTransferred(Hash, AccountId, AccountId),
+        // 订单 id, 债权持有人, 计价币种, 价格
+        LoanClaimListed(Hash, AccountId, AssetId, TokenBalance),
+        // 订单 id
+        LoanClaimUnlisted(Hash),
+        // 订单 id, 卖方, 买方, 计价币种, 价格
+        LoanClaimSold(Hash, AccountId, AccountId, AssetId, TokenBalance),
+        // 订单 id, 出借人, 借款人, 抵押币种, 出借人拿走的抵押物
+        Defaulted(Hash, AccountId, AccountId, AssetId, TokenBalance),
+        // 保险基金从利息中抽取的比例
+        InsuranceFeeShareSet(u32),
+        // 币种, 存入的账户, 数量
+        InsuranceDeposited(AssetId, AccountId, TokenBalance),
+        // 订单 id, 币种, 从利息中抽取的数量
+        InsuranceFeeCollected(Hash, AssetId, TokenBalance),
+        // 币种, 承担亏空的账户, 保险基金补偿的数量, 没有补偿的坏账
+        ShortfallCovered(AssetId, AccountId, TokenBalance, TokenBalance),
+        // 治理币种, 投票期, 执行延迟, quorum, threshold
+        GovernanceSet(Option<AssetId>, BlockNumber, BlockNumber, u32, u32),
+        // 旧 Admin 账户可以直接调用特权调用的最后期限
+        LegacyAdminUntilSet(Option<BlockNumber>),
+        // 提案序号, 提案人, 投票期结束的区块
+        Proposed(u32, AccountId, BlockNumber),
+        // 提案序号, 投票人, 是否赞成, 冻结的数量
+        Voted(u32, AccountId, bool, TokenBalance),
+        // 提案序号, 投票人, 解冻的数量
+        VoteUnlocked(u32, AccountId, TokenBalance),
+        // 提案押金
+        ProposalDepositSet(TokenBalance),
+        // 提案序号, 执行是否成功
+        ProposalEnacted(u32, bool),
+        ProposalRejected(u32),
+        // 新的签名人, 门槛
+        AdminSignatoriesSet(Vec<AccountId>, u32),
+        // 多签提案序号, 提案人
+        AdminCallProposed(u32, AccountId),
+        // 多签提案序号, 批准人, 当前签名人中已批准的人数
+        AdminCallApproved(u32, AccountId, u32),
+        // 多签提案序号, 执行是否成功
+        AdminCallExecuted(u32, bool),
+        AdminCallCancelled(u32),
+        // 闪电贷的费率
+        FlashLoanFeeSet(u32),
+        // 币种, 借款人, 借款数量, 归入资金池的费用
+        FlashLoan(AssetId, AccountId, TokenBalance, TokenBalance),
+        AllowAssetSet(AssetId, bool),
+        HistoryCapSet(u64),
+        // 提交者, 账户数, 授权数
+        V0AccountsMigrated(AccountId, u32, u32),
+
+        // 币种, 份额币种
+        PoolCreated(AssetId, AssetId),
+        // 币种, base_rate, slope1, kink, slope2
+        RateModelSet(AssetId, u32, u32, u32, u32),
+        // 币种, 存款人, 存入数量, 得到的份额
+        Deposited(AssetId, AccountId, TokenBalance, TokenBalance),
+        // 币种, 存款人, 取出数量, 销毁的份额
+        Withdrawn(AssetId, AccountId, TokenBalance, TokenBalance),
+        // 币种, 借款人, 借款数量, 抵押币种, 追加的抵押物数量
+        PoolBorrowed(AssetId, AccountId, TokenBalance, AssetId, TokenBalance),
+        // 币种, 借款人, 归还数量
+        PoolRepaid(AssetId, AccountId, TokenBalance),
+        // 币种, 清算人, 借款人, 抵押币种, 清算人获得的抵押物数量
+        PoolLiquidated(AssetId, AccountId, AccountId, AssetId, TokenBalance),
+
         Reserve(AssetId, AccountId, TokenBalance),
         UnReserve(AssetId, AccountId, TokenBalance),
 
@@ -107,8 +596,13 @@ decl_event!(
 );
 
 decl_storage! {
-    trait Store for Module<T: Trait> as KittyStorage {
-        BorrowOrderDetail get(borrow_order_detail): map T::Hash => BorrowOrder<T::TokenBalance, T::AccountId, T::AssetId, T::Hash>;
+    trait Store for Module<T: Trait> as Oumuamua {
+        // 存储版本，新链在创世时直接写入当前版本；旧链升级后在 on_initialize 中迁移。
+        StorageVersion get(storage_version) build(|_: &GenesisConfig<T>| CURRENT_STORAGE_VERSION): u32;
+        // 正在进行的迁移处理到的位置，见 migration.rs
+        MigrationCursor get(migration_cursor): u64;
+
+        BorrowOrderDetail get(borrow_order_detail): map T::Hash => BorrowOrder<T::TokenBalance, T::AccountId, T::AssetId, T::Hash, T::BlockNumber>;
         BorrowOrderOwner get(owner_of_borrow): map T::Hash => Option<T::AccountId>;
 
         AllBorrowOrder get(borrow_by_index): map u64 => T::Hash;
@@ -119,7 +613,7 @@ decl_storage! {
         OwnedBorrowCount get(owned_borrow_count): map T::AccountId => u64;
         OwnedBorrowIndex: map T::Hash => u64;
 
-        SupplyOrderDetail get(supply_order_detail): map T::Hash => SupplyOrder<T::TokenBalance, T::AccountId, T::AssetId, T::Hash>;
+        SupplyOrderDetail get(supply_order_detail): map T::Hash => SupplyOrder<T::TokenBalance, T::AccountId, T::AssetId, T::Hash, T::BlockNumber>;
         SupplyOrderOwner get(owner_of_supply): map T::Hash => Option<T::AccountId>;
 
         AllSupplyOrder get(supply_by_index): map u64 => T::Hash;
@@ -137,17 +631,294 @@ decl_storage! {
 
         TokenId get(token_id) config(): T::AssetId;
         Tokens get(token_details): map T::AssetId => Erc20Token<T::TokenBalance>;
-        BalanceOf get(balance_of): map (T::AssetId, T::AccountId) => T::TokenBalance;
-        FreeBalanceOf get(free_balance_of): map (T::AssetId, T::AccountId) => T::TokenBalance;
-        ReserveBalanceOf get(reserve_balance_of): map(T::AssetId, T::AccountId) => T::TokenBalance;
+        // 余额和授权没有迁移的还留在版本 0 的前缀下，通过 balance_of 等函数读取，见 migration.rs
+        BalanceOf: map (T::AssetId, T::AccountId) => T::TokenBalance;
+        FreeBalanceOf: map (T::AssetId, T::AccountId) => T::TokenBalance;
+        ReserveBalanceOf: map(T::AssetId, T::AccountId) => T::TokenBalance;
 
-        Allowance get(allowance): map (T::AssetId, T::AccountId, T::AccountId) => T::TokenBalance;
+        Allowance: map (T::AssetId, T::AccountId, T::AccountId) => T::TokenBalance;
+        // 授权按第一次写入的顺序编号，导出状态时据此列出所有授权；AllowancesIndexedFrom 之前写入的授权没有编号
+        AllowanceCount get(allowance_count): u64;
+        AllowanceByIndex get(allowance_by_index): map u64 => (T::AssetId, T::AccountId, T::AccountId);
+        AllowancesIndexedFrom get(allowances_indexed_from): T::BlockNumber;
 
         Admin get(admin) config(): T::AccountId;
 
 // 本来这里应该是通过一个 Oracle 来获取价格。为了简便，直接用数据保存了。初始化时，设置 token_id 为 1 的是 USDT，
 // TokenPrice 表示 每单元该币种 能兑换 TokenPrice/10000 的 USDT。
         TokenPrice get(token_price): map T::AssetId => u64;
+
+        // 每个币种的风险参数，未设置时见 RiskParams::default
+        AssetRiskParams get(risk_params): map T::AssetId => RiskParams<T::TokenBalance>;
+        // 每个币种在订单中已被借出、尚未结清的本金总量，加上资金池的借款用于 borrow_cap
+        TotalBorrowed get(total_borrowed): map T::AssetId => T::TokenBalance;
+        // 每个币种在订单中挂单提供和已借出的总量，加上资金池的资金用于 supply_cap
+        TotalSupplied get(total_supplied): map T::AssetId => T::TokenBalance;
+
+        // 一年的区块数，用于把年利率换算成每个区块的利率，为 0 时不计息
+        BlocksPerYear get(blocks_per_year) config(): u64;
+        // 已被接单的订单 => 累计利率指数，结清后删除
+        LoanIndexes get(loan_index): map T::Hash => Option<LoanIndex<T::BlockNumber>>;
+        // 未结束订单的还款条款，订单结束或取消后删除
+        OrderTerms get(loan_terms): map T::Hash => LoanTerms<T::BlockNumber>;
+        // 转让过的债权的当前持有人，还款和清算所得都归持有人。没有转让过的债权属于订单上的出借人。
+        LoanClaims get(loan_claim): map T::Hash => Option<T::AccountId>;
+        // 挂出出售的债权
+        ClaimListings get(claim_listing): map T::Hash => Option<ClaimListing<T::AssetId, T::TokenBalance>>;
+
+        // 订单还款时利息（含提前还款费用）中归保险基金的比例，万分之 x
+        InsuranceFeeShare get(insurance_fee_share) config(): u32;
+        // 每个币种的保险基金，和资金池的资金一样不属于任何账户
+        InsuranceFunds get(insurance_fund): map T::AssetId => T::TokenBalance;
+        BadDebts get(bad_debt): map T::AssetId => BadDebt<T::TokenBalance>;
+
+        // 治理：持有治理币种的账户按余额投票，通过的提案以 Root 身份执行特权调用。没有治理币种时不能提案。
+        GovernanceAsset get(governance_asset) config(): Option<T::AssetId>;
+        GovernanceParameters get(governance_params) config(): GovernanceParams<T::BlockNumber>;
+        // 过渡期：这个区块之前 Admin 账户仍然可以直接调用特权调用，None 表示不限期
+        LegacyAdminUntil get(legacy_admin_until) config(): Option<T::BlockNumber>;
+        ProposalCount get(proposal_count): u32;
+        Proposals get(proposal): map u32 => Option<GovernanceProposal<T::AccountId, Call<T>, T::AssetId, T::BlockNumber, T::TokenBalance>>;
+        // (提案序号, 投票人) => 投票，解锁后删除
+        ProposalVotes get(proposal_vote): map (u32, T::AccountId) => Option<GovernanceVote<T::TokenBalance>>;
+        // 提案时从提案人冻结的治理币种数量，限制同时进行的提案
+        ProposalDeposit get(proposal_deposit) config(): T::TokenBalance;
+        // 提案序号 => 提案人冻结的押金，提案结束时解冻并删除
+        ProposalDeposits get(proposal_deposit_of): map u32 => T::TokenBalance;
+
+        // 多签管理员：签名人提出特权调用，threshold 个签名人批准后以 Root 身份执行。没有签名人时不能提案。
+        AdminSignatories get(admin_signatories) config(): Vec<T::AccountId>;
+        AdminThreshold get(admin_threshold) config(): u32;
+        AdminProposalCount get(admin_proposal_count): u32;
+        // 所有多签提案，执行和取消后仍然保留
+        AdminProposals get(admin_proposal): map u32 => Option<AdminProposal<T::AccountId, Call<T>, T::BlockNumber>>;
+        // 等待批准的多签提案序号，按提出的顺序
+        PendingAdminProposals get(pending_admin_proposals): Vec<u32>;
+        // 正在以 Root 身份执行的治理提案或多签调用的来源，只在执行期间存在
+        DispatchingAs get(dispatching_as): Option<AdminOrigin>;
+
+        // 闪电贷的费用，按借款数量的万分之 x 向上取整，归入资金池
+        FlashLoanFee get(flash_loan_fee) config(): u32;
+        // 闪电贷执行期间写入过的存储项和它们原来的值，只在执行期间存在，见 journal.rs
+        FlashLoanJournal: Option<Vec<(Vec<u8>, Option<Vec<u8>>)>>;
+
+        // 每个币种的资金池，以及份额币种 => 资金池币种
+        Pools get(pool): map T::AssetId => Option<Pool<T::AssetId, T::TokenBalance, T::BlockNumber>>;
+        PoolOfShare get(pool_of_share): map T::AssetId => Option<T::AssetId>;
+        // (资金池币种, 借款人) => 借款
+        PoolLoans get(pool_loan): map (T::AssetId, T::AccountId) => Option<PoolLoan<T::AssetId, T::TokenBalance>>;
+
+        // 每个账户的借贷历史，只追加。(账户, 序号) => 记录，序号从 0 开始递增。
+        HistoryOf get(history_of): map (T::AccountId, u64) => Option<HistoryEntry<T::TokenBalance, T::AccountId, T::AssetId, T::Hash, T::BlockNumber>>;
+        // 该账户累计追加过的记录条数，也就是下一条记录的序号。
+        HistoryCount get(history_count): map T::AccountId => u64;
+        // 该账户最早一条仍然保存的记录序号，更早的已被清理。
+        HistoryStart get(history_start): map T::AccountId => u64;
+        // 每个账户最多保留的记录条数，为 0 时表示不限制。
+        HistoryCap get(history_cap) config(): u64;
+
+        // 每单位权重收取的手续费，为 0 时不收费
+        FeePerWeight get(fee_per_weight) config(): FeeBalanceOf<T>;
+    }
+    add_extra_genesis {
+        // (名称, 代码, 精度, 总发行量, 初始持有人及数量)，剩余部分归 Admin 所有。币种 id 从 token_id 开始依次分配。
+        config(tokens): Vec<(Vec<u8>, Vec<u8>, u8, T::TokenBalance, Vec<(T::AccountId, T::TokenBalance)>)>;
+        config(prices): Vec<(T::AssetId, u64)>;
+        config(allowed_assets): Vec<T::AssetId>;
+        // (币种, 所有者, 被授权人, 额度)
+        config(allowances): Vec<(T::AssetId, T::AccountId, T::AccountId, T::TokenBalance)>;
+        // 从旧链导出的订单，按顺序编号。未结束订单的抵押物/资金从持有人的可用余额中冻结，规则与 create_borrow/take_supply 等一致。
+        config(borrow_orders): Vec<BorrowOrder<T::TokenBalance, T::AccountId, T::AssetId, T::Hash, T::BlockNumber>>;
+        config(supply_orders): Vec<SupplyOrder<T::TokenBalance, T::AccountId, T::AssetId, T::Hash, T::BlockNumber>>;
+        config(risk_params): Vec<(T::AssetId, RiskParams<T::TokenBalance>)>;
+        // (订单 id, 累计利率指数)，没有指数的进行中订单从创世区块开始按订单的年利率计息
+        config(loan_indexes): Vec<(T::Hash, LoanIndex<T::BlockNumber>)>;
+        // (订单 id, 还款条款)，只能用于未结束的订单
+        config(loan_terms): Vec<(T::Hash, LoanTerms<T::BlockNumber>)>;
+        // (订单 id, 债权持有人) 和 (订单 id, 卖单)，只能用于进行中的订单
+        config(loan_claims): Vec<(T::Hash, T::AccountId)>;
+        config(claim_listings): Vec<(T::Hash, ClaimListing<T::AssetId, T::TokenBalance>)>;
+        // (币种, 保险基金)，和资金池的 cash 一样从 Admin 的余额中扣除
+        config(insurance_funds): Vec<(T::AssetId, T::TokenBalance)>;
+        config(bad_debts): Vec<(T::AssetId, BadDebt<T::TokenBalance>)>;
+        // (币种, 资金池)，份额币种须在 tokens 中。池中的 cash 不分给 tokens 的持有人，而是从 Admin 的余额中扣除。
+        config(pools): Vec<(T::AssetId, Pool<T::AssetId, T::TokenBalance, T::BlockNumber>)>;
+        // (资金池币种, 借款人, 借款)，抵押物从借款人的可用余额中冻结，scaled_debt 之和须等于资金池的 scaled_borrowed
+        config(pool_loans): Vec<(T::AssetId, T::AccountId, PoolLoan<T::AssetId, T::TokenBalance>)>;
+
+        build(|storage: &mut runtime_primitives::StorageOverlay, _: &mut runtime_primitives::ChildrenStorageOverlay, config: &GenesisConfig<T>| {
+            runtime_io::with_storage(storage, || {
+                for (name, ticker, decimals, total_supply, holders) in &config.tokens {
+                    let token_id = <Module<T>>::_create_token(&config.admin, name.clone(), ticker.clone(), *decimals, *total_supply)
+                        .expect("genesis tokens must be valid");
+                    let admin_key = (token_id, config.admin.clone());
+                    for (who, amount) in holders {
+                        let remaining = <FreeBalanceOf<T>>::get(&admin_key).checked_sub(amount)
+                            .expect("genesis token holders cannot exceed the total supply");
+                        <BalanceOf<T>>::insert(&admin_key, remaining);
+                        <FreeBalanceOf<T>>::insert(&admin_key, remaining);
+                        <BalanceOf<T>>::mutate((token_id, who.clone()), |b| *b += *amount);
+                        <FreeBalanceOf<T>>::mutate((token_id, who.clone()), |b| *b += *amount);
+                    }
+                }
+
+                for &(token_id, price) in &config.prices {
+                    assert!(<Tokens<T>>::exists(token_id), "genesis price for an unknown token");
+                    <TokenPrice<T>>::insert(token_id, price);
+                }
+
+                for &token_id in &config.allowed_assets {
+                    assert!(<Tokens<T>>::exists(token_id), "genesis allowed asset is an unknown token");
+                    <AllowAssets<T>>::insert(token_id, true);
+                }
+
+                for (token_id, owner, spender, amount) in &config.allowances {
+                    assert!(<Tokens<T>>::exists(token_id), "genesis allowance for an unknown token");
+                    <Module<T>>::_insert_allowance((*token_id, owner.clone(), spender.clone()), *amount);
+                }
+
+                let reserve = |token_id: T::AssetId, who: &T::AccountId, amount: T::TokenBalance| {
+                    let key = (token_id, who.clone());
+                    let free = <FreeBalanceOf<T>>::get(&key).checked_sub(&amount)
+                        .expect("genesis orders cannot reserve more than the free balance");
+                    <FreeBalanceOf<T>>::insert(&key, free);
+                    <ReserveBalanceOf<T>>::mutate(&key, |b| *b += amount);
+                };
+
+                for order in &config.borrow_orders {
+                    assert!(!<BorrowOrderOwner<T>>::exists(order.id), "duplicate genesis borrow order");
+                    if order.status == OrderStatus::Open || order.status == OrderStatus::Taken {
+                        reserve(order.stoken_id, &order.owner, order.stotal);
+                    }
+                    let index = <AllBorrowOrderCount<T>>::get();
+                    let owned_index = <OwnedBorrowCount<T>>::get(&order.owner);
+                    <BorrowOrderOwner<T>>::insert(order.id, &order.owner);
+                    <AllBorrowOrder<T>>::insert(index, order.id);
+                    <AllBorrowOrderCount<T>>::put(index + 1);
+                    <AllBorrowOrderIndex<T>>::insert(order.id, index);
+                    <OwnedBorrowOrder<T>>::insert((order.owner.clone(), owned_index), order.id);
+                    <OwnedBorrowCount<T>>::insert(&order.owner, owned_index + 1);
+                    <OwnedBorrowIndex<T>>::insert(order.id, owned_index);
+                    <BorrowOrderDetail<T>>::insert(order.id, order.clone());
+                }
+
+                for order in &config.supply_orders {
+                    assert!(!<SupplyOrderOwner<T>>::exists(order.id), "duplicate genesis supply order");
+                    match (order.status, &order.taker) {
+                        (OrderStatus::Open, _) => reserve(order.stoken, &order.owner, order.total),
+                        (OrderStatus::Taken, Some(taker)) => reserve(order.btoken, taker, order.btotal),
+                        (OrderStatus::Taken, None) => panic!("a taken genesis supply order needs a taker"),
+                        _ => {},
+                    }
+                    let index = <AllSupplyOrderCount<T>>::get();
+                    let owned_index = <OwnedSupplyCount<T>>::get(&order.owner);
+                    <SupplyOrderOwner<T>>::insert(order.id, &order.owner);
+                    <AllSupplyOrder<T>>::insert(index, order.id);
+                    <AllSupplyOrderCount<T>>::put(index + 1);
+                    <AllSupplyOrderIndex<T>>::insert(order.id, index);
+                    <OwnedSupplyOrder<T>>::insert((order.owner.clone(), owned_index), order.id);
+                    <OwnedSupplyCount<T>>::insert(&order.owner, owned_index + 1);
+                    <OwnedSupplyIndex<T>>::insert(order.id, owned_index);
+                    <SupplyOrderDetail<T>>::insert(order.id, order.clone());
+                }
+                migration::recount_totals::<T>();
+
+                for (order_id, index) in &config.loan_indexes {
+                    assert!(<LoanIndexes<T>>::get(order_id).is_none(), "duplicate genesis loan index");
+                    assert!(index.index >= interest::INDEX_ONE, "genesis loan indexes start at one");
+                    <LoanIndexes<T>>::insert(order_id, index);
+                }
+                for (order_id, terms) in &config.loan_terms {
+                    let running = |status: OrderStatus| status == OrderStatus::Open || status == OrderStatus::Taken;
+                    assert!(
+                        config.borrow_orders.iter().any(|order| order.id == *order_id && running(order.status))
+                            || config.supply_orders.iter().any(|order| order.id == *order_id && running(order.status)),
+                        "genesis loan terms for an order that is not open or taken"
+                    );
+                    assert!(terms.prepayment_fee <= yiwan as u32, "genesis prepayment fees cannot exceed 100%");
+                    <OrderTerms<T>>::insert(order_id, terms);
+                }
+                let taken = |order_id: &T::Hash| {
+                    config.borrow_orders.iter().any(|order| order.id == *order_id && order.status == OrderStatus::Taken)
+                        || config.supply_orders.iter().any(|order| order.id == *order_id && order.status == OrderStatus::Taken)
+                };
+                for (order_id, holder) in &config.loan_claims {
+                    assert!(taken(order_id), "genesis loan claim of an order that is not taken");
+                    <LoanClaims<T>>::insert(order_id, holder);
+                }
+                for (order_id, listing) in &config.claim_listings {
+                    assert!(taken(order_id), "genesis claim listing of an order that is not taken");
+                    <ClaimListings<T>>::insert(order_id, listing);
+                }
+                let genesis = T::BlockNumber::zero();
+                for order in &config.borrow_orders {
+                    if order.status == OrderStatus::Taken && <LoanIndexes<T>>::get(order.id).is_none() {
+                        <LoanIndexes<T>>::insert(order.id, LoanIndex::new(order.interest, config.blocks_per_year, genesis));
+                    }
+                }
+                for order in &config.supply_orders {
+                    if order.status == OrderStatus::Taken && <LoanIndexes<T>>::get(order.id).is_none() {
+                        <LoanIndexes<T>>::insert(order.id, LoanIndex::new(order.interest, config.blocks_per_year, genesis));
+                    }
+                }
+
+                for (token_id, pool) in &config.pools {
+                    assert!(<Tokens<T>>::exists(token_id), "genesis pool for an unknown token");
+                    assert!(<Tokens<T>>::exists(pool.share_token), "genesis pool with an unknown share token");
+                    assert!(!<Pools<T>>::exists(token_id), "duplicate genesis pool");
+                    assert!(!<PoolOfShare<T>>::exists(pool.share_token), "genesis pools cannot share a share token");
+                    assert!(pool.borrow_index >= interest::INDEX_ONE, "genesis pool borrow indexes start at one");
+                    <Module<T>>::_check_rate_model(&pool.rate_model).expect("genesis rate models must be valid");
+                    let admin_key = (*token_id, config.admin.clone());
+                    let remaining = <FreeBalanceOf<T>>::get(&admin_key).checked_sub(&pool.cash)
+                        .expect("genesis pool cash must be left to the admin by the token holders");
+                    <BalanceOf<T>>::insert(&admin_key, remaining);
+                    <FreeBalanceOf<T>>::insert(&admin_key, remaining);
+                    <PoolOfShare<T>>::insert(pool.share_token, *token_id);
+                    <Pools<T>>::insert(token_id, pool.clone());
+                }
+
+                for (token_id, who, loan) in &config.pool_loans {
+                    assert!(<Pools<T>>::exists(token_id), "genesis pool loan for an unknown pool");
+                    assert!(!<PoolLoans<T>>::exists((*token_id, who.clone())), "duplicate genesis pool loan");
+                    reserve(loan.collateral_asset, who, loan.collateral);
+                    <PoolLoans<T>>::insert((*token_id, who.clone()), loan.clone());
+                }
+                for (token_id, pool) in &config.pools {
+                    let scaled = config.pool_loans.iter()
+                        .filter(|(id, _, _)| id == token_id)
+                        .fold(T::TokenBalance::zero(), |sum, (_, _, loan)| sum + loan.scaled_debt);
+                    assert!(scaled == pool.scaled_borrowed, "genesis pool loans must add up to the borrowed amount");
+                }
+
+                assert!(config.insurance_fee_share <= yiwan as u32, "the genesis insurance fee share cannot exceed 100%");
+                assert!(config.flash_loan_fee <= yiwan as u32, "the genesis flash loan fee cannot exceed 100%");
+                if let Some(asset) = config.governance_asset {
+                    assert!(<Tokens<T>>::exists(asset), "genesis governance asset is an unknown token");
+                    <Module<T>>::_check_governance_params(&config.governance_params).expect("genesis governance parameters must be valid");
+                }
+                <Module<T>>::_check_admin_signatories(&config.admin_signatories, config.admin_threshold)
+                    .expect("genesis admin signatories must be valid");
+                for (token_id, fund) in &config.insurance_funds {
+                    assert!(<Tokens<T>>::exists(token_id), "genesis insurance fund for an unknown token");
+                    let admin_key = (*token_id, config.admin.clone());
+                    let remaining = <FreeBalanceOf<T>>::get(&admin_key).checked_sub(fund)
+                        .expect("genesis insurance funds must be left to the admin by the token holders");
+                    <BalanceOf<T>>::insert(&admin_key, remaining);
+                    <FreeBalanceOf<T>>::insert(&admin_key, remaining);
+                    <InsuranceFunds<T>>::insert(token_id, fund);
+                }
+                for (token_id, bad_debt) in &config.bad_debts {
+                    <BadDebts<T>>::insert(token_id, bad_debt);
+                }
+
+                for (token_id, params) in &config.risk_params {
+                    assert!(<Tokens<T>>::exists(token_id), "genesis risk parameters for an unknown token");
+                    <Module<T>>::_check_risk_params(params).expect("genesis risk parameters must be valid");
+                    <AssetRiskParams<T>>::insert(token_id, params);
+                }
+            });
+        });
     }
 }
 
@@ -156,74 +927,57 @@ decl_module! {
 
         fn deposit_event<T>() = default;
 
-        fn init(origin, name: Vec<u8>, ticker: Vec<u8>, total_supply: T::TokenBalance) -> Result {
-            let sender = ensure_signed(origin)?;
-
-            ensure!(sender == Self::admin(), "only Admin can new a token");
-
-            ensure!(name.len() <= 64, "token name cannot exceed 64 bytes");
-            ensure!(ticker.len() <= 32, "token ticker cannot exceed 32 bytes");
-
-            let token_id = Self::token_id();
+        fn on_initialize(_n: T::BlockNumber) {
+            if Self::storage_version() < CURRENT_STORAGE_VERSION {
+                migration::migrate::<T>();
+            }
+        }
 
-            <TokenId<T>>::mutate(|id| *id += One::one());
+        // 新建代币，全部发行量归 owner
+        fn init(origin, owner: T::AccountId, name: Vec<u8>, ticker: Vec<u8>, decimals: u8, total_supply: T::TokenBalance) -> Result {
+            Self::_ensure_admin(origin, &weights::INIT, "only Admin can new a token")?;
 
-            let token = Erc20Token {
-                name,
-                ticker,
-                total_supply,
-            };
+            let token_id = Self::_create_token(&owner, name, ticker, decimals, total_supply)?;
 
-            <Tokens<T>>::insert(token_id, token);
-            <BalanceOf<T>>::insert((token_id, sender.clone()), total_supply);
-            <FreeBalanceOf<T>>::insert((token_id, sender.clone()), total_supply);
-            <ReserveBalanceOf<T>>::insert((token_id, sender.clone()), T::TokenBalance::from(0u64));
+            Self::deposit_event(RawEvent::NewToken(token_id, owner, total_supply));
 
             Ok(())
         }
 
-    fn issue(origin, token_id: T::AssetId, added: T::TokenBalance) {
-        let origin = ensure_signed(origin)?;
-
+        // 增发 added 给 to
+        fn issue(origin, token_id: T::AssetId, to: T::AccountId, added: T::TokenBalance) -> Result {
+            Self::_ensure_admin(origin, &weights::ISSUE, "only Admin can issue a token")?;
             ensure!(<Tokens<T>>::exists(token_id), "the token does not exist");
-            ensure!(origin == Self::admin(), "only Admin can issue a token");
-
-            let admin_balance = Self::balance_of((token_id, origin.clone()));
-            let admin_free_balance = Self::free_balance_of((token_id, origin.clone()));
-            let total = Self::token_details(token_id).total_supply;
 
-            let admin_balance = admin_balance.checked_add(&added)
-                .ok_or("overflow in calculating admin balance")?;
-            let admin_free_balance = admin_free_balance.checked_add(&added)
-                .ok_or("overflow in calculating admin free balance")?;
-            let total = total.checked_add(&added)
+            let balance = Self::balance_of((token_id, to.clone())).checked_add(&added)
+                .ok_or("overflow in calculating balance")?;
+            let free_balance = Self::free_balance_of((token_id, to.clone())).checked_add(&added)
+                .ok_or("overflow in calculating free balance")?;
+            let mut token = Self::token_details(token_id);
+            token.total_supply = token.total_supply.checked_add(&added)
                 .ok_or("overflow in calculating total supply")?;
 
-            let token = Erc20Token {
-                name: Self::token_details(token_id).name,
-                ticker: Self::token_details(token_id).ticker,
-                total_supply: total,
-            };
+            <BalanceOf<T>>::insert((token_id, to.clone()), balance);
+            <FreeBalanceOf<T>>::insert((token_id, to.clone()), free_balance);
+            <Tokens<T>>::insert(token_id, token);
 
-        <BalanceOf<T>>::insert((token_id, origin.clone()), admin_balance);
-            <FreeBalanceOf<T>>::insert((token_id, origin.clone()), admin_free_balance);
-        <Tokens<T>>::insert(token_id, token);
+            Self::deposit_event(RawEvent::Issue(token_id, to, added));
 
-        Self::deposit_event(RawEvent::Issue(token_id, origin, total));
-    }
+            Ok(())
+        }
 
-        fn destroy(origin, token_id: T::AssetId, burned: T::TokenBalance) {
-            let origin = ensure_signed(origin)?;
+        // 销毁 from 的 burned 个代币
+        fn destroy(origin, token_id: T::AssetId, from: T::AccountId, burned: T::TokenBalance) {
+            Self::_ensure_admin(origin, &weights::DESTROY, "only Admin can destroy a token")?;
             ensure!(<Tokens<T>>::exists(token_id), "the token does not exist");
-            ensure!(origin == Self::admin(), "only Admin can new a token");
 
-            let free_balance = Self::free_balance_of((token_id, origin.clone()));
-            ensure!(free_balance >= burned, "origin free balance less than burned");
+            let free_balance = Self::free_balance_of((token_id, from.clone()));
+            ensure!(free_balance >= burned, "free balance less than burned");
 
             let free_balance = free_balance.checked_sub(&burned)
                 .ok_or("overflow in calculating free burn")?;
 
-            let balance = Self::balance_of((token_id, origin.clone()));
+            let balance = Self::balance_of((token_id, from.clone()));
             let balance = balance.checked_sub(&burned)
                 .ok_or("overflow in calculting balance burn")?;
 
@@ -231,44 +985,85 @@ decl_module! {
             let total = total.checked_sub(&burned)
                 .ok_or("overflow in calculating total supply")?;
 
-            let token = Erc20Token {
-                name: Self::token_details(token_id).name,
-                ticker: Self::token_details(token_id).ticker,
-                total_supply: total,
-            };
+            let mut token = Self::token_details(token_id);
+            token.total_supply = total;
 
-            <BalanceOf<T>>::insert((token_id, origin.clone()), balance);
-            <FreeBalanceOf<T>>::insert((token_id, origin.clone()), free_balance);
+            <BalanceOf<T>>::insert((token_id, from.clone()), balance);
+            <FreeBalanceOf<T>>::insert((token_id, from.clone()), free_balance);
             <Tokens<T>>::insert(token_id, token);
 
-            Self::deposit_event(RawEvent::Destroyed(token_id, origin, burned));
+            Self::deposit_event(RawEvent::Destroyed(token_id, from, burned));
         }
 
         fn set_price(origin, token_id: T::AssetId, price: u64) -> Result {
-            let sender = ensure_signed(origin)?;
-
-            ensure!(sender == Self::admin(), "only Admin can set a token price");
+            Self::_ensure_admin(origin, &weights::SET_PRICE, "only Admin can set a token price")?;
 
             ensure!(<Tokens<T>>::exists(token_id), "the token does not exist");
 
             <TokenPrice<T>>::insert(token_id, price);
 
+            Self::deposit_event(RawEvent::PriceSet(token_id, price));
+
+            Ok(())
+        }
+
+        fn set_risk_params(origin, token_id: T::AssetId, params: RiskParams<T::TokenBalance>) -> Result {
+            Self::_ensure_admin(origin, &weights::SET_RISK_PARAMS, "only Admin can set risk parameters")?;
+            ensure!(<Tokens<T>>::exists(token_id), "the token does not exist");
+            Self::_check_risk_params(&params)?;
+
+            <AssetRiskParams<T>>::insert(token_id, params);
+
+            Self::deposit_event(RawEvent::RiskParamsSet(
+                token_id, params.max_ltv, params.liquidation_threshold, params.liquidation_bonus,
+                params.borrow_cap, params.supply_cap,
+            ));
+
+            Ok(())
+        }
+
+
+        fn set_history_cap(origin, cap: u64) -> Result {
+            Self::_ensure_admin(origin, &weights::SET_HISTORY_CAP, "only Admin can set the history cap")?;
+
+            <HistoryCap<T>>::put(cap);
+
+            Self::deposit_event(RawEvent::HistoryCapSet(cap));
+
             Ok(())
         }
 
+        // 把迁移无法找到的账户的余额，以及版本 0 的授权移到新的前缀下，清除旧的条目
+        fn migrate_v0_accounts(
+            origin,
+            accounts: Vec<T::AccountId>,
+            allowances: Vec<(T::AssetId, T::AccountId, T::AccountId)>
+        ) -> Result {
+            let sender = ensure_signed(origin)?;
+            ensure!(accounts.len() + allowances.len() <= weights::MAX_MIGRATE_V0_ENTRIES, "too many accounts to migrate");
+            Self::charge_fee(&sender, &weights::migrate_v0_accounts::<T>(accounts.len(), allowances.len()))?;
+
+            migration::migrate_accounts::<T>(&accounts, &allowances);
+
+            Self::deposit_event(RawEvent::V0AccountsMigrated(sender, accounts.len() as u32, allowances.len() as u32));
+
+            Ok(())
+        }
 
         fn transfer(_origin, token_id: T::AssetId, to: T::AccountId, value: T::TokenBalance) -> Result {
             let sender = ensure_signed(_origin)?;
+            Self::charge_fee(&sender, &weights::TRANSFER)?;
             Self::_transfer(token_id, sender, to, value)
         }
 
         fn approve(_origin, token_id: T::AssetId, spender: T::AccountId, value: T::TokenBalance) -> Result {
             let sender = ensure_signed(_origin)?;
-            ensure!(<BalanceOf<T>>::exists((token_id, sender.clone())), "Account does not own this token");
+            Self::charge_fee(&sender, &weights::APPROVE)?;
+            ensure!(Self::_owns_token((token_id, sender.clone())), "Account does not own this token");
 
             let allowance = Self::allowance((token_id, sender.clone(), spender.clone()));
             let updated_allowance = allowance.checked_add(&value).ok_or("overflow in calculating allowance")?;
-            <Allowance<T>>::insert((token_id, sender.clone(), spender.clone()), updated_allowance);
+            Self::_insert_allowance((token_id, sender.clone(), spender.clone()), updated_allowance);
 
             Self::deposit_event(RawEvent::Approval(token_id, sender.clone(), spender.clone(), value));
 
@@ -279,27 +1074,33 @@ decl_module! {
         // implemented in the open-zeppelin way - increase/decrease allownace
         // if approved, transfer from an account to another account without owner's signature
         pub fn transfer_from(_origin, token_id: T::AssetId, from: T::AccountId, to: T::AccountId, value: T::TokenBalance) -> Result {
-            ensure!(<Allowance<T>>::exists((token_id, from.clone(), to.clone())), "Allowance does not exist.");
+            let sender = ensure_signed(_origin)?;
+            Self::charge_fee(&sender, &weights::TRANSFER_FROM)?;
+            ensure!(Self::_allowance_exists((token_id, from.clone(), to.clone())), "Allowance does not exist.");
             let allowance = Self::allowance((token_id, from.clone(), to.clone()));
             ensure!(allowance >= value, "Not enough allowance.");
 
             // using checked_sub (safe math) to avoid overflow
             let updated_allowance = allowance.checked_sub(&value).ok_or("overflow in calculating allowance")?;
-            <Allowance<T>>::insert((token_id, from.clone(), to.clone()), updated_allowance);
 
-            Self::deposit_event(RawEvent::Approval(token_id, from.clone(), to.clone(), value));
-            Self::_transfer(token_id, from, to, value)
+            // the transfer may still fail, so the allowance is only spent afterwards
+            Self::_transfer(token_id, from.clone(), to.clone(), value)?;
+
+            Self::_insert_allowance((token_id, from.clone(), to.clone()), updated_allowance);
+            Self::deposit_event(RawEvent::Approval(token_id, from, to, value));
+
+            Ok(())
         }
 
         fn set_allow_assets(origin, token_id: T::AssetId, add_or_del: bool) -> Result {
-            let sender = ensure_signed(origin)?;
-
-            ensure!(sender == Self::admin(), "only Admin can set allow assets");
+            Self::_ensure_admin(origin, &weights::SET_ALLOW_ASSETS, "only Admin can set allow assets")?;
 
             ensure!(<Tokens<T>>::exists(token_id), "the token does not exist");
 
             <AllowAssets<T>>::insert(token_id, add_or_del);
 
+            Self::deposit_event(RawEvent::AllowAssetSet(token_id, add_or_del));
+
             Ok(())
         }
 
@@ -307,9 +1108,10 @@ decl_module! {
         fn create_borrow(origin, btotal: T::TokenBalance, btokenid: T::AssetId, duration: u64, stotal: T::TokenBalance,
                          stokenid: T::AssetId, interest: u32) -> Result {
             let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::CREATE_BORROW)?;
 
             ensure!(
-                <BalanceOf<T>>::exists((stokenid, sender.clone())),
+                Self::_owns_token((stokenid, sender.clone())),
                 "Account does not own this token"
             );
 
@@ -322,24 +1124,24 @@ decl_module! {
             ensure!(Self::allow_asset(stokenid) == true, "the supply asset is not allowed");
 
             ensure!(stotal > T::TokenBalance::from(0u64), "stotal should bigger than 0");
+            ensure!(interest <= interest::MAX_ANNUAL_RATE, "the interest cannot exceed 1000% a year");
 
 
             let bprice = Self::token_price(btokenid);
-            // todo: need checked_mul
-            let btotalprice = btotal * T::TokenBalance::from(bprice);
+            let btotalprice = btotal.checked_mul(&T::TokenBalance::from(bprice))
+                .ok_or("overflow in calculating borrow value")?;
 
             let sprice = Self::token_price(stokenid);
-            // todo: need checked_mul
-            let stotalprice = stotal * T::TokenBalance::from(sprice);
+            let stotalprice = stotal.checked_mul(&T::TokenBalance::from(sprice))
+                .ok_or("overflow in calculating supply value")?;
 
             ensure!(stotalprice >= btotalprice, "the value of supply lower than borrow"); // 等额或超额抵押，还没考虑手续费。
+            Self::_check_ltv(btokenid, btotal, stokenid, stotal)?;
 
             let nonce = <Nonce<T>>::get();
             let random_hash = (<system::Module<T>>::random_seed(), &sender, nonce)
                 .using_encoded(<T as system::Trait>::Hashing::hash);
 
-            Self::_reserve(stokenid, sender.clone(), stotal);
-
             ensure!(!<BorrowOrderOwner<T>>::exists(random_hash), "Borrow order already exists");
 
             let new_borrow_order = BorrowOrder {
@@ -352,6 +1154,10 @@ decl_module! {
                 stotal: stotal,
                 stoken_id: stokenid,
                 interest: interest,
+                status: OrderStatus::Open,
+                taker: None,
+                created_at: <system::Module<T>>::block_number(),
+                updated_at: <system::Module<T>>::block_number(),
             };
 
             let owned_borrow_count = Self::owned_borrow_count(&sender);
@@ -362,6 +1168,9 @@ decl_module! {
             let new_all_borrow_order_count = all_borrow_order_count.checked_add(1)
                 .ok_or("Overflow adding a new borrow order")?;
 
+            // 所有检查通过后再冻结抵押物，失败的调用不能留下部分写入
+            Self::_reserve(stokenid, sender.clone(), stotal)?;
+
             <BorrowOrderDetail<T>>::insert(random_hash, new_borrow_order);
             <BorrowOrderOwner<T>>::insert(random_hash, &sender);
 
@@ -376,7 +1185,9 @@ decl_module! {
 
             <Nonce<T>>::mutate(|n| *n += 1);
 
-            Self::deposit_event(RawEvent::CreateBorrow(sender, btotal, duration, stotal, interest));
+            Self::_record_history(&sender, HistoryKind::OrderCreated, random_hash, None, btokenid, btotal);
+
+            Self::deposit_event(RawEvent::CreateBorrow(sender, random_hash, btokenid, btotal, stokenid, stotal, duration, interest));
 
             Ok(())
         }
@@ -384,6 +1195,7 @@ decl_module! {
 
         fn cancel_borrow(origin, orderid: T::Hash) -> Result {
             let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::CANCEL_BORROW)?;
 
             ensure!(<BorrowOrderDetail<T>>::exists(orderid), "the borrow order does not exist");
 
@@ -393,46 +1205,61 @@ decl_module! {
 
             ensure!(owner == sender, "only owner can cancel order");
             ensure!(order.already == T::TokenBalance::from(0u64), "the borrow order already begin");
-            ensure!(order.stotal > T::TokenBalance::from(0u64), "the borrow order is invalid");
+            ensure!(order.status == OrderStatus::Open, "the borrow order is invalid");
 
             let stoken_id = order.stoken_id;
             let svalue = order.stotal;
 
-            Self::_unreserve(stoken_id, sender.clone(), svalue);
+            Self::_unreserve(stoken_id, sender.clone(), svalue)?;
 
-            order.stotal = T::TokenBalance::from(0u64);
+            order.status = OrderStatus::Cancelled;
+            order.updated_at = <system::Module<T>>::block_number();
 
             <BorrowOrderDetail<T>>::insert(orderid, order);
+            <OrderTerms<T>>::remove(orderid);
+
+            Self::_record_history(&sender, HistoryKind::OrderCancelled, orderid, None, stoken_id, svalue);
 
-            Self::deposit_event(RawEvent::CancelBorrow(sender, orderid));
+            Self::deposit_event(RawEvent::CancelBorrow(sender, orderid, stoken_id, svalue));
 
             Ok(())
         }
 
         fn take_borrow(origin, borderid: T::Hash) -> Result {
             let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::TAKE_BORROW)?;
 
             ensure!(<BorrowOrderDetail<T>>::exists(borderid), "the supply order does not exist");
             
             let  border = Self::borrow_order_detail(borderid);
-            ensure!(border.stotal > T::TokenBalance::from(0u64), "the borrow order is invalid or finished");
+            ensure!(border.status == OrderStatus::Open, "the borrow order is invalid or finished");
             let bowner = border.owner;
             let btotal = border.btotal;
             let btokenid = border.btoken_id;
             let stotal = border.stotal;
             let stokenid = border.stoken_id;
 
+            // 挂单之后价格和风险参数都可能变化，成交时按当前的重新检查
+            Self::_check_ltv(btokenid, btotal, stokenid, stotal)?;
+            let supplied = Self::_check_supply_cap(btokenid, btotal)?;
+            let borrowed = Self::_check_borrow_cap(btokenid, btotal)?;
 
+            Self::_transfer(btokenid, sender.clone(), bowner.clone(), btotal)?;
+            <TotalSupplied<T>>::insert(btokenid, supplied);
+            <TotalBorrowed<T>>::insert(btokenid, borrowed);
 
+            Self::_record_history(&bowner, HistoryKind::OrderFilled, borderid, Some(sender.clone()), btokenid, btotal);
+            Self::_record_history(&sender, HistoryKind::OrderFilled, borderid, Some(bowner.clone()), btokenid, btotal);
 
-            Self::_transfer(btokenid, sender.clone(), bowner.clone(), btotal);
-
-
-            Self::deposit_event(RawEvent::TakeBorrow(sender));
+            Self::deposit_event(RawEvent::TakeBorrow(borderid, sender.clone(), bowner, btokenid, btotal));
 
+            let now = <system::Module<T>>::block_number();
             let mut bborder = Self::borrow_order_detail(borderid);
-            bborder.stotal = T::TokenBalance::from(0u64);
+            bborder.status = OrderStatus::Taken;
+            bborder.taker = Some(sender);
+            bborder.updated_at = now;
 
+            <LoanIndexes<T>>::insert(borderid, LoanIndex::new(bborder.interest, Self::blocks_per_year(), now));
             <BorrowOrderDetail<T>>::insert(borderid, bborder);
 
             Ok(())
@@ -442,13 +1269,17 @@ decl_module! {
         fn create_Supply(origin, stotal: T::TokenBalance, stokenid: T::AssetId, btokenids: Vec<T::AssetId>, amortgage: u32,
                            duration: u64, interest: u32) -> Result {
             let sender = ensure_signed(origin)?;
+            ensure!(btokenids.len() <= weights::MAX_COLLATERAL_TOKENS, "too many collateral tokens");
+            Self::charge_fee(&sender, &weights::create_supply(btokenids.len()))?;
 
             ensure!(
-                <BalanceOf<T>>::exists((stokenid, sender.clone())),
+                Self::_owns_token((stokenid, sender.clone())),
                 "Account does not own this token"
             );
 
             ensure!(stotal > T::TokenBalance::from(0u64), "stotal should bigger than 0");
+            ensure!(amortgage > 0, "amortgage should bigger than 0");
+            ensure!(interest <= interest::MAX_ANNUAL_RATE, "the interest cannot exceed 1000% a year");
 
             ensure!(Self::allow_asset(stokenid) == true, "the supply asset is not allowed");
 
@@ -458,13 +1289,13 @@ decl_module! {
                 ensure!(Self::allow_asset(i) == true, "the btokenid asset is not allowed");
             }
 
+            let supplied = Self::_check_supply_cap(stokenid, stotal)?;
+
 
             let nonce = <Nonce<T>>::get();
             let random_hash = (<system::Module<T>>::random_seed(), &sender, nonce)
                 .using_encoded(<T as system::Trait>::Hashing::hash);
 
-            Self::_reserve(stokenid, sender.clone(), stotal);
-
             ensure!(!<SupplyOrderOwner<T>>::exists(random_hash), "Supply order already exists");
 
             let new_supply_order = SupplyOrder {
@@ -472,10 +1303,16 @@ decl_module! {
                 owner: sender.clone(),
                 total: stotal,
                 stoken: stokenid,
-                tokens: btokenids,
+                tokens: btokenids.clone(),
                 amortgage: amortgage,
                 duration: duration,
                 interest: interest,
+                status: OrderStatus::Open,
+                taker: None,
+                btoken: T::AssetId::default(),
+                btotal: T::TokenBalance::from(0u64),
+                created_at: <system::Module<T>>::block_number(),
+                updated_at: <system::Module<T>>::block_number(),
             };
 
             let owned_supply_count = Self::owned_supply_count(&sender);
@@ -486,6 +1323,9 @@ decl_module! {
             let new_all_supply_order_count = all_supply_order_count.checked_add(1)
                 .ok_or("Overflow adding a new supply order")?;
 
+            Self::_reserve(stokenid, sender.clone(), stotal)?;
+            <TotalSupplied<T>>::insert(stokenid, supplied);
+
             <SupplyOrderDetail<T>>::insert(random_hash, new_supply_order);
             <SupplyOrderOwner<T>>::insert(random_hash, &sender);
 
@@ -500,7 +1340,9 @@ decl_module! {
 
             <Nonce<T>>::mutate(|n| *n += 1);
 
-            Self::deposit_event(RawEvent::CreateSupply(sender));
+            Self::_record_history(&sender, HistoryKind::OrderCreated, random_hash, None, stokenid, stotal);
+
+            Self::deposit_event(RawEvent::CreateSupply(sender, random_hash, stokenid, stotal, btokenids, amortgage, duration, interest));
 
             Ok(())
         }
@@ -508,6 +1350,7 @@ decl_module! {
 
         fn cance_supply(origin, orderid: T::Hash) -> Result {
             let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::CANCEL_SUPPLY)?;
 
             ensure!(<SupplyOrderDetail<T>>::exists(orderid), "the supply order does not exist");
 
@@ -516,18 +1359,24 @@ decl_module! {
             let mut order = Self::supply_order_detail(orderid);
 
             ensure!(owner == sender, "only owner can cancel order");
-            ensure!(order.total > T::TokenBalance::from(0u64), "the borrow order is invalid");
+            ensure!(order.status == OrderStatus::Open, "the supply order is invalid");
 
             let stoken_id = order.stoken;
             let svalue = order.total;
 
-            Self::_unreserve(stoken_id, sender.clone(), svalue);
+            Self::_unreserve(stoken_id, sender.clone(), svalue)?;
+            // 未被接单的借款订单不计入总量，只有提供资金订单撤销时要减掉
+            <TotalSupplied<T>>::mutate(stoken_id, |total| *total = total.checked_sub(&svalue).unwrap_or_else(Zero::zero));
 
-            order.total = T::TokenBalance::from(0u64);
+            order.status = OrderStatus::Cancelled;
+            order.updated_at = <system::Module<T>>::block_number();
 
             <SupplyOrderDetail<T>>::insert(orderid, order);
+            <OrderTerms<T>>::remove(orderid);
 
-            Self::deposit_event(RawEvent::CancelSupply(sender, orderid));
+            Self::_record_history(&sender, HistoryKind::OrderCancelled, orderid, None, stoken_id, svalue);
+
+            Self::deposit_event(RawEvent::CancelSupply(sender, orderid, stoken_id, svalue));
 
             Ok(())
         }
@@ -535,6 +1384,7 @@ decl_module! {
 
         fn take_supply(origin, sorderid: T::Hash, btokenid: T::AssetId) -> Result {
             let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::TAKE_SUPPLY)?;
 
             ensure!(<SupplyOrderDetail<T>>::exists(sorderid), "the supply order does not exist");
             ensure!(<Tokens<T>>::exists(btokenid), "the btoken does not exist");
@@ -544,122 +1394,1733 @@ decl_module! {
             let  sorder = Self::supply_order_detail(sorderid);
 
             ensure!(sorder.clone().tokens.contains(&btokenid), "the supply order does not support this token");
-            ensure!(sorder.clone().total > T::TokenBalance::from(0u64), "the supply order is valid or finished." );
+            ensure!(sorder.status == OrderStatus::Open, "the supply order is invalid or finished." );
             let stokenid = sorder.stoken;
             let stotal = sorder.total;
             let sowner = sorder.owner;
 
 
             let sprice = Self::token_price(stokenid);
-            // todo: need checked_mul
-            let stotalprice = stotal * T::TokenBalance::from(sprice);
+            let stotalprice = stotal.checked_mul(&T::TokenBalance::from(sprice))
+                .ok_or("overflow in calculating supply value")?;
             let bprice = Self::token_price(btokenid);
 
-            // todo: need checked_div
-            let btotalprice = stotalprice * T::TokenBalance::from(10000u64) / T::TokenBalance::from(u64::from(sorder.amortgage));
-            let btotal = btotalprice / T::TokenBalance::from(bprice);
+            // 出借人要求的抵押率不能高于抵押币种的 max_ltv
+            let max_ltv = Self::risk_params(btokenid).max_ltv;
+            ensure!(max_ltv > 0, "the asset cannot be used as collateral");
+            let amortgage = cmp::min(sorder.amortgage, max_ltv);
+
+            // 迁移过来的旧订单可能没有抵押率，价格也可能被设置为 0，都不能用来做除数
+            let btotalprice = stotalprice.checked_mul(&T::TokenBalance::from(yiwan))
+                .ok_or("overflow in calculating collateral value")?
+                .checked_div(&T::TokenBalance::from(u64::from(amortgage)))
+                .ok_or("the supply order has no amortgage")?;
+            let btotal = btotalprice.checked_div(&T::TokenBalance::from(bprice))
+                .ok_or("the btoken price does not exist")?;
+            let borrowed = Self::_check_borrow_cap(stokenid, stotal)?;
 
-            Self::_reserve(btokenid, sender.clone(), btotal);
+            Self::_reserve(btokenid, sender.clone(), btotal)?;
 
-            Self::_transfer(stokenid, sowner.clone(), sender.clone(), stotal);
+            // 出借的资金在挂单时被冻结，先解冻才能转给借款人
+            Self::_unreserve(stokenid, sowner.clone(), stotal)?;
 
-            Self::_unreserve(stokenid, sowner.clone(), stotal);
+            Self::_transfer(stokenid, sowner.clone(), sender.clone(), stotal)?;
+            <TotalBorrowed<T>>::insert(stokenid, borrowed);
 
-            Self::deposit_event(RawEvent::TakeSupply(sender));
+            Self::_record_history(&sowner, HistoryKind::OrderFilled, sorderid, Some(sender.clone()), stokenid, stotal);
+            Self::_record_history(&sender, HistoryKind::OrderFilled, sorderid, Some(sowner.clone()), stokenid, stotal);
 
+            Self::deposit_event(RawEvent::TakeSupply(sorderid, sowner, sender.clone(), stokenid, stotal, btokenid, btotal));
+
+            let now = <system::Module<T>>::block_number();
             let mut ssorder = Self::supply_order_detail(sorderid);
-            ssorder.total = T::TokenBalance::from(0u64);
+            ssorder.status = OrderStatus::Taken;
+            ssorder.taker = Some(sender);
+            ssorder.btoken = btokenid;
+            ssorder.btotal = btotal;
+            ssorder.updated_at = now;
 
+            <LoanIndexes<T>>::insert(sorderid, LoanIndex::new(ssorder.interest, Self::blocks_per_year(), now));
             <SupplyOrderDetail<T>>::insert(sorderid, ssorder);
 
 
             Ok(())
 
         }
-    }
-}
 
-impl<T: Trait> Module<T> {
-    // the ERC20 standard transfer function
-    // internal
-    fn _transfer(
-        token_id: T::AssetId,
-        from: T::AccountId,
-        to: T::AccountId,
-        value: T::TokenBalance,
-    ) -> Result {
-        ensure!(
-            <BalanceOf<T>>::exists((token_id, from.clone())),
-            "Account does not own this token"
-        );
+        // 任何人都可以清算抵押不足的借贷：清算人替借款人向出借人还清本息，换取价值为 本息 + 清算奖励 的抵押物，
+        // 剩余的抵押物退还给借款人。出借人自己清算时不用付款，抵押物不够时全部归出借人，亏空由保险基金补偿。
+        fn liquidate(origin, order_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::LIQUIDATE)?;
 
-        let sender_balance = Self::balance_of((token_id, from.clone()));
-        ensure!(sender_balance >= value, "Not enough balance.");
+            let loan = Self::_taken_loan(order_id)?;
+            let seized = Self::_liquidation_seizure(loan.asset, loan.debt, loan.collateral_asset, loan.collateral)?;
 
-        let sender_free_balance = Self::free_balance_of((token_id, from.clone()));
-        ensure!(sender_free_balance >= value, "Not enough free balance.");
+            if sender != loan.lender {
+                Self::_transfer(loan.asset, sender.clone(), loan.lender.clone(), loan.debt)?;
+            }
+            Self::_unreserve(loan.collateral_asset, loan.borrower.clone(), loan.collateral)?;
+            Self::_transfer(loan.collateral_asset, loan.borrower.clone(), sender.clone(), seized)?;
+            Self::_settle_loan(&loan, OrderStatus::Liquidated);
+            Self::_cover_shortfall(loan.asset, loan.debt, loan.collateral_asset, seized, &sender)?;
 
-        let updated_from_balance = sender_balance
-            .checked_sub(&value)
-            .ok_or("overflow in calculating balance")?;
+            Self::_record_history(&loan.borrower, HistoryKind::Liquidated, order_id, Some(sender.clone()), loan.collateral_asset, seized);
+            Self::_record_history(&loan.lender, HistoryKind::Liquidated, order_id, Some(loan.borrower.clone()), loan.asset, loan.debt);
 
-        let updated_from_free_balance = sender_free_balance
-            .checked_sub(&value)
-            .ok_or("overflow in calculating free balance")?;
+            Self::deposit_event(RawEvent::Liquidated(order_id, sender, loan.borrower, loan.collateral_asset, seized));
 
-        let receiver_balance = Self::balance_of((token_id, to.clone()));
+            Ok(())
+        }
 
-        let receiver_free_balance = Self::free_balance_of((token_id, to.clone()));
+        // 为某个币种开设资金池，同时创建份额币种：名称前加 "Oumuamua "，代码前加 "o"，精度不变
+        fn create_pool(origin, token_id: T::AssetId, rate_model: RateModel) -> Result {
+            Self::_ensure_admin(origin, &weights::CREATE_POOL, "only Admin can create a pool")?;
+            ensure!(<Tokens<T>>::exists(token_id), "the token does not exist");
+            ensure!(!<Pools<T>>::exists(token_id), "the token already has a pool");
+            ensure!(!<PoolOfShare<T>>::exists(token_id), "a share token cannot have a pool");
+            Self::_check_rate_model(&rate_model)?;
+
+            let token = Self::token_details(token_id);
+            let mut name = b"Oumuamua ".to_vec();
+            name.extend_from_slice(&token.name);
+            name.truncate(64);
+            let mut ticker = b"o".to_vec();
+            ticker.extend_from_slice(&token.ticker);
+            ticker.truncate(32);
+            let share_token = Self::_create_token(&Self::admin(), name, ticker, token.decimals, Zero::zero())?;
+
+            <PoolOfShare<T>>::insert(share_token, token_id);
+            <Pools<T>>::insert(token_id, Pool {
+                share_token,
+                cash: Zero::zero(),
+                scaled_borrowed: Zero::zero(),
+                rate_model,
+                borrow_index: interest::INDEX_ONE,
+                accrued_at: <system::Module<T>>::block_number(),
+                reserves: Zero::zero(),
+            });
+
+            Self::deposit_event(RawEvent::PoolCreated(token_id, share_token));
 
-        let updated_to_balance = receiver_balance
-            .checked_add(&value)
-            .ok_or("overflow in calculating balance")?;
+            Ok(())
+        }
 
-        let updated_to_free_balance = receiver_free_balance
-            .checked_add(&value)
-            .ok_or("overflow in calculating free balance")?;
+        fn set_rate_model(origin, token_id: T::AssetId, rate_model: RateModel) -> Result {
+            Self::_ensure_admin(origin, &weights::SET_RATE_MODEL, "only Admin can set a rate model")?;
+            // 之前的区块按原来的利率累计
+            let mut pool = Self::_accrued_pool(token_id).ok_or("the pool does not exist")?;
+            Self::_check_rate_model(&rate_model)?;
 
-        // reduce sender's balance
-        <BalanceOf<T>>::insert((token_id, from.clone()), updated_from_balance);
-        <FreeBalanceOf<T>>::insert((token_id, from.clone()), updated_from_free_balance);
+            pool.rate_model = rate_model;
+            Self::_put_pool(token_id, pool);
 
-        // increase receiver's balance
-        <BalanceOf<T>>::insert((token_id, to.clone()), updated_to_balance);
-        <FreeBalanceOf<T>>::insert((token_id, to.clone()), updated_to_free_balance);
+            Self::deposit_event(RawEvent::RateModelSet(
+                token_id, rate_model.base_rate, rate_model.slope1, rate_model.kink, rate_model.slope2,
+            ));
 
-        Self::deposit_event(RawEvent::Transfer(token_id, from, to, value));
-        Ok(())
-    }
+            Ok(())
+        }
 
-    fn _reserve(token_id: T::AssetId, sender: T::AccountId, value: T::TokenBalance) -> Result {
-        ensure!(
-            <BalanceOf<T>>::exists((token_id, sender.clone())),
-            "Account does not own this token"
-        );
+        // 存入资金池，按 存入数量 × 份额总量 / 资金池总额 得到份额，第一笔存款 1:1
+        fn deposit(origin, token_id: T::AssetId, amount: T::TokenBalance) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::DEPOSIT)?;
 
-        let sender_free_balance = Self::free_balance_of((token_id, sender.clone()));
-        ensure!(sender_free_balance >= value, "Not enough free balance.");
+            let mut pool = Self::_accrued_pool(token_id).ok_or("the pool does not exist")?;
+            ensure!(Self::allow_asset(token_id), "the asset is not allowed");
 
-        let sender_reserve_balance = Self::reserve_balance_of((token_id, sender.clone()));
+            let shares = Self::_shares_for(&pool, amount)?;
+            ensure!(!shares.is_zero(), "the deposit is too small for a share");
+            Self::_check_supply_cap(token_id, amount)?;
+            pool.cash = pool.cash.checked_add(&amount).ok_or("overflow in calculating pool cash")?;
 
-        let updated_sender_free_balance = sender_free_balance
-            .checked_sub(&value)
-            .ok_or("overflow in calculating reserve free balance")?;
+            Self::_take(token_id, &sender, amount)?;
+            Self::_mint(pool.share_token, &sender, shares)?;
+            Self::_put_pool(token_id, pool);
 
-        let updated_sender_reserve_balance = sender_reserve_balance
-            .checked_add(&value)
-            .ok_or("overflow in calculating reserve reserve balance")?;
+            Self::deposit_event(RawEvent::Deposited(token_id, sender, amount, shares));
 
-        <FreeBalanceOf<T>>::insert((token_id, sender.clone()), updated_sender_free_balance);
-        <ReserveBalanceOf<T>>::insert((token_id, sender.clone()), updated_sender_reserve_balance);
+            Ok(())
+        }
 
-        Self::deposit_event(RawEvent::Reserve(token_id, sender, value));
-        Ok(())
-    }
+        // 用份额取回资金，数量为 份额 × 资金池总额 / 份额总量，只能取出尚未借出的部分
+        fn withdraw(origin, token_id: T::AssetId, shares: T::TokenBalance) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::WITHDRAW)?;
+
+            let mut pool = Self::_accrued_pool(token_id).ok_or("the pool does not exist")?;
+            let amount = Self::_amount_for(&pool, shares)?;
+            ensure!(!amount.is_zero(), "the withdrawal is too small");
+            ensure!(amount <= pool.cash, "not enough cash in the pool");
+            pool.cash -= amount;
+
+            Self::_burn(pool.share_token, &sender, shares)?;
+            Self::_give(token_id, &sender, amount)?;
+            Self::_put_pool(token_id, pool);
+
+            Self::deposit_event(RawEvent::Withdrawn(token_id, sender, amount, shares));
+
+            Ok(())
+        }
+
+        // 从资金池借款并追加抵押物。每个账户在每个资金池只有一笔借款，抵押币种固定为第一次借款时的币种。
+        fn pool_borrow(
+            origin,
+            token_id: T::AssetId,
+            amount: T::TokenBalance,
+            collateral_asset: T::AssetId,
+            collateral: T::TokenBalance
+        ) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::POOL_BORROW)?;
+
+            let mut pool = Self::_accrued_pool(token_id).ok_or("the pool does not exist")?;
+            ensure!(<Tokens<T>>::exists(collateral_asset), "the collateral token does not exist");
+            ensure!(collateral_asset != token_id, "the collateral cannot be the borrowed asset");
+            ensure!(Self::allow_asset(collateral_asset), "the collateral asset is not allowed");
+            ensure!(!amount.is_zero() || !collateral.is_zero(), "nothing to borrow or add");
+
+            let mut loan = Self::pool_loan((token_id, sender.clone())).unwrap_or_else(|| PoolLoan {
+                scaled_debt: Zero::zero(),
+                collateral_asset,
+                collateral: Zero::zero(),
+            });
+            ensure!(loan.collateral_asset == collateral_asset, "the loan is backed by another collateral asset");
+            // 折算时向上取整，借款人的债务不会少于借到的数量
+            let scaled = pool.scaled_up(amount);
+            loan.scaled_debt = loan.scaled_debt.checked_add(&scaled).ok_or("overflow in calculating the loan debt")?;
+            loan.collateral = loan.collateral.checked_add(&collateral).ok_or("overflow in calculating the loan collateral")?;
+
+            ensure!(amount <= pool.cash, "not enough cash in the pool");
+            Self::_check_ltv(token_id, pool.debt(loan.scaled_debt), collateral_asset, loan.collateral)?;
+            Self::_check_borrow_cap(token_id, amount)?;
+            pool.cash -= amount;
+            pool.scaled_borrowed = pool.scaled_borrowed.checked_add(&scaled).ok_or("overflow in calculating pool borrows")?;
+
+            Self::_reserve(collateral_asset, sender.clone(), collateral)?;
+            Self::_give(token_id, &sender, amount)?;
+            Self::_put_pool(token_id, pool);
+            <PoolLoans<T>>::insert((token_id, sender.clone()), loan);
+            if !amount.is_zero() {
+                Self::_record_history(&sender, HistoryKind::PoolBorrowed, T::Hash::default(), None, token_id, amount);
+            }
+
+            Self::deposit_event(RawEvent::PoolBorrowed(token_id, sender, amount, collateral_asset, collateral));
+
+            Ok(())
+        }
+
+        // 向资金池还款，最多还清本息；还清后抵押物全部解冻
+        fn pool_repay(origin, token_id: T::AssetId, amount: T::TokenBalance) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::POOL_REPAY)?;
+
+            let mut pool = Self::_accrued_pool(token_id).ok_or("the pool does not exist")?;
+            let mut loan = Self::pool_loan((token_id, sender.clone())).ok_or("no loan from the pool")?;
+            let debt = pool.debt(loan.scaled_debt);
+            let amount = cmp::min(amount, debt);
+            // 部分还款折算时向下取整，剩余的债务不会因为取整而减少
+            let scaled = if amount == debt {
+                loan.scaled_debt
+            } else {
+                pool.scaled(amount)
+            };
+            loan.scaled_debt -= scaled;
+            pool.cash = pool.cash.checked_add(&amount).ok_or("overflow in calculating pool cash")?;
+            pool.scaled_borrowed -= scaled;
+
+            Self::_take(token_id, &sender, amount)?;
+            Self::_journal::<PoolLoans<T>, _, _>(&(token_id, sender.clone()));
+            if loan.scaled_debt.is_zero() {
+                Self::_unreserve(loan.collateral_asset, sender.clone(), loan.collateral)?;
+                <PoolLoans<T>>::remove((token_id, sender.clone()));
+            } else {
+                <PoolLoans<T>>::insert((token_id, sender.clone()), loan);
+            }
+            Self::_put_pool(token_id, pool);
+            Self::_record_history(&sender, HistoryKind::PoolRepaid, T::Hash::default(), None, token_id, amount);
+
+            Self::deposit_event(RawEvent::PoolRepaid(token_id, sender, amount));
+
+            Ok(())
+        }
+
+        // 和 liquidate 一样：清算人替借款人向资金池还清本息，换取价值为 本息 + 清算奖励 的抵押物
+        fn liquidate_pool_loan(origin, token_id: T::AssetId, borrower: T::AccountId) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::LIQUIDATE_POOL_LOAN)?;
+
+            let mut pool = Self::_accrued_pool(token_id).ok_or("the pool does not exist")?;
+            let loan = Self::pool_loan((token_id, borrower.clone())).ok_or("no loan from the pool")?;
+            let debt = pool.debt(loan.scaled_debt);
+            let seized = Self::_liquidation_seizure(token_id, debt, loan.collateral_asset, loan.collateral)?;
+            pool.cash = pool.cash.checked_add(&debt).ok_or("overflow in calculating pool cash")?;
+            pool.scaled_borrowed -= loan.scaled_debt;
+
+            Self::_take(token_id, &sender, debt)?;
+            Self::_unreserve(loan.collateral_asset, borrower.clone(), loan.collateral)?;
+            Self::_transfer(loan.collateral_asset, borrower.clone(), sender.clone(), seized)?;
+            Self::_journal::<PoolLoans<T>, _, _>(&(token_id, borrower.clone()));
+            <PoolLoans<T>>::remove((token_id, borrower.clone()));
+            Self::_put_pool(token_id, pool);
+            Self::_cover_shortfall(token_id, debt, loan.collateral_asset, seized, &sender)?;
+            Self::_record_history(&borrower, HistoryKind::PoolLiquidated, T::Hash::default(), Some(sender.clone()), loan.collateral_asset, seized);
+            Self::_record_history(&sender, HistoryKind::PoolLiquidated, T::Hash::default(), Some(borrower.clone()), token_id, debt);
+
+            Self::deposit_event(RawEvent::PoolLiquidated(token_id, sender, borrower, loan.collateral_asset, seized));
+
+            Ok(())
+        }
+
+        // 借款人连本带息还给出借人，抵押物全部解冻。到期前还款时另外支付订单约定的提前还款费用。
+        fn repay(origin, order_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::REPAY)?;
+
+            let loan = Self::_taken_loan(order_id)?;
+            ensure!(sender == loan.borrower, "only the borrower can repay");
+            let paid = loan.debt.checked_add(&Self::_prepayment_fee(&loan))
+                .ok_or("overflow in calculating the repayment")?;
+            let interest = paid - loan.principal;
+            let insurance = Self::_insurance_part(interest);
+            let fund = Self::insurance_fund(loan.asset).checked_add(&insurance)
+                .ok_or("overflow in calculating the insurance fund")?;
+
+            // 出借人收到还款后，利息中的一部分归保险基金
+            Self::_transfer(loan.asset, sender.clone(), loan.lender.clone(), paid)?;
+            Self::_take(loan.asset, &loan.lender, insurance)?;
+            Self::_journal::<InsuranceFunds<T>, _, _>(&loan.asset);
+            <InsuranceFunds<T>>::insert(loan.asset, fund);
+            Self::_unreserve(loan.collateral_asset, sender.clone(), loan.collateral)?;
+            Self::_settle_loan(&loan, OrderStatus::Repaid);
+
+            let earned = interest - insurance;
+            Self::_record_history(&sender, HistoryKind::Repaid, order_id, Some(loan.lender.clone()), loan.asset, loan.principal);
+            Self::_record_history(&loan.lender, HistoryKind::Repaid, order_id, Some(sender.clone()), loan.asset, loan.principal);
+            if !interest.is_zero() {
+                Self::_record_history(&sender, HistoryKind::InterestPaid, order_id, Some(loan.lender.clone()), loan.asset, interest);
+            }
+            if !earned.is_zero() {
+                Self::_record_history(&loan.lender, HistoryKind::InterestEarned, order_id, Some(sender.clone()), loan.asset, earned);
+            }
+            if !insurance.is_zero() {
+                Self::deposit_event(RawEvent::InsuranceFeeCollected(order_id, loan.asset, insurance));
+            }
+
+            Self::deposit_event(RawEvent::Repaid(order_id, sender, loan.lender, loan.asset, paid));
+
+            Ok(())
+        }
+
+        // 订单所有者在挂单期间设置提前还款费用，接单即表示对方接受
+        fn set_prepayment_fee(origin, order_id: T::Hash, fee: u32) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::SET_PREPAYMENT_FEE)?;
+
+            let (owner, status) = Self::_order_owner_and_status(order_id)?;
+            ensure!(sender == owner, "only owner can set the loan terms");
+            ensure!(status == OrderStatus::Open, "the loan terms can only change while the order is open");
+            ensure!(fee <= yiwan as u32, "the prepayment fee cannot exceed 100%");
+
+            <OrderTerms<T>>::mutate(order_id, |terms| terms.prepayment_fee = fee);
+
+            Self::deposit_event(RawEvent::PrepaymentFeeSet(order_id, fee));
+
+            Ok(())
+        }
+
+        // 借款人请求展期，覆盖之前尚未被接受的请求
+        fn request_extension(origin, order_id: T::Hash, blocks: u64, interest: u32) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::REQUEST_EXTENSION)?;
+
+            let loan = Self::_taken_loan(order_id)?;
+            ensure!(sender == loan.borrower, "only the borrower can request an extension");
+            ensure!(blocks > 0, "the extension must be at least one block");
+            ensure!(interest <= interest::MAX_ANNUAL_RATE, "the interest cannot exceed 1000% a year");
+
+            <OrderTerms<T>>::mutate(order_id, |terms| terms.extension = Some(Extension { blocks, interest }));
+
+            Self::deposit_event(RawEvent::ExtensionRequested(order_id, blocks, interest));
+
+            Ok(())
+        }
+
+        // 出借人接受展期：之前的利息按原利率累计，之后按新的年利率计息，到期区块延后
+        fn accept_extension(origin, order_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::ACCEPT_EXTENSION)?;
+
+            let loan = Self::_taken_loan(order_id)?;
+            ensure!(sender == loan.lender, "only the lender can accept an extension");
+            let mut terms = Self::loan_terms(order_id);
+            let extension = terms.extension.take().ok_or("no extension was requested")?;
+            let maturity = Self::_maturity(&loan).checked_add(&T::BlockNumber::sa(extension.blocks))
+                .ok_or("overflow in calculating the maturity")?;
+            terms.maturity = Some(maturity);
+
+            Self::_reprice_loan(&loan, extension.interest);
+            <OrderTerms<T>>::insert(order_id, terms);
+
+            Self::deposit_event(RawEvent::ExtensionAccepted(order_id, extension.blocks, extension.interest, maturity));
+
+            Ok(())
+        }
+
+        // 订单所有者在挂单期间，或借贷双方在借贷期间选择是否在到期时续期
+        fn set_rollover(origin, order_id: T::Hash, opt_in: bool) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::SET_ROLLOVER)?;
+
+            let (owner, status) = Self::_order_owner_and_status(order_id)?;
+            let mut terms = Self::loan_terms(order_id);
+            if status == OrderStatus::Open {
+                ensure!(sender == owner, "only owner can set the loan terms");
+                if <BorrowOrderDetail<T>>::exists(order_id) {
+                    terms.borrower_rollover = opt_in;
+                } else {
+                    terms.lender_rollover = opt_in;
+                }
+            } else {
+                let loan = Self::_taken_loan(order_id)?;
+                ensure!(sender == loan.borrower || sender == loan.lender, "only the borrower or the lender can set the rollover");
+                if sender == loan.borrower {
+                    terms.borrower_rollover = opt_in;
+                }
+                if sender == loan.lender {
+                    terms.lender_rollover = opt_in;
+                }
+            }
+
+            <OrderTerms<T>>::insert(order_id, terms);
+
+            Self::deposit_event(RawEvent::RolloverSet(order_id, sender, opt_in));
+
+            Ok(())
+        }
+
+        // 到期时双方都同意续期的借贷，任何人都可以续期一个 duration：利息继续累计，抵押率须满足 max_ltv
+        fn roll_over(origin, order_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::ROLL_OVER)?;
+
+            let loan = Self::_taken_loan(order_id)?;
+            let mut terms = Self::loan_terms(order_id);
+            ensure!(terms.borrower_rollover && terms.lender_rollover, "both parties must opt in to the rollover");
+            let now = <system::Module<T>>::block_number();
+            ensure!(now >= Self::_maturity(&loan), "the loan has not matured");
+            Self::_check_ltv(loan.asset, loan.debt, loan.collateral_asset, loan.collateral)?;
+            let maturity = now.checked_add(&T::BlockNumber::sa(loan.duration))
+                .ok_or("overflow in calculating the maturity")?;
+
+            terms.maturity = Some(maturity);
+            <OrderTerms<T>>::insert(order_id, terms);
+
+            Self::deposit_event(RawEvent::RolledOver(order_id, maturity));
+
+            Ok(())
+        }
+
+        // 借款人在借贷期间追加抵押物，避免被清算
+        fn add_collateral(origin, order_id: T::Hash, amount: T::TokenBalance) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::ADD_COLLATERAL)?;
+
+            let loan = Self::_taken_loan(order_id)?;
+            ensure!(sender == loan.borrower, "only the borrower can change the collateral");
+            ensure!(!amount.is_zero(), "the amount should be bigger than 0");
+            let collateral = loan.collateral.checked_add(&amount).ok_or("overflow in calculating the loan collateral")?;
+
+            Self::_reserve(loan.collateral_asset, sender, amount)?;
+            Self::_set_collateral(&loan, loan.collateral_asset, collateral);
+
+            Self::deposit_event(RawEvent::CollateralAdded(order_id, loan.collateral_asset, amount, collateral));
+
+            Ok(())
+        }
+
+        // 借款人取回部分抵押物，剩余的抵押物按当前价格仍须满足 max_ltv 和出借人要求的抵押率
+        fn withdraw_collateral(origin, order_id: T::Hash, amount: T::TokenBalance) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::WITHDRAW_COLLATERAL)?;
+
+            let loan = Self::_taken_loan(order_id)?;
+            ensure!(sender == loan.borrower, "only the borrower can change the collateral");
+            ensure!(!amount.is_zero(), "the amount should be bigger than 0");
+            let collateral = loan.collateral.checked_sub(&amount).ok_or("not enough collateral to withdraw")?;
+            Self::_check_loan_collateral(&loan, loan.collateral_asset, collateral)?;
+
+            Self::_unreserve(loan.collateral_asset, sender, amount)?;
+            Self::_set_collateral(&loan, loan.collateral_asset, collateral);
+
+            Self::deposit_event(RawEvent::CollateralWithdrawn(order_id, loan.collateral_asset, amount, collateral));
+
+            Ok(())
+        }
+
+        // 借款人一次性把抵押物换成另一种币种：冻结新的抵押物，解冻原来的抵押物
+        fn substitute_collateral(origin, order_id: T::Hash, collateral_asset: T::AssetId, collateral: T::TokenBalance) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::SUBSTITUTE_COLLATERAL)?;
+
+            let loan = Self::_taken_loan(order_id)?;
+            ensure!(sender == loan.borrower, "only the borrower can change the collateral");
+            ensure!(collateral_asset != loan.collateral_asset, "the loan already uses this collateral");
+            ensure!(<Tokens<T>>::exists(collateral_asset), "the token does not exist");
+            ensure!(Self::allow_asset(collateral_asset), "the supply asset is not allowed");
+            ensure!(<TokenPrice<T>>::exists(collateral_asset), "the stoken price does not exist");
+            if !loan.is_borrow_order {
+                ensure!(Self::supply_order_detail(order_id).tokens.contains(&collateral_asset), "the supply order does not support this token");
+            }
+            Self::_check_loan_collateral(&loan, collateral_asset, collateral)?;
+
+            Self::_reserve(collateral_asset, sender.clone(), collateral)?;
+            Self::_unreserve(loan.collateral_asset, sender, loan.collateral)?;
+            Self::_set_collateral(&loan, collateral_asset, collateral);
+
+            Self::deposit_event(RawEvent::CollateralSubstituted(order_id, loan.collateral_asset, loan.collateral, collateral_asset, collateral));
+
+            Ok(())
+        }
+
+        // 债权持有人把债权转给别人，之后的还款和清算所得都归新的持有人
+        fn transfer_loan_claim(origin, order_id: T::Hash, to: T::AccountId) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::TRANSFER_LOAN_CLAIM)?;
+
+            let loan = Self::_taken_loan(order_id)?;
+            ensure!(sender == loan.lender, "only the claim holder can transfer the claim");
+            ensure!(to != loan.borrower, "the borrower cannot hold the claim of the loan");
+
+            Self::_set_claim_holder(order_id, &to);
+
+            Self::deposit_event(RawEvent::LoanClaimTransferred(order_id, sender, to));
+
+            Ok(())
+        }
+
+        // 债权持有人按任意允许的币种挂单出售债权，覆盖之前的卖单
+        fn list_loan_claim(origin, order_id: T::Hash, price_asset: T::AssetId, price: T::TokenBalance) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::LIST_LOAN_CLAIM)?;
+
+            let loan = Self::_taken_loan(order_id)?;
+            ensure!(sender == loan.lender, "only the claim holder can list the claim");
+            ensure!(<Tokens<T>>::exists(price_asset), "the token does not exist");
+            ensure!(Self::allow_asset(price_asset), "the price asset is not allowed");
+
+            <ClaimListings<T>>::insert(order_id, ClaimListing { price_asset, price });
+
+            Self::deposit_event(RawEvent::LoanClaimListed(order_id, sender, price_asset, price));
+
+            Ok(())
+        }
+
+        fn unlist_loan_claim(origin, order_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::UNLIST_LOAN_CLAIM)?;
+
+            let loan = Self::_taken_loan(order_id)?;
+            ensure!(sender == loan.lender, "only the claim holder can unlist the claim");
+            ensure!(<ClaimListings<T>>::exists(order_id), "the claim is not listed");
+
+            <ClaimListings<T>>::remove(order_id);
+
+            Self::deposit_event(RawEvent::LoanClaimUnlisted(order_id));
+
+            Ok(())
+        }
+
+        // 按挂单价格买下债权：付款和债权转移在同一个调用中完成。价格和币种须与调用者看到的一致，防止卖方抢先改价。
+        fn buy_loan_claim(origin, order_id: T::Hash, price_asset: T::AssetId, price: T::TokenBalance) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::BUY_LOAN_CLAIM)?;
+
+            let loan = Self::_taken_loan(order_id)?;
+            let listing = Self::claim_listing(order_id).ok_or("the claim is not listed")?;
+            ensure!(listing == ClaimListing { price_asset, price }, "the listing price has changed");
+            ensure!(sender != loan.lender, "the buyer already holds the claim");
+            ensure!(sender != loan.borrower, "the borrower cannot hold the claim of the loan");
+
+            Self::_transfer(price_asset, sender.clone(), loan.lender.clone(), price)?;
+            Self::_set_claim_holder(order_id, &sender);
+
+            Self::_record_history(&loan.lender, HistoryKind::ClaimSold, order_id, Some(sender.clone()), price_asset, price);
+            Self::_record_history(&sender, HistoryKind::ClaimBought, order_id, Some(loan.lender.clone()), price_asset, price);
+
+            Self::deposit_event(RawEvent::LoanClaimSold(order_id, loan.lender, sender, price_asset, price));
+
+            Ok(())
+        }
+
+        // 到期没有还款也没有续期的借贷，债权持有人可以像清算一样拿走价值为 本息 + 清算奖励 的抵押物，其余退还借款人
+        fn claim_default(origin, order_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::CLAIM_DEFAULT)?;
+
+            let loan = Self::_taken_loan(order_id)?;
+            ensure!(sender == loan.lender, "only the claim holder can claim a default");
+            ensure!(<system::Module<T>>::block_number() >= Self::_maturity(&loan), "the loan has not matured");
+            let bonus = Self::risk_params(loan.collateral_asset).liquidation_bonus;
+            let seized = Self::_seized_collateral(loan.collateral_asset, loan.collateral, Self::_value(loan.asset, loan.debt), bonus);
+
+            Self::_unreserve(loan.collateral_asset, loan.borrower.clone(), loan.collateral)?;
+            Self::_transfer(loan.collateral_asset, loan.borrower.clone(), sender.clone(), seized)?;
+            Self::_settle_loan(&loan, OrderStatus::Defaulted);
+            Self::_cover_shortfall(loan.asset, loan.debt, loan.collateral_asset, seized, &sender)?;
+
+            Self::_record_history(&loan.borrower, HistoryKind::Defaulted, order_id, Some(sender.clone()), loan.collateral_asset, seized);
+            Self::_record_history(&sender, HistoryKind::Defaulted, order_id, Some(loan.borrower.clone()), loan.asset, loan.debt);
+
+            Self::deposit_event(RawEvent::Defaulted(order_id, sender, loan.borrower, loan.collateral_asset, seized));
+
+            Ok(())
+        }
+
+        fn set_insurance_fee_share(origin, share: u32) -> Result {
+            Self::_ensure_admin(origin, &weights::SET_INSURANCE_FEE_SHARE, "only Admin can set the insurance fee share")?;
+            ensure!(share <= yiwan as u32, "the insurance fee share cannot exceed 100%");
+
+            <InsuranceFeeShare<T>>::put(share);
+
+            Self::deposit_event(RawEvent::InsuranceFeeShareSet(share));
+
+            Ok(())
+        }
+
+        // 任何人都可以向某个币种的保险基金捐款，存入后不能取回
+        fn deposit_insurance(origin, token_id: T::AssetId, amount: T::TokenBalance) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::DEPOSIT_INSURANCE)?;
+
+            ensure!(<Tokens<T>>::exists(token_id), "the token does not exist");
+            ensure!(!amount.is_zero(), "the amount should be bigger than 0");
+            let fund = Self::insurance_fund(token_id).checked_add(&amount)
+                .ok_or("overflow in calculating the insurance fund")?;
+
+            Self::_take(token_id, &sender, amount)?;
+            <InsuranceFunds<T>>::insert(token_id, fund);
+
+            Self::deposit_event(RawEvent::InsuranceDeposited(token_id, sender, amount));
+
+            Ok(())
+        }
+
+        fn set_flash_loan_fee(origin, fee: u32) -> Result {
+            Self::_ensure_admin(origin, &weights::SET_FLASH_LOAN_FEE, "only Admin can set the flash loan fee")?;
+            ensure!(fee <= yiwan as u32, "the flash loan fee cannot exceed 100%");
+
+            <FlashLoanFee<T>>::put(fee);
+
+            Self::deposit_event(RawEvent::FlashLoanFeeSet(fee));
+
+            Ok(())
+        }
+
+        // 从资金池借出 amount，以借款人的身份依次执行 calls，结束时从借款人那里收回 amount 加费用。
+        // 内部调用失败或者没有还清时撤销借出和内部调用的写入并返回错误，已经收取的手续费不退，见 journal。
+        fn flash_loan(origin, token_id: T::AssetId, amount: T::TokenBalance, calls: Vec<Call<T>>) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::flash_loan(calls.len()))?;
+
+            let pool = Self::_accrued_pool(token_id).ok_or("the pool does not exist")?;
+            ensure!(!amount.is_zero(), "the amount should be bigger than 0");
+            ensure!(amount <= pool.cash, "not enough cash in the pool");
+            ensure!(calls.len() <= weights::MAX_FLASH_LOAN_CALLS, "too many calls in the flash loan");
+            ensure!(calls.iter().all(journal::allowed), "the call is not allowed in a flash loan");
+            let fee = (Self::_wide(amount) * u128::from(Self::flash_loan_fee()) + u128::from(yiwan) - 1) / u128::from(yiwan);
+            let fee = T::TokenBalance::from(fee as u64);
+            let repayment = amount.checked_add(&fee).ok_or("overflow in calculating the flash loan repayment")?;
+
+            journal::start::<T>();
+            let result = Self::_run_flash_loan(&sender, token_id, amount, fee, repayment, calls);
+            journal::finish::<T>(result.is_err());
+            result?;
+            Self::_record_history(&sender, HistoryKind::FlashLoan, T::Hash::default(), None, token_id, amount);
+
+            Self::deposit_event(RawEvent::FlashLoan(token_id, sender, amount, fee));
+
+            Ok(())
+        }
+
+        // 设置治理币种和参数，只影响之后的提案。asset 为 None 时停止接受新提案。
+        fn set_governance(origin, asset: Option<T::AssetId>, params: GovernanceParams<T::BlockNumber>) -> Result {
+            Self::_ensure_admin(origin, &weights::SET_GOVERNANCE, "only Admin can set the governance")?;
+            if let Some(asset) = asset {
+                ensure!(<Tokens<T>>::exists(asset), "the token does not exist");
+                Self::_check_governance_params(&params)?;
+            }
+
+            <GovernanceAsset<T>>::put(asset);
+            <GovernanceParameters<T>>::put(params);
+
+            Self::deposit_event(RawEvent::GovernanceSet(
+                asset, params.voting_period, params.enactment_delay, params.quorum, params.threshold,
+            ));
+
+            Ok(())
+        }
+
+        // 设置过渡期的结束区块，之后 Admin 账户只能通过治理提案调用特权调用
+        fn set_legacy_admin_until(origin, until: Option<T::BlockNumber>) -> Result {
+            Self::_ensure_admin(origin, &weights::SET_LEGACY_ADMIN_UNTIL, "only Admin can set the transition period")?;
+
+            <LegacyAdminUntil<T>>::put(until);
+
+            Self::deposit_event(RawEvent::LegacyAdminUntilSet(until));
+
+            Ok(())
+        }
+
+        // 设置之后的提案要冻结的押金
+        fn set_proposal_deposit(origin, deposit: T::TokenBalance) -> Result {
+            Self::_ensure_admin(origin, &weights::SET_PROPOSAL_DEPOSIT, "only Admin can set the proposal deposit")?;
+
+            <ProposalDeposit<T>>::put(deposit);
+
+            Self::deposit_event(RawEvent::ProposalDepositSet(deposit));
+
+            Ok(())
+        }
+
+        // 持有治理币种的账户冻结押金后可以提案，通过后 call 以治理的身份执行
+        fn propose(origin, call: Box<Call<T>>) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::PROPOSE)?;
+
+            let asset = Self::governance_asset().ok_or("governance is not enabled")?;
+            ensure!(!Self::balance_of((asset, sender.clone())).is_zero(), "only holders of the governance asset can propose");
+            let id = Self::proposal_count();
+            let count = id.checked_add(1).ok_or("overflow in calculating the proposal count")?;
+            let params = Self::governance_params();
+            let now = <system::Module<T>>::block_number();
+            let voting_ends = now.checked_add(&params.voting_period).ok_or("overflow in calculating the voting period")?;
+            let deposit = Self::proposal_deposit();
+
+            if !deposit.is_zero() {
+                Self::_reserve(asset, sender.clone(), deposit)?;
+                <ProposalDeposits<T>>::insert(id, deposit);
+            }
+            <Proposals<T>>::insert(id, GovernanceProposal {
+                proposer: sender.clone(),
+                call: *call,
+                asset,
+                params,
+                created_at: now,
+                ayes: Zero::zero(),
+                nays: Zero::zero(),
+                status: ProposalStatus::Voting,
+            });
+            <ProposalCount<T>>::put(count);
+
+            Self::deposit_event(RawEvent::Proposed(id, sender, voting_ends));
+
+            Ok(())
+        }
+
+        // 按冻结的治理币种数量投票，每个账户在每个提案上只能投一次
+        fn vote(origin, proposal_id: u32, aye: bool, balance: T::TokenBalance) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::VOTE)?;
+
+            let mut proposal = Self::proposal(proposal_id).ok_or("the proposal does not exist")?;
+            let voting_ends = proposal.created_at.saturating_add(proposal.params.voting_period);
+            ensure!(
+                proposal.status == ProposalStatus::Voting && <system::Module<T>>::block_number() < voting_ends,
+                "the voting has ended"
+            );
+            ensure!(!<ProposalVotes<T>>::exists((proposal_id, sender.clone())), "already voted on the proposal");
+            ensure!(!balance.is_zero(), "the amount should be bigger than 0");
+            if aye {
+                proposal.ayes = proposal.ayes.checked_add(&balance).ok_or("overflow in calculating the votes")?;
+            } else {
+                proposal.nays = proposal.nays.checked_add(&balance).ok_or("overflow in calculating the votes")?;
+            }
+
+            Self::_reserve(proposal.asset, sender.clone(), balance)?;
+            <Proposals<T>>::insert(proposal_id, proposal);
+            <ProposalVotes<T>>::insert((proposal_id, sender.clone()), GovernanceVote { aye, balance });
+
+            Self::deposit_event(RawEvent::Voted(proposal_id, sender, aye, balance));
+
+            Ok(())
+        }
+
+        // 投票期结束后解冻投票的治理币种
+        fn unlock_vote(origin, proposal_id: u32) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::UNLOCK_VOTE)?;
+
+            let vote = Self::proposal_vote((proposal_id, sender.clone())).ok_or("no vote to unlock")?;
+            let proposal = Self::proposal(proposal_id).ok_or("the proposal does not exist")?;
+            let voting_ends = proposal.created_at.saturating_add(proposal.params.voting_period);
+            ensure!(
+                proposal.status != ProposalStatus::Voting || <system::Module<T>>::block_number() >= voting_ends,
+                "the proposal is still being voted on"
+            );
+
+            Self::_unreserve(proposal.asset, sender.clone(), vote.balance)?;
+            <ProposalVotes<T>>::remove((proposal_id, sender.clone()));
+
+            Self::deposit_event(RawEvent::VoteUnlocked(proposal_id, sender, vote.balance));
+
+            Ok(())
+        }
+
+        // 任何人都可以在投票期结束后结算提案：没有通过的标记为否决，通过的在执行延迟之后以治理的身份执行。
+        // 两种情况都解冻提案人的押金。
+        fn enact_proposal(origin, proposal_id: u32) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::ENACT_PROPOSAL)?;
+
+            let mut proposal = Self::proposal(proposal_id).ok_or("the proposal does not exist")?;
+            ensure!(proposal.status == ProposalStatus::Voting, "the proposal is already closed");
+            let now = <system::Module<T>>::block_number();
+            let voting_ends = proposal.created_at.saturating_add(proposal.params.voting_period);
+            ensure!(now >= voting_ends, "the voting has not ended");
+
+            if !Self::_proposal_passed(&proposal) {
+                Self::_release_proposal_deposit(proposal_id, &proposal)?;
+                proposal.status = ProposalStatus::Rejected;
+                <Proposals<T>>::insert(proposal_id, proposal);
+                Self::deposit_event(RawEvent::ProposalRejected(proposal_id));
+                return Ok(());
+            }
+            ensure!(now >= voting_ends.saturating_add(proposal.params.enactment_delay), "the enactment delay has not passed");
+
+            // 先关闭提案再执行，执行失败时提案也不能再次执行
+            Self::_release_proposal_deposit(proposal_id, &proposal)?;
+            let call = proposal.call.clone();
+            proposal.status = ProposalStatus::Enacted;
+            <Proposals<T>>::insert(proposal_id, proposal);
+            let result = Self::_dispatch_as(AdminOrigin::Governance, call);
+
+            Self::deposit_event(RawEvent::ProposalEnacted(proposal_id, result.is_ok()));
+
+            Ok(())
+        }
+
+        // 更换多签管理员的签名人和门槛，正在等待批准的提案全部作废。只能由多签提案本身或者治理提案执行，
+        // Admin 账户和 sudo 都不能绕过多签。
+        fn set_admin_signatories(origin, signatories: Vec<T::AccountId>, threshold: u32) -> Result {
+            let error = "only the admin multisig or governance can set the admin signatories";
+            let origin = Self::_admin_origin(origin, &weights::SET_ADMIN_SIGNATORIES, error)?;
+            ensure!(origin == AdminOrigin::AdminMultisig || origin == AdminOrigin::Governance, error);
+            ensure!(!signatories.is_empty(), "the admin multisig needs at least one signatory");
+            Self::_check_admin_signatories(&signatories, threshold)?;
+
+            for id in Self::pending_admin_proposals() {
+                <AdminProposals<T>>::mutate(id, |proposal| {
+                    if let Some(proposal) = proposal {
+                        proposal.status = AdminProposalStatus::Cancelled;
+                    }
+                });
+                Self::deposit_event(RawEvent::AdminCallCancelled(id));
+            }
+            <PendingAdminProposals<T>>::kill();
+            <AdminSignatories<T>>::put(signatories.clone());
+            <AdminThreshold<T>>::put(threshold);
+
+            Self::deposit_event(RawEvent::AdminSignatoriesSet(signatories, threshold));
+
+            Ok(())
+        }
+
+        // 签名人提出特权调用，提案人自动批准
+        fn propose_admin_call(origin, call: Box<Call<T>>) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::PROPOSE_ADMIN_CALL)?;
+
+            ensure!(Self::admin_signatories().contains(&sender), "only an admin signatory can propose");
+            let mut pending = Self::pending_admin_proposals();
+            ensure!(pending.len() < weights::MAX_PENDING_ADMIN_PROPOSALS, "too many pending admin proposals");
+            let id = Self::admin_proposal_count();
+            let count = id.checked_add(1).ok_or("overflow in calculating the admin proposal count")?;
+
+            pending.push(id);
+            <PendingAdminProposals<T>>::put(pending);
+            <AdminProposalCount<T>>::put(count);
+            Self::deposit_event(RawEvent::AdminCallProposed(id, sender.clone()));
+
+            let proposal = AdminProposal {
+                proposer: sender.clone(),
+                call: *call,
+                approvals: vec![],
+                created_at: <system::Module<T>>::block_number(),
+                status: AdminProposalStatus::Pending,
+            };
+            Self::_approve_admin_call(id, proposal, sender);
+
+            Ok(())
+        }
+
+        fn approve_admin_call(origin, proposal_id: u32) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::APPROVE_ADMIN_CALL)?;
+
+            ensure!(Self::admin_signatories().contains(&sender), "only an admin signatory can approve");
+            let proposal = Self::admin_proposal(proposal_id).ok_or("the admin proposal does not exist")?;
+            ensure!(proposal.status == AdminProposalStatus::Pending, "the admin proposal is not pending");
+            ensure!(!proposal.approvals.contains(&sender), "already approved the admin proposal");
+
+            Self::_approve_admin_call(proposal_id, proposal, sender);
+
+            Ok(())
+        }
+
+        fn cancel_admin_call(origin, proposal_id: u32) -> Result {
+            let sender = ensure_signed(origin)?;
+            Self::charge_fee(&sender, &weights::CANCEL_ADMIN_CALL)?;
+
+            let mut proposal = Self::admin_proposal(proposal_id).ok_or("the admin proposal does not exist")?;
+            ensure!(proposal.status == AdminProposalStatus::Pending, "the admin proposal is not pending");
+            ensure!(proposal.proposer == sender, "only the proposer can cancel the admin proposal");
+
+            proposal.status = AdminProposalStatus::Cancelled;
+            <AdminProposals<T>>::insert(proposal_id, proposal);
+            <PendingAdminProposals<T>>::mutate(|pending| pending.retain(|&id| id != proposal_id));
+
+            Self::deposit_event(RawEvent::AdminCallCancelled(proposal_id));
+
+            Ok(())
+        }
+    }
+}
+
+impl<T: Trait> Module<T> {
+    /// Withdraw the fee of a call from `who` through the fee currency. The fee is kept even if
+    /// the call fails afterwards, just like the transaction fee of the balances module.
+    fn charge_fee(who: &T::AccountId, weight: &weights::Weight) -> Result {
+        Self::_ensure_migrated()?;
+        Self::_withdraw_fee(who, weight)
+    }
+
+    /// Check that `who` can pay the fee of `call` on top of the `payment` already taken for the
+    /// transaction and stay alive, so the transaction pool rejects calls that would fail to pay
+    /// before they take a place in a block.
+    pub fn can_pay(who: &T::AccountId, call: &Call<T>, payment: FeeBalanceOf<T>) -> Result {
+        Self::_ensure_migrated()?;
+        let fee = Self::_fee(&weights::of(call))?
+            .checked_add(&payment)
+            .ok_or("overflow in calculating call fee")?;
+        let free = T::Currency::free_balance(who);
+        ensure!(free >= fee && free - fee >= T::Currency::minimum_balance(), "not enough balance to pay the call fee");
+        Ok(())
+    }
+
+    fn _fee(weight: &weights::Weight) -> rstd::result::Result<FeeBalanceOf<T>, &'static str> {
+        Self::fee_per_weight().checked_mul(&FeeBalanceOf::<T>::sa(weight.total()))
+            .ok_or("overflow in calculating call fee")
+    }
+
+    // 迁移没有完成时币种和订单还不能读取，所有调用都要等待迁移完成
+    fn _ensure_migrated() -> Result {
+        ensure!(Self::storage_version() >= CURRENT_STORAGE_VERSION, "storage migration in progress");
+        Ok(())
+    }
+
+    fn _withdraw_fee(who: &T::AccountId, weight: &weights::Weight) -> Result {
+        let fee = Self::_fee(weight)?;
+        if fee.is_zero() {
+            return Ok(());
+        }
+
+        let imbalance = T::Currency::withdraw(
+            who,
+            fee,
+            WithdrawReason::TransactionPayment,
+            ExistenceRequirement::KeepAlive,
+        )?;
+        T::TransactionPayment::on_unbalanced(imbalance);
+        Ok(())
+    }
+
+    // 还没有在新前缀下写入的余额和授权读取版本 0 的条目
+    pub fn balance_of<K: Borrow<(T::AssetId, T::AccountId)>>(key: K) -> T::TokenBalance {
+        let key = key.borrow();
+        if <BalanceOf<T>>::exists(key) {
+            <BalanceOf<T>>::get(key)
+        } else {
+            migration::legacy_map(b"BalanceOf", key).unwrap_or_default()
+        }
+    }
+
+    pub fn free_balance_of<K: Borrow<(T::AssetId, T::AccountId)>>(key: K) -> T::TokenBalance {
+        let key = key.borrow();
+        if <FreeBalanceOf<T>>::exists(key) {
+            <FreeBalanceOf<T>>::get(key)
+        } else {
+            migration::legacy_map(b"FreeBalanceOf", key).unwrap_or_default()
+        }
+    }
+
+    pub fn reserve_balance_of<K: Borrow<(T::AssetId, T::AccountId)>>(key: K) -> T::TokenBalance {
+        let key = key.borrow();
+        if <ReserveBalanceOf<T>>::exists(key) {
+            <ReserveBalanceOf<T>>::get(key)
+        } else {
+            migration::legacy_map(b"ReserveBalanceOf", key).unwrap_or_default()
+        }
+    }
+
+    pub fn allowance<K: Borrow<(T::AssetId, T::AccountId, T::AccountId)>>(key: K) -> T::TokenBalance {
+        let key = key.borrow();
+        if <Allowance<T>>::exists(key) {
+            <Allowance<T>>::get(key)
+        } else {
+            migration::legacy_map(b"Allowance", key).unwrap_or_default()
+        }
+    }
+
+    fn _owns_token<K: Borrow<(T::AssetId, T::AccountId)>>(key: K) -> bool {
+        let key = key.borrow();
+        <BalanceOf<T>>::exists(key) || migration::legacy_map::<_, T::TokenBalance>(b"BalanceOf", key).is_some()
+    }
+
+    fn _allowance_exists<K: Borrow<(T::AssetId, T::AccountId, T::AccountId)>>(key: K) -> bool {
+        let key = key.borrow();
+        <Allowance<T>>::exists(key) || migration::legacy_map::<_, T::TokenBalance>(b"Allowance", key).is_some()
+    }
+
+    // 闪电贷执行期间，写入之前记下存储项原来的值，见 journal
+    fn _journal<S: StorageMap<K, V>, K: Codec, V: Codec>(key: &K) {
+        journal::keep::<T>(blake2_256(&S::key_for(key)).to_vec());
+    }
+
+    fn _journal_balance(key: &(T::AssetId, T::AccountId)) {
+        Self::_journal::<BalanceOf<T>, _, _>(key);
+        Self::_journal::<FreeBalanceOf<T>, _, _>(key);
+        Self::_journal::<ReserveBalanceOf<T>, _, _>(key);
+    }
+
+    // 第一次写入的授权先编号
+    fn _insert_allowance(key: (T::AssetId, T::AccountId, T::AccountId), amount: T::TokenBalance) {
+        if !<Allowance<T>>::exists(&key) {
+            let index = Self::allowance_count();
+            <AllowanceByIndex<T>>::insert(index, key.clone());
+            <AllowanceCount<T>>::put(index + 1);
+        }
+        <Allowance<T>>::insert(key, amount);
+    }
+
+    fn _check_risk_params(params: &RiskParams<T::TokenBalance>) -> Result {
+        ensure!(params.liquidation_threshold <= yiwan as u32, "the liquidation threshold cannot exceed 100%");
+        ensure!(params.max_ltv <= params.liquidation_threshold, "the max loan-to-value cannot exceed the liquidation threshold");
+        ensure!(params.liquidation_bonus <= yiwan as u32, "the liquidation bonus cannot exceed 100%");
+        Ok(())
+    }
+
+    fn _wide(amount: T::TokenBalance) -> u128 {
+        u128::from(<T::TokenBalance as As<u64>>::as_(amount))
+    }
+
+    // 数量 × 价格，用 u128 计算不会溢出
+    fn _value(token_id: T::AssetId, amount: T::TokenBalance) -> u128 {
+        Self::_wide(amount) * u128::from(Self::token_price(token_id))
+    }
+
+    // 借款价值不能超过抵押物价值的 max_ltv
+    fn _check_ltv(token_id: T::AssetId, amount: T::TokenBalance, collateral_id: T::AssetId, collateral: T::TokenBalance) -> Result {
+        let max_ltv = Self::risk_params(collateral_id).max_ltv;
+        ensure!(
+            Self::_value(token_id, amount) * u128::from(yiwan) <= Self::_value(collateral_id, collateral) * u128::from(max_ltv),
+            "the loan exceeds the max loan-to-value of the collateral"
+        );
+        Ok(())
+    }
+
+    // 订单和资金池的借出总量加上 amount 不能超过 borrow_cap，返回加上 amount 之后订单的借出总量
+    fn _check_borrow_cap(token_id: T::AssetId, amount: T::TokenBalance) -> rstd::result::Result<T::TokenBalance, &'static str> {
+        let cap = Self::risk_params(token_id).borrow_cap;
+        let total = Self::total_borrowed(token_id).checked_add(&amount)
+            .ok_or("overflow in calculating the total borrowed")?;
+        let pooled = Self::_accrued_pool(token_id).map_or(0, |pool| Self::_wide(pool.borrowed()));
+        ensure!(cap.is_zero() || Self::_wide(total) + pooled <= Self::_wide(cap), "the borrow cap of the asset is reached");
+        Ok(total)
+    }
+
+    // 订单和资金池的提供总量加上 amount 不能超过 supply_cap，返回加上 amount 之后订单的提供总量
+    fn _check_supply_cap(token_id: T::AssetId, amount: T::TokenBalance) -> rstd::result::Result<T::TokenBalance, &'static str> {
+        let cap = Self::risk_params(token_id).supply_cap;
+        let total = Self::total_supplied(token_id).checked_add(&amount)
+            .ok_or("overflow in calculating the total supplied")?;
+        let pooled = Self::_accrued_pool(token_id).map_or(0, |pool| Self::_wide(pool.cash) + Self::_wide(pool.borrowed()));
+        ensure!(cap.is_zero() || Self::_wide(total) + pooled <= Self::_wide(cap), "the supply cap of the asset is reached");
+        Ok(total)
+    }
+
+    // 本金按订单的累计利率指数计算到当前区块的债务
+    fn _order_debt(order_id: T::Hash, principal: T::TokenBalance) -> T::TokenBalance {
+        match Self::loan_index(order_id) {
+            Some(index) => {
+                let index = index.at(<system::Module<T>>::block_number());
+                T::TokenBalance::from(interest::grow(<T::TokenBalance as As<u64>>::as_(principal), index))
+            }
+            None => principal,
+        }
+    }
+
+    fn _taken_loan(order_id: T::Hash) -> rstd::result::Result<Loan<T>, &'static str> {
+        if <BorrowOrderDetail<T>>::exists(order_id) {
+            let order = Self::borrow_order_detail(order_id);
+            ensure!(order.status == OrderStatus::Taken, "the order is not a running loan");
+            return Ok(Loan {
+                id: order_id,
+                is_borrow_order: true,
+                lender: Self::loan_claim(order_id).or(order.taker).ok_or("the order is not a running loan")?,
+                borrower: order.owner,
+                asset: order.btoken_id,
+                principal: order.btotal,
+                debt: Self::_order_debt(order_id, order.btotal),
+                collateral_asset: order.stoken_id,
+                collateral: order.stotal,
+                interest: order.interest,
+                duration: order.duration,
+                taken_at: order.updated_at,
+            });
+        }
+
+        ensure!(<SupplyOrderDetail<T>>::exists(order_id), "the order does not exist");
+        let order = Self::supply_order_detail(order_id);
+        ensure!(order.status == OrderStatus::Taken, "the order is not a running loan");
+        Ok(Loan {
+            id: order_id,
+            is_borrow_order: false,
+            lender: Self::loan_claim(order_id).unwrap_or(order.owner),
+            borrower: order.taker.ok_or("the order is not a running loan")?,
+            asset: order.stoken,
+            principal: order.total,
+            debt: Self::_order_debt(order_id, order.total),
+            collateral_asset: order.btoken,
+            collateral: order.btotal,
+            interest: order.interest,
+            duration: order.duration,
+            taken_at: order.updated_at,
+        })
+    }
+
+    // 价值为 债务 × (1 + bonus) 的抵押物，向上取整，最多为全部抵押物；抵押币种没有价格时全部拿走
+    fn _seized_collateral(collateral_asset: T::AssetId, collateral: T::TokenBalance, debt_value: u128, bonus: u32) -> T::TokenBalance {
+        let price = u128::from(Self::token_price(collateral_asset));
+        if price == 0 {
+            return collateral;
+        }
+        let claim = debt_value * (u128::from(yiwan) + u128::from(bonus)) / u128::from(yiwan);
+        let all = Self::_wide(collateral);
+        T::TokenBalance::from(cmp::min((claim + price - 1) / price, all) as u64)
+    }
+
+    // 借款价值超过抵押物价值的 liquidation_threshold 时才能清算，返回清算人获得的抵押物数量
+    fn _liquidation_seizure(
+        token_id: T::AssetId,
+        debt: T::TokenBalance,
+        collateral_asset: T::AssetId,
+        collateral: T::TokenBalance,
+    ) -> rstd::result::Result<T::TokenBalance, &'static str> {
+        let params = Self::risk_params(collateral_asset);
+        let debt_value = Self::_value(token_id, debt);
+        ensure!(
+            debt_value * u128::from(yiwan) > Self::_value(collateral_asset, collateral) * u128::from(params.liquidation_threshold),
+            "the loan is not below the liquidation threshold"
+        );
+        Ok(Self::_seized_collateral(collateral_asset, collateral, debt_value, params.liquidation_bonus))
+    }
+
+    fn _check_rate_model(model: &RateModel) -> Result {
+        ensure!(model.kink > 0 && model.kink <= yiwan as u32, "the kink must be between 0 and 100%");
+        Ok(())
+    }
+
+    fn _check_governance_params(params: &GovernanceParams<T::BlockNumber>) -> Result {
+        ensure!(!params.voting_period.is_zero(), "the voting period must be at least one block");
+        ensure!(params.quorum <= yiwan as u32, "the quorum cannot exceed 100%");
+        ensure!(params.threshold < yiwan as u32, "the threshold must be below 100%");
+        Ok(())
+    }
+
+    fn _check_admin_signatories(signatories: &[T::AccountId], threshold: u32) -> Result {
+        ensure!(signatories.len() <= weights::MAX_ADMIN_SIGNATORIES, "too many admin signatories");
+        for (i, who) in signatories.iter().enumerate() {
+            ensure!(!signatories[..i].contains(who), "duplicate admin signatories");
+        }
+        // 没有签名人时多签不启用，门槛也必须为 0
+        ensure!(
+            threshold as usize <= signatories.len() && (threshold > 0 || signatories.is_empty()),
+            "the threshold must be between 1 and the number of signatories"
+        );
+        Ok(())
+    }
+
+    // 记录 who 的批准，当前签名人中批准的人数达到门槛时关闭提案并以多签的身份执行
+    fn _approve_admin_call(id: u32, mut proposal: AdminProposal<T::AccountId, Call<T>, T::BlockNumber>, who: T::AccountId) {
+        proposal.approvals.push(who.clone());
+        let signatories = Self::admin_signatories();
+        let approvals = proposal.approvals.iter().filter(|a| signatories.contains(a)).count() as u32;
+        Self::deposit_event(RawEvent::AdminCallApproved(id, who, approvals));
+        if approvals < Self::admin_threshold() {
+            <AdminProposals<T>>::insert(id, proposal);
+            return;
+        }
+
+        // 先关闭提案再执行，执行失败时提案也不能再次执行
+        let call = proposal.call.clone();
+        proposal.status = AdminProposalStatus::Executed;
+        <AdminProposals<T>>::insert(id, proposal);
+        <PendingAdminProposals<T>>::mutate(|pending| pending.retain(|&pending_id| pending_id != id));
+        let result = Self::_dispatch_as(AdminOrigin::AdminMultisig, call);
+
+        Self::deposit_event(RawEvent::AdminCallExecuted(id, result.is_ok()));
+    }
+
+    /// Check that `origin` may make a privileged call: root, which pays no fee, or the legacy
+    /// admin until the transition period ends.
+    fn _ensure_admin(origin: T::Origin, weight: &weights::Weight, error: &'static str) -> Result {
+        Self::_admin_origin(origin, weight, error).map(|_| ())
+    }
+
+    /// Like `_ensure_admin`, returning where the privileged call comes from. Passed governance
+    /// proposals and the admin multisig dispatch as root too, and are told apart from other root
+    /// calls by `DispatchingAs`.
+    fn _admin_origin(origin: T::Origin, weight: &weights::Weight, error: &'static str) -> rstd::result::Result<AdminOrigin, &'static str> {
+        let origin: Option<system::RawOrigin<T::AccountId>> = origin.into();
+        match origin {
+            Some(system::RawOrigin::Root) => {
+                Self::_ensure_migrated()?;
+                Ok(Self::dispatching_as().unwrap_or(AdminOrigin::Root))
+            }
+            Some(system::RawOrigin::Signed(who)) => {
+                Self::charge_fee(&who, weight)?;
+                ensure!(who == Self::admin(), error);
+                let open = Self::legacy_admin_until().map_or(true, |until| <system::Module<T>>::block_number() < until);
+                ensure!(open, "the legacy admin can no longer make privileged calls");
+                Ok(AdminOrigin::LegacyAdmin)
+            }
+            _ => Err("bad origin: expected a signed or root origin"),
+        }
+    }
+
+    // 以 Root 身份执行治理提案或多签调用，执行期间记下来源
+    fn _dispatch_as(origin: AdminOrigin, call: Call<T>) -> Result {
+        let outer = <DispatchingAs<T>>::get();
+        <DispatchingAs<T>>::put(origin);
+        let result = call.dispatch(system::RawOrigin::Root.into());
+        match outer {
+            Some(outer) => <DispatchingAs<T>>::put(outer),
+            None => <DispatchingAs<T>>::kill(),
+        }
+        result
+    }
+
+    // 提案结束时解冻提案人的押金
+    fn _release_proposal_deposit(
+        proposal_id: u32,
+        proposal: &GovernanceProposal<T::AccountId, Call<T>, T::AssetId, T::BlockNumber, T::TokenBalance>,
+    ) -> Result {
+        let deposit = Self::proposal_deposit_of(proposal_id);
+        if !deposit.is_zero() {
+            Self::_unreserve(proposal.asset, proposal.proposer.clone(), deposit)?;
+            <ProposalDeposits<T>>::remove(proposal_id);
+        }
+        Ok(())
+    }
+
+    // 投票期已经结束的提案是否通过
+    fn _proposal_passed(proposal: &GovernanceProposal<T::AccountId, Call<T>, T::AssetId, T::BlockNumber, T::TokenBalance>) -> bool {
+        let votes = Self::_wide(proposal.ayes) + Self::_wide(proposal.nays);
+        let supply = Self::_wide(Self::token_details(proposal.asset).total_supply);
+        votes * u128::from(yiwan) >= supply * u128::from(proposal.params.quorum)
+            && Self::_wide(proposal.ayes) * u128::from(yiwan) > votes * u128::from(proposal.params.threshold)
+    }
+
+    // 闪电贷的借出、内部调用和还款，出错时由调用者按 journal 撤销
+    fn _run_flash_loan(
+        sender: &T::AccountId,
+        token_id: T::AssetId,
+        amount: T::TokenBalance,
+        fee: T::TokenBalance,
+        repayment: T::TokenBalance,
+        calls: Vec<Call<T>>,
+    ) -> Result {
+        // 借出的资金在同一个交易内收回，期间资金池的 cash 保持不变，内部调用看到的份额价格不受借出的影响
+        Self::_give(token_id, sender, amount)?;
+
+        for call in calls {
+            call.dispatch(system::RawOrigin::Signed(sender.clone()).into())?;
+        }
+
+        // 内部调用可能存取过这个资金池，重新读取。费用中的一部分和利息一样归保险基金
+        let mut pool = Self::_accrued_pool(token_id).ok_or("the pool does not exist")?;
+        let insurance = Self::_insurance_part(fee);
+        pool.cash = pool.cash.checked_add(&(fee - insurance)).ok_or("overflow in calculating the pool cash")?;
+        let fund = Self::insurance_fund(token_id).checked_add(&insurance)
+            .ok_or("overflow in calculating the insurance fund")?;
+        Self::_take(token_id, sender, repayment)?;
+        Self::_journal::<InsuranceFunds<T>, _, _>(&token_id);
+        <InsuranceFunds<T>>::insert(token_id, fund);
+        Self::_put_pool(token_id, pool);
+        Ok(())
+    }
+
+    // 累计到当前区块的资金池，只在内存中，由调用者在成功后用 _put_pool 写回
+    fn _accrued_pool(token_id: T::AssetId) -> Option<Pool<T::AssetId, T::TokenBalance, T::BlockNumber>> {
+        Self::pool(token_id).map(|mut pool| {
+            pool.accrue(<system::Module<T>>::block_number(), Self::blocks_per_year(), Self::insurance_fee_share());
+            pool
+        })
+    }
+
+    // 写回资金池，池中的资金先把计提的 reserves 转入保险基金。保险基金和资金池的资金都是发行量的一部分，相加不会溢出。
+    fn _put_pool(token_id: T::AssetId, mut pool: Pool<T::AssetId, T::TokenBalance, T::BlockNumber>) {
+        let collected = cmp::min(pool.reserves, pool.cash);
+        if !collected.is_zero() {
+            pool.cash -= collected;
+            pool.reserves -= collected;
+            Self::_journal::<InsuranceFunds<T>, _, _>(&token_id);
+            <InsuranceFunds<T>>::mutate(token_id, |fund| *fund = fund.saturating_add(collected));
+        }
+        Self::_journal::<Pools<T>, _, _>(&token_id);
+        <Pools<T>>::insert(token_id, pool);
+    }
+
+    // 利息和费用中归保险基金的部分
+    fn _insurance_part(amount: T::TokenBalance) -> T::TokenBalance {
+        T::TokenBalance::from((Self::_wide(amount) * u128::from(Self::insurance_fee_share()) / u128::from(yiwan)) as u64)
+    }
+
+    fn _order_owner_and_status(order_id: T::Hash) -> rstd::result::Result<(T::AccountId, OrderStatus), &'static str> {
+        if <BorrowOrderDetail<T>>::exists(order_id) {
+            let order = Self::borrow_order_detail(order_id);
+            return Ok((order.owner, order.status));
+        }
+        ensure!(<SupplyOrderDetail<T>>::exists(order_id), "the order does not exist");
+        let order = Self::supply_order_detail(order_id);
+        Ok((order.owner, order.status))
+    }
+
+    fn _maturity(loan: &Loan<T>) -> T::BlockNumber {
+        Self::loan_terms(loan.id).maturity
+            .unwrap_or_else(|| loan.taken_at.saturating_add(T::BlockNumber::sa(loan.duration)))
+    }
+
+    // 到期前还款时的提前还款费用，按本金计算
+    fn _prepayment_fee(loan: &Loan<T>) -> T::TokenBalance {
+        if <system::Module<T>>::block_number() >= Self::_maturity(loan) {
+            return Zero::zero();
+        }
+        let fee = Self::_wide(loan.principal) * u128::from(Self::loan_terms(loan.id).prepayment_fee) / u128::from(yiwan);
+        T::TokenBalance::from(fee as u64)
+    }
+
+    // 把利率指数累计到当前区块，此后按 annual 计息，订单上的年利率随之更新
+    fn _reprice_loan(loan: &Loan<T>, annual: u32) {
+        let now = <system::Module<T>>::block_number();
+        let index = match Self::loan_index(loan.id) {
+            Some(index) => LoanIndex { index: index.at(now), ..LoanIndex::new(annual, Self::blocks_per_year(), now) },
+            None => LoanIndex::new(annual, Self::blocks_per_year(), now),
+        };
+        <LoanIndexes<T>>::insert(loan.id, index);
+        if loan.is_borrow_order {
+            <BorrowOrderDetail<T>>::mutate(loan.id, |order| order.interest = annual);
+        } else {
+            <SupplyOrderDetail<T>>::mutate(loan.id, |order| order.interest = annual);
+        }
+    }
+
+    // 按当前价格检查借贷的抵押物：不能超过抵押币种的 max_ltv，出借单还不能低于出借人要求的抵押率
+    fn _check_loan_collateral(loan: &Loan<T>, collateral_asset: T::AssetId, collateral: T::TokenBalance) -> Result {
+        Self::_check_ltv(loan.asset, loan.debt, collateral_asset, collateral)?;
+        if !loan.is_borrow_order {
+            let amortgage = Self::supply_order_detail(loan.id).amortgage;
+            ensure!(
+                Self::_value(loan.asset, loan.debt) * u128::from(yiwan) <= Self::_value(collateral_asset, collateral) * u128::from(amortgage),
+                "the collateral is below the amortgage of the supply order"
+            );
+        }
+        Ok(())
+    }
+
+    // 订单的 updated_at 是借贷开始的区块，修改抵押物时保持不变
+    fn _set_collateral(loan: &Loan<T>, collateral_asset: T::AssetId, collateral: T::TokenBalance) {
+        if loan.is_borrow_order {
+            Self::_journal::<BorrowOrderDetail<T>, _, _>(&loan.id);
+            <BorrowOrderDetail<T>>::mutate(loan.id, |order| {
+                order.stoken_id = collateral_asset;
+                order.stotal = collateral;
+            });
+        } else {
+            Self::_journal::<SupplyOrderDetail<T>, _, _>(&loan.id);
+            <SupplyOrderDetail<T>>::mutate(loan.id, |order| {
+                order.btoken = collateral_asset;
+                order.btotal = collateral;
+            });
+        }
+    }
+
+    // 抵押物不够还清债务时，保险基金在余额之内把差额补给承担亏空的一方，即替借款人还债或免去债务的人，
+    // 补不上的部分记为坏账
+    fn _cover_shortfall(
+        token_id: T::AssetId,
+        debt: T::TokenBalance,
+        collateral_asset: T::AssetId,
+        seized: T::TokenBalance,
+        who: &T::AccountId,
+    ) -> Result {
+        let price = u128::from(Self::token_price(token_id));
+        let (debt_value, seized_value) = (Self::_value(token_id, debt), Self::_value(collateral_asset, seized));
+        if price == 0 || seized_value >= debt_value {
+            return Ok(());
+        }
+        let shortfall = T::TokenBalance::from(cmp::min((debt_value - seized_value + price - 1) / price, Self::_wide(debt)) as u64);
+        let fund = Self::insurance_fund(token_id);
+        let covered = cmp::min(fund, shortfall);
+
+        Self::_give(token_id, who, covered)?;
+        Self::_journal::<InsuranceFunds<T>, _, _>(&token_id);
+        Self::_journal::<BadDebts<T>, _, _>(&token_id);
+        <InsuranceFunds<T>>::insert(token_id, fund - covered);
+        <BadDebts<T>>::mutate(token_id, |bad_debt| {
+            bad_debt.covered = bad_debt.covered.saturating_add(covered);
+            bad_debt.uncovered = bad_debt.uncovered.saturating_add(shortfall - covered);
+        });
+
+        Self::deposit_event(RawEvent::ShortfallCovered(token_id, who.clone(), covered, shortfall - covered));
+        Ok(())
+    }
+
+    // 债权换了持有人，原持有人的卖单作废。转回订单上的出借人时不再单独记录。
+    fn _set_claim_holder(order_id: T::Hash, holder: &T::AccountId) {
+        let original = if <BorrowOrderDetail<T>>::exists(order_id) {
+            Self::borrow_order_detail(order_id).taker
+        } else {
+            Some(Self::supply_order_detail(order_id).owner)
+        };
+        if original.as_ref() == Some(holder) {
+            <LoanClaims<T>>::remove(order_id);
+        } else {
+            <LoanClaims<T>>::insert(order_id, holder);
+        }
+        <ClaimListings<T>>::remove(order_id);
+    }
+
+    /// Current holder of the claim on the running loan of `order_id`, who is repaid.
+    pub fn claim_holder(order_id: T::Hash) -> Option<T::AccountId> {
+        Self::_taken_loan(order_id).ok().map(|loan| loan.lender)
+    }
+
+    /// Block at which the running loan of `order_id` matures, after extensions and rollovers.
+    pub fn maturity_of(order_id: T::Hash) -> Option<T::BlockNumber> {
+        Self::_taken_loan(order_id).ok().map(|loan| Self::_maturity(&loan))
+    }
+
+    /// Principal plus interest up to the current block of the running loan of `order_id`.
+    pub fn debt_of(order_id: T::Hash) -> Option<T::TokenBalance> {
+        Self::_taken_loan(order_id).ok().map(|loan| loan.debt)
+    }
+
+    /// Debt of `who` to the pool of `token_id` up to the current block, interest included.
+    pub fn pool_debt(token_id: T::AssetId, who: &T::AccountId) -> T::TokenBalance {
+        match (Self::_accrued_pool(token_id), Self::pool_loan((token_id, who.clone()))) {
+            (Some(pool), Some(loan)) => pool.debt(loan.scaled_debt),
+            _ => Zero::zero(),
+        }
+    }
+
+    /// Borrowed part of the pool of `token_id`, in 1/10000.
+    pub fn utilization(token_id: T::AssetId) -> u32 {
+        Self::_accrued_pool(token_id).map_or(0, |pool| pool.utilization())
+    }
+
+    /// Annual borrow rate of the pool of `token_id` at its current utilization, in 1/10000.
+    pub fn borrow_rate(token_id: T::AssetId) -> u32 {
+        Self::_accrued_pool(token_id).map_or(0, |pool| pool.rate_model.borrow_rate(pool.utilization()))
+    }
+
+    /// Annual rate earned by the deposits of the pool of `token_id`, in 1/10000: the borrow rate
+    /// spread over borrowed and idle funds.
+    pub fn supply_rate(token_id: T::AssetId) -> u32 {
+        (u64::from(Self::borrow_rate(token_id)) * u64::from(Self::utilization(token_id)) / yiwan) as u32
+    }
+
+    /// What `shares` of the pool of `token_id` are worth up to the current block, interest included.
+    pub fn share_value(token_id: T::AssetId, shares: T::TokenBalance) -> T::TokenBalance {
+        Self::_accrued_pool(token_id)
+            .and_then(|pool| Self::_amount_for(&pool, shares).ok())
+            .unwrap_or_else(Zero::zero)
+    }
+
+    // 存入 amount 得到的份额
+    fn _shares_for(pool: &Pool<T::AssetId, T::TokenBalance, T::BlockNumber>, amount: T::TokenBalance) -> rstd::result::Result<T::TokenBalance, &'static str> {
+        let supply = Self::_wide(Self::token_details(pool.share_token).total_supply);
+        let value = Self::_wide(pool.value());
+        let amount = Self::_wide(amount);
+        let shares = if supply == 0 || value == 0 { amount } else { amount * supply / value };
+        ensure!(supply + shares <= u128::from(u64::max_value()), "overflow in calculating shares");
+        Ok(T::TokenBalance::from(shares as u64))
+    }
+
+    // shares 份额可以取回的数量
+    fn _amount_for(pool: &Pool<T::AssetId, T::TokenBalance, T::BlockNumber>, shares: T::TokenBalance) -> rstd::result::Result<T::TokenBalance, &'static str> {
+        let supply = Self::_wide(Self::token_details(pool.share_token).total_supply);
+        ensure!(Self::_wide(shares) <= supply, "not enough shares");
+        if supply == 0 {
+            return Ok(Zero::zero());
+        }
+        let value = Self::_wide(pool.value());
+        Ok(T::TokenBalance::from((Self::_wide(shares) * value / supply) as u64))
+    }
+
+    // 从账户的可用余额中扣除，放入资金池，总发行量不变
+    fn _take(token_id: T::AssetId, who: &T::AccountId, amount: T::TokenBalance) -> Result {
+        let key = (token_id, who.clone());
+        ensure!(Self::_owns_token(&key), "Account does not own this token");
+        let free = Self::free_balance_of(&key);
+        ensure!(free >= amount, "Not enough free balance.");
+        Self::_journal_balance(&key);
+        <BalanceOf<T>>::insert(&key, Self::balance_of(&key) - amount);
+        <FreeBalanceOf<T>>::insert(&key, free - amount);
+        Ok(())
+    }
+
+    // 从资金池付给账户，总发行量不变
+    fn _give(token_id: T::AssetId, who: &T::AccountId, amount: T::TokenBalance) -> Result {
+        let key = (token_id, who.clone());
+        let balance = Self::balance_of(&key).checked_add(&amount).ok_or("overflow in calculating balance")?;
+        let free = Self::free_balance_of(&key).checked_add(&amount).ok_or("overflow in calculating free balance")?;
+        Self::_journal_balance(&key);
+        <BalanceOf<T>>::insert(&key, balance);
+        <FreeBalanceOf<T>>::insert(&key, free);
+        Ok(())
+    }
+
+    fn _mint(token_id: T::AssetId, who: &T::AccountId, amount: T::TokenBalance) -> Result {
+        let mut token = Self::token_details(token_id);
+        token.total_supply = token.total_supply.checked_add(&amount).ok_or("overflow in calculating total supply")?;
+        Self::_give(token_id, who, amount)?;
+        Self::_journal::<Tokens<T>, _, _>(&token_id);
+        <Tokens<T>>::insert(token_id, token);
+        Ok(())
+    }
+
+    fn _burn(token_id: T::AssetId, who: &T::AccountId, amount: T::TokenBalance) -> Result {
+        let mut token = Self::token_details(token_id);
+        token.total_supply = token.total_supply.checked_sub(&amount).ok_or("overflow in calculating total supply")?;
+        Self::_take(token_id, who, amount)?;
+        Self::_journal::<Tokens<T>, _, _>(&token_id);
+        <Tokens<T>>::insert(token_id, token);
+        Ok(())
+    }
+
+    // 结束一笔借贷：更新订单状态，删除利率指数、还款条款和债权转让记录，并从借出和提供总量中扣除本金。调用者负责抵押物和资金的划转。
+    fn _settle_loan(loan: &Loan<T>, status: OrderStatus) {
+        let now = <system::Module<T>>::block_number();
+        Self::_journal::<BorrowOrderDetail<T>, _, _>(&loan.id);
+        Self::_journal::<SupplyOrderDetail<T>, _, _>(&loan.id);
+        Self::_journal::<LoanIndexes<T>, _, _>(&loan.id);
+        Self::_journal::<OrderTerms<T>, _, _>(&loan.id);
+        Self::_journal::<LoanClaims<T>, _, _>(&loan.id);
+        Self::_journal::<ClaimListings<T>, _, _>(&loan.id);
+        Self::_journal::<TotalBorrowed<T>, _, _>(&loan.asset);
+        Self::_journal::<TotalSupplied<T>, _, _>(&loan.asset);
+        if loan.is_borrow_order {
+            <BorrowOrderDetail<T>>::mutate(loan.id, |order| {
+                order.status = status;
+                order.updated_at = now;
+            });
+        } else {
+            <SupplyOrderDetail<T>>::mutate(loan.id, |order| {
+                order.status = status;
+                order.updated_at = now;
+            });
+        }
+        <LoanIndexes<T>>::remove(loan.id);
+        <OrderTerms<T>>::remove(loan.id);
+        <LoanClaims<T>>::remove(loan.id);
+        <ClaimListings<T>>::remove(loan.id);
+        let principal = loan.principal;
+        <TotalBorrowed<T>>::mutate(loan.asset, |total| *total = total.checked_sub(&principal).unwrap_or_else(Zero::zero));
+        <TotalSupplied<T>>::mutate(loan.asset, |total| *total = total.checked_sub(&principal).unwrap_or_else(Zero::zero));
+
+        Self::deposit_event(RawEvent::FinishBorrow(loan.borrower.clone(), loan.id));
+        Self::deposit_event(RawEvent::FinishSupply(loan.lender.clone(), loan.id));
+    }
+
+    fn _create_token(
+        owner: &T::AccountId,
+        name: Vec<u8>,
+        ticker: Vec<u8>,
+        decimals: u8,
+        total_supply: T::TokenBalance,
+    ) -> rstd::result::Result<T::AssetId, &'static str> {
+        ensure!(name.len() <= 64, "token name cannot exceed 64 bytes");
+        ensure!(ticker.len() <= 32, "token ticker cannot exceed 32 bytes");
+
+        let token_id = Self::token_id();
+
+        <TokenId<T>>::mutate(|id| *id += One::one());
+
+        let token = Erc20Token {
+            name,
+            ticker,
+            decimals,
+            total_supply,
+        };
+
+        <Tokens<T>>::insert(token_id, token);
+        <BalanceOf<T>>::insert((token_id, owner.clone()), total_supply);
+        <FreeBalanceOf<T>>::insert((token_id, owner.clone()), total_supply);
+        <ReserveBalanceOf<T>>::insert((token_id, owner.clone()), T::TokenBalance::from(0u64));
+
+        Ok(token_id)
+    }
+
+    /// Returns up to `limit` history entries of `who`, starting at sequence number `from`.
+    /// Entries that have already been pruned are skipped.
+    pub fn history_page(
+        who: &T::AccountId,
+        from: u64,
+        limit: u64,
+    ) -> Vec<HistoryEntry<T::TokenBalance, T::AccountId, T::AssetId, T::Hash, T::BlockNumber>> {
+        let start = cmp::max(from, Self::history_start(who));
+        let end = cmp::min(Self::history_count(who), from.saturating_add(limit));
+
+        (start..end)
+            .filter_map(|i| Self::history_of((who.clone(), i)))
+            .collect()
+    }
+
+    fn _record_history(
+        who: &T::AccountId,
+        kind: HistoryKind,
+        order_id: T::Hash,
+        counterparty: Option<T::AccountId>,
+        asset_id: T::AssetId,
+        amount: T::TokenBalance,
+    ) {
+
+        let index = Self::history_count(who);
+        let entry = HistoryEntry {
+            kind,
+            order_id,
+            counterparty,
+            block: <system::Module<T>>::block_number(),
+            asset_id,
+            amount,
+        };
+
+        Self::_journal::<HistoryOf<T>, _, _>(&(who.clone(), index));
+        Self::_journal::<HistoryCount<T>, _, _>(who);
+        <HistoryOf<T>>::insert((who.clone(), index), entry);
+        let count = index.saturating_add(1);
+        <HistoryCount<T>>::insert(who, count);
+
+        let cap = Self::history_cap();
+        if cap == 0 {
+            return;
+        }
+
+        let mut start = Self::history_start(who);
+        let mut pruned = 0;
+        while count - start > cap && pruned < MAX_HISTORY_PRUNE {
+            Self::_journal::<HistoryOf<T>, _, _>(&(who.clone(), start));
+            <HistoryOf<T>>::remove((who.clone(), start));
+            start += 1;
+            pruned += 1;
+        }
+        Self::_journal::<HistoryStart<T>, _, _>(who);
+        <HistoryStart<T>>::insert(who, start);
+    }
+
+    // the ERC20 standard transfer function
+    // internal
+    fn _transfer(
+        token_id: T::AssetId,
+        from: T::AccountId,
+        to: T::AccountId,
+        value: T::TokenBalance,
+    ) -> Result {
+
+        ensure!(
+            Self::_owns_token((token_id, from.clone())),
+            "Account does not own this token"
+        );
+
+        let sender_balance = Self::balance_of((token_id, from.clone()));
+        ensure!(sender_balance >= value, "Not enough balance.");
+
+        let sender_free_balance = Self::free_balance_of((token_id, from.clone()));
+        ensure!(sender_free_balance >= value, "Not enough free balance.");
+
+        let updated_from_balance = sender_balance
+            .checked_sub(&value)
+            .ok_or("overflow in calculating balance")?;
+
+        let updated_from_free_balance = sender_free_balance
+            .checked_sub(&value)
+            .ok_or("overflow in calculating free balance")?;
+
+        // 给自己转账时收款方余额要在扣款之后读取，否则会凭空增发
+        let (receiver_balance, receiver_free_balance) = if from == to {
+            (updated_from_balance, updated_from_free_balance)
+        } else {
+            (Self::balance_of((token_id, to.clone())), Self::free_balance_of((token_id, to.clone())))
+        };
+
+        let updated_to_balance = receiver_balance
+            .checked_add(&value)
+            .ok_or("overflow in calculating balance")?;
+
+        let updated_to_free_balance = receiver_free_balance
+            .checked_add(&value)
+            .ok_or("overflow in calculating free balance")?;
+
+        Self::_journal_balance(&(token_id, from.clone()));
+        Self::_journal_balance(&(token_id, to.clone()));
+
+        // reduce sender's balance
+        <BalanceOf<T>>::insert((token_id, from.clone()), updated_from_balance);
+        <FreeBalanceOf<T>>::insert((token_id, from.clone()), updated_from_free_balance);
+
+        // increase receiver's balance
+        <BalanceOf<T>>::insert((token_id, to.clone()), updated_to_balance);
+        <FreeBalanceOf<T>>::insert((token_id, to.clone()), updated_to_free_balance);
+
+        Self::deposit_event(RawEvent::Transfer(token_id, from, to, value));
+        Ok(())
+    }
+
+    fn _reserve(token_id: T::AssetId, sender: T::AccountId, value: T::TokenBalance) -> Result {
+
+        ensure!(
+            Self::_owns_token((token_id, sender.clone())),
+            "Account does not own this token"
+        );
+
+        let sender_free_balance = Self::free_balance_of((token_id, sender.clone()));
+        ensure!(sender_free_balance >= value, "Not enough free balance.");
+
+        let sender_reserve_balance = Self::reserve_balance_of((token_id, sender.clone()));
+
+        let updated_sender_free_balance = sender_free_balance
+            .checked_sub(&value)
+            .ok_or("overflow in calculating reserve free balance")?;
+
+        let updated_sender_reserve_balance = sender_reserve_balance
+            .checked_add(&value)
+            .ok_or("overflow in calculating reserve reserve balance")?;
+
+        Self::_journal_balance(&(token_id, sender.clone()));
+        <FreeBalanceOf<T>>::insert((token_id, sender.clone()), updated_sender_free_balance);
+        <ReserveBalanceOf<T>>::insert((token_id, sender.clone()), updated_sender_reserve_balance);
+
+        Self::deposit_event(RawEvent::Reserve(token_id, sender, value));
+        Ok(())
+    }
+
+    fn _unreserve(token_id: T::AssetId, sender: T::AccountId, value: T::TokenBalance) -> Result {
 
-    fn _unreserve(token_id: T::AssetId, sender: T::AccountId, value: T::TokenBalance) -> Result {
         ensure!(
-            <BalanceOf<T>>::exists((token_id, sender.clone())),
+            Self::_owns_token((token_id, sender.clone())),
             "Account does not own this token"
         );
 
@@ -679,6 +3140,7 @@ impl<T: Trait> Module<T> {
             .checked_sub(&value)
             .ok_or("overflow in calculating unreserve reserve balance")?;
 
+        Self::_journal_balance(&(token_id, sender.clone()));
         <FreeBalanceOf<T>>::insert((token_id, sender.clone()), updated_sender_free_balance);
         <ReserveBalanceOf<T>>::insert((token_id, sender.clone()), updated_sender_reserve_balance);
 
@@ -0,0 +1,109 @@
+//! Fixed-point interest indexes.
+//!
+//! An index starts at `INDEX_ONE` and grows by a per-block rate, compounded every block:
+//! `index * (1 + rate) ^ blocks`. A debt taken at index `i0` is worth `amount * i / i0` at index
+//! `i`, so the debt of any loan can be read at any block without iterating over the blocks in
+//! between. Everything is integer arithmetic on `u128` with a fixed 18 digit fraction, rounded
+//! down except where noted, so every node computes the same result.
+
+use rstd::cmp;
+
+/// 1.0 as an index or a per-block rate.
+pub const INDEX_ONE: u128 = 1_000_000_000_000_000_000;
+
+/// Highest annual rate of a loan, in 1/10000 a year: 1000%.
+pub const MAX_ANNUAL_RATE: u32 = 100_000;
+
+/// Per-block rate of `annual`, in 1/10000 a year. No interest accrues with `blocks_per_year` 0.
+pub fn rate_per_block(annual: u32, blocks_per_year: u64) -> u128 {
+    if blocks_per_year == 0 {
+        return 0;
+    }
+    u128::from(annual) * INDEX_ONE / (10_000 * u128::from(blocks_per_year))
+}
+
+/// `a * b / INDEX_ONE`, rounded down and saturating.
+pub fn mul(a: u128, b: u128) -> u128 {
+    let (a_int, a_frac) = (a / INDEX_ONE, a % INDEX_ONE);
+    let (b_int, b_frac) = (b / INDEX_ONE, b % INDEX_ONE);
+    a_int.saturating_mul(b_int).saturating_mul(INDEX_ONE)
+        .saturating_add(a_int.saturating_mul(b_frac))
+        .saturating_add(a_frac.saturating_mul(b_int))
+        .saturating_add(a_frac * b_frac / INDEX_ONE)
+}
+
+/// `index` after `blocks` blocks at `rate` per block.
+pub fn accrue(index: u128, rate: u128, blocks: u64) -> u128 {
+    // 按位平方求幂，区块数再大也只需要 64 次乘法
+    let mut factor = INDEX_ONE;
+    let mut base = INDEX_ONE.saturating_add(rate);
+    let mut blocks = blocks;
+    while blocks > 0 {
+        if blocks & 1 == 1 {
+            factor = mul(factor, base);
+        }
+        base = mul(base, base);
+        blocks >>= 1;
+    }
+    mul(index, factor)
+}
+
+/// `amount` taken at index `INDEX_ONE`, now that the index is `index`. Rounded up, so a borrower
+/// never owes less than was lent.
+pub fn grow(amount: u64, index: u128) -> u64 {
+    let amount = u128::from(amount);
+    let (int, frac) = (index / INDEX_ONE, index % INDEX_ONE);
+    let grown = amount.saturating_mul(int).saturating_add((amount * frac + INDEX_ONE - 1) / INDEX_ONE);
+    cmp::min(grown, u128::from(u64::max_value())) as u64
+}
+
+/// `amount` at index `index`, expressed at index `INDEX_ONE`. Rounded down; `grow` of the result
+/// is at most `amount`.
+pub fn shrink(amount: u64, index: u128) -> u64 {
+    if index == 0 {
+        return 0;
+    }
+    cmp::min(u128::from(amount) * INDEX_ONE / index, u128::from(u64::max_value())) as u64
+}
+
+/// Like `shrink`, but rounded up, so that `grow` of the result is at least `amount`.
+pub fn shrink_up(amount: u64, index: u128) -> u64 {
+    if index == 0 {
+        return 0;
+    }
+    cmp::min((u128::from(amount) * INDEX_ONE + index - 1) / index, u128::from(u64::max_value())) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compounds_per_block() {
+        // 年利率 10%，一年 100 个区块，每个区块 0.1%
+        let rate = rate_per_block(1_000, 100);
+        assert_eq!(rate, INDEX_ONE / 1_000);
+        assert_eq!(accrue(INDEX_ONE, rate, 0), INDEX_ONE);
+        assert_eq!(accrue(INDEX_ONE, rate, 1), INDEX_ONE + rate);
+        // 1.001^5
+        assert_eq!(accrue(INDEX_ONE, rate, 5), 1_005_010_010_005_001_000);
+        // 分两次累计和一次累计结果相同
+        assert_eq!(accrue(accrue(INDEX_ONE, rate, 2), rate, 3), accrue(INDEX_ONE, rate, 5));
+        assert_eq!(rate_per_block(1_000, 0), 0);
+    }
+
+    #[test]
+    fn rounds_debts_up_and_scaled_amounts_down() {
+        let index = accrue(INDEX_ONE, rate_per_block(1_000, 100), 5);
+        assert_eq!(grow(1_000, INDEX_ONE), 1_000);
+        assert_eq!(grow(1_000, index), 1_006);
+        assert_eq!(shrink(1_006, index), 1_000);
+        assert_eq!(shrink(1_005, index), 999);
+        assert_eq!(shrink_up(1_005, index), 1_000);
+        assert_eq!(shrink_up(1_006, index), 1_001);
+        assert!(grow(shrink_up(1_006, index), index) >= 1_006);
+        assert!(grow(shrink(1_005, index), index) <= 1_005);
+        assert_eq!(grow(u64::max_value(), 2 * INDEX_ONE), u64::max_value());
+        assert_eq!(grow(u64::max_value(), u128::max_value()), u64::max_value());
+    }
+}
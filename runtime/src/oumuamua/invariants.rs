@@ -0,0 +1,452 @@
+//! Ledger invariants of the oumuamua module, shared by the property tests and the fuzz target.
+
+use super::*;
+use crate::oumuamua::mock::*;
+
+/// The signed origins used by the property tests and the fuzz target.
+pub const ACCOUNTS: [u64; 4] = [ADMIN, BORROWER, LENDER, NOBODY];
+
+/// The amount every account should have reserved per asset, derived from the order books, the
+/// pool loans, the governance votes and the proposal deposits of `accounts`.
+fn expected_reserves(accounts: &[u64]) -> Vec<((u32, u64), u64)> {
+    let mut reserves: Vec<((u32, u64), u64)> = Vec::new();
+    let mut add = |key: (u32, u64), value: u64| match reserves.iter_mut().find(|(k, _)| *k == key) {
+        Some((_, total)) => *total += value,
+        None => reserves.push((key, value)),
+    };
+
+    for i in 0..Oumuamua::borrow_order_count() {
+        let order = Oumuamua::borrow_order_detail(Oumuamua::borrow_by_index(i));
+        // 借款单的抵押物在挂单和成交后都保持冻结
+        if order.status == OrderStatus::Open || order.status == OrderStatus::Taken {
+            add((order.stoken_id, order.owner), order.stotal);
+        }
+    }
+
+    for i in 0..Oumuamua::supply_order_count() {
+        let order = Oumuamua::supply_order_detail(Oumuamua::supply_by_index(i));
+        match order.status {
+            OrderStatus::Open => add((order.stoken, order.owner), order.total),
+            OrderStatus::Taken => add((order.btoken, order.taker.expect("taken orders have a taker")), order.btotal),
+            _ => {}
+        }
+    }
+
+    for token_id in 1..Oumuamua::token_id() {
+        for &who in accounts {
+            if let Some(loan) = Oumuamua::pool_loan((token_id, who)) {
+                add((loan.collateral_asset, who), loan.collateral);
+            }
+        }
+    }
+
+    for id in 0..Oumuamua::proposal_count() {
+        let proposal = Oumuamua::proposal(id).expect("proposals are never removed");
+        add((proposal.asset, proposal.proposer), Oumuamua::proposal_deposit_of(id));
+        for &who in accounts {
+            if let Some(vote) = Oumuamua::proposal_vote((id, who)) {
+                add((proposal.asset, who), vote.balance);
+            }
+        }
+    }
+
+    reserves
+}
+
+/// (asset, supplied, borrowed) derived from the order books, see `TotalSupplied` and `TotalBorrowed`.
+fn expected_totals() -> Vec<(u32, u64, u64)> {
+    let mut totals: Vec<(u32, u64, u64)> = Vec::new();
+    let mut add = |token_id: u32, supplied: u64, borrowed: u64| match totals.iter_mut().find(|(t, _, _)| *t == token_id) {
+        Some((_, s, b)) => {
+            *s += supplied;
+            *b += borrowed;
+        }
+        None => totals.push((token_id, supplied, borrowed)),
+    };
+
+    for i in 0..Oumuamua::borrow_order_count() {
+        let order = Oumuamua::borrow_order_detail(Oumuamua::borrow_by_index(i));
+        if order.status == OrderStatus::Taken {
+            add(order.btoken_id, order.btotal, order.btotal);
+        }
+    }
+
+    for i in 0..Oumuamua::supply_order_count() {
+        let order = Oumuamua::supply_order_detail(Oumuamua::supply_by_index(i));
+        match order.status {
+            OrderStatus::Open => add(order.stoken, order.total, 0),
+            OrderStatus::Taken => add(order.stoken, order.total, order.total),
+            _ => {}
+        }
+    }
+
+    totals
+}
+
+/// Panics unless the ledger of every existing token is consistent for `accounts`, which must
+/// include every account that ever held a balance.
+pub fn assert_ledger_invariants(accounts: &[u64]) {
+    let reserves = expected_reserves(accounts);
+
+    for token_id in 1..Oumuamua::token_id() {
+        let mut sum = 0u64;
+        for &who in accounts {
+            let balance = Oumuamua::balance_of((token_id, who));
+            let free = Oumuamua::free_balance_of((token_id, who));
+            let reserved = Oumuamua::reserve_balance_of((token_id, who));
+            assert_eq!(balance, free + reserved, "balance != free + reserved for ({}, {})", token_id, who);
+
+            let expected = reserves
+                .iter()
+                .find(|(k, _)| *k == (token_id, who))
+                .map(|(_, v)| *v)
+                .unwrap_or(0);
+            assert_eq!(reserved, expected, "reserved balance does not match open orders for ({}, {})", token_id, who);
+
+            sum += balance;
+        }
+        // 资金池和保险基金中的资金不属于任何账户
+        sum += Oumuamua::insurance_fund(token_id);
+        if let Some(pool) = Oumuamua::pool(token_id) {
+            sum += pool.cash;
+            let scaled: u64 = accounts.iter().filter_map(|&who| Oumuamua::pool_loan((token_id, who))).map(|loan| loan.scaled_debt).sum();
+            assert_eq!(scaled, pool.scaled_borrowed, "pool loans do not add up to the borrowed amount of {}", token_id);
+            assert!(pool.borrow_index >= interest::INDEX_ONE, "the borrow index of {} went below one", token_id);
+            assert_eq!(Oumuamua::pool_of_share(pool.share_token), Some(token_id), "share token {} does not point back to its pool", pool.share_token);
+        }
+        assert_eq!(sum, Oumuamua::token_details(token_id).total_supply, "balances do not add up to total supply of {}", token_id);
+    }
+
+    let totals = expected_totals();
+    for token_id in 1..Oumuamua::token_id() {
+        let (supplied, borrowed) = totals
+            .iter()
+            .find(|(t, _, _)| *t == token_id)
+            .map(|(_, s, b)| (*s, *b))
+            .unwrap_or((0, 0));
+        assert_eq!(Oumuamua::total_supplied(token_id), supplied, "supplied total does not match the orders of {}", token_id);
+        assert_eq!(Oumuamua::total_borrowed(token_id), borrowed, "borrowed total does not match the loans of {}", token_id);
+    }
+    // 只有进行中的借贷有利率指数，只有挂单中和进行中的订单有还款条款
+    let mut orders: Vec<(<Test as system::Trait>::Hash, OrderStatus)> = Vec::new();
+    for i in 0..Oumuamua::borrow_order_count() {
+        let order = Oumuamua::borrow_order_detail(Oumuamua::borrow_by_index(i));
+        orders.push((order.id, order.status));
+    }
+    for i in 0..Oumuamua::supply_order_count() {
+        let order = Oumuamua::supply_order_detail(Oumuamua::supply_by_index(i));
+        orders.push((order.id, order.status));
+    }
+    for (id, status) in orders {
+        assert_eq!(Oumuamua::loan_index(id).is_some(), status == OrderStatus::Taken, "loan index of {:?}", id);
+        if <OrderTerms<Test>>::exists(id) {
+            assert!(status == OrderStatus::Open || status == OrderStatus::Taken, "loan terms of the closed order {:?}", id);
+            assert!(Oumuamua::loan_terms(id).prepayment_fee <= yiwan as u32, "prepayment fee of {:?}", id);
+        }
+        // 转让过的债权不属于借款人，也不记录转回订单上出借人的情况
+        if let Some(holder) = Oumuamua::loan_claim(id) {
+            assert_eq!(status, OrderStatus::Taken, "loan claim of {:?}", id);
+            let (lender, borrower) = if <BorrowOrderDetail<Test>>::exists(id) {
+                let order = Oumuamua::borrow_order_detail(id);
+                (order.taker.unwrap(), order.owner)
+            } else {
+                let order = Oumuamua::supply_order_detail(id);
+                (order.owner, order.taker.unwrap())
+            };
+            assert!(holder != lender && holder != borrower, "loan claim of {:?} held by {}", id, holder);
+        }
+        if Oumuamua::claim_listing(id).is_some() {
+            assert_eq!(status, OrderStatus::Taken, "claim listing of {:?}", id);
+        }
+    }
+    // 等待批准的多签提案列表和提案状态一致，每个签名人只批准一次
+    let pending = Oumuamua::pending_admin_proposals();
+    for id in 0..Oumuamua::admin_proposal_count() {
+        let proposal = Oumuamua::admin_proposal(id).expect("admin proposals are never removed");
+        assert_eq!(pending.contains(&id), proposal.status == AdminProposalStatus::Pending, "admin proposal {} pending", id);
+        for (i, who) in proposal.approvals.iter().enumerate() {
+            assert!(!proposal.approvals[..i].contains(who), "admin proposal {} approved twice by {}", id, who);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitives::H256;
+    use proptest::prelude::*;
+    use runtime_io::with_externalities;
+
+    // 未知币种 3 用来覆盖 "does not exist" 分支
+    const ASSETS: [u32; 3] = [USDT, BTC, 3];
+
+    #[derive(Clone, Debug)]
+    enum Action {
+        Issue(u64, u64),
+        Destroy(u32, u64, u64),
+        SetPrice(u32, u64),
+        SetAllowAssets(u32, bool),
+        Transfer(u64, u32, u64, u64),
+        Approve(u64, u32, u64, u64),
+        TransferFrom(u32, u64, u64, u64),
+        CreateBorrow(u64, u64, u32, u64, u64, u32, u32),
+        CancelBorrow(u64, u64),
+        TakeBorrow(u64, u64),
+        CreateSupply(u64, u64, u32, Vec<u32>, u32, u64, u32),
+        CancelSupply(u64, u64),
+        TakeSupply(u64, u64, u32),
+        SetRiskParams(u32, u32, u32, u32, u64, u64),
+        Liquidate(u64, bool, u64),
+        Repay(u64, bool, u64),
+        NextBlocks(u64),
+        SetPrepaymentFee(u64, bool, u64, u32),
+        RequestExtension(u64, bool, u64, u64, u32),
+        AcceptExtension(u64, bool, u64),
+        SetRollover(u64, bool, u64, bool),
+        RollOver(u64, bool, u64),
+        AddCollateral(u64, bool, u64, u64),
+        WithdrawCollateral(u64, bool, u64, u64),
+        SubstituteCollateral(u64, bool, u64, u32, u64),
+        TransferLoanClaim(u64, bool, u64, u64),
+        ListLoanClaim(u64, bool, u64, u32, u64),
+        UnlistLoanClaim(u64, bool, u64),
+        BuyLoanClaim(u64, bool, u64),
+        ClaimDefault(u64, bool, u64),
+        SetInsuranceFeeShare(u32),
+        DepositInsurance(u64, u32, u64),
+        SetFlashLoanFee(u32),
+        FlashLoan(u64, u32, u64, Vec<Call<Test>>),
+        SetGovernance(Option<u32>, u64, u64, u32, u32),
+        SetLegacyAdminUntil(Option<u64>),
+        SetProposalDeposit(u64),
+        Propose(u64, u64),
+        Vote(u64, u32, bool, u64),
+        UnlockVote(u64, u32),
+        EnactProposal(u64, u32),
+        SetAdminSignatories(Vec<u64>, u32),
+        ProposeAdminCall(u64, u64),
+        ApproveAdminCall(u64, u32),
+        CancelAdminCall(u64, u32),
+        CreatePool(u32, u32, u32, u32, u32),
+        Deposit(u64, u32, u64),
+        Withdraw(u64, u32, u64),
+        PoolBorrow(u64, u32, u64, u32, u64),
+        PoolRepay(u64, u32, u64),
+        LiquidatePoolLoan(u64, u32, u64),
+    }
+
+    fn account() -> impl Strategy<Value = u64> {
+        prop::sample::select(ACCOUNTS.to_vec())
+    }
+
+    fn asset() -> impl Strategy<Value = u32> {
+        prop::sample::select(ASSETS.to_vec())
+    }
+
+    fn amount() -> impl Strategy<Value = u64> {
+        0u64..12_000
+    }
+
+    // 闪电贷里的调用，包括不被接受的 approve
+    fn flash_loan_call() -> impl Strategy<Value = Call<Test>> {
+        prop_oneof![
+            (asset(), account(), amount()).prop_map(|(t, to, a)| Call::transfer(t, to, a)),
+            (asset(), amount()).prop_map(|(t, a)| Call::deposit(t, a)),
+            (asset(), amount()).prop_map(|(t, s)| Call::withdraw(t, s)),
+            (asset(), amount()).prop_map(|(t, a)| Call::pool_repay(t, a)),
+            (asset(), account()).prop_map(|(t, b)| Call::liquidate_pool_loan(t, b)),
+            (asset(), account(), amount()).prop_map(|(t, s, a)| Call::approve(t, s, a)),
+        ]
+    }
+
+    // 订单用序号引用，越界的序号会变成一个不存在的订单
+    fn order_index() -> impl Strategy<Value = u64> {
+        0u64..8
+    }
+
+    fn action() -> impl Strategy<Value = Action> {
+        prop_oneof![
+            (account(), amount()).prop_map(|(to, a)| Action::Issue(to, a)),
+            (asset(), account(), amount()).prop_map(|(t, w, a)| Action::Destroy(t, w, a)),
+            (asset(), 1u64..50_000).prop_map(|(t, p)| Action::SetPrice(t, p)),
+            (asset(), any::<bool>()).prop_map(|(t, b)| Action::SetAllowAssets(t, b)),
+            (account(), asset(), account(), amount()).prop_map(|(f, t, to, a)| Action::Transfer(f, t, to, a)),
+            (account(), asset(), account(), amount()).prop_map(|(f, t, s, a)| Action::Approve(f, t, s, a)),
+            (asset(), account(), account(), amount()).prop_map(|(t, f, to, a)| Action::TransferFrom(t, f, to, a)),
+            (account(), amount(), asset(), 0u64..100, amount(), asset(), 0u32..2_000)
+                .prop_map(|(w, b, bt, d, s, st, i)| Action::CreateBorrow(w, b, bt, d, s, st, i)),
+            (account(), order_index()).prop_map(|(w, i)| Action::CancelBorrow(w, i)),
+            (account(), order_index()).prop_map(|(w, i)| Action::TakeBorrow(w, i)),
+            (account(), amount(), asset(), prop::collection::vec(asset(), 0..3), 1u32..20_000, 0u64..100, 0u32..2_000)
+                .prop_map(|(w, s, st, ts, m, d, i)| Action::CreateSupply(w, s, st, ts, m, d, i)),
+            (account(), order_index()).prop_map(|(w, i)| Action::CancelSupply(w, i)),
+            (account(), order_index(), asset()).prop_map(|(w, i, t)| Action::TakeSupply(w, i, t)),
+            (asset(), 0u32..12_000, 0u32..12_000, 0u32..12_000, amount(), amount())
+                .prop_map(|(t, l, th, b, bc, sc)| Action::SetRiskParams(t, l, th, b, bc, sc)),
+            (account(), any::<bool>(), order_index()).prop_map(|(w, b, i)| Action::Liquidate(w, b, i)),
+            (account(), any::<bool>(), order_index()).prop_map(|(w, b, i)| Action::Repay(w, b, i)),
+            (1u64..200).prop_map(Action::NextBlocks),
+            (account(), any::<bool>(), order_index(), 0u32..11_000).prop_map(|(w, b, i, f)| Action::SetPrepaymentFee(w, b, i, f)),
+            (account(), any::<bool>(), order_index(), 0u64..100, 0u32..2_000)
+                .prop_map(|(w, b, i, d, r)| Action::RequestExtension(w, b, i, d, r)),
+            (account(), any::<bool>(), order_index()).prop_map(|(w, b, i)| Action::AcceptExtension(w, b, i)),
+            (account(), any::<bool>(), order_index(), any::<bool>()).prop_map(|(w, b, i, o)| Action::SetRollover(w, b, i, o)),
+            (account(), any::<bool>(), order_index()).prop_map(|(w, b, i)| Action::RollOver(w, b, i)),
+            (account(), any::<bool>(), order_index(), amount()).prop_map(|(w, b, i, a)| Action::AddCollateral(w, b, i, a)),
+            (account(), any::<bool>(), order_index(), amount()).prop_map(|(w, b, i, a)| Action::WithdrawCollateral(w, b, i, a)),
+            (account(), any::<bool>(), order_index(), asset(), amount())
+                .prop_map(|(w, b, i, t, a)| Action::SubstituteCollateral(w, b, i, t, a)),
+            (account(), any::<bool>(), order_index(), account()).prop_map(|(w, b, i, to)| Action::TransferLoanClaim(w, b, i, to)),
+            (account(), any::<bool>(), order_index(), asset(), amount())
+                .prop_map(|(w, b, i, t, a)| Action::ListLoanClaim(w, b, i, t, a)),
+            (account(), any::<bool>(), order_index()).prop_map(|(w, b, i)| Action::UnlistLoanClaim(w, b, i)),
+            (account(), any::<bool>(), order_index()).prop_map(|(w, b, i)| Action::BuyLoanClaim(w, b, i)),
+            (account(), any::<bool>(), order_index()).prop_map(|(w, b, i)| Action::ClaimDefault(w, b, i)),
+            (0u32..11_000).prop_map(Action::SetInsuranceFeeShare),
+            (account(), asset(), amount()).prop_map(|(w, t, a)| Action::DepositInsurance(w, t, a)),
+            (0u32..11_000).prop_map(Action::SetFlashLoanFee),
+            (account(), asset(), amount(), prop::collection::vec(flash_loan_call(), 0..4))
+                .prop_map(|(w, t, a, cs)| Action::FlashLoan(w, t, a, cs)),
+            (prop::option::of(asset()), 0u64..5, 0u64..3, 0u32..11_000, 0u32..11_000)
+                .prop_map(|(t, p, d, q, th)| Action::SetGovernance(t, p, d, q, th)),
+            prop::option::of(0u64..20).prop_map(Action::SetLegacyAdminUntil),
+            amount().prop_map(Action::SetProposalDeposit),
+            (account(), 1u64..50_000).prop_map(|(w, p)| Action::Propose(w, p)),
+            (account(), 0u32..4, any::<bool>(), amount()).prop_map(|(w, i, b, a)| Action::Vote(w, i, b, a)),
+            (account(), 0u32..4).prop_map(|(w, i)| Action::UnlockVote(w, i)),
+            (account(), 0u32..4).prop_map(|(w, i)| Action::EnactProposal(w, i)),
+            (prop::collection::vec(account(), 0..4), 0u32..4).prop_map(|(ws, th)| Action::SetAdminSignatories(ws, th)),
+            (account(), 1u64..50_000).prop_map(|(w, p)| Action::ProposeAdminCall(w, p)),
+            (account(), 0u32..4).prop_map(|(w, i)| Action::ApproveAdminCall(w, i)),
+            (account(), 0u32..4).prop_map(|(w, i)| Action::CancelAdminCall(w, i)),
+            (asset(), 0u32..2_000, 0u32..5_000, 0u32..11_000, 0u32..50_000)
+                .prop_map(|(t, b, s1, k, s2)| Action::CreatePool(t, b, s1, k, s2)),
+            (account(), asset(), amount()).prop_map(|(w, t, a)| Action::Deposit(w, t, a)),
+            (account(), asset(), amount()).prop_map(|(w, t, s)| Action::Withdraw(w, t, s)),
+            (account(), asset(), amount(), asset(), amount()).prop_map(|(w, t, a, c, ca)| Action::PoolBorrow(w, t, a, c, ca)),
+            (account(), asset(), amount()).prop_map(|(w, t, a)| Action::PoolRepay(w, t, a)),
+            (account(), asset(), account()).prop_map(|(w, t, b)| Action::LiquidatePoolLoan(w, t, b)),
+        ]
+    }
+
+    fn borrow_order(index: u64) -> H256 {
+        if index < Oumuamua::borrow_order_count() {
+            Oumuamua::borrow_by_index(index)
+        } else {
+            H256::repeat_byte(0xff)
+        }
+    }
+
+    fn supply_order(index: u64) -> H256 {
+        if index < Oumuamua::supply_order_count() {
+            Oumuamua::supply_by_index(index)
+        } else {
+            H256::repeat_byte(0xff)
+        }
+    }
+
+    fn order(is_borrow_order: bool, index: u64) -> H256 {
+        if is_borrow_order {
+            borrow_order(index)
+        } else {
+            supply_order(index)
+        }
+    }
+
+    fn apply(action: Action) -> Result {
+        match action {
+            Action::Issue(to, a) => Oumuamua::issue(Origin::signed(ADMIN), USDT, to, a),
+            Action::Destroy(t, w, a) => Oumuamua::destroy(Origin::signed(ADMIN), t, w, a),
+            Action::SetPrice(t, p) => Oumuamua::set_price(Origin::signed(ADMIN), t, p),
+            Action::SetAllowAssets(t, b) => Oumuamua::set_allow_assets(Origin::signed(ADMIN), t, b),
+            Action::Transfer(f, t, to, a) => Oumuamua::transfer(Origin::signed(f), t, to, a),
+            Action::Approve(f, t, s, a) => Oumuamua::approve(Origin::signed(f), t, s, a),
+            Action::TransferFrom(t, f, to, a) => Oumuamua::transfer_from(Origin::signed(to), t, f, to, a),
+            Action::CreateBorrow(w, b, bt, d, s, st, i) => Oumuamua::create_borrow(Origin::signed(w), b, bt, d, s, st, i),
+            Action::CancelBorrow(w, i) => Oumuamua::cancel_borrow(Origin::signed(w), borrow_order(i)),
+            Action::TakeBorrow(w, i) => Oumuamua::take_borrow(Origin::signed(w), borrow_order(i)),
+            Action::CreateSupply(w, s, st, ts, m, d, i) => Oumuamua::create_Supply(Origin::signed(w), s, st, ts, m, d, i),
+            Action::CancelSupply(w, i) => Oumuamua::cance_supply(Origin::signed(w), supply_order(i)),
+            Action::TakeSupply(w, i, t) => Oumuamua::take_supply(Origin::signed(w), supply_order(i), t),
+            Action::SetRiskParams(t, l, th, b, bc, sc) => {
+                let params = RiskParams { max_ltv: l, liquidation_threshold: th, liquidation_bonus: b, borrow_cap: bc, supply_cap: sc };
+                Oumuamua::set_risk_params(Origin::signed(ADMIN), t, params)
+            }
+            Action::Liquidate(w, true, i) => Oumuamua::liquidate(Origin::signed(w), borrow_order(i)),
+            Action::Liquidate(w, false, i) => Oumuamua::liquidate(Origin::signed(w), supply_order(i)),
+            Action::Repay(w, true, i) => Oumuamua::repay(Origin::signed(w), borrow_order(i)),
+            Action::Repay(w, false, i) => Oumuamua::repay(Origin::signed(w), supply_order(i)),
+            Action::NextBlocks(n) => {
+                System::set_block_number(System::block_number() + n);
+                Ok(())
+            }
+            Action::SetPrepaymentFee(w, b, i, f) => Oumuamua::set_prepayment_fee(Origin::signed(w), order(b, i), f),
+            Action::RequestExtension(w, b, i, d, r) => Oumuamua::request_extension(Origin::signed(w), order(b, i), d, r),
+            Action::AcceptExtension(w, b, i) => Oumuamua::accept_extension(Origin::signed(w), order(b, i)),
+            Action::SetRollover(w, b, i, o) => Oumuamua::set_rollover(Origin::signed(w), order(b, i), o),
+            Action::RollOver(w, b, i) => Oumuamua::roll_over(Origin::signed(w), order(b, i)),
+            Action::AddCollateral(w, b, i, a) => Oumuamua::add_collateral(Origin::signed(w), order(b, i), a),
+            Action::WithdrawCollateral(w, b, i, a) => Oumuamua::withdraw_collateral(Origin::signed(w), order(b, i), a),
+            Action::SubstituteCollateral(w, b, i, t, a) => Oumuamua::substitute_collateral(Origin::signed(w), order(b, i), t, a),
+            Action::TransferLoanClaim(w, b, i, to) => Oumuamua::transfer_loan_claim(Origin::signed(w), order(b, i), to),
+            Action::ListLoanClaim(w, b, i, t, a) => Oumuamua::list_loan_claim(Origin::signed(w), order(b, i), t, a),
+            Action::UnlistLoanClaim(w, b, i) => Oumuamua::unlist_loan_claim(Origin::signed(w), order(b, i)),
+            // 按当前卖单的价格购买，没有卖单时覆盖 "the claim is not listed" 分支
+            Action::BuyLoanClaim(w, b, i) => {
+                let listing = Oumuamua::claim_listing(order(b, i)).unwrap_or_default();
+                Oumuamua::buy_loan_claim(Origin::signed(w), order(b, i), listing.price_asset, listing.price)
+            }
+            Action::ClaimDefault(w, b, i) => Oumuamua::claim_default(Origin::signed(w), order(b, i)),
+            Action::SetInsuranceFeeShare(s) => Oumuamua::set_insurance_fee_share(Origin::signed(ADMIN), s),
+            Action::DepositInsurance(w, t, a) => Oumuamua::deposit_insurance(Origin::signed(w), t, a),
+            Action::SetGovernance(t, voting_period, enactment_delay, quorum, threshold) => {
+                let params = GovernanceParams { voting_period, enactment_delay, quorum, threshold };
+                Oumuamua::set_governance(Origin::signed(ADMIN), t, params)
+            }
+            Action::SetLegacyAdminUntil(b) => Oumuamua::set_legacy_admin_until(Origin::signed(ADMIN), b),
+            Action::SetProposalDeposit(d) => Oumuamua::set_proposal_deposit(Origin::signed(ADMIN), d),
+            // 提案修改 BTC 的价格，执行时经过治理的 Root 身份
+            Action::Propose(w, p) => Oumuamua::propose(Origin::signed(w), Box::new(Call::set_price(BTC, p))),
+            Action::Vote(w, i, b, a) => Oumuamua::vote(Origin::signed(w), i, b, a),
+            Action::UnlockVote(w, i) => Oumuamua::unlock_vote(Origin::signed(w), i),
+            Action::EnactProposal(w, i) => Oumuamua::enact_proposal(Origin::signed(w), i),
+            // 签名人只能由多签或治理设置，这里当作一个通过的治理提案执行
+            Action::SetAdminSignatories(ws, th) => {
+                Oumuamua::_dispatch_as(AdminOrigin::Governance, Call::set_admin_signatories(ws, th))
+            }
+            Action::ProposeAdminCall(w, p) => Oumuamua::propose_admin_call(Origin::signed(w), Box::new(Call::set_price(BTC, p))),
+            Action::ApproveAdminCall(w, i) => Oumuamua::approve_admin_call(Origin::signed(w), i),
+            Action::CancelAdminCall(w, i) => Oumuamua::cancel_admin_call(Origin::signed(w), i),
+            Action::SetFlashLoanFee(f) => Oumuamua::set_flash_loan_fee(Origin::signed(ADMIN), f),
+            Action::FlashLoan(w, t, a, cs) => Oumuamua::flash_loan(Origin::signed(w), t, a, cs),
+            Action::CreatePool(t, base_rate, slope1, kink, slope2) => {
+                let model = RateModel { base_rate, slope1, kink, slope2 };
+                Oumuamua::create_pool(Origin::signed(ADMIN), t, model)
+            }
+            Action::Deposit(w, t, a) => Oumuamua::deposit(Origin::signed(w), t, a),
+            Action::Withdraw(w, t, s) => Oumuamua::withdraw(Origin::signed(w), t, s),
+            Action::PoolBorrow(w, t, a, c, ca) => Oumuamua::pool_borrow(Origin::signed(w), t, a, c, ca),
+            Action::PoolRepay(w, t, a) => Oumuamua::pool_repay(Origin::signed(w), t, a),
+            Action::LiquidatePoolLoan(w, t, b) => Oumuamua::liquidate_pool_loan(Origin::signed(w), t, b),
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(128))]
+
+        #[test]
+        fn random_call_sequences_keep_the_ledger_consistent(actions in prop::collection::vec(action(), 1..40)) {
+            with_externalities(&mut new_market_ext(), || {
+                assert_ledger_invariants(&ACCOUNTS);
+
+                for action in actions {
+                    let root = runtime_io::storage_root();
+                    if let Err(e) = apply(action.clone()) {
+                        assert_eq!(runtime_io::storage_root(), root, "{:?} failed with {:?} but left writes behind", action, e);
+                    }
+                    assert_ledger_invariants(&ACCOUNTS);
+                }
+            });
+        }
+    }
+}
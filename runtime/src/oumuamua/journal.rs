@@ -0,0 +1,58 @@
+//! 闪电贷的撤销记录。
+//!
+//! 运行时没有存储事务。闪电贷执行期间，模块在写入 `allowed` 的调用可能写到的存储项之前都经过
+//! `Module::_journal` 记下原值（同一个存储项只记第一次），记录保存在 `FlashLoanJournal` 中；调用失败或者
+//! 没有还清时逐项写回。记下哪些存储项由实际写入的地方决定，不需要另外维护一份列表；测试检查
+//! `allowed` 的每个调用在闪电贷失败后不留下任何写入。事件也会撤销。内部调用的手续费付给 balances
+//! 模块，不会退回。
+
+use super::*;
+use runtime_io::{clear_storage, set_storage, storage, twox_128};
+
+/// Whether a flash loan accepts `call`.
+pub fn allowed<T: Trait>(call: &Call<T>) -> bool {
+    match call {
+        Call::transfer(..)
+        | Call::deposit(..)
+        | Call::withdraw(..)
+        | Call::pool_repay(..)
+        | Call::liquidate_pool_loan(..)
+        | Call::repay(..)
+        | Call::liquidate(..)
+        | Call::claim_default(..)
+        | Call::withdraw_collateral(..) => true,
+        _ => false,
+    }
+}
+
+/// Start recording the storage entries written before they change.
+pub fn start<T: Trait>() {
+    <FlashLoanJournal<T>>::put(Vec::new());
+    // 事件由 system 模块写入
+    keep::<T>(twox_128(b"System Events").to_vec());
+}
+
+/// Remember the value of the storage entry `key` if a flash loan is running and has not written it yet.
+pub fn keep<T: Trait>(key: Vec<u8>) {
+    if let Some(mut entries) = <FlashLoanJournal<T>>::get() {
+        if !entries.iter().any(|(recorded, _)| *recorded == key) {
+            let value = storage(&key);
+            entries.push((key, value));
+            <FlashLoanJournal<T>>::put(entries);
+        }
+    }
+}
+
+/// Stop recording; with `undo`, write the remembered values back.
+pub fn finish<T: Trait>(undo: bool) {
+    let entries = <FlashLoanJournal<T>>::take().unwrap_or_default();
+    if !undo {
+        return;
+    }
+    for (key, value) in entries {
+        match value {
+            Some(value) => set_storage(&key, &value),
+            None => clear_storage(&key),
+        }
+    }
+}
@@ -0,0 +1,599 @@
+//! Storage migrations of the oumuamua module.
+//!
+//! Version 0 is the layout used before `StorageVersion` existed: every item lived under the
+//! `KittyStorage` prefix, tokens had no decimals and orders had no status or timestamps. Migrating
+//! to version 1 moves the values, the tokens and the order books to the `Oumuamua` prefix in their
+//! current layout, starts the order totals and the allowance numbering, and moves the balances of
+//! the admin and of every order owner. The migration runs in `on_initialize`, at most
+//! `MIGRATION_ITEMS_PER_BLOCK` entries per block, and the module refuses every call until it is
+//! done.
+//!
+//! Balances and allowances are maps over accounts, which storage cannot enumerate, so the other
+//! ones stay under `KittyStorage`. Reading them falls back to the legacy entry while there is none
+//! under `Oumuamua`, and the first write stores them under the new prefix, so nothing is lost or
+//! waits for them. Anyone can move them with `migrate_v0_accounts` to clear the legacy entries.
+
+use super::*;
+use runtime_io::{blake2_256, clear_storage, set_storage, storage, twox_128};
+
+const LEGACY_PREFIX: &[u8] = b"KittyStorage ";
+const PREFIX: &[u8] = b"Oumuamua ";
+
+/// Storage entries moved per block before the migration continues in the next block.
+pub const MIGRATION_ITEMS_PER_BLOCK: u64 = 512;
+
+// 升级前的链没有 BlocksPerYear，按 10 秒一个区块计算
+const DEFAULT_BLOCKS_PER_YEAR: u64 = 3_153_600;
+
+#[derive(Encode, Decode, Default)]
+struct Erc20TokenV0<U> {
+    name: Vec<u8>,
+    ticker: Vec<u8>,
+    total_supply: U,
+}
+
+#[derive(Encode, Decode, Default)]
+struct BorrowOrderV0<TokenBalance, AccountId, AssetId, Hash> {
+    id: Hash,
+    owner: AccountId,
+    btotal: TokenBalance,
+    btoken_id: AssetId,
+    already: TokenBalance,
+    duration: u64,
+    stotal: TokenBalance, // 为 0 时表示订单已结束
+    stoken_id: AssetId,
+    interest: u32,
+}
+
+#[derive(Encode, Decode, Default)]
+struct SupplyOrderV0<TokenBalance, AccountId, AssetId, Hash> {
+    id: Hash,
+    owner: AccountId,
+    total: TokenBalance, // 为 0 时表示订单已结束
+    stoken: AssetId,
+    tokens: Vec<AssetId>,
+    amortgage: u32,
+    duration: u64,
+    interest: u32,
+}
+
+fn legacy_value_key(name: &[u8]) -> [u8; 16] {
+    let mut raw = LEGACY_PREFIX.to_vec();
+    raw.extend_from_slice(name);
+    twox_128(&raw)
+}
+
+fn prefixed_map_key<K: Encode>(prefix: &[u8], name: &[u8], key: &K) -> [u8; 32] {
+    let mut raw = prefix.to_vec();
+    raw.extend_from_slice(name);
+    key.encode_to(&mut raw);
+    blake2_256(&raw)
+}
+
+fn legacy_map_key<K: Encode>(name: &[u8], key: &K) -> [u8; 32] {
+    prefixed_map_key(LEGACY_PREFIX, name, key)
+}
+
+pub(super) fn map_key<K: Encode>(name: &[u8], key: &K) -> [u8; 32] {
+    prefixed_map_key(PREFIX, name, key)
+}
+
+fn take_raw<V: Decode>(key: &[u8]) -> Option<V> {
+    let raw = storage(key)?;
+    clear_storage(key);
+    Decode::decode(&mut &raw[..])
+}
+
+fn take_value<V: Decode>(name: &[u8]) -> Option<V> {
+    take_raw(&legacy_value_key(name))
+}
+
+fn take_map<K: Encode, V: Decode>(name: &[u8], key: &K) -> Option<V> {
+    take_raw(&legacy_map_key(name, key))
+}
+
+/// The version 0 entry of the map `name` at `key`, if it has not been moved yet.
+pub(super) fn legacy_map<K: Encode, V: Decode>(name: &[u8], key: &K) -> Option<V> {
+    storage(&legacy_map_key(name, key)).and_then(|raw| Decode::decode(&mut &raw[..]))
+}
+
+// 移动旧的条目；新前缀下已经写入的条目比旧的新，这时只清除旧的条目
+fn move_map<K: Encode>(name: &[u8], key: &K) -> u64 {
+    let legacy_key = legacy_map_key(name, key);
+    let raw = match storage(&legacy_key) {
+        Some(raw) => raw,
+        None => return 0,
+    };
+    let key = map_key(name, key);
+    if storage(&key).is_none() {
+        set_storage(&key, &raw);
+    }
+    clear_storage(&legacy_key);
+    1
+}
+
+/// Move the version 0 balances of `who`. Returns the number of moved entries.
+fn migrate_account<T: Trait>(who: &T::AccountId) -> u64 {
+    let mut moved = 0;
+    let mut token_id = T::AssetId::default();
+    while token_id < Module::<T>::token_id() {
+        let key = (token_id, who.clone());
+        moved += move_map(b"BalanceOf", &key) + move_map(b"FreeBalanceOf", &key) + move_map(b"ReserveBalanceOf", &key);
+        token_id += One::one();
+    }
+    moved
+}
+
+/// Move the version 0 allowance of `spender` over the tokens of `owner`, if there is one.
+fn migrate_allowance<T: Trait>(token_id: T::AssetId, owner: &T::AccountId, spender: &T::AccountId) {
+    let key = (token_id, owner.clone(), spender.clone());
+    if let Some(allowance) = take_map::<_, T::TokenBalance>(b"Allowance", &key) {
+        // 已经写入过的授权比旧的新
+        if !<Allowance<T>>::exists(&key) {
+            Module::<T>::_insert_allowance(key, allowance);
+        }
+    }
+}
+
+/// Move the version 0 balances of `accounts` and the version 0 `allowances` to the `Oumuamua`
+/// prefix. Reads already fall back to them; moving only clears the legacy entries.
+pub fn migrate_accounts<T: Trait>(
+    accounts: &[T::AccountId],
+    allowances: &[(T::AssetId, T::AccountId, T::AccountId)],
+) {
+    for who in accounts {
+        migrate_account::<T>(who);
+    }
+    for (token_id, owner, spender) in allowances {
+        migrate_allowance::<T>(*token_id, owner, spender);
+    }
+}
+
+/// Run the migrations between the stored version and the current version, moving at most about
+/// `MIGRATION_ITEMS_PER_BLOCK` entries. `MigrationCursor` remembers where the running step stopped.
+pub fn migrate<T: Trait>() {
+    let mut budget = MIGRATION_ITEMS_PER_BLOCK;
+    while budget > 0 {
+        let version = Module::<T>::storage_version();
+        let cursor = Module::<T>::migration_cursor();
+        let (next, done) = match version {
+            0 => (1, migrate_v0_to_v1::<T>(cursor, &mut budget)),
+            _ => return,
+        };
+        match done {
+            Ok(()) => {
+                <MigrationCursor<T>>::kill();
+                <StorageVersion<T>>::put(next);
+            }
+            Err(cursor) => {
+                <MigrationCursor<T>>::put(cursor);
+                return;
+            }
+        }
+    }
+}
+
+// 从 `cursor` 开始逐个处理 `count` 个条目，预算用完时返回下一个条目的位置
+fn for_each_item<F: FnMut(u64) -> u64>(
+    cursor: &mut u64,
+    offset: u64,
+    count: u64,
+    budget: &mut u64,
+    mut f: F,
+) -> rstd::result::Result<(), u64> {
+    while *cursor < offset + count {
+        if *budget == 0 {
+            return Err(*cursor);
+        }
+        *budget = budget.saturating_sub(f(*cursor - offset).max(1));
+        *cursor += 1;
+    }
+    Ok(())
+}
+
+fn token_count<T: Trait>() -> u64 {
+    <T::AssetId as As<u64>>::as_(Module::<T>::token_id())
+}
+
+fn asset_id<T: Trait>(index: u64) -> T::AssetId {
+    T::AssetId::sa(index)
+}
+
+// 条目依次是：各个币种，借款订单，提供资金订单。版本 0 的订单只有挂单和已结束两种状态，没有资金池
+fn migrate_v0_to_v1<T: Trait>(mut cursor: u64, budget: &mut u64) -> rstd::result::Result<(), u64> {
+    let now = <system::Module<T>>::block_number();
+
+    if cursor == 0 {
+        if let Some(token_id) = take_value::<T::AssetId>(b"TokenId") {
+            <TokenId<T>>::put(token_id);
+        }
+        if let Some(admin) = take_value::<T::AccountId>(b"Admin") {
+            <Admin<T>>::put(admin);
+        }
+        if let Some(nonce) = take_value::<u64>(b"Nonce") {
+            <Nonce<T>>::put(nonce);
+        }
+        if let Some(count) = take_value::<u64>(b"AllBorrowOrderCount") {
+            <AllBorrowOrderCount<T>>::put(count);
+        }
+        if let Some(count) = take_value::<u64>(b"AllSupplyOrderCount") {
+            <AllSupplyOrderCount<T>>::put(count);
+        }
+        if Module::<T>::blocks_per_year() == 0 {
+            <BlocksPerYear<T>>::put(DEFAULT_BLOCKS_PER_YEAR);
+        }
+        // 之前的授权没有编号，只能从 Approval 事件中找到
+        <AllowancesIndexedFrom<T>>::put(now);
+        *budget = budget.saturating_sub(migrate_account::<T>(&Module::<T>::admin()));
+    }
+
+    let tokens = token_count::<T>();
+    for_each_item(&mut cursor, 0, tokens, budget, |index| {
+        let token_id = asset_id::<T>(index);
+        if let Some(old) = take_map::<_, Erc20TokenV0<T::TokenBalance>>(b"Tokens", &token_id) {
+            <Tokens<T>>::insert(token_id, Erc20Token {
+                name: old.name,
+                ticker: old.ticker,
+                decimals: 0,
+                total_supply: old.total_supply,
+            });
+        }
+        if let Some(price) = take_map::<_, u64>(b"TokenPrice", &token_id) {
+            <TokenPrice<T>>::insert(token_id, price);
+        }
+        if let Some(allowed) = take_map::<_, bool>(b"AllowAssets", &token_id) {
+            <AllowAssets<T>>::insert(token_id, allowed);
+        }
+        1
+    })?;
+
+    let borrows = Module::<T>::borrow_order_count();
+    for_each_item(&mut cursor, tokens, borrows, budget, |index| {
+        let hash: T::Hash = match take_map(b"AllBorrowOrder", &index) {
+            Some(hash) => hash,
+            None => return 1,
+        };
+        <AllBorrowOrder<T>>::insert(index, hash);
+        <AllBorrowOrderIndex<T>>::insert(hash, take_map(b"AllBorrowOrderIndex", &hash).unwrap_or(index));
+
+        let old: BorrowOrderV0<T::TokenBalance, T::AccountId, T::AssetId, T::Hash> =
+            take_map(b"BorrowOrderDetail", &hash).unwrap_or_default();
+        let owner = old.owner.clone();
+        let closed = old.stotal.is_zero();
+        let order = BorrowOrder {
+            id: old.id,
+            owner: old.owner,
+            btotal: old.btotal,
+            btoken_id: old.btoken_id,
+            already: old.already,
+            duration: old.duration,
+            stotal: old.stotal,
+            stoken_id: old.stoken_id,
+            interest: old.interest,
+            status: if closed { OrderStatus::Closed } else { OrderStatus::Open },
+            taker: None,
+            created_at: now,
+            updated_at: now,
+        };
+        count_borrow_order::<T>(&order);
+        <BorrowOrderDetail<T>>::insert(hash, order);
+
+        if let Some(owner) = take_map::<_, T::AccountId>(b"BorrowOrderOwner", &hash) {
+            <BorrowOrderOwner<T>>::insert(hash, owner);
+        }
+        if let Some(owned_index) = take_map::<_, u64>(b"OwnedBorrowIndex", &hash) {
+            <OwnedBorrowIndex<T>>::insert(hash, owned_index);
+            <OwnedBorrowOrder<T>>::insert((owner.clone(), owned_index), hash);
+            clear_storage(&legacy_map_key(b"OwnedBorrowOrder", &(owner.clone(), owned_index)));
+        }
+        if let Some(count) = take_map::<_, u64>(b"OwnedBorrowCount", &owner) {
+            <OwnedBorrowCount<T>>::insert(&owner, count);
+        }
+
+        1 + migrate_account::<T>(&owner)
+    })?;
+
+    let supplies = Module::<T>::supply_order_count();
+    for_each_item(&mut cursor, tokens + borrows, supplies, budget, |index| {
+        let hash: T::Hash = match take_map(b"AllSupplyOrder", &index) {
+            Some(hash) => hash,
+            None => return 1,
+        };
+        <AllSupplyOrder<T>>::insert(index, hash);
+        <AllSupplyOrderIndex<T>>::insert(hash, take_map(b"AllSupplyOrderIndex", &hash).unwrap_or(index));
+
+        let old: SupplyOrderV0<T::TokenBalance, T::AccountId, T::AssetId, T::Hash> =
+            take_map(b"SupplyOrderDetail", &hash).unwrap_or_default();
+        let owner = old.owner.clone();
+        let closed = old.total.is_zero();
+        let order = SupplyOrder {
+            id: old.id,
+            owner: old.owner,
+            total: old.total,
+            stoken: old.stoken,
+            tokens: old.tokens,
+            amortgage: old.amortgage,
+            duration: old.duration,
+            interest: old.interest,
+            status: if closed { OrderStatus::Closed } else { OrderStatus::Open },
+            taker: None,
+            btoken: T::AssetId::default(),
+            btotal: Zero::zero(),
+            created_at: now,
+            updated_at: now,
+        };
+        count_supply_order::<T>(&order);
+        <SupplyOrderDetail<T>>::insert(hash, order);
+
+        if let Some(owner) = take_map::<_, T::AccountId>(b"SupplyOrderOwner", &hash) {
+            <SupplyOrderOwner<T>>::insert(hash, owner);
+        }
+        if let Some(owned_index) = take_map::<_, u64>(b"OwnedSupplyIndex", &hash) {
+            <OwnedSupplyIndex<T>>::insert(hash, owned_index);
+            <OwnedSupplyOrder<T>>::insert((owner.clone(), owned_index), hash);
+            clear_storage(&legacy_map_key(b"OwnedSupplyOrder", &(owner.clone(), owned_index)));
+        }
+        if let Some(count) = take_map::<_, u64>(b"OwnedSupplyCount", &owner) {
+            <OwnedSupplyCount<T>>::insert(&owner, count);
+        }
+
+        1 + migrate_account::<T>(&owner)
+    })
+}
+
+fn add_totals<T: Trait>(token_id: T::AssetId, supplied: T::TokenBalance, borrowed: T::TokenBalance) {
+    <TotalSupplied<T>>::mutate(token_id, |total| *total = total.saturating_add(supplied));
+    <TotalBorrowed<T>>::mutate(token_id, |total| *total = total.saturating_add(borrowed));
+}
+
+fn count_borrow_order<T: Trait>(order: &BorrowOrder<T::TokenBalance, T::AccountId, T::AssetId, T::Hash, T::BlockNumber>) {
+    if order.status == OrderStatus::Taken {
+        add_totals::<T>(order.btoken_id, order.btotal, order.btotal);
+    }
+}
+
+fn count_supply_order<T: Trait>(order: &SupplyOrder<T::TokenBalance, T::AccountId, T::AssetId, T::Hash, T::BlockNumber>) {
+    match order.status {
+        OrderStatus::Open => add_totals::<T>(order.stoken, order.total, Zero::zero()),
+        OrderStatus::Taken => add_totals::<T>(order.stoken, order.total, order.total),
+        _ => {},
+    }
+}
+
+/// Count `TotalBorrowed` and `TotalSupplied` from the orders: open supply orders count as
+/// supplied, running loans as both supplied and borrowed. Pools are not included.
+pub fn recount_totals<T: Trait>() {
+    let mut token_id = T::AssetId::default();
+    while token_id < Module::<T>::token_id() {
+        <TotalSupplied<T>>::remove(token_id);
+        <TotalBorrowed<T>>::remove(token_id);
+        token_id += One::one();
+    }
+    for index in 0..Module::<T>::borrow_order_count() {
+        count_borrow_order::<T>(&Module::<T>::borrow_order_detail(Module::<T>::borrow_by_index(index)));
+    }
+    for index in 0..Module::<T>::supply_order_count() {
+        count_supply_order::<T>(&Module::<T>::supply_order_detail(Module::<T>::supply_by_index(index)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oumuamua::mock::{count_accesses, new_test_ext, Oumuamua, Origin, System, Test, ADMIN};
+    use primitives::H256;
+    use runtime_io::with_externalities;
+    use runtime_primitives::traits::OnInitialize;
+    use support::assert_ok;
+
+    fn put_legacy_value<V: Encode>(name: &[u8], value: &V) {
+        set_storage(&legacy_value_key(name), &value.encode());
+    }
+
+    fn put_legacy_map<K: Encode, V: Encode>(name: &[u8], key: &K, value: &V) {
+        set_storage(&legacy_map_key(name, key), &value.encode());
+    }
+
+    fn put_legacy_balance(token_id: u32, who: u64, free: u64, reserved: u64) {
+        put_legacy_map(b"BalanceOf", &(token_id, who), &(free + reserved));
+        put_legacy_map(b"FreeBalanceOf", &(token_id, who), &free);
+        put_legacy_map(b"ReserveBalanceOf", &(token_id, who), &reserved);
+    }
+
+    fn put_legacy_borrow(index: u64, owner: u64, owned_index: u64, order: BorrowOrderV0<u64, u64, u32, H256>) {
+        let hash = order.id;
+        put_legacy_map(b"AllBorrowOrder", &index, &hash);
+        put_legacy_map(b"AllBorrowOrderIndex", &hash, &index);
+        put_legacy_map(b"BorrowOrderOwner", &hash, &owner);
+        put_legacy_map(b"OwnedBorrowOrder", &(owner, owned_index), &hash);
+        put_legacy_map(b"OwnedBorrowIndex", &hash, &owned_index);
+        put_legacy_map(b"BorrowOrderDetail", &hash, &order);
+    }
+
+    // 两个币种，一个未结束和一个已结束的借款订单，一个提供资金的订单，以及管理员和两个用户的余额。
+    fn build_v0_state() {
+        <StorageVersion<Test>>::put(0);
+        <BlocksPerYear<Test>>::kill();
+
+        put_legacy_value(b"TokenId", &3u32);
+        put_legacy_value(b"Admin", &ADMIN);
+        put_legacy_value(b"Nonce", &3u64);
+        put_legacy_value(b"AllBorrowOrderCount", &2u64);
+        put_legacy_value(b"AllSupplyOrderCount", &1u64);
+
+        for (token_id, total_supply) in vec![(1u32, 950u64), (2, 75)] {
+            let token = Erc20TokenV0 { name: b"token".to_vec(), ticker: b"TKN".to_vec(), total_supply };
+            put_legacy_map(b"Tokens", &token_id, &token);
+            put_legacy_map(b"TokenPrice", &token_id, &10000u64);
+            put_legacy_map(b"AllowAssets", &token_id, &true);
+        }
+
+        put_legacy_balance(1, ADMIN, 800, 0);
+        put_legacy_balance(1, 2, 50, 100);
+        put_legacy_balance(2, 3, 0, 50);
+        put_legacy_balance(2, 4, 25, 0);
+        put_legacy_map(b"Allowance", &(1u32, 2u64, 4u64), &10u64);
+
+        put_legacy_borrow(0, 2, 0, BorrowOrderV0 {
+            id: H256::repeat_byte(1),
+            owner: 2,
+            btotal: 80,
+            btoken_id: 2,
+            stotal: 100,
+            stoken_id: 1,
+            duration: 30,
+            interest: 500,
+            ..Default::default()
+        });
+        put_legacy_borrow(1, 2, 1, BorrowOrderV0 {
+            id: H256::repeat_byte(2),
+            owner: 2,
+            btotal: 10,
+            btoken_id: 2,
+            stotal: 0,
+            stoken_id: 1,
+            ..Default::default()
+        });
+        put_legacy_map(b"OwnedBorrowCount", &2u64, &2u64);
+
+        let supply = SupplyOrderV0 {
+            id: H256::repeat_byte(3),
+            owner: 3u64,
+            total: 50u64,
+            stoken: 2u32,
+            tokens: vec![1],
+            amortgage: 5000,
+            duration: 30,
+            interest: 300,
+        };
+        put_legacy_map(b"AllSupplyOrder", &0u64, &supply.id);
+        put_legacy_map(b"AllSupplyOrderIndex", &supply.id, &0u64);
+        put_legacy_map(b"SupplyOrderOwner", &supply.id, &3u64);
+        put_legacy_map(b"OwnedSupplyOrder", &(3u64, 0u64), &supply.id);
+        put_legacy_map(b"OwnedSupplyIndex", &supply.id, &0u64);
+        put_legacy_map(b"OwnedSupplyCount", &3u64, &1u64);
+        put_legacy_map(b"SupplyOrderDetail", &supply.id, &supply);
+    }
+
+    #[test]
+    fn migrates_v0_state_once() {
+        with_externalities(&mut new_test_ext(), || {
+            build_v0_state();
+            assert_eq!(Oumuamua::transfer(Origin::signed(2), 1, 5, 5), Err("storage migration in progress"));
+            System::set_block_number(7);
+            Oumuamua::on_initialize(7);
+
+            assert_eq!(Oumuamua::storage_version(), CURRENT_STORAGE_VERSION);
+            assert_eq!(Oumuamua::migration_cursor(), 0);
+            assert_eq!(Oumuamua::token_id(), 3);
+            assert_eq!(Oumuamua::admin(), ADMIN);
+            assert_eq!(<Nonce<Test>>::get(), 3);
+            assert_eq!(Oumuamua::token_details(2).total_supply, 75);
+            assert_eq!(Oumuamua::token_details(2).decimals, 0);
+            assert_eq!(Oumuamua::token_price(2), 10000);
+            assert!(Oumuamua::allow_asset(1));
+            assert_eq!(Oumuamua::blocks_per_year(), DEFAULT_BLOCKS_PER_YEAR);
+            assert_eq!(Oumuamua::allowances_indexed_from(), 7);
+
+            assert_eq!(Oumuamua::balance_of((1, ADMIN)), 800);
+            assert_eq!(Oumuamua::balance_of((1, 2)), 150);
+            assert_eq!(Oumuamua::free_balance_of((1, 2)), 50);
+            assert_eq!(Oumuamua::reserve_balance_of((1, 2)), 100);
+            assert_eq!(Oumuamua::reserve_balance_of((2, 3)), 50);
+
+            let open = Oumuamua::borrow_order_detail(H256::repeat_byte(1));
+            assert_eq!(open.status, OrderStatus::Open);
+            assert_eq!(open.stotal, 100);
+            assert_eq!(open.interest, 500);
+            assert_eq!(open.created_at, 7);
+            let closed = Oumuamua::borrow_order_detail(H256::repeat_byte(2));
+            assert_eq!(closed.status, OrderStatus::Closed);
+
+            assert_eq!(Oumuamua::borrow_order_count(), 2);
+            assert_eq!(Oumuamua::borrow_by_index(1), H256::repeat_byte(2));
+            assert_eq!(Oumuamua::owner_of_borrow(H256::repeat_byte(1)), Some(2));
+            assert_eq!(Oumuamua::owned_borrow_count(2), 2);
+            assert_eq!(Oumuamua::borrow_of_owner_by_index((2, 1)), H256::repeat_byte(2));
+
+            let supply = Oumuamua::supply_order_detail(H256::repeat_byte(3));
+            assert_eq!(supply.status, OrderStatus::Open);
+            assert_eq!(supply.tokens, vec![1]);
+            assert_eq!(supply.amortgage, 5000);
+            assert_eq!(Oumuamua::supply_of_owner_by_index((3, 0)), H256::repeat_byte(3));
+            // 挂单的提供资金订单计入总量，挂单的借款订单不计入
+            assert_eq!((Oumuamua::total_supplied(2), Oumuamua::total_borrowed(2)), (50, 0));
+            assert_eq!(Oumuamua::total_supplied(1), 0);
+
+            assert!(storage(&legacy_value_key(b"TokenId")).is_none());
+            assert!(storage(&legacy_map_key(b"Tokens", &2u32)).is_none());
+            assert!(storage(&legacy_map_key(b"BorrowOrderDetail", &H256::repeat_byte(1))).is_none());
+            assert!(storage(&legacy_map_key(b"BalanceOf", &(1u32, 2u64))).is_none());
+
+            // a later block does not run the migration again
+            <TokenId<Test>>::put(5);
+            Oumuamua::on_initialize(8);
+            assert_eq!(Oumuamua::token_id(), 5);
+        });
+    }
+
+    #[test]
+    fn reads_accounts_without_orders_from_the_legacy_prefix() {
+        with_externalities(&mut new_test_ext(), || {
+            build_v0_state();
+            Oumuamua::on_initialize(1);
+
+            // account 4 owns no order, so the migration cannot find it
+            assert_eq!(Oumuamua::storage_version(), CURRENT_STORAGE_VERSION);
+            assert!(storage(&legacy_map_key(b"BalanceOf", &(2u32, 4u64))).is_some());
+            assert_eq!(Oumuamua::balance_of((2, 4)), 25);
+            assert_eq!(Oumuamua::free_balance_of((2, 4)), 25);
+            assert_eq!(Oumuamua::allowance((1, 2, 4)), 10);
+
+            assert_ok!(Oumuamua::transfer(Origin::signed(4), 2, 5, 5));
+            assert_eq!(Oumuamua::free_balance_of((2, 4)), 20);
+            assert_eq!(Oumuamua::free_balance_of((2, 5)), 5);
+            assert_ok!(Oumuamua::transfer_from(Origin::signed(4), 1, 2, 4, 4));
+            assert_eq!(Oumuamua::allowance((1, 2, 4)), 6);
+            assert_eq!(Oumuamua::allowance_count(), 1);
+
+            // moving keeps the newer entries and clears the legacy ones
+            assert_ok!(Oumuamua::migrate_v0_accounts(Origin::signed(5), vec![4], vec![(1, 2, 4)]));
+            assert!(storage(&legacy_map_key(b"BalanceOf", &(2u32, 4u64))).is_none());
+            assert!(storage(&legacy_map_key(b"Allowance", &(1u32, 2u64, 4u64))).is_none());
+            assert_eq!(Oumuamua::balance_of((2, 4)), 20);
+            assert_eq!(Oumuamua::reserve_balance_of((2, 4)), 0);
+            assert_eq!(Oumuamua::allowance((1, 2, 4)), 6);
+            assert_eq!(Oumuamua::allowance_count(), 1);
+        });
+    }
+
+    #[test]
+    fn moves_untouched_accounts() {
+        with_externalities(&mut new_test_ext(), || {
+            build_v0_state();
+            Oumuamua::on_initialize(1);
+
+            assert_ok!(Oumuamua::migrate_v0_accounts(Origin::signed(5), vec![4], vec![(1, 2, 4)]));
+
+            assert!(storage(&legacy_map_key(b"FreeBalanceOf", &(2u32, 4u64))).is_none());
+            assert_eq!(<BalanceOf<Test>>::get((2, 4)), 25);
+            assert_eq!(<FreeBalanceOf<Test>>::get((2, 4)), 25);
+            assert_eq!(<Allowance<Test>>::get((1, 2, 4)), 10);
+            assert_eq!(Oumuamua::allowance_by_index(0), (1, 2, 4));
+        });
+    }
+
+    #[test]
+    fn moving_accounts_stays_within_its_weight() {
+        let mut ext = new_test_ext();
+        with_externalities(&mut ext, || {
+            build_v0_state();
+            Oumuamua::on_initialize(1);
+        });
+        let call = Call::<Test>::migrate_v0_accounts(vec![4], vec![(1, 2, 4)]);
+        let weight = with_externalities(&mut ext, || weights::of(&call));
+        let (result, used) = count_accesses(&mut ext, || call.dispatch(Origin::signed(5)));
+        assert_ok!(result);
+        assert!(used.reads <= weight.reads && used.writes <= weight.writes);
+    }
+}
@@ -0,0 +1,238 @@
+//! Test environment for the oumuamua module, also used by the fuzz target and the simulator.
+
+use super::*;
+use primitives::{Blake2Hasher, H256};
+use runtime_io::{ChildStorageKey, Externalities, TestExternalities};
+use runtime_primitives::{
+    testing::{Digest, DigestItem, Header},
+    traits::{BlakeTwo256, IdentityLookup},
+    BuildStorage,
+};
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use support::{impl_outer_event, impl_outer_origin};
+
+impl_outer_origin! {
+    pub enum Origin for Test {}
+}
+
+mod oumuamua {
+    pub use super::super::Event;
+}
+
+impl_outer_event! {
+    pub enum TestEvent for Test {
+        balances<T>,
+        oumuamua<T>,
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Test;
+
+impl system::Trait for Test {
+    type Origin = Origin;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type Digest = Digest;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<u64>;
+    type Header = Header;
+    type Event = TestEvent;
+    type Log = DigestItem;
+}
+
+impl balances::Trait for Test {
+    type Balance = u64;
+    type OnFreeBalanceZero = ();
+    type OnNewAccount = ();
+    type Event = TestEvent;
+    type TransactionPayment = ();
+    type DustRemoval = ();
+    type TransferPayment = ();
+}
+
+impl Trait for Test {
+    type Event = TestEvent;
+    type AssetId = u32;
+    type TokenBalance = u64;
+    type Currency = Balances;
+    type TransactionPayment = ();
+}
+
+pub type Oumuamua = Module<Test>;
+pub type System = system::Module<Test>;
+pub type Balances = balances::Module<Test>;
+
+pub const ADMIN: u64 = 1;
+pub const BORROWER: u64 = 2;
+pub const LENDER: u64 = 3;
+// 没有任何币种余额的账户
+pub const NOBODY: u64 = 9;
+
+pub const USDT: u32 = 1;
+pub const BTC: u32 = 2;
+
+/// Native balance of the admin, the borrower and the lender, used to pay call fees.
+pub const NATIVE_BALANCE: u64 = 1_000_000;
+
+pub fn build(config: GenesisConfig<Test>) -> runtime_io::TestExternalities<Blake2Hasher> {
+    let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+    t.extend(balances::GenesisConfig::<Test> {
+        balances: vec![(ADMIN, NATIVE_BALANCE), (BORROWER, NATIVE_BALANCE), (LENDER, NATIVE_BALANCE)],
+        ..Default::default()
+    }.build_storage().unwrap().0);
+    t.extend(config.build_storage().unwrap().0);
+    t.into()
+}
+
+/// Only the admin, no tokens.
+pub fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+    build(GenesisConfig::<Test> {
+        admin: ADMIN,
+        token_id: 1,
+        history_cap: 0,
+        fee_per_weight: 0,
+        insurance_fee_share: 0,
+        flash_loan_fee: 0,
+        governance_asset: None,
+        governance_params: Default::default(),
+        legacy_admin_until: None,
+        proposal_deposit: 0,
+        admin_signatories: vec![],
+        admin_threshold: 0,
+        // 一年 100 个区块，年利率 10% 时每个区块 0.1%
+        blocks_per_year: 100,
+        tokens: vec![],
+        prices: vec![],
+        allowed_assets: vec![],
+        allowances: vec![],
+        borrow_orders: vec![],
+        supply_orders: vec![],
+        risk_params: vec![],
+        loan_indexes: vec![],
+        loan_terms: vec![],
+        loan_claims: vec![],
+        claim_listings: vec![],
+        insurance_funds: vec![],
+        bad_debts: vec![],
+        pools: vec![],
+        pool_loans: vec![],
+    })
+}
+
+/// USDT (price 1) held by the lender and BTC (price 2) held by the borrower, both allowed.
+pub fn market_config() -> GenesisConfig<Test> {
+    GenesisConfig::<Test> {
+        admin: ADMIN,
+        token_id: 1,
+        history_cap: 0,
+        fee_per_weight: 0,
+        insurance_fee_share: 0,
+        flash_loan_fee: 0,
+        governance_asset: None,
+        governance_params: Default::default(),
+        legacy_admin_until: None,
+        proposal_deposit: 0,
+        admin_signatories: vec![],
+        admin_threshold: 0,
+        blocks_per_year: 100,
+        tokens: vec![
+            (b"Tether USD".to_vec(), b"USDT".to_vec(), 2, 1_000_000, vec![(LENDER, 10_000)]),
+            (b"Bitcoin".to_vec(), b"BTC".to_vec(), 4, 1_000_000, vec![(BORROWER, 10_000)]),
+        ],
+        prices: vec![(USDT, 10_000), (BTC, 20_000)],
+        allowed_assets: vec![USDT, BTC],
+        allowances: vec![],
+        borrow_orders: vec![],
+        supply_orders: vec![],
+        risk_params: vec![],
+        loan_indexes: vec![],
+        loan_terms: vec![],
+        loan_claims: vec![],
+        claim_listings: vec![],
+        insurance_funds: vec![],
+        bad_debts: vec![],
+        pools: vec![],
+        pool_loans: vec![],
+    }
+}
+
+pub fn new_market_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+    build(market_config())
+}
+
+pub fn last_event() -> TestEvent {
+    System::events().last().expect("an event was deposited").event.clone()
+}
+
+// 记下读写过的不同存储项，其余都交给被包装的 externalities
+struct CountingExternalities<'a> {
+    inner: &'a mut TestExternalities<Blake2Hasher>,
+    reads: RefCell<BTreeSet<Vec<u8>>>,
+    writes: BTreeSet<Vec<u8>>,
+}
+
+impl<'a> Externalities<Blake2Hasher> for CountingExternalities<'a> {
+    fn storage(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.reads.borrow_mut().insert(key.to_vec());
+        self.inner.storage(key)
+    }
+
+    fn original_storage(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.inner.original_storage(key)
+    }
+
+    fn child_storage(&self, storage_key: ChildStorageKey<Blake2Hasher>, key: &[u8]) -> Option<Vec<u8>> {
+        self.inner.child_storage(storage_key, key)
+    }
+
+    fn place_storage(&mut self, key: Vec<u8>, value: Option<Vec<u8>>) {
+        self.writes.insert(key.clone());
+        self.inner.place_storage(key, value)
+    }
+
+    fn place_child_storage(&mut self, storage_key: ChildStorageKey<Blake2Hasher>, key: Vec<u8>, value: Option<Vec<u8>>) {
+        self.inner.place_child_storage(storage_key, key, value)
+    }
+
+    fn kill_child_storage(&mut self, storage_key: ChildStorageKey<Blake2Hasher>) {
+        self.inner.kill_child_storage(storage_key)
+    }
+
+    fn clear_prefix(&mut self, prefix: &[u8]) {
+        self.writes.insert(prefix.to_vec());
+        self.inner.clear_prefix(prefix)
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.inner.chain_id()
+    }
+
+    fn storage_root(&mut self) -> H256 {
+        self.inner.storage_root()
+    }
+
+    fn child_storage_root(&mut self, storage_key: ChildStorageKey<Blake2Hasher>) -> Vec<u8> {
+        self.inner.child_storage_root(storage_key)
+    }
+
+    fn storage_changes_root(&mut self, parent: H256, parent_num: u64) -> Option<H256> {
+        self.inner.storage_changes_root(parent, parent_num)
+    }
+
+    fn submit_extrinsic(&mut self, extrinsic: Vec<u8>) -> rstd::result::Result<(), ()> {
+        self.inner.submit_extrinsic(extrinsic)
+    }
+}
+
+/// Run `f` on `ext`, returning its result and the distinct storage keys it read and wrote.
+pub fn count_accesses<R>(ext: &mut TestExternalities<Blake2Hasher>, f: impl FnOnce() -> R) -> (R, weights::Weight) {
+    let mut counting = CountingExternalities { inner: ext, reads: RefCell::new(BTreeSet::new()), writes: BTreeSet::new() };
+    let result = runtime_io::with_externalities(&mut counting, f);
+    let reads = counting.reads.into_inner().len() as u64;
+    let writes = counting.writes.len() as u64;
+    (result, weights::Weight { reads, writes })
+}
@@ -0,0 +1,450 @@
+//! Backtesting of lending scenarios against the mock runtime.
+//!
+//! A `Scenario` gives the tokens with their holders, a price series per token and the actions of
+//! every block. `run` replays it block by block in mock externalities: prices are set through
+//! `set_price`, orders are created and taken through the real calls, and every taken loan is
+//! checked against the current prices after the actions of a block.
+//!
+//! Each token may come with `riskParams`, set at genesis, and `blocksPerYear` sets the
+//! `BlocksPerYear` of the module, so debts accrue through its loan indexes. Loans are settled as
+//! follows:
+//! - a loan the module lets its lender `liquidate` is liquidated on chain after every block;
+//! - `repayBorrow` / `repaySupply` call `repay`, paying principal plus interest to the lender;
+//! - a loan not repaid at maturity defaults through `claim_default`: the lender takes collateral
+//!   worth the debt plus the liquidation bonus of the collateral, the rest goes back to the borrower.
+//! Whatever the seized collateral does not cover of the debt, interest included, is bad debt;
+//! scenarios have no insurance fund to cover it. Values are `amount * price`, in the units of
+//! `TokenPrice`.
+
+use super::mock::*;
+use super::*;
+use primitives::H256;
+use runtime_io::with_externalities;
+use serde::{Deserialize, Serialize};
+
+/// A scripted lending scenario.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Scenario {
+    /// Number of blocks to run, starting at block 1.
+    pub blocks: u64,
+    /// Blocks per year, the unit of the order interest rates.
+    pub blocks_per_year: u64,
+    /// Tokens, with ids from 1 in this order. Every token is allowed.
+    pub tokens: Vec<TokenSpec>,
+    pub actions: Vec<ScheduledAction>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TokenSpec {
+    pub ticker: String,
+    pub decimals: u8,
+    /// Initial balances, which make up the whole supply.
+    pub holders: Vec<(u64, u64)>,
+    /// (block, price) pairs. A price holds until the next pair; the first one is set at genesis.
+    pub prices: Vec<(u64, u64)>,
+    /// Risk parameters of the token, the module default if not given.
+    #[serde(default)]
+    pub risk_params: Option<RiskParams<u64>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ScheduledAction {
+    pub block: u64,
+    pub who: u64,
+    pub action: Action,
+}
+
+/// Orders are referred to by their creation index, `0` being the first borrow (or supply) order
+/// of the scenario.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Action {
+    CreateBorrow { btotal: u64, btoken: u32, duration: u64, stotal: u64, stoken: u32, interest: u32 },
+    CreateSupply { total: u64, stoken: u32, tokens: Vec<u32>, amortgage: u32, duration: u64, interest: u32 },
+    TakeBorrow { order: u64 },
+    TakeSupply { order: u64, btoken: u32 },
+    RepayBorrow { order: u64 },
+    RepaySupply { order: u64 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LoanKind {
+    Borrow,
+    Supply,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Outcome {
+    Open,
+    Repaid,
+    Liquidated,
+    Defaulted,
+}
+
+/// A taken order and how it ended.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoanReport {
+    pub kind: LoanKind,
+    pub order: u64,
+    pub lender: u64,
+    pub borrower: u64,
+    pub asset: u32,
+    pub principal: u64,
+    pub collateral_asset: u32,
+    pub collateral: u64,
+    pub interest: u32,
+    pub taken_at: u64,
+    pub maturity: u64,
+    pub outcome: Outcome,
+    pub settled_at: Option<u64>,
+    /// Value lent, at the prices of `taken_at`.
+    pub lent_value: u128,
+    /// Value paid back or seized, at the prices of `settled_at`.
+    pub received_value: u128,
+    pub bad_debt: u128,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LenderReport {
+    pub account: u64,
+    pub lent_value: u128,
+    pub received_value: u128,
+    /// (received - lent) / lent of the settled loans, in 1/10000.
+    pub return_bps: i64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedAction {
+    pub block: u64,
+    pub who: u64,
+    pub action: Action,
+    pub error: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    pub repaid: u32,
+    pub liquidations: u32,
+    pub defaults: u32,
+    pub open: u32,
+    pub bad_debt: u128,
+    pub lenders: Vec<LenderReport>,
+    pub loans: Vec<LoanReport>,
+    pub failed_actions: Vec<FailedAction>,
+}
+
+/// Replay `scenario` and report what happened to every loan.
+pub fn run(scenario: &Scenario) -> core::result::Result<Report, String> {
+    let mut ext = build(genesis(scenario)?);
+    with_externalities(&mut ext, || {
+        let mut sim = Simulation { scenario, loans: Vec::new(), report: Report::default() };
+        for block in 1..=scenario.blocks {
+            System::set_block_number(block);
+            sim.set_prices(block);
+            for scheduled in scenario.actions.iter().filter(|a| a.block == block) {
+                if let Err(e) = sim.apply(block, scheduled) {
+                    sim.report.failed_actions.push(FailedAction {
+                        block,
+                        who: scheduled.who,
+                        action: scheduled.action.clone(),
+                        error: e.into(),
+                    });
+                }
+            }
+            sim.check_loans(block)?;
+        }
+        Ok(sim.finish())
+    })
+}
+
+fn genesis(scenario: &Scenario) -> core::result::Result<GenesisConfig<Test>, String> {
+    let mut config = market_config();
+    config.tokens = scenario.tokens.iter().map(|token| {
+        let held: u64 = token.holders.iter().map(|(_, amount)| amount).sum();
+        let name = token.ticker.clone().into_bytes();
+        (name.clone(), name, token.decimals, held, token.holders.clone())
+    }).collect();
+    config.prices = Vec::new();
+    for (index, token) in scenario.tokens.iter().enumerate() {
+        let &(_, price) = token.prices.first()
+            .ok_or_else(|| format!("Token {} has no price", token.ticker))?;
+        config.prices.push((index as u32 + 1, price));
+    }
+    config.allowed_assets = (1..=scenario.tokens.len() as u32).collect();
+    config.blocks_per_year = scenario.blocks_per_year;
+    config.risk_params = scenario.tokens.iter().enumerate()
+        .filter_map(|(index, token)| token.risk_params.map(|params| (index as u32 + 1, params)))
+        .collect();
+    Ok(config)
+}
+
+struct Loan {
+    id: H256,
+    report: LoanReport,
+    // 清算和到期前每个区块都要检查
+    active: bool,
+}
+
+struct Simulation<'a> {
+    scenario: &'a Scenario,
+    loans: Vec<Loan>,
+    report: Report,
+}
+
+impl<'a> Simulation<'a> {
+    fn set_prices(&self, block: u64) {
+        for (index, token) in self.scenario.tokens.iter().enumerate() {
+            for &(_, price) in token.prices.iter().filter(|(at, _)| *at == block) {
+                Oumuamua::set_price(Origin::signed(ADMIN), index as u32 + 1, price)
+                    .expect("the admin can always set prices; qed");
+            }
+        }
+    }
+
+    fn apply(&mut self, block: u64, scheduled: &ScheduledAction) -> Result {
+        let origin = Origin::signed(scheduled.who);
+        match scheduled.action.clone() {
+            Action::CreateBorrow { btotal, btoken, duration, stotal, stoken, interest } =>
+                Oumuamua::create_borrow(origin, btotal, btoken, duration, stotal, stoken, interest),
+            Action::CreateSupply { total, stoken, tokens, amortgage, duration, interest } =>
+                Oumuamua::create_Supply(origin, total, stoken, tokens, amortgage, duration, interest),
+            Action::TakeBorrow { order } => {
+                ensure!(order < Oumuamua::borrow_order_count(), "no such borrow order");
+                let id = Oumuamua::borrow_by_index(order);
+                Oumuamua::take_borrow(origin, id)?;
+                let o = Oumuamua::borrow_order_detail(id);
+                self.open_loan(block, id, LoanKind::Borrow, order, scheduled.who, o.owner,
+                    o.btoken_id, o.btotal, o.stoken_id, o.stotal, o.interest, o.duration);
+                Ok(())
+            },
+            Action::TakeSupply { order, btoken } => {
+                ensure!(order < Oumuamua::supply_order_count(), "no such supply order");
+                let id = Oumuamua::supply_by_index(order);
+                Oumuamua::take_supply(origin, id, btoken)?;
+                let o = Oumuamua::supply_order_detail(id);
+                self.open_loan(block, id, LoanKind::Supply, order, o.owner, scheduled.who,
+                    o.stoken, o.total, o.btoken, o.btotal, o.interest, o.duration);
+                Ok(())
+            },
+            Action::RepayBorrow { order } => self.repay(block, LoanKind::Borrow, order, scheduled.who),
+            Action::RepaySupply { order } => self.repay(block, LoanKind::Supply, order, scheduled.who),
+        }
+    }
+
+    fn open_loan(
+        &mut self, block: u64, id: H256, kind: LoanKind, order: u64, lender: u64, borrower: u64,
+        asset: u32, principal: u64, collateral_asset: u32, collateral: u64, interest: u32, duration: u64,
+    ) {
+        self.loans.push(Loan {
+            id,
+            report: LoanReport {
+                kind, order, lender, borrower, asset, principal, collateral_asset, collateral, interest,
+                taken_at: block,
+                maturity: block.saturating_add(duration),
+                outcome: Outcome::Open,
+                settled_at: None,
+                lent_value: value(asset, principal),
+                received_value: 0,
+                bad_debt: 0,
+            },
+            active: true,
+        });
+    }
+
+    fn repay(&mut self, block: u64, kind: LoanKind, order: u64, who: u64) -> Result {
+        let index = self.loans.iter()
+            .position(|l| l.active && l.report.kind == kind && l.report.order == order)
+            .ok_or("the order is not an active loan")?;
+        let asset = self.loans[index].report.asset;
+
+        Oumuamua::repay(Origin::signed(who), self.loans[index].id)?;
+        let paid = match last_event() {
+            TestEvent::oumuamua(RawEvent::Repaid(_, _, _, _, paid)) => paid,
+            _ => return Err("repay deposited no Repaid event"),
+        };
+
+        self.settle(index, block, Outcome::Repaid, value(asset, paid), 0);
+        Ok(())
+    }
+
+    fn check_loans(&mut self, block: u64) -> core::result::Result<(), String> {
+        for index in 0..self.loans.len() {
+            if !self.loans[index].active {
+                continue;
+            }
+            let id = self.loans[index].id;
+            let loan = self.loans[index].report.clone();
+            let debt = Oumuamua::debt_of(id).ok_or("an active loan has a debt")?;
+            let debt_value = value(loan.asset, debt);
+
+            // 出借人自己清算，不满足清算条件时调用失败
+            let (outcome, seized) = if Oumuamua::liquidate(Origin::signed(loan.lender), id).is_ok() {
+                match last_event() {
+                    TestEvent::oumuamua(RawEvent::Liquidated(_, _, _, _, seized)) => (Outcome::Liquidated, seized),
+                    event => return Err(format!("liquidation deposited {:?}", event)),
+                }
+            } else if block >= loan.maturity {
+                // 到期违约：和清算一样拿走价值为 债务 + 奖励 的抵押物
+                Oumuamua::claim_default(Origin::signed(loan.lender), id)?;
+                match last_event() {
+                    TestEvent::oumuamua(RawEvent::Defaulted(_, _, _, _, seized)) => (Outcome::Defaulted, seized),
+                    event => return Err(format!("default deposited {:?}", event)),
+                }
+            } else {
+                continue;
+            };
+
+            // 抵押物不够偿还 本金 + 利息 的部分记为坏账
+            let seized_value = value(loan.collateral_asset, seized);
+            self.settle(index, block, outcome, seized_value, debt_value.saturating_sub(seized_value));
+        }
+        Ok(())
+    }
+
+    fn settle(&mut self, index: usize, block: u64, outcome: Outcome, received_value: u128, bad_debt: u128) {
+        let loan = &mut self.loans[index];
+        loan.active = false;
+        loan.report.outcome = outcome;
+        loan.report.settled_at = Some(block);
+        loan.report.received_value = received_value;
+        loan.report.bad_debt = bad_debt;
+    }
+
+    fn finish(mut self) -> Report {
+        let mut lenders: Vec<LenderReport> = Vec::new();
+        for loan in &self.loans {
+            let loan = &loan.report;
+            match loan.outcome {
+                Outcome::Open => self.report.open += 1,
+                Outcome::Repaid => self.report.repaid += 1,
+                Outcome::Liquidated => self.report.liquidations += 1,
+                Outcome::Defaulted => self.report.defaults += 1,
+            }
+            self.report.bad_debt += loan.bad_debt;
+            if loan.outcome == Outcome::Open {
+                continue;
+            }
+
+            let position = match lenders.iter().position(|l| l.account == loan.lender) {
+                Some(position) => position,
+                None => {
+                    lenders.push(LenderReport { account: loan.lender, lent_value: 0, received_value: 0, return_bps: 0 });
+                    lenders.len() - 1
+                },
+            };
+            lenders[position].lent_value += loan.lent_value;
+            lenders[position].received_value += loan.received_value;
+        }
+        for lender in &mut lenders {
+            if lender.lent_value > 0 {
+                let (received, lent) = (lender.received_value as i128, lender.lent_value as i128);
+                lender.return_bps = ((received - lent) * i128::from(yiwan) / lent) as i64;
+            }
+        }
+        lenders.sort_by_key(|l| l.account);
+
+        self.report.lenders = lenders;
+        self.report.loans = self.loans.into_iter().map(|l| l.report).collect();
+        self.report
+    }
+}
+
+fn value(asset: u32, amount: u64) -> u128 {
+    u128::from(amount) * u128::from(Oumuamua::token_price(asset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE: u64 = 10;
+    const BOB: u64 = 11;
+
+    // USDT 价格 1，BTC 价格 100，Alice 持有 USDT，Bob 持有 BTC。BTC 借款价值超过抵押物的 90% 时清算
+    fn scenario(btc_prices: Vec<(u64, u64)>, actions: Vec<(u64, u64, Action)>) -> Scenario {
+        Scenario {
+            blocks: 20,
+            blocks_per_year: 100,
+            tokens: vec![
+                TokenSpec { ticker: "USDT".into(), decimals: 2, holders: vec![(ALICE, 100_000)], prices: vec![(0, 1)], risk_params: None },
+                TokenSpec {
+                    ticker: "BTC".into(),
+                    decimals: 4,
+                    holders: vec![(BOB, 1_000)],
+                    prices: btc_prices,
+                    risk_params: Some(RiskParams { max_ltv: 5_000, liquidation_threshold: 9_000, liquidation_bonus: 500, borrow_cap: 0, supply_cap: 0 }),
+                },
+            ],
+            actions: actions.into_iter()
+                .map(|(block, who, action)| ScheduledAction { block, who, action })
+                .collect(),
+        }
+    }
+
+    // Bob 抵押 20 BTC（价值 2000）借 1000 USDT，年利率 10%，期限 10 个区块，Alice 在第 2 个区块出借
+    fn borrow_and_take() -> Vec<(u64, u64, Action)> {
+        vec![
+            (1, BOB, Action::CreateBorrow { btotal: 1_000, btoken: USDT, duration: 10, stotal: 20, stoken: BTC, interest: 1_000 }),
+            (2, ALICE, Action::TakeBorrow { order: 0 }),
+        ]
+    }
+
+    #[test]
+    fn repaid_loan_pays_interest_to_the_lender() {
+        let mut actions = borrow_and_take();
+        actions.push((7, BOB, Action::RepayBorrow { order: 0 }));
+        let mut scenario = scenario(vec![(0, 100)], actions);
+        // Bob 需要额外的 USDT 支付利息
+        scenario.tokens[0].holders.push((BOB, 100));
+        let report = run(&scenario).unwrap();
+
+        assert!(report.failed_actions.is_empty(), "{:?}", report.failed_actions);
+        let loan = &report.loans[0];
+        assert_eq!(loan.outcome, Outcome::Repaid);
+        // 5 个区块，每个区块 0.1% 复利：1000 * 1.001^5 = 1005.01，向上取整
+        assert_eq!(loan.received_value, 1_006);
+        assert_eq!(report.repaid, 1);
+        assert_eq!(report.bad_debt, 0);
+    }
+
+    #[test]
+    fn price_drop_liquidates_and_crash_leaves_bad_debt() {
+        // 价格跌到 55：1000 的借款超过抵押物价值 1100 的 90%
+        let report = run(&scenario(vec![(0, 100), (5, 55)], borrow_and_take())).unwrap();
+        let loan = &report.loans[0];
+        assert_eq!((loan.outcome, loan.settled_at), (Outcome::Liquidated, Some(5)));
+        assert_eq!(report.bad_debt, 0);
+        assert!(report.lenders[0].return_bps > 0);
+
+        // 价格直接跌到 40：抵押物只值 800
+        let report = run(&scenario(vec![(0, 100), (5, 40)], borrow_and_take())).unwrap();
+        assert_eq!(report.liquidations, 1);
+        assert!(report.bad_debt > 0);
+        assert!(report.lenders[0].return_bps < 0);
+    }
+
+    #[test]
+    fn unpaid_loan_defaults_at_maturity() {
+        let report = run(&scenario(vec![(0, 100)], borrow_and_take())).unwrap();
+        let loan = &report.loans[0];
+        assert_eq!((loan.outcome, loan.settled_at), (Outcome::Defaulted, Some(12)));
+        assert_eq!(report.defaults, 1);
+        assert_eq!(report.bad_debt, 0);
+    }
+
+    #[test]
+    fn failed_actions_are_reported() {
+        let report = run(&scenario(vec![(0, 100)], vec![(1, ALICE, Action::TakeBorrow { order: 3 })])).unwrap();
+        assert_eq!(report.failed_actions.len(), 1);
+        assert_eq!(report.failed_actions[0].error, "no such borrow order");
+    }
+}
@@ -0,0 +1,2268 @@
+//! Tests for the oumuamua module.
+
+#![cfg(test)]
+
+use super::*;
+use crate::oumuamua::mock::*;
+use primitives::H256;
+use runtime_io::with_externalities;
+use support::{assert_noop, assert_ok};
+
+fn first_borrow_order() -> <Test as system::Trait>::Hash {
+    Oumuamua::borrow_by_index(0)
+}
+
+fn first_supply_order() -> <Test as system::Trait>::Hash {
+    Oumuamua::supply_by_index(0)
+}
+
+fn free_and_reserved(token_id: u32, who: u64) -> (u64, u64) {
+    (Oumuamua::free_balance_of((token_id, who)), Oumuamua::reserve_balance_of((token_id, who)))
+}
+
+// 借 100 USDT（价值 100），抵押 60 BTC（价值 120）
+fn create_default_borrow() {
+    assert_ok!(Oumuamua::create_borrow(Origin::signed(BORROWER), 100, USDT, 30, 60, BTC, 500));
+}
+
+// 提供 1000 USDT，接受 BTC 抵押，抵押率 50%
+fn create_default_supply() {
+    assert_ok!(Oumuamua::create_Supply(Origin::signed(LENDER), 1000, USDT, vec![BTC], 5000, 30, 300));
+}
+
+#[test]
+fn genesis_creates_tokens_prices_and_holders() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_eq!(Oumuamua::token_id(), 3);
+        assert_eq!(Oumuamua::token_details(USDT).ticker, b"USDT".to_vec());
+        assert_eq!(Oumuamua::token_details(BTC).decimals, 4);
+        assert_eq!(Oumuamua::token_details(BTC).total_supply, 1_000_000);
+        assert_eq!(Oumuamua::balance_of((USDT, LENDER)), 10_000);
+        assert_eq!(Oumuamua::balance_of((USDT, ADMIN)), 990_000);
+        assert_eq!(Oumuamua::free_balance_of((BTC, ADMIN)), 990_000);
+        assert_eq!(Oumuamua::token_price(BTC), 20_000);
+        assert!(Oumuamua::allow_asset(USDT));
+        assert_eq!(Oumuamua::storage_version(), CURRENT_STORAGE_VERSION);
+    });
+}
+
+#[test]
+fn init_creates_a_token_owned_by_the_given_account() {
+    with_externalities(&mut new_test_ext(), || {
+        assert_ok!(Oumuamua::init(Origin::signed(ADMIN), LENDER, b"Tether USD".to_vec(), b"USDT".to_vec(), 2, 1000));
+
+        assert_eq!(Oumuamua::token_id(), 2);
+        assert_eq!(Oumuamua::token_details(1).name, b"Tether USD".to_vec());
+        assert_eq!(Oumuamua::token_details(1).total_supply, 1000);
+        assert_eq!(Oumuamua::balance_of((1, LENDER)), 1000);
+        assert_eq!(Oumuamua::balance_of((1, ADMIN)), 0);
+        assert_eq!(free_and_reserved(1, LENDER), (1000, 0));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::NewToken(1, LENDER, 1000)));
+    });
+}
+
+#[test]
+fn init_checks_admin_and_lengths() {
+    with_externalities(&mut new_test_ext(), || {
+        assert_noop!(
+            Oumuamua::init(Origin::signed(BORROWER), ADMIN, b"x".to_vec(), b"X".to_vec(), 0, 1),
+            "only Admin can new a token"
+        );
+        assert_noop!(
+            Oumuamua::init(Origin::signed(ADMIN), ADMIN, vec![b'x'; 65], b"X".to_vec(), 0, 1),
+            "token name cannot exceed 64 bytes"
+        );
+        assert_noop!(
+            Oumuamua::init(Origin::signed(ADMIN), ADMIN, b"x".to_vec(), vec![b'X'; 33], 0, 1),
+            "token ticker cannot exceed 32 bytes"
+        );
+    });
+}
+
+#[test]
+fn issue_mints_to_the_given_account() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_ok!(Oumuamua::issue(Origin::signed(ADMIN), USDT, ADMIN, 500));
+        assert_eq!(Oumuamua::token_details(USDT).total_supply, 1_000_500);
+        assert_eq!(Oumuamua::balance_of((USDT, ADMIN)), 990_500);
+        assert_eq!(Oumuamua::free_balance_of((USDT, ADMIN)), 990_500);
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Issue(USDT, ADMIN, 500)));
+
+        assert_ok!(Oumuamua::issue(Origin::signed(ADMIN), USDT, NOBODY, 300));
+        assert_eq!(Oumuamua::token_details(USDT).total_supply, 1_000_800);
+        assert_eq!(free_and_reserved(USDT, NOBODY), (300, 0));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Issue(USDT, NOBODY, 300)));
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn issue_checks_token_admin_and_overflow() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(Oumuamua::issue(Origin::signed(ADMIN), 7, ADMIN, 500), "the token does not exist");
+        assert_noop!(Oumuamua::issue(Origin::signed(LENDER), USDT, LENDER, 500), "only Admin can issue a token");
+        assert_noop!(
+            Oumuamua::issue(Origin::signed(ADMIN), USDT, ADMIN, u64::max_value()),
+            "overflow in calculating balance"
+        );
+    });
+}
+
+#[test]
+fn destroy_burns_from_the_given_account() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_ok!(Oumuamua::destroy(Origin::signed(ADMIN), USDT, ADMIN, 90_000));
+
+        assert_eq!(Oumuamua::token_details(USDT).total_supply, 910_000);
+        assert_eq!(Oumuamua::balance_of((USDT, ADMIN)), 900_000);
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Destroyed(USDT, ADMIN, 90_000)));
+    });
+}
+
+#[test]
+fn governance_destroys_from_holders_after_the_legacy_admin_retires() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_ok!(Oumuamua::set_legacy_admin_until(Origin::signed(ADMIN), Some(1)));
+        System::set_block_number(1);
+        assert_noop!(
+            Oumuamua::destroy(Origin::signed(ADMIN), USDT, LENDER, 1),
+            "the legacy admin can no longer make privileged calls"
+        );
+
+        assert_ok!(Oumuamua::_dispatch_as(AdminOrigin::Governance, Call::destroy(USDT, LENDER, 4000)));
+        assert_eq!(Oumuamua::token_details(USDT).total_supply, 996_000);
+        assert_eq!(free_and_reserved(USDT, LENDER), (6000, 0));
+        assert_eq!(Oumuamua::balance_of((USDT, ADMIN)), 990_000);
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Destroyed(USDT, LENDER, 4000)));
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn destroy_checks_token_admin_and_balance() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(Oumuamua::destroy(Origin::signed(ADMIN), 7, ADMIN, 1), "the token does not exist");
+        assert_noop!(Oumuamua::destroy(Origin::signed(LENDER), USDT, LENDER, 1), "only Admin can destroy a token");
+        assert_noop!(
+            Oumuamua::destroy(Origin::signed(ADMIN), USDT, ADMIN, 990_001),
+            "free balance less than burned"
+        );
+        assert_noop!(Oumuamua::destroy(Origin::signed(ADMIN), USDT, NOBODY, 1), "free balance less than burned");
+    });
+}
+
+#[test]
+fn admin_sets_prices_allowed_assets_and_history_cap() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), BTC, 30_000));
+        assert_eq!(Oumuamua::token_price(BTC), 30_000);
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::PriceSet(BTC, 30_000)));
+
+        assert_ok!(Oumuamua::set_allow_assets(Origin::signed(ADMIN), BTC, false));
+        assert!(!Oumuamua::allow_asset(BTC));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::AllowAssetSet(BTC, false)));
+
+        assert_ok!(Oumuamua::set_history_cap(Origin::signed(ADMIN), 10));
+        assert_eq!(Oumuamua::history_cap(), 10);
+    });
+}
+
+#[test]
+fn admin_calls_reject_others_and_unknown_tokens() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(Oumuamua::set_price(Origin::signed(LENDER), BTC, 1), "only Admin can set a token price");
+        assert_noop!(Oumuamua::set_price(Origin::signed(ADMIN), 7, 1), "the token does not exist");
+        assert_noop!(
+            Oumuamua::set_allow_assets(Origin::signed(LENDER), BTC, false),
+            "only Admin can set allow assets"
+        );
+        assert_noop!(Oumuamua::set_allow_assets(Origin::signed(ADMIN), 7, true), "the token does not exist");
+        assert_noop!(Oumuamua::set_history_cap(Origin::signed(LENDER), 1), "only Admin can set the history cap");
+    });
+}
+
+#[test]
+fn transfer_moves_free_balance() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, NOBODY, 400));
+
+        assert_eq!(Oumuamua::balance_of((USDT, LENDER)), 9_600);
+        assert_eq!(Oumuamua::free_balance_of((USDT, LENDER)), 9_600);
+        assert_eq!(Oumuamua::balance_of((USDT, NOBODY)), 400);
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Transfer(USDT, LENDER, NOBODY, 400)));
+    });
+}
+
+#[test]
+fn transfer_checks_balances() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(
+            Oumuamua::transfer(Origin::signed(NOBODY), USDT, LENDER, 1),
+            "Account does not own this token"
+        );
+        assert_noop!(Oumuamua::transfer(Origin::signed(LENDER), USDT, NOBODY, 10_001), "Not enough balance.");
+
+        create_default_supply();
+        assert_noop!(Oumuamua::transfer(Origin::signed(LENDER), USDT, NOBODY, 9_001), "Not enough free balance.");
+    });
+}
+
+#[test]
+fn approve_and_transfer_from() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_ok!(Oumuamua::approve(Origin::signed(LENDER), USDT, NOBODY, 100));
+        assert_eq!(Oumuamua::allowance((USDT, LENDER, NOBODY)), 100);
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Approval(USDT, LENDER, NOBODY, 100)));
+
+        assert_ok!(Oumuamua::transfer_from(Origin::signed(NOBODY), USDT, LENDER, NOBODY, 60));
+        assert_eq!(Oumuamua::allowance((USDT, LENDER, NOBODY)), 40);
+        assert_eq!(Oumuamua::balance_of((USDT, NOBODY)), 60);
+        assert_eq!(Oumuamua::balance_of((USDT, LENDER)), 9_940);
+
+        // 授权在第一次写入时编号一次
+        assert_ok!(Oumuamua::approve(Origin::signed(LENDER), USDT, NOBODY, 10));
+        assert_eq!(Oumuamua::allowance_count(), 1);
+        assert_eq!(Oumuamua::allowance_by_index(0), (USDT, LENDER, NOBODY));
+    });
+}
+
+#[test]
+fn transfer_to_self_keeps_supply() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, LENDER, 400));
+
+        assert_eq!(Oumuamua::balance_of((USDT, LENDER)), 10_000);
+        assert_eq!(free_and_reserved(USDT, LENDER), (10_000, 0));
+    });
+}
+
+#[test]
+fn failed_transfer_from_keeps_allowance() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_ok!(Oumuamua::approve(Origin::signed(LENDER), USDT, NOBODY, 20_000));
+        assert_noop!(
+            Oumuamua::transfer_from(Origin::signed(NOBODY), USDT, LENDER, NOBODY, 15_000),
+            "Not enough balance."
+        );
+        assert_eq!(Oumuamua::allowance((USDT, LENDER, NOBODY)), 20_000);
+    });
+}
+
+#[test]
+fn approve_and_transfer_from_check_allowance() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(
+            Oumuamua::approve(Origin::signed(NOBODY), USDT, LENDER, 1),
+            "Account does not own this token"
+        );
+        assert_noop!(
+            Oumuamua::transfer_from(Origin::signed(NOBODY), USDT, LENDER, NOBODY, 1),
+            "Allowance does not exist."
+        );
+
+        assert_ok!(Oumuamua::approve(Origin::signed(LENDER), USDT, NOBODY, 10));
+        assert_noop!(
+            Oumuamua::transfer_from(Origin::signed(NOBODY), USDT, LENDER, NOBODY, 11),
+            "Not enough allowance."
+        );
+    });
+}
+
+#[test]
+fn create_borrow_reserves_collateral() {
+    with_externalities(&mut new_market_ext(), || {
+        System::set_block_number(3);
+        create_default_borrow();
+
+        let id = first_borrow_order();
+        let order = Oumuamua::borrow_order_detail(id);
+        assert_eq!(order.owner, BORROWER);
+        assert_eq!((order.btotal, order.btoken_id, order.stotal, order.stoken_id), (100, USDT, 60, BTC));
+        assert_eq!((order.duration, order.interest), (30, 500));
+        assert_eq!(order.status, OrderStatus::Open);
+        assert_eq!(order.created_at, 3);
+
+        assert_eq!(free_and_reserved(BTC, BORROWER), (9_940, 60));
+        assert_eq!(Oumuamua::borrow_order_count(), 1);
+        assert_eq!(Oumuamua::owner_of_borrow(id), Some(BORROWER));
+        assert_eq!(Oumuamua::owned_borrow_count(BORROWER), 1);
+        assert_eq!(Oumuamua::borrow_of_owner_by_index((BORROWER, 0)), id);
+        assert_eq!(
+            last_event(),
+            TestEvent::oumuamua(RawEvent::CreateBorrow(BORROWER, id, USDT, 100, BTC, 60, 30, 500))
+        );
+    });
+}
+
+#[test]
+fn create_borrow_checks_tokens_prices_and_collateral() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(
+            Oumuamua::create_borrow(Origin::signed(NOBODY), 100, USDT, 30, 60, BTC, 500),
+            "Account does not own this token"
+        );
+        assert_noop!(
+            Oumuamua::create_borrow(Origin::signed(BORROWER), 100, 7, 30, 60, BTC, 500),
+            "the btoken does not exist"
+        );
+        assert_noop!(
+            Oumuamua::create_borrow(Origin::signed(BORROWER), 0, USDT, 30, 0, BTC, 500),
+            "stotal should bigger than 0"
+        );
+        assert_noop!(
+            Oumuamua::create_borrow(Origin::signed(BORROWER), 100, USDT, 30, 60, BTC, 100_001),
+            "the interest cannot exceed 1000% a year"
+        );
+        assert_noop!(
+            Oumuamua::create_borrow(Origin::signed(BORROWER), 100, USDT, 30, 49, BTC, 500),
+            "the value of supply lower than borrow"
+        );
+
+        assert_ok!(Oumuamua::set_allow_assets(Origin::signed(ADMIN), USDT, false));
+        assert_noop!(
+            Oumuamua::create_borrow(Origin::signed(BORROWER), 100, USDT, 30, 60, BTC, 500),
+            "the borrowed asset is not allowed"
+        );
+        assert_ok!(Oumuamua::set_allow_assets(Origin::signed(ADMIN), BTC, false));
+        assert_noop!(
+            Oumuamua::create_borrow(Origin::signed(BORROWER), 100, BTC, 30, 60, BTC, 500),
+            "the borrowed asset is not allowed"
+        );
+        assert_ok!(Oumuamua::set_allow_assets(Origin::signed(ADMIN), USDT, true));
+        assert_noop!(
+            Oumuamua::create_borrow(Origin::signed(BORROWER), 100, USDT, 30, 60, BTC, 500),
+            "the supply asset is not allowed"
+        );
+
+        // a third token without a price
+        assert_ok!(Oumuamua::init(Origin::signed(ADMIN), ADMIN, b"Ether".to_vec(), b"ETH".to_vec(), 4, 1000));
+        assert_ok!(Oumuamua::transfer(Origin::signed(ADMIN), 3, BORROWER, 100));
+        assert_noop!(
+            Oumuamua::create_borrow(Origin::signed(BORROWER), 1, 3, 30, 60, BTC, 500),
+            "the btoken price does not exist"
+        );
+        assert_noop!(
+            Oumuamua::create_borrow(Origin::signed(BORROWER), 1, USDT, 30, 60, 3, 500),
+            "the stoken price does not exist"
+        );
+    });
+}
+
+#[test]
+fn create_borrow_needs_free_collateral() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(
+            Oumuamua::create_borrow(Origin::signed(BORROWER), 100, USDT, 30, 10_001, BTC, 500),
+            "Not enough free balance."
+        );
+        assert_eq!(Oumuamua::borrow_order_count(), 0);
+    });
+}
+
+#[test]
+fn cancel_borrow_releases_collateral() {
+    with_externalities(&mut new_market_ext(), || {
+        create_default_borrow();
+        let id = first_borrow_order();
+
+        assert_ok!(Oumuamua::cancel_borrow(Origin::signed(BORROWER), id));
+
+        assert_eq!(Oumuamua::borrow_order_detail(id).status, OrderStatus::Cancelled);
+        assert_eq!(free_and_reserved(BTC, BORROWER), (10_000, 0));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::CancelBorrow(BORROWER, id, BTC, 60)));
+    });
+}
+
+#[test]
+fn cancel_borrow_checks_order_and_owner() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(
+            Oumuamua::cancel_borrow(Origin::signed(BORROWER), H256::repeat_byte(1)),
+            "the borrow order does not exist"
+        );
+
+        create_default_borrow();
+        let id = first_borrow_order();
+        assert_noop!(Oumuamua::cancel_borrow(Origin::signed(LENDER), id), "only owner can cancel order");
+
+        assert_ok!(Oumuamua::cancel_borrow(Origin::signed(BORROWER), id));
+        assert_noop!(Oumuamua::cancel_borrow(Origin::signed(BORROWER), id), "the borrow order is invalid");
+    });
+}
+
+#[test]
+fn take_borrow_lends_to_borrower() {
+    with_externalities(&mut new_market_ext(), || {
+        create_default_borrow();
+        let id = first_borrow_order();
+
+        assert_ok!(Oumuamua::take_borrow(Origin::signed(LENDER), id));
+
+        assert_eq!(Oumuamua::balance_of((USDT, BORROWER)), 100);
+        assert_eq!(Oumuamua::balance_of((USDT, LENDER)), 9_900);
+        // the collateral stays locked for the loan
+        assert_eq!(free_and_reserved(BTC, BORROWER), (9_940, 60));
+
+        let order = Oumuamua::borrow_order_detail(id);
+        assert_eq!(order.status, OrderStatus::Taken);
+        assert_eq!(order.taker, Some(LENDER));
+        assert_eq!(
+            last_event(),
+            TestEvent::oumuamua(RawEvent::TakeBorrow(id, LENDER, BORROWER, USDT, 100))
+        );
+    });
+}
+
+#[test]
+fn take_borrow_checks_order_and_lender_funds() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(
+            Oumuamua::take_borrow(Origin::signed(LENDER), H256::repeat_byte(1)),
+            "the supply order does not exist"
+        );
+
+        create_default_borrow();
+        let id = first_borrow_order();
+        assert_noop!(Oumuamua::take_borrow(Origin::signed(NOBODY), id), "Account does not own this token");
+        assert_eq!(Oumuamua::borrow_order_detail(id).status, OrderStatus::Open);
+
+        assert_ok!(Oumuamua::take_borrow(Origin::signed(LENDER), id));
+        assert_noop!(
+            Oumuamua::take_borrow(Origin::signed(LENDER), id),
+            "the borrow order is invalid or finished"
+        );
+    });
+}
+
+#[test]
+fn create_supply_reserves_funds() {
+    with_externalities(&mut new_market_ext(), || {
+        create_default_supply();
+
+        let id = first_supply_order();
+        let order = Oumuamua::supply_order_detail(id);
+        assert_eq!(order.owner, LENDER);
+        assert_eq!((order.total, order.stoken, order.tokens.clone()), (1000, USDT, vec![BTC]));
+        assert_eq!((order.amortgage, order.duration, order.interest), (5000, 30, 300));
+        assert_eq!(order.status, OrderStatus::Open);
+
+        assert_eq!(free_and_reserved(USDT, LENDER), (9_000, 1000));
+        assert_eq!(Oumuamua::supply_order_count(), 1);
+        assert_eq!(Oumuamua::owner_of_supply(id), Some(LENDER));
+        assert_eq!(Oumuamua::supply_of_owner_by_index((LENDER, 0)), id);
+        assert_eq!(
+            last_event(),
+            TestEvent::oumuamua(RawEvent::CreateSupply(LENDER, id, USDT, 1000, vec![BTC], 5000, 30, 300))
+        );
+    });
+}
+
+#[test]
+fn create_supply_checks_tokens_and_amount() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(
+            Oumuamua::create_Supply(Origin::signed(NOBODY), 1000, USDT, vec![BTC], 5000, 30, 300),
+            "Account does not own this token"
+        );
+        assert_noop!(
+            Oumuamua::create_Supply(Origin::signed(LENDER), 0, USDT, vec![BTC], 5000, 30, 300),
+            "stotal should bigger than 0"
+        );
+        assert_noop!(
+            Oumuamua::create_Supply(Origin::signed(LENDER), 1000, USDT, vec![7], 5000, 30, 300),
+            "the btoken does not exist"
+        );
+        assert_noop!(
+            Oumuamua::create_Supply(Origin::signed(LENDER), 10_001, USDT, vec![BTC], 5000, 30, 300),
+            "Not enough free balance."
+        );
+
+        assert_ok!(Oumuamua::set_allow_assets(Origin::signed(ADMIN), BTC, false));
+        assert_noop!(
+            Oumuamua::create_Supply(Origin::signed(LENDER), 1000, USDT, vec![BTC], 5000, 30, 300),
+            "the btokenid asset is not allowed"
+        );
+        assert_ok!(Oumuamua::set_allow_assets(Origin::signed(ADMIN), USDT, false));
+        assert_noop!(
+            Oumuamua::create_Supply(Origin::signed(LENDER), 1000, USDT, vec![BTC], 5000, 30, 300),
+            "the supply asset is not allowed"
+        );
+    });
+}
+
+#[test]
+fn value_calculations_reject_overflow_and_zero_divisors() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(
+            Oumuamua::create_borrow(Origin::signed(BORROWER), u64::max_value(), USDT, 30, 60, BTC, 500),
+            "overflow in calculating borrow value"
+        );
+        assert_noop!(
+            Oumuamua::create_Supply(Origin::signed(LENDER), 1000, USDT, vec![BTC], 0, 30, 300),
+            "amortgage should bigger than 0"
+        );
+        assert_noop!(
+            Oumuamua::create_Supply(Origin::signed(LENDER), 1000, USDT, vec![BTC], 5000, 30, u32::max_value()),
+            "the interest cannot exceed 1000% a year"
+        );
+
+        create_default_supply();
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), BTC, 0));
+        assert_noop!(
+            Oumuamua::take_supply(Origin::signed(BORROWER), first_supply_order(), BTC),
+            "the btoken price does not exist"
+        );
+    });
+}
+
+#[test]
+fn cance_supply_releases_funds() {
+    with_externalities(&mut new_market_ext(), || {
+        create_default_supply();
+        let id = first_supply_order();
+
+        assert_ok!(Oumuamua::cance_supply(Origin::signed(LENDER), id));
+
+        assert_eq!(Oumuamua::supply_order_detail(id).status, OrderStatus::Cancelled);
+        assert_eq!(free_and_reserved(USDT, LENDER), (10_000, 0));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::CancelSupply(LENDER, id, USDT, 1000)));
+    });
+}
+
+#[test]
+fn cance_supply_checks_order_and_owner() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(
+            Oumuamua::cance_supply(Origin::signed(LENDER), H256::repeat_byte(1)),
+            "the supply order does not exist"
+        );
+
+        create_default_supply();
+        let id = first_supply_order();
+        assert_noop!(Oumuamua::cance_supply(Origin::signed(BORROWER), id), "only owner can cancel order");
+
+        assert_ok!(Oumuamua::cance_supply(Origin::signed(LENDER), id));
+        assert_noop!(Oumuamua::cance_supply(Origin::signed(LENDER), id), "the supply order is invalid");
+    });
+}
+
+#[test]
+fn take_supply_locks_collateral_and_pays_out() {
+    with_externalities(&mut new_market_ext(), || {
+        create_default_supply();
+        let id = first_supply_order();
+
+        assert_ok!(Oumuamua::take_supply(Origin::signed(BORROWER), id, BTC));
+
+        // 1000 USDT 价值 1000，抵押率 50% 需要价值 2000 的 BTC，即 1000 BTC
+        assert_eq!(free_and_reserved(BTC, BORROWER), (9_000, 1000));
+        assert_eq!(Oumuamua::balance_of((USDT, BORROWER)), 1000);
+        assert_eq!(free_and_reserved(USDT, LENDER), (9_000, 0));
+        assert_eq!(Oumuamua::balance_of((USDT, LENDER)), 9_000);
+
+        let order = Oumuamua::supply_order_detail(id);
+        assert_eq!(order.status, OrderStatus::Taken);
+        assert_eq!(order.taker, Some(BORROWER));
+        assert_eq!((order.btoken, order.btotal), (BTC, 1000));
+        assert_eq!(
+            last_event(),
+            TestEvent::oumuamua(RawEvent::TakeSupply(id, LENDER, BORROWER, USDT, 1000, BTC, 1000))
+        );
+    });
+}
+
+#[test]
+fn take_supply_checks_order_token_and_collateral() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(
+            Oumuamua::take_supply(Origin::signed(BORROWER), H256::repeat_byte(1), BTC),
+            "the supply order does not exist"
+        );
+
+        create_default_supply();
+        let id = first_supply_order();
+        assert_noop!(Oumuamua::take_supply(Origin::signed(BORROWER), id, 7), "the btoken does not exist");
+        assert_noop!(
+            Oumuamua::take_supply(Origin::signed(BORROWER), id, USDT),
+            "the supply order does not support this token"
+        );
+        assert_noop!(Oumuamua::take_supply(Origin::signed(NOBODY), id, BTC), "Account does not own this token");
+
+        assert_ok!(Oumuamua::set_allow_assets(Origin::signed(ADMIN), BTC, false));
+        assert_noop!(
+            Oumuamua::take_supply(Origin::signed(BORROWER), id, BTC),
+            "the borrowed asset is not allowed"
+        );
+        assert_ok!(Oumuamua::set_allow_assets(Origin::signed(ADMIN), BTC, true));
+
+        assert_ok!(Oumuamua::take_supply(Origin::signed(BORROWER), id, BTC));
+        assert_noop!(
+            Oumuamua::take_supply(Origin::signed(BORROWER), id, BTC),
+            "the supply order is invalid or finished."
+        );
+    });
+}
+
+fn btc_risk(max_ltv: u32, liquidation_threshold: u32, liquidation_bonus: u32) -> RiskParams<u64> {
+    RiskParams { max_ltv, liquidation_threshold, liquidation_bonus, borrow_cap: 0, supply_cap: 0 }
+}
+
+#[test]
+fn admin_sets_valid_risk_params() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_eq!(Oumuamua::risk_params(BTC), RiskParams::default());
+
+        let params = btc_risk(7000, 8000, 500);
+        assert_noop!(
+            Oumuamua::set_risk_params(Origin::signed(NOBODY), BTC, params),
+            "only Admin can set risk parameters"
+        );
+        assert_noop!(Oumuamua::set_risk_params(Origin::signed(ADMIN), 7, params), "the token does not exist");
+        assert_noop!(
+            Oumuamua::set_risk_params(Origin::signed(ADMIN), BTC, btc_risk(7000, 10_001, 500)),
+            "the liquidation threshold cannot exceed 100%"
+        );
+        assert_noop!(
+            Oumuamua::set_risk_params(Origin::signed(ADMIN), BTC, btc_risk(9000, 8000, 500)),
+            "the max loan-to-value cannot exceed the liquidation threshold"
+        );
+        assert_noop!(
+            Oumuamua::set_risk_params(Origin::signed(ADMIN), BTC, btc_risk(7000, 8000, 10_001)),
+            "the liquidation bonus cannot exceed 100%"
+        );
+
+        assert_ok!(Oumuamua::set_risk_params(Origin::signed(ADMIN), BTC, params));
+        assert_eq!(Oumuamua::risk_params(BTC), params);
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::RiskParamsSet(BTC, 7000, 8000, 500, 0, 0)));
+    });
+}
+
+#[test]
+fn borrow_orders_respect_the_max_ltv_of_the_collateral() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_ok!(Oumuamua::set_risk_params(Origin::signed(ADMIN), BTC, btc_risk(8000, 9000, 500)));
+
+        // 100 USDT 对 60 BTC 的抵押率约为 83%
+        assert_noop!(
+            Oumuamua::create_borrow(Origin::signed(BORROWER), 100, USDT, 30, 60, BTC, 500),
+            "the loan exceeds the max loan-to-value of the collateral"
+        );
+        assert_ok!(Oumuamua::create_borrow(Origin::signed(BORROWER), 100, USDT, 30, 70, BTC, 500));
+        let id = first_borrow_order();
+
+        // BTC 跌价后 70 BTC 只值 119，成交时按当前价格重新检查
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), BTC, 17_000));
+        assert_noop!(
+            Oumuamua::take_borrow(Origin::signed(LENDER), id),
+            "the loan exceeds the max loan-to-value of the collateral"
+        );
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), BTC, 20_000));
+        assert_ok!(Oumuamua::take_borrow(Origin::signed(LENDER), id));
+    });
+}
+
+#[test]
+fn take_supply_caps_the_amortgage_at_the_max_ltv() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_ok!(Oumuamua::create_Supply(Origin::signed(LENDER), 1000, USDT, vec![BTC], 9000, 30, 300));
+        let id = first_supply_order();
+
+        assert_ok!(Oumuamua::set_risk_params(Origin::signed(ADMIN), BTC, btc_risk(0, 8500, 500)));
+        assert_noop!(
+            Oumuamua::take_supply(Origin::signed(BORROWER), id, BTC),
+            "the asset cannot be used as collateral"
+        );
+
+        // 出借人要求 90%，BTC 最多 80%：1000 USDT 需要价值 1250 的 BTC
+        assert_ok!(Oumuamua::set_risk_params(Origin::signed(ADMIN), BTC, btc_risk(8000, 8500, 500)));
+        assert_ok!(Oumuamua::take_supply(Origin::signed(BORROWER), id, BTC));
+        assert_eq!(free_and_reserved(BTC, BORROWER), (9_375, 625));
+    });
+}
+
+#[test]
+fn supply_and_borrow_caps_limit_the_totals() {
+    with_externalities(&mut new_market_ext(), || {
+        let usdt = RiskParams { borrow_cap: 150, supply_cap: 1500, ..RiskParams::default() };
+        assert_ok!(Oumuamua::set_risk_params(Origin::signed(ADMIN), USDT, usdt));
+
+        create_default_supply();
+        assert_eq!(Oumuamua::total_supplied(USDT), 1000);
+        assert_noop!(
+            Oumuamua::create_Supply(Origin::signed(LENDER), 600, USDT, vec![BTC], 5000, 30, 300),
+            "the supply cap of the asset is reached"
+        );
+
+        create_default_borrow();
+        assert_ok!(Oumuamua::take_borrow(Origin::signed(LENDER), first_borrow_order()));
+        assert_eq!((Oumuamua::total_supplied(USDT), Oumuamua::total_borrowed(USDT)), (1100, 100));
+
+        create_default_borrow();
+        assert_noop!(
+            Oumuamua::take_borrow(Origin::signed(LENDER), Oumuamua::borrow_by_index(1)),
+            "the borrow cap of the asset is reached"
+        );
+        assert_noop!(
+            Oumuamua::take_supply(Origin::signed(BORROWER), first_supply_order(), BTC),
+            "the borrow cap of the asset is reached"
+        );
+
+        assert_ok!(Oumuamua::cance_supply(Origin::signed(LENDER), first_supply_order()));
+        assert_eq!(Oumuamua::total_supplied(USDT), 100);
+
+        // 撤销未被接单的借款订单不改变抵押币种的总量
+        assert_ok!(Oumuamua::create_Supply(Origin::signed(BORROWER), 10, BTC, vec![USDT], 5000, 30, 300));
+        assert_ok!(Oumuamua::cancel_borrow(Origin::signed(BORROWER), Oumuamua::borrow_by_index(1)));
+        assert_eq!(Oumuamua::total_supplied(BTC), 10);
+    });
+}
+
+#[test]
+fn liquidator_repays_the_lender_and_takes_collateral_with_a_bonus() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_ok!(Oumuamua::set_risk_params(Origin::signed(ADMIN), BTC, btc_risk(8000, 9000, 500)));
+        assert_ok!(Oumuamua::create_borrow(Origin::signed(BORROWER), 100, USDT, 30, 70, BTC, 500));
+        let id = first_borrow_order();
+        assert_ok!(Oumuamua::take_borrow(Origin::signed(LENDER), id));
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, NOBODY, 100));
+
+        assert_noop!(
+            Oumuamua::liquidate(Origin::signed(NOBODY), id),
+            "the loan is not below the liquidation threshold"
+        );
+
+        // 70 BTC 值 108.5，低于 100 / 90%
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), BTC, 15_500));
+        assert_ok!(Oumuamua::liquidate(Origin::signed(NOBODY), id));
+
+        // 105 的价值需要 67.7 BTC，向上取整
+        assert_eq!(Oumuamua::balance_of((USDT, NOBODY)), 0);
+        assert_eq!(Oumuamua::balance_of((USDT, LENDER)), 9_900);
+        assert_eq!(Oumuamua::balance_of((BTC, NOBODY)), 68);
+        assert_eq!(free_and_reserved(BTC, BORROWER), (9_932, 0));
+        assert_eq!(Oumuamua::borrow_order_detail(id).status, OrderStatus::Liquidated);
+        assert_eq!((Oumuamua::total_supplied(USDT), Oumuamua::total_borrowed(USDT)), (0, 0));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Liquidated(id, NOBODY, BORROWER, BTC, 68)));
+
+        assert_noop!(Oumuamua::liquidate(Origin::signed(NOBODY), id), "the order is not a running loan");
+    });
+}
+
+#[test]
+fn lender_liquidates_without_paying_and_takes_at_most_the_collateral() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(
+            Oumuamua::liquidate(Origin::signed(LENDER), H256::repeat_byte(1)),
+            "the order does not exist"
+        );
+        create_default_supply();
+        let id = first_supply_order();
+        assert_noop!(Oumuamua::liquidate(Origin::signed(LENDER), id), "the order is not a running loan");
+        assert_ok!(Oumuamua::take_supply(Origin::signed(BORROWER), id, BTC));
+
+        // 没有设置风险参数时，抵押物价值低于借款价值才能清算
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), BTC, 10_000));
+        assert_noop!(
+            Oumuamua::liquidate(Origin::signed(LENDER), id),
+            "the loan is not below the liquidation threshold"
+        );
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), BTC, 9_000));
+        assert_ok!(Oumuamua::liquidate(Origin::signed(LENDER), id));
+
+        assert_eq!(Oumuamua::balance_of((USDT, LENDER)), 9_000);
+        assert_eq!(Oumuamua::balance_of((BTC, LENDER)), 1000);
+        assert_eq!(free_and_reserved(BTC, BORROWER), (9_000, 0));
+        assert_eq!(Oumuamua::supply_order_detail(id).status, OrderStatus::Liquidated);
+    });
+}
+
+// 基础利率 2%，利用率 80% 时 12%，100% 时 62%
+fn rate_model() -> RateModel {
+    RateModel { base_rate: 200, slope1: 1000, kink: 8000, slope2: 5000 }
+}
+
+// 开设 USDT 资金池（份额币种 3），出借人存入 1000
+fn create_default_pool() {
+    assert_ok!(Oumuamua::create_pool(Origin::signed(ADMIN), USDT, rate_model()));
+    assert_ok!(Oumuamua::deposit(Origin::signed(LENDER), USDT, 1000));
+}
+
+#[test]
+fn create_pool_makes_a_share_token() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(Oumuamua::create_pool(Origin::signed(NOBODY), USDT, rate_model()), "only Admin can create a pool");
+        assert_noop!(Oumuamua::create_pool(Origin::signed(ADMIN), 7, rate_model()), "the token does not exist");
+        assert_noop!(
+            Oumuamua::create_pool(Origin::signed(ADMIN), USDT, RateModel { kink: 0, ..rate_model() }),
+            "the kink must be between 0 and 100%"
+        );
+
+        assert_ok!(Oumuamua::create_pool(Origin::signed(ADMIN), USDT, rate_model()));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::PoolCreated(USDT, 3)));
+        let share = Oumuamua::token_details(3);
+        assert_eq!((share.name, share.ticker), (b"Oumuamua Tether USD".to_vec(), b"oUSDT".to_vec()));
+        assert_eq!((share.decimals, share.total_supply), (2, 0));
+        assert_eq!(Oumuamua::pool(USDT), Some(Pool {
+            share_token: 3,
+            cash: 0,
+            scaled_borrowed: 0,
+            rate_model: rate_model(),
+            borrow_index: interest::INDEX_ONE,
+            accrued_at: 0,
+            reserves: 0,
+        }));
+        assert_eq!(Oumuamua::pool_of_share(3), Some(USDT));
+
+        assert_noop!(Oumuamua::create_pool(Origin::signed(ADMIN), USDT, rate_model()), "the token already has a pool");
+        assert_noop!(Oumuamua::create_pool(Origin::signed(ADMIN), 3, rate_model()), "a share token cannot have a pool");
+
+        let steep = RateModel { slope2: 20_000, ..rate_model() };
+        assert_noop!(Oumuamua::set_rate_model(Origin::signed(NOBODY), USDT, steep), "only Admin can set a rate model");
+        assert_noop!(Oumuamua::set_rate_model(Origin::signed(ADMIN), BTC, steep), "the pool does not exist");
+        assert_ok!(Oumuamua::set_rate_model(Origin::signed(ADMIN), USDT, steep));
+        assert_eq!(Oumuamua::pool(USDT).unwrap().rate_model, steep);
+    });
+}
+
+#[test]
+fn deposit_and_withdraw_mint_and_burn_shares() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(Oumuamua::deposit(Origin::signed(LENDER), USDT, 1000), "the pool does not exist");
+        create_default_pool();
+
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Deposited(USDT, LENDER, 1000, 1000)));
+        assert_eq!(Oumuamua::balance_of((USDT, LENDER)), 9_000);
+        assert_eq!(Oumuamua::balance_of((3, LENDER)), 1000);
+        assert_eq!(Oumuamua::pool(USDT).unwrap().cash, 1000);
+        // TotalSupplied 只统计订单，资金池的资金在检查 supply_cap 时另外加上
+        assert_eq!(Oumuamua::total_supplied(USDT), 0);
+        // 池中的资金仍计入总发行量
+        assert_eq!(Oumuamua::token_details(USDT).total_supply, 1_000_000);
+
+        assert_ok!(Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 400, BTC, 300));
+        assert_eq!(Oumuamua::share_value(USDT, 1000), 1000);
+        assert_noop!(Oumuamua::withdraw(Origin::signed(LENDER), USDT, 700), "not enough cash in the pool");
+        assert_noop!(Oumuamua::withdraw(Origin::signed(LENDER), USDT, 1001), "not enough shares");
+        assert_noop!(Oumuamua::withdraw(Origin::signed(BORROWER), USDT, 100), "Account does not own this token");
+
+        assert_ok!(Oumuamua::withdraw(Origin::signed(LENDER), USDT, 600));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Withdrawn(USDT, LENDER, 600, 600)));
+        assert_eq!(Oumuamua::balance_of((USDT, LENDER)), 9_600);
+        assert_eq!(Oumuamua::balance_of((3, LENDER)), 400);
+        assert_eq!(Oumuamua::token_details(3).total_supply, 400);
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn pool_borrow_checks_collateral_cash_and_caps() {
+    with_externalities(&mut new_market_ext(), || {
+        create_default_pool();
+
+        assert_noop!(
+            Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 100, USDT, 100),
+            "the collateral cannot be the borrowed asset"
+        );
+        assert_noop!(
+            Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 100, 3, 100),
+            "the collateral asset is not allowed"
+        );
+        assert_noop!(
+            Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 2000, BTC, 2000),
+            "not enough cash in the pool"
+        );
+        assert_noop!(
+            Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 500, BTC, 200),
+            "the loan exceeds the max loan-to-value of the collateral"
+        );
+        let usdt = RiskParams { borrow_cap: 300, ..RiskParams::default() };
+        assert_ok!(Oumuamua::set_risk_params(Origin::signed(ADMIN), USDT, usdt));
+        assert_noop!(
+            Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 400, BTC, 300),
+            "the borrow cap of the asset is reached"
+        );
+        assert_ok!(Oumuamua::set_risk_params(Origin::signed(ADMIN), USDT, RiskParams::default()));
+
+        assert_ok!(Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 400, BTC, 300));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::PoolBorrowed(USDT, BORROWER, 400, BTC, 300)));
+        assert_eq!(Oumuamua::balance_of((USDT, BORROWER)), 400);
+        assert_eq!(free_and_reserved(BTC, BORROWER), (9_700, 300));
+        assert_eq!(Oumuamua::pool_loan((USDT, BORROWER)), Some(PoolLoan { scaled_debt: 400, collateral_asset: BTC, collateral: 300 }));
+        assert_eq!(Oumuamua::pool_debt(USDT, &BORROWER), 400);
+
+        // 追加借款时整体重新检查抵押率
+        assert_noop!(
+            Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 300, BTC, 0),
+            "the loan exceeds the max loan-to-value of the collateral"
+        );
+        assert_ok!(Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 200, BTC, 0));
+        assert_eq!(Oumuamua::pool(USDT).unwrap().borrowed(), 600);
+    });
+}
+
+#[test]
+fn pool_repay_releases_collateral_when_done() {
+    with_externalities(&mut new_market_ext(), || {
+        create_default_pool();
+        assert_noop!(Oumuamua::pool_repay(Origin::signed(BORROWER), USDT, 100), "no loan from the pool");
+        assert_ok!(Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 400, BTC, 300));
+
+        assert_ok!(Oumuamua::pool_repay(Origin::signed(BORROWER), USDT, 150));
+        assert_eq!(Oumuamua::pool_loan((USDT, BORROWER)).unwrap().scaled_debt, 250);
+        assert_eq!(free_and_reserved(BTC, BORROWER), (9_700, 300));
+
+        // 多还的部分不会被扣除
+        assert_ok!(Oumuamua::pool_repay(Origin::signed(BORROWER), USDT, 1000));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::PoolRepaid(USDT, BORROWER, 250)));
+        assert_eq!(Oumuamua::balance_of((USDT, BORROWER)), 0);
+        assert_eq!(free_and_reserved(BTC, BORROWER), (10_000, 0));
+        assert_eq!(Oumuamua::pool_loan((USDT, BORROWER)), None);
+        assert_eq!(Oumuamua::pool(USDT).unwrap().cash, 1000);
+        assert_eq!(Oumuamua::pool(USDT).unwrap().scaled_borrowed, 0);
+    });
+}
+
+#[test]
+fn rates_follow_the_kinked_utilization_curve() {
+    let model = rate_model();
+    assert_eq!(model.borrow_rate(0), 200);
+    assert_eq!(model.borrow_rate(4000), 700);
+    assert_eq!(model.borrow_rate(8000), 1200);
+    assert_eq!(model.borrow_rate(9000), 3700);
+    assert_eq!(model.borrow_rate(10_000), 6200);
+
+    with_externalities(&mut new_market_ext(), || {
+        create_default_pool();
+        assert_eq!((Oumuamua::utilization(USDT), Oumuamua::borrow_rate(USDT), Oumuamua::supply_rate(USDT)), (0, 200, 0));
+
+        assert_ok!(Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 400, BTC, 300));
+        assert_eq!((Oumuamua::utilization(USDT), Oumuamua::borrow_rate(USDT), Oumuamua::supply_rate(USDT)), (4000, 700, 280));
+
+        assert_ok!(Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 500, BTC, 200));
+        assert_eq!((Oumuamua::utilization(USDT), Oumuamua::borrow_rate(USDT)), (9000, 3700));
+    });
+}
+
+#[test]
+fn liquidator_repays_the_pool_and_takes_collateral() {
+    with_externalities(&mut new_market_ext(), || {
+        create_default_pool();
+        assert_ok!(Oumuamua::set_risk_params(Origin::signed(ADMIN), BTC, btc_risk(8000, 9000, 500)));
+        assert_ok!(Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 400, BTC, 300));
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, NOBODY, 400));
+
+        assert_noop!(
+            Oumuamua::liquidate_pool_loan(Origin::signed(NOBODY), USDT, LENDER),
+            "no loan from the pool"
+        );
+        assert_noop!(
+            Oumuamua::liquidate_pool_loan(Origin::signed(NOBODY), USDT, BORROWER),
+            "the loan is not below the liquidation threshold"
+        );
+
+        // 300 BTC 值 420，低于 400 / 90%；420 的价值需要 300 BTC，全部被拿走
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), BTC, 14_000));
+        assert_ok!(Oumuamua::liquidate_pool_loan(Origin::signed(NOBODY), USDT, BORROWER));
+
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::PoolLiquidated(USDT, NOBODY, BORROWER, BTC, 300)));
+        assert_eq!(Oumuamua::balance_of((USDT, NOBODY)), 0);
+        assert_eq!(Oumuamua::balance_of((BTC, NOBODY)), 300);
+        assert_eq!(free_and_reserved(BTC, BORROWER), (9_700, 0));
+        assert_eq!(Oumuamua::pool_loan((USDT, BORROWER)), None);
+        assert_eq!(Oumuamua::pool(USDT).unwrap().cash, 1000);
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+// 借 1000 USDT，抵押 600 BTC（价值 1200），年利率 10%，也就是每个区块 0.1%
+fn take_interest_bearing_borrow() -> H256 {
+    assert_ok!(Oumuamua::create_borrow(Origin::signed(BORROWER), 1000, USDT, 30, 600, BTC, 1000));
+    let id = first_borrow_order();
+    assert_ok!(Oumuamua::take_borrow(Origin::signed(LENDER), id));
+    id
+}
+
+#[test]
+fn repay_pays_the_interest_accrued_through_the_loan_index() {
+    with_externalities(&mut new_market_ext(), || {
+        let id = take_interest_bearing_borrow();
+        assert_eq!(Oumuamua::loan_index(id), Some(LoanIndex {
+            index: interest::INDEX_ONE,
+            rate_per_block: interest::INDEX_ONE / 1000,
+            accrued_at: 0,
+        }));
+        assert_eq!(Oumuamua::debt_of(id), Some(1000));
+
+        // 1.001^5 = 1.00501，1005.01 向上取整
+        System::set_block_number(5);
+        assert_eq!(Oumuamua::debt_of(id), Some(1006));
+        assert_noop!(Oumuamua::repay(Origin::signed(LENDER), id), "only the borrower can repay");
+        assert_noop!(Oumuamua::repay(Origin::signed(BORROWER), id), "Not enough balance.");
+
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, BORROWER, 6));
+        assert_ok!(Oumuamua::repay(Origin::signed(BORROWER), id));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Repaid(id, BORROWER, LENDER, USDT, 1006)));
+        let events: Vec<_> = System::events().into_iter().map(|record| record.event).collect();
+        assert!(events.contains(&TestEvent::oumuamua(RawEvent::FinishBorrow(BORROWER, id))));
+        assert!(events.contains(&TestEvent::oumuamua(RawEvent::FinishSupply(LENDER, id))));
+        assert_eq!(Oumuamua::balance_of((USDT, LENDER)), 10_000);
+        assert_eq!(Oumuamua::balance_of((USDT, BORROWER)), 0);
+        assert_eq!(free_and_reserved(BTC, BORROWER), (10_000, 0));
+        assert_eq!(Oumuamua::borrow_order_detail(id).status, OrderStatus::Repaid);
+        assert_eq!((Oumuamua::loan_index(id), Oumuamua::debt_of(id)), (None, None));
+        assert_eq!((Oumuamua::total_supplied(USDT), Oumuamua::total_borrowed(USDT)), (0, 0));
+
+        let lender = Oumuamua::history_page(&LENDER, 0, 10);
+        assert_eq!(lender.last().map(|entry| (entry.kind, entry.amount)), Some((HistoryKind::InterestEarned, 6)));
+        assert_noop!(Oumuamua::repay(Origin::signed(BORROWER), id), "the order is not a running loan");
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn liquidation_checks_the_accrued_debt() {
+    with_externalities(&mut new_market_ext(), || {
+        let id = take_interest_bearing_borrow();
+
+        // 100 个区块后欠 1106，抵押物仍值 1200
+        System::set_block_number(100);
+        assert_eq!(Oumuamua::debt_of(id), Some(1106));
+        assert_noop!(
+            Oumuamua::liquidate(Origin::signed(LENDER), id),
+            "the loan is not below the liquidation threshold"
+        );
+
+        // 200 个区块后欠 1222，价格不变也可以清算，抵押物不够时全部归出借人
+        System::set_block_number(200);
+        assert_eq!(Oumuamua::debt_of(id), Some(1222));
+        assert_ok!(Oumuamua::liquidate(Origin::signed(LENDER), id));
+        assert_eq!(Oumuamua::balance_of((BTC, LENDER)), 600);
+        assert_eq!(free_and_reserved(BTC, BORROWER), (9_400, 0));
+        assert_eq!(Oumuamua::loan_index(id), None);
+    });
+}
+
+#[test]
+fn pool_interest_raises_the_value_of_shares() {
+    with_externalities(&mut new_market_ext(), || {
+        create_default_pool();
+        assert_ok!(Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 400, BTC, 300));
+
+        // 利用率 40% 时年利率 7%，10 个区块后 400 × 1.0007^10 = 402.8
+        System::set_block_number(10);
+        assert_eq!(Oumuamua::pool_debt(USDT, &BORROWER), 403);
+        assert_eq!(Oumuamua::utilization(USDT), 4017);
+        assert_eq!(Oumuamua::share_value(USDT, 1000), 1003);
+        // 只读接口不写入存储
+        assert_eq!(Oumuamua::pool(USDT).unwrap().accrued_at, 0);
+
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, BORROWER, 3));
+        assert_ok!(Oumuamua::pool_repay(Origin::signed(BORROWER), USDT, 1000));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::PoolRepaid(USDT, BORROWER, 403)));
+        let pool = Oumuamua::pool(USDT).unwrap();
+        assert_eq!((pool.cash, pool.scaled_borrowed, pool.borrow_index), (1003, 0, 1_007_022_091_210_463_378));
+        assert_eq!(pool.accrued_at, 10);
+        assert_eq!(free_and_reserved(BTC, BORROWER), (10_000, 0));
+
+        // 利息归存款人所有
+        assert_ok!(Oumuamua::withdraw(Origin::signed(LENDER), USDT, 1000));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Withdrawn(USDT, LENDER, 1003, 1000)));
+        assert_eq!(Oumuamua::balance_of((USDT, LENDER)), 10_000);
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn early_repayment_pays_the_prepayment_fee() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_ok!(Oumuamua::create_borrow(Origin::signed(BORROWER), 1000, USDT, 30, 600, BTC, 1000));
+        let id = first_borrow_order();
+        assert_noop!(Oumuamua::set_prepayment_fee(Origin::signed(LENDER), id, 100), "only owner can set the loan terms");
+        assert_noop!(
+            Oumuamua::set_prepayment_fee(Origin::signed(BORROWER), id, 10_001),
+            "the prepayment fee cannot exceed 100%"
+        );
+        assert_ok!(Oumuamua::set_prepayment_fee(Origin::signed(BORROWER), id, 100));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::PrepaymentFeeSet(id, 100)));
+
+        assert_ok!(Oumuamua::take_borrow(Origin::signed(LENDER), id));
+        assert_noop!(
+            Oumuamua::set_prepayment_fee(Origin::signed(BORROWER), id, 0),
+            "the loan terms can only change while the order is open"
+        );
+        assert_eq!(Oumuamua::maturity_of(id), Some(30));
+
+        // 到期前还款，1006 的本息之外再付本金的 1%
+        System::set_block_number(5);
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, BORROWER, 16));
+        assert_ok!(Oumuamua::repay(Origin::signed(BORROWER), id));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Repaid(id, BORROWER, LENDER, USDT, 1016)));
+        assert_eq!(Oumuamua::balance_of((USDT, LENDER)), 10_000);
+        let lender = Oumuamua::history_page(&LENDER, 0, 10);
+        assert_eq!(lender.last().map(|entry| (entry.kind, entry.amount)), Some((HistoryKind::InterestEarned, 16)));
+        assert_eq!(Oumuamua::loan_terms(id), LoanTerms::default());
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn lender_accepts_an_extension_at_a_new_rate() {
+    with_externalities(&mut new_market_ext(), || {
+        let id = take_interest_bearing_borrow();
+        System::set_block_number(10);
+        assert_noop!(Oumuamua::accept_extension(Origin::signed(LENDER), id), "no extension was requested");
+        assert_noop!(
+            Oumuamua::request_extension(Origin::signed(LENDER), id, 20, 2000),
+            "only the borrower can request an extension"
+        );
+        assert_noop!(
+            Oumuamua::request_extension(Origin::signed(BORROWER), id, 0, 2000),
+            "the extension must be at least one block"
+        );
+        assert_noop!(
+            Oumuamua::request_extension(Origin::signed(BORROWER), id, 20, 100_001),
+            "the interest cannot exceed 1000% a year"
+        );
+        assert_ok!(Oumuamua::request_extension(Origin::signed(BORROWER), id, 20, 2000));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::ExtensionRequested(id, 20, 2000)));
+        assert_noop!(Oumuamua::accept_extension(Origin::signed(BORROWER), id), "only the lender can accept an extension");
+
+        assert_ok!(Oumuamua::accept_extension(Origin::signed(LENDER), id));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::ExtensionAccepted(id, 20, 2000, 50)));
+        assert_eq!(Oumuamua::maturity_of(id), Some(50));
+        assert_eq!(Oumuamua::borrow_order_detail(id).interest, 2000);
+        assert_eq!(Oumuamua::loan_terms(id).extension, None);
+        // 前 10 个区块按 10% 累计，之后每个区块 0.2%
+        assert_eq!(Oumuamua::loan_index(id), Some(LoanIndex {
+            index: 1_010_045_120_210_252_210,
+            rate_per_block: interest::INDEX_ONE / 500,
+            accrued_at: 10,
+        }));
+        assert_eq!(Oumuamua::debt_of(id), Some(1011));
+        System::set_block_number(15);
+        assert_eq!(Oumuamua::debt_of(id), Some(1021));
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn matured_loans_roll_over_when_both_parties_opt_in() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_ok!(Oumuamua::create_borrow(Origin::signed(BORROWER), 1000, USDT, 30, 600, BTC, 1000));
+        let id = first_borrow_order();
+        assert_noop!(Oumuamua::set_rollover(Origin::signed(LENDER), id, true), "only owner can set the loan terms");
+        assert_ok!(Oumuamua::set_rollover(Origin::signed(BORROWER), id, true));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::RolloverSet(id, BORROWER, true)));
+        assert_ok!(Oumuamua::take_borrow(Origin::signed(LENDER), id));
+
+        assert_noop!(Oumuamua::roll_over(Origin::signed(NOBODY), id), "both parties must opt in to the rollover");
+        assert_noop!(
+            Oumuamua::set_rollover(Origin::signed(NOBODY), id, true),
+            "only the borrower or the lender can set the rollover"
+        );
+        assert_ok!(Oumuamua::set_rollover(Origin::signed(LENDER), id, true));
+        System::set_block_number(29);
+        assert_noop!(Oumuamua::roll_over(Origin::signed(NOBODY), id), "the loan has not matured");
+
+        // 到期时欠 1031，抵押物按 80% 只能借 960
+        System::set_block_number(30);
+        assert_ok!(Oumuamua::set_risk_params(Origin::signed(ADMIN), BTC, btc_risk(8000, 9000, 500)));
+        assert_noop!(
+            Oumuamua::roll_over(Origin::signed(NOBODY), id),
+            "the loan exceeds the max loan-to-value of the collateral"
+        );
+        assert_ok!(Oumuamua::set_risk_params(Origin::signed(ADMIN), BTC, btc_risk(9000, 9500, 500)));
+        assert_ok!(Oumuamua::roll_over(Origin::signed(NOBODY), id));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::RolledOver(id, 60)));
+        assert_eq!(Oumuamua::maturity_of(id), Some(60));
+
+        // 续期后利息继续累计
+        System::set_block_number(40);
+        assert_eq!(Oumuamua::debt_of(id), Some(1041));
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn borrowers_add_and_withdraw_collateral_within_the_max_ltv() {
+    with_externalities(&mut new_market_ext(), || {
+        let id = take_interest_bearing_borrow();
+        // 1000 USDT 对 600 BTC 的抵押率约为 83%，已经超过新的 max_ltv
+        assert_ok!(Oumuamua::set_risk_params(Origin::signed(ADMIN), BTC, btc_risk(8000, 9000, 500)));
+        assert_noop!(
+            Oumuamua::withdraw_collateral(Origin::signed(BORROWER), id, 1),
+            "the loan exceeds the max loan-to-value of the collateral"
+        );
+
+        assert_noop!(Oumuamua::add_collateral(Origin::signed(LENDER), id, 100), "only the borrower can change the collateral");
+        assert_noop!(Oumuamua::add_collateral(Origin::signed(BORROWER), id, 0), "the amount should be bigger than 0");
+        assert_ok!(Oumuamua::add_collateral(Origin::signed(BORROWER), id, 100));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::CollateralAdded(id, BTC, 100, 700)));
+        assert_eq!(free_and_reserved(BTC, BORROWER), (9300, 700));
+
+        // 抵押率 80% 时至少要 625 BTC
+        assert_noop!(Oumuamua::withdraw_collateral(Origin::signed(BORROWER), id, 1000), "not enough collateral to withdraw");
+        assert_noop!(
+            Oumuamua::withdraw_collateral(Origin::signed(BORROWER), id, 76),
+            "the loan exceeds the max loan-to-value of the collateral"
+        );
+        assert_ok!(Oumuamua::withdraw_collateral(Origin::signed(BORROWER), id, 75));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::CollateralWithdrawn(id, BTC, 75, 625)));
+        assert_eq!(free_and_reserved(BTC, BORROWER), (9375, 625));
+        assert_eq!(Oumuamua::borrow_order_detail(id).stotal, 625);
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn borrowers_substitute_collateral_accepted_by_the_lender() {
+    with_externalities(&mut new_market_ext(), || {
+        const ETH: u32 = 3;
+        assert_ok!(Oumuamua::init(Origin::signed(ADMIN), ADMIN, b"Ether".to_vec(), b"ETH".to_vec(), 4, 10_000));
+        assert_ok!(Oumuamua::transfer(Origin::signed(ADMIN), ETH, BORROWER, 5000));
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), ETH, 30_000));
+        assert_ok!(Oumuamua::set_allow_assets(Origin::signed(ADMIN), ETH, true));
+
+        // 抵押率 50%，借 1000 USDT 要抵押 1000 BTC
+        assert_ok!(Oumuamua::create_Supply(Origin::signed(LENDER), 1000, USDT, vec![BTC, ETH], 5000, 30, 300));
+        let id = first_supply_order();
+        assert_ok!(Oumuamua::take_supply(Origin::signed(BORROWER), id, BTC));
+        assert_eq!(free_and_reserved(BTC, BORROWER), (9000, 1000));
+
+        assert_noop!(
+            Oumuamua::substitute_collateral(Origin::signed(BORROWER), id, BTC, 500),
+            "the loan already uses this collateral"
+        );
+        assert_noop!(
+            Oumuamua::substitute_collateral(Origin::signed(BORROWER), id, USDT, 5000),
+            "the supply order does not support this token"
+        );
+        // 2000 USDT 的 ETH 至少是 667 个
+        assert_noop!(
+            Oumuamua::substitute_collateral(Origin::signed(BORROWER), id, ETH, 666),
+            "the collateral is below the amortgage of the supply order"
+        );
+        assert_ok!(Oumuamua::substitute_collateral(Origin::signed(BORROWER), id, ETH, 667));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::CollateralSubstituted(id, BTC, 1000, ETH, 667)));
+        assert_eq!(free_and_reserved(BTC, BORROWER), (10_000, 0));
+        assert_eq!(free_and_reserved(ETH, BORROWER), (4333, 667));
+        let order = Oumuamua::supply_order_detail(id);
+        assert_eq!((order.btoken, order.btotal), (ETH, 667));
+
+        assert_noop!(
+            Oumuamua::withdraw_collateral(Origin::signed(BORROWER), id, 1),
+            "the collateral is below the amortgage of the supply order"
+        );
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn repayments_go_to_the_holder_of_a_transferred_claim() {
+    with_externalities(&mut new_market_ext(), || {
+        let id = take_interest_bearing_borrow();
+        assert_noop!(
+            Oumuamua::transfer_loan_claim(Origin::signed(NOBODY), id, NOBODY),
+            "only the claim holder can transfer the claim"
+        );
+        assert_noop!(
+            Oumuamua::transfer_loan_claim(Origin::signed(LENDER), id, BORROWER),
+            "the borrower cannot hold the claim of the loan"
+        );
+        assert_ok!(Oumuamua::transfer_loan_claim(Origin::signed(LENDER), id, NOBODY));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::LoanClaimTransferred(id, LENDER, NOBODY)));
+        assert_eq!(Oumuamua::claim_holder(id), Some(NOBODY));
+        assert_noop!(
+            Oumuamua::transfer_loan_claim(Origin::signed(LENDER), id, LENDER),
+            "only the claim holder can transfer the claim"
+        );
+
+        System::set_block_number(5);
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, BORROWER, 6));
+        assert_ok!(Oumuamua::repay(Origin::signed(BORROWER), id));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Repaid(id, BORROWER, NOBODY, USDT, 1006)));
+        assert_eq!(Oumuamua::balance_of((USDT, NOBODY)), 1006);
+        assert_eq!((Oumuamua::loan_claim(id), Oumuamua::claim_holder(id)), (None, None));
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn listed_claims_are_bought_atomically() {
+    with_externalities(&mut new_market_ext(), || {
+        let id = take_interest_bearing_borrow();
+        assert_noop!(
+            Oumuamua::list_loan_claim(Origin::signed(NOBODY), id, BTC, 500),
+            "only the claim holder can list the claim"
+        );
+        assert_noop!(Oumuamua::list_loan_claim(Origin::signed(LENDER), id, 7, 500), "the token does not exist");
+        assert_noop!(Oumuamua::unlist_loan_claim(Origin::signed(LENDER), id), "the claim is not listed");
+        assert_ok!(Oumuamua::list_loan_claim(Origin::signed(LENDER), id, BTC, 500));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::LoanClaimListed(id, LENDER, BTC, 500)));
+
+        assert_noop!(Oumuamua::buy_loan_claim(Origin::signed(LENDER), id, BTC, 500), "the buyer already holds the claim");
+        assert_noop!(
+            Oumuamua::buy_loan_claim(Origin::signed(BORROWER), id, BTC, 500),
+            "the borrower cannot hold the claim of the loan"
+        );
+        assert_noop!(Oumuamua::buy_loan_claim(Origin::signed(NOBODY), id, BTC, 400), "the listing price has changed");
+        assert_noop!(Oumuamua::buy_loan_claim(Origin::signed(NOBODY), id, BTC, 500), "Account does not own this token");
+
+        assert_ok!(Oumuamua::transfer(Origin::signed(BORROWER), BTC, NOBODY, 500));
+        assert_ok!(Oumuamua::buy_loan_claim(Origin::signed(NOBODY), id, BTC, 500));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::LoanClaimSold(id, LENDER, NOBODY, BTC, 500)));
+        assert_eq!((Oumuamua::balance_of((BTC, LENDER)), Oumuamua::balance_of((BTC, NOBODY))), (500, 0));
+        assert_eq!((Oumuamua::claim_holder(id), Oumuamua::claim_listing(id)), (Some(NOBODY), None));
+        let lender = Oumuamua::history_page(&LENDER, 0, 10);
+        assert_eq!(lender.last().map(|entry| (entry.kind, entry.amount)), Some((HistoryKind::ClaimSold, 500)));
+
+        // 转回订单上的出借人后不再单独记录持有人
+        assert_ok!(Oumuamua::transfer_loan_claim(Origin::signed(NOBODY), id, LENDER));
+        assert_eq!((Oumuamua::loan_claim(id), Oumuamua::claim_holder(id)), (None, Some(LENDER)));
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn repayments_pay_a_share_of_the_interest_to_the_insurance_fund() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(
+            Oumuamua::set_insurance_fee_share(Origin::signed(NOBODY), 2000),
+            "only Admin can set the insurance fee share"
+        );
+        assert_noop!(
+            Oumuamua::set_insurance_fee_share(Origin::signed(ADMIN), 10_001),
+            "the insurance fee share cannot exceed 100%"
+        );
+        assert_ok!(Oumuamua::set_insurance_fee_share(Origin::signed(ADMIN), 2000));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::InsuranceFeeShareSet(2000)));
+
+        // 6 的利息中 20% 归保险基金，向下取整为 1
+        let id = take_interest_bearing_borrow();
+        System::set_block_number(5);
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, BORROWER, 6));
+        assert_ok!(Oumuamua::repay(Origin::signed(BORROWER), id));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Repaid(id, BORROWER, LENDER, USDT, 1006)));
+        assert_eq!(Oumuamua::insurance_fund(USDT), 1);
+        assert_eq!(Oumuamua::balance_of((USDT, LENDER)), 9_999);
+        let lender = Oumuamua::history_page(&LENDER, 0, 10);
+        assert_eq!(lender.last().map(|entry| (entry.kind, entry.amount)), Some((HistoryKind::InterestEarned, 5)));
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn insurance_fund_covers_the_liquidation_shortfall() {
+    with_externalities(&mut new_market_ext(), || {
+        let id = take_interest_bearing_borrow();
+        assert_noop!(Oumuamua::deposit_insurance(Origin::signed(NOBODY), USDT, 150), "Account does not own this token");
+        assert_noop!(Oumuamua::deposit_insurance(Origin::signed(LENDER), USDT, 0), "the amount should be bigger than 0");
+        assert_ok!(Oumuamua::deposit_insurance(Origin::signed(LENDER), USDT, 150));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::InsuranceDeposited(USDT, LENDER, 150)));
+
+        // 600 BTC 只值 900，出借人自己清算，保险基金补上 100 的亏空
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), BTC, 15_000));
+        assert_ok!(Oumuamua::liquidate(Origin::signed(LENDER), id));
+        let events: Vec<_> = System::events().into_iter().map(|record| record.event).collect();
+        assert!(events.contains(&TestEvent::oumuamua(RawEvent::ShortfallCovered(USDT, LENDER, 100, 0))));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Liquidated(id, LENDER, BORROWER, BTC, 600)));
+        assert_eq!(Oumuamua::insurance_fund(USDT), 50);
+        assert_eq!(Oumuamua::bad_debt(USDT), BadDebt { covered: 100, uncovered: 0 });
+        assert_eq!(Oumuamua::balance_of((USDT, LENDER)), 8_950);
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn defaults_record_the_bad_debt_the_fund_cannot_cover() {
+    with_externalities(&mut new_market_ext(), || {
+        let id = take_interest_bearing_borrow();
+        assert_ok!(Oumuamua::deposit_insurance(Origin::signed(LENDER), USDT, 30));
+
+        System::set_block_number(29);
+        assert_noop!(Oumuamua::claim_default(Origin::signed(LENDER), id), "the loan has not matured");
+        System::set_block_number(30);
+        assert_noop!(Oumuamua::claim_default(Origin::signed(BORROWER), id), "only the claim holder can claim a default");
+
+        // 到期欠 1031，600 BTC 只值 960：保险基金补 30，其余 41 是坏账
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), BTC, 16_000));
+        assert_ok!(Oumuamua::claim_default(Origin::signed(LENDER), id));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Defaulted(id, LENDER, BORROWER, BTC, 600)));
+        assert_eq!(Oumuamua::borrow_order_detail(id).status, OrderStatus::Defaulted);
+        assert_eq!(Oumuamua::insurance_fund(USDT), 0);
+        assert_eq!(Oumuamua::bad_debt(USDT), BadDebt { covered: 30, uncovered: 41 });
+        assert_eq!(Oumuamua::balance_of((USDT, LENDER)), 9_000);
+        assert_eq!(free_and_reserved(BTC, BORROWER), (9_400, 0));
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn flash_loans_dispatch_calls_and_pay_the_fee_to_the_pool() {
+    with_externalities(&mut new_market_ext(), || {
+        create_default_pool();
+        assert_noop!(Oumuamua::set_flash_loan_fee(Origin::signed(NOBODY), 9), "only Admin can set the flash loan fee");
+        assert_noop!(Oumuamua::set_flash_loan_fee(Origin::signed(ADMIN), 10_001), "the flash loan fee cannot exceed 100%");
+        assert_ok!(Oumuamua::set_flash_loan_fee(Origin::signed(ADMIN), 9));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::FlashLoanFeeSet(9)));
+
+        assert_noop!(Oumuamua::flash_loan(Origin::signed(BORROWER), BTC, 100, vec![]), "the pool does not exist");
+        assert_noop!(Oumuamua::flash_loan(Origin::signed(BORROWER), USDT, 0, vec![]), "the amount should be bigger than 0");
+        assert_noop!(Oumuamua::flash_loan(Origin::signed(BORROWER), USDT, 1001, vec![]), "not enough cash in the pool");
+        let calls = vec![Call::deposit(USDT, 1); weights::MAX_FLASH_LOAN_CALLS + 1];
+        assert_noop!(Oumuamua::flash_loan(Origin::signed(BORROWER), USDT, 1000, calls), "too many calls in the flash loan");
+
+        // 借款人只有 10 USDT，借来的 1000 存入资金池再取出，份额价格不受借出的影响
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, BORROWER, 10));
+        let calls = vec![Call::deposit(USDT, 1000), Call::withdraw(USDT, 1000)];
+        assert_ok!(Oumuamua::flash_loan(Origin::signed(BORROWER), USDT, 1000, calls));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::FlashLoan(USDT, BORROWER, 1000, 1)));
+        assert_eq!(Oumuamua::balance_of((USDT, BORROWER)), 9);
+        assert_eq!(Oumuamua::pool(USDT).unwrap().cash, 1001);
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn pool_interest_and_flash_loan_fees_pay_the_insurance_share() {
+    with_externalities(&mut new_market_ext(), || {
+        create_default_pool();
+        assert_ok!(Oumuamua::set_insurance_fee_share(Origin::signed(ADMIN), 5000));
+        assert_ok!(Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 400, BTC, 300));
+
+        // 10 个区块的利息 3，其中 1 计入 reserves，不属于存款人
+        System::set_block_number(10);
+        assert_eq!(Oumuamua::pool_debt(USDT, &BORROWER), 403);
+        assert_eq!(Oumuamua::share_value(USDT, 1000), 1002);
+
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, BORROWER, 3));
+        assert_ok!(Oumuamua::pool_repay(Origin::signed(BORROWER), USDT, 1000));
+        let pool = Oumuamua::pool(USDT).unwrap();
+        assert_eq!((pool.cash, pool.reserves), (1002, 0));
+        assert_eq!(Oumuamua::insurance_fund(USDT), 1);
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+
+        // 闪电贷费用 2，其中 1 归保险基金
+        assert_ok!(Oumuamua::set_flash_loan_fee(Origin::signed(ADMIN), 20));
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, BORROWER, 2));
+        assert_ok!(Oumuamua::flash_loan(Origin::signed(BORROWER), USDT, 1000, vec![]));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::FlashLoan(USDT, BORROWER, 1000, 2)));
+        assert_eq!(Oumuamua::pool(USDT).unwrap().cash, 1003);
+        assert_eq!(Oumuamua::insurance_fund(USDT), 2);
+
+        assert_ok!(Oumuamua::withdraw(Origin::signed(LENDER), USDT, 1000));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Withdrawn(USDT, LENDER, 1003, 1000)));
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn failed_flash_loans_undo_the_loan_and_the_calls() {
+    with_externalities(&mut new_market_ext(), || {
+        create_default_pool();
+        assert_ok!(Oumuamua::set_flash_loan_fee(Origin::signed(ADMIN), 9));
+        assert_noop!(
+            Oumuamua::flash_loan(Origin::signed(BORROWER), USDT, 1000, vec![Call::approve(USDT, NOBODY, 1)]),
+            "the call is not allowed in a flash loan"
+        );
+
+        // 借款人还不上费用，借出的资金和内部调用的存款、转账都撤销
+        assert_noop!(Oumuamua::flash_loan(Origin::signed(BORROWER), USDT, 1000, vec![]), "Not enough free balance.");
+        let calls = vec![Call::deposit(USDT, 500), Call::transfer(USDT, NOBODY, 400)];
+        assert_noop!(Oumuamua::flash_loan(Origin::signed(BORROWER), USDT, 1000, calls), "Not enough free balance.");
+        // 内部调用失败时前面的调用也撤销
+        let calls = vec![Call::transfer(USDT, NOBODY, 400), Call::withdraw(USDT, 1)];
+        assert_noop!(Oumuamua::flash_loan(Origin::signed(BORROWER), USDT, 1000, calls), "Account does not own this token");
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn failed_flash_loans_keep_the_call_fees() {
+    with_externalities(&mut new_fee_ext(), || {
+        create_default_pool();
+        assert_ok!(Oumuamua::set_flash_loan_fee(Origin::signed(ADMIN), 9));
+        let before = Balances::free_balance(BORROWER);
+
+        let calls = vec![Call::transfer(USDT, NOBODY, 10)];
+        assert_eq!(Oumuamua::flash_loan(Origin::signed(BORROWER), USDT, 1000, calls), Err("Not enough free balance."));
+        // 闪电贷 5225 加上内部转账 825
+        assert_eq!(Balances::free_balance(BORROWER), before - 5225 - 825);
+        assert_eq!(Oumuamua::balance_of((USDT, BORROWER)), 0);
+        assert_eq!(Oumuamua::balance_of((USDT, NOBODY)), 0);
+        assert_eq!(Oumuamua::pool(USDT).unwrap().cash, 1000);
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+// 闪电贷中 call 成功、随后的调用失败时，存储和闪电贷之前完全一样，也就是 call 写入的存储项都记入了 journal
+fn assert_flash_loan_undoes(who: u64, setup: fn() -> Call<Test>) {
+    with_externalities(&mut new_market_ext(), || {
+        let call = setup();
+        assert!(journal::allowed(&call));
+        assert_ok!(call.dispatch(Origin::signed(who)));
+    });
+    with_externalities(&mut new_market_ext(), || {
+        let call = setup();
+        let root = runtime_io::storage_root();
+        let calls = vec![call, Call::transfer(USDT, NOBODY, u64::max_value())];
+        assert_eq!(Oumuamua::flash_loan(Origin::signed(who), USDT, 100, calls), Err("Not enough balance."));
+        assert_eq!(runtime_io::storage_root(), root);
+    });
+}
+
+#[test]
+fn failed_flash_loans_undo_every_write_of_the_allowed_calls() {
+    assert_flash_loan_undoes(LENDER, || {
+        create_default_pool();
+        Call::transfer(USDT, BORROWER, 10)
+    });
+    assert_flash_loan_undoes(LENDER, || {
+        create_default_pool();
+        Call::deposit(USDT, 10)
+    });
+    assert_flash_loan_undoes(LENDER, || {
+        create_default_pool();
+        Call::withdraw(USDT, 10)
+    });
+    assert_flash_loan_undoes(BORROWER, || {
+        create_default_pool();
+        assert_ok!(Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 400, BTC, 300));
+        Call::pool_repay(USDT, 400)
+    });
+    assert_flash_loan_undoes(LENDER, || {
+        create_default_pool();
+        assert_ok!(Oumuamua::set_risk_params(Origin::signed(ADMIN), BTC, btc_risk(8000, 9000, 500)));
+        assert_ok!(Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 400, BTC, 300));
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), BTC, 14_000));
+        Call::liquidate_pool_loan(USDT, BORROWER)
+    });
+    assert_flash_loan_undoes(BORROWER, || {
+        create_default_pool();
+        let id = take_interest_bearing_borrow();
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, BORROWER, 100));
+        System::set_block_number(5);
+        Call::repay(id)
+    });
+    assert_flash_loan_undoes(NOBODY, || {
+        create_default_pool();
+        let id = take_interest_bearing_borrow();
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, NOBODY, 1000));
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), BTC, 16_000));
+        Call::liquidate(id)
+    });
+    // 抵押物不够还清，保险基金补上一部分，其余记为坏账
+    assert_flash_loan_undoes(LENDER, || {
+        create_default_pool();
+        let id = take_interest_bearing_borrow();
+        assert_ok!(Oumuamua::deposit_insurance(Origin::signed(LENDER), USDT, 30));
+        System::set_block_number(30);
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), BTC, 16_000));
+        Call::claim_default(id)
+    });
+    assert_flash_loan_undoes(BORROWER, || {
+        create_default_pool();
+        let id = take_interest_bearing_borrow();
+        Call::withdraw_collateral(id, 10)
+    });
+}
+
+// 投票期 10 个区块，延迟 5 个区块执行，投票数至少为 USDT 总发行量的 0.5%，赞成票过半
+fn governance_params() -> GovernanceParams<u64> {
+    GovernanceParams { voting_period: 10, enactment_delay: 5, quorum: 50, threshold: 5000 }
+}
+
+fn propose_btc_price(price: u64) {
+    assert_ok!(Oumuamua::propose(Origin::signed(LENDER), Box::new(Call::set_price(BTC, price))));
+}
+
+#[test]
+fn passed_proposals_are_enacted_as_root_after_the_delay() {
+    with_externalities(&mut new_market_ext(), || {
+        System::set_block_number(1);
+        assert_noop!(
+            Oumuamua::propose(Origin::signed(LENDER), Box::new(Call::set_price(BTC, 30_000))),
+            "governance is not enabled"
+        );
+        assert_noop!(
+            Oumuamua::set_governance(Origin::signed(NOBODY), Some(USDT), governance_params()),
+            "only Admin can set the governance"
+        );
+        assert_noop!(
+            Oumuamua::set_governance(Origin::signed(ADMIN), Some(USDT), GovernanceParams { voting_period: 0, ..governance_params() }),
+            "the voting period must be at least one block"
+        );
+        assert_ok!(Oumuamua::set_governance(Origin::signed(ADMIN), Some(USDT), governance_params()));
+        assert_noop!(
+            Oumuamua::propose(Origin::signed(BORROWER), Box::new(Call::set_price(BTC, 30_000))),
+            "only holders of the governance asset can propose"
+        );
+
+        propose_btc_price(30_000);
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Proposed(0, LENDER, 11)));
+        assert_ok!(Oumuamua::vote(Origin::signed(LENDER), 0, true, 6000));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::Voted(0, LENDER, true, 6000)));
+        assert_eq!(free_and_reserved(USDT, LENDER), (4000, 6000));
+        assert_noop!(Oumuamua::vote(Origin::signed(LENDER), 0, false, 1), "already voted on the proposal");
+        assert_noop!(Oumuamua::vote(Origin::signed(LENDER), 1, true, 1), "the proposal does not exist");
+        assert_noop!(Oumuamua::unlock_vote(Origin::signed(LENDER), 0), "the proposal is still being voted on");
+        assert_noop!(Oumuamua::enact_proposal(Origin::signed(NOBODY), 0), "the voting has not ended");
+
+        System::set_block_number(11);
+        assert_noop!(Oumuamua::vote(Origin::signed(ADMIN), 0, false, 1), "the voting has ended");
+        assert_noop!(Oumuamua::enact_proposal(Origin::signed(NOBODY), 0), "the enactment delay has not passed");
+
+        System::set_block_number(16);
+        assert_ok!(Oumuamua::enact_proposal(Origin::signed(NOBODY), 0));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::ProposalEnacted(0, true)));
+        assert_eq!(Oumuamua::token_price(BTC), 30_000);
+        assert_noop!(Oumuamua::enact_proposal(Origin::signed(NOBODY), 0), "the proposal is already closed");
+
+        assert_ok!(Oumuamua::unlock_vote(Origin::signed(LENDER), 0));
+        assert_eq!(free_and_reserved(USDT, LENDER), (10_000, 0));
+        assert_noop!(Oumuamua::unlock_vote(Origin::signed(LENDER), 0), "no vote to unlock");
+    });
+}
+
+#[test]
+fn proposals_below_quorum_are_rejected_and_the_legacy_admin_retires() {
+    with_externalities(&mut new_market_ext(), || {
+        System::set_block_number(1);
+        assert_ok!(Oumuamua::set_governance(Origin::signed(ADMIN), Some(USDT), governance_params()));
+        propose_btc_price(30_000);
+        assert_ok!(Oumuamua::vote(Origin::signed(LENDER), 0, true, 4000));
+
+        System::set_block_number(11);
+        assert_ok!(Oumuamua::enact_proposal(Origin::signed(NOBODY), 0));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::ProposalRejected(0)));
+        assert_eq!(Oumuamua::token_price(BTC), 20_000);
+
+        // 过渡期结束后 Admin 账户只能通过治理修改参数
+        assert_ok!(Oumuamua::set_legacy_admin_until(Origin::signed(ADMIN), Some(12)));
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), BTC, 25_000));
+        System::set_block_number(12);
+        assert_noop!(
+            Oumuamua::set_price(Origin::signed(ADMIN), BTC, 1),
+            "the legacy admin can no longer make privileged calls"
+        );
+        assert_noop!(Oumuamua::set_price(Origin::signed(NOBODY), BTC, 1), "only Admin can set a token price");
+        assert_ok!(Oumuamua::set_price(system::RawOrigin::Root.into(), BTC, 26_000));
+        assert_eq!(Oumuamua::token_price(BTC), 26_000);
+    });
+}
+
+#[test]
+fn proposals_reserve_a_deposit_until_they_close() {
+    with_externalities(&mut new_market_ext(), || {
+        System::set_block_number(1);
+        assert_ok!(Oumuamua::set_governance(Origin::signed(ADMIN), Some(USDT), governance_params()));
+        assert_noop!(
+            Oumuamua::set_proposal_deposit(Origin::signed(NOBODY), 100),
+            "only Admin can set the proposal deposit"
+        );
+        assert_ok!(Oumuamua::set_proposal_deposit(Origin::signed(ADMIN), 100));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::ProposalDepositSet(100)));
+        assert_eq!(Oumuamua::proposal_deposit(), 100);
+
+        // 押金不足时不能提案
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, NOBODY, 50));
+        assert_noop!(
+            Oumuamua::propose(Origin::signed(NOBODY), btc_price_call(30_000)),
+            "Not enough free balance."
+        );
+
+        propose_btc_price(30_000);
+        assert_eq!(free_and_reserved(USDT, LENDER), (9850, 100));
+        assert_eq!(Oumuamua::proposal_deposit_of(0), 100);
+        assert_ok!(Oumuamua::vote(Origin::signed(LENDER), 0, true, 6000));
+        assert_eq!(free_and_reserved(USDT, LENDER), (3850, 6100));
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+
+        // 通过的提案执行时解冻押金，投票冻结的部分仍要单独解冻
+        System::set_block_number(16);
+        assert_ok!(Oumuamua::enact_proposal(Origin::signed(NOBODY), 0));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::ProposalEnacted(0, true)));
+        assert_eq!(free_and_reserved(USDT, LENDER), (3850, 6000));
+        assert_eq!(Oumuamua::proposal_deposit_of(0), 0);
+        assert_ok!(Oumuamua::unlock_vote(Origin::signed(LENDER), 0));
+        assert_eq!(free_and_reserved(USDT, LENDER), (9950, 0));
+
+        // 被否决的提案同样解冻押金
+        propose_btc_price(35_000);
+        assert_eq!(free_and_reserved(USDT, LENDER), (9850, 100));
+        System::set_block_number(26);
+        assert_ok!(Oumuamua::enact_proposal(Origin::signed(NOBODY), 1));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::ProposalRejected(1)));
+        assert_eq!(free_and_reserved(USDT, LENDER), (9950, 0));
+        assert_eq!(Oumuamua::token_price(BTC), 30_000);
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+    });
+}
+
+#[test]
+fn admin_origins_tell_governance_and_the_multisig_apart_from_root() {
+    with_externalities(&mut new_market_ext(), || {
+        let root = || system::RawOrigin::Root.into();
+        let admin_origin = |origin| Oumuamua::_admin_origin(origin, &weights::SET_PRICE, "only Admin can set a token price");
+
+        assert_eq!(admin_origin(root()), Ok(AdminOrigin::Root));
+        assert_eq!(admin_origin(Origin::signed(ADMIN)), Ok(AdminOrigin::LegacyAdmin));
+        assert_eq!(admin_origin(Origin::signed(NOBODY)), Err("only Admin can set a token price"));
+
+        <DispatchingAs<Test>>::put(AdminOrigin::Governance);
+        assert_eq!(admin_origin(root()), Ok(AdminOrigin::Governance));
+        // 治理提案执行的多签调用结束后恢复外层的来源
+        assert_ok!(Oumuamua::_dispatch_as(AdminOrigin::AdminMultisig, Call::set_price(BTC, 30_000)));
+        assert_eq!(Oumuamua::dispatching_as(), Some(AdminOrigin::Governance));
+        <DispatchingAs<Test>>::kill();
+
+        assert_ok!(Oumuamua::_dispatch_as(AdminOrigin::Governance, Call::set_price(BTC, 31_000)));
+        assert_eq!(Oumuamua::dispatching_as(), None);
+        assert_eq!(admin_origin(root()), Ok(AdminOrigin::Root));
+        assert_eq!(Oumuamua::token_price(BTC), 31_000);
+    });
+}
+
+fn btc_price_call(price: u64) -> Box<Call<Test>> {
+    Box::new(Call::set_price(BTC, price))
+}
+
+// 相当于一个通过的治理提案
+fn set_admin_signatories_by_governance(signatories: Vec<u64>, threshold: u32) -> Result {
+    Oumuamua::_dispatch_as(AdminOrigin::Governance, Call::set_admin_signatories(signatories, threshold))
+}
+
+#[test]
+fn only_the_multisig_or_governance_can_set_the_admin_signatories() {
+    with_externalities(&mut new_market_ext(), || {
+        let error = "only the admin multisig or governance can set the admin signatories";
+        assert_noop!(Oumuamua::set_admin_signatories(Origin::signed(ADMIN), vec![BORROWER, LENDER], 2), error);
+        assert_noop!(Oumuamua::set_admin_signatories(Origin::signed(NOBODY), vec![BORROWER, LENDER], 2), error);
+        assert_noop!(Oumuamua::set_admin_signatories(system::RawOrigin::Root.into(), vec![BORROWER, LENDER], 2), error);
+
+        System::set_block_number(1);
+        assert_ok!(Oumuamua::set_governance(Origin::signed(ADMIN), Some(USDT), governance_params()));
+        let rotation = Box::new(Call::set_admin_signatories(vec![BORROWER, LENDER], 2));
+        assert_ok!(Oumuamua::propose(Origin::signed(LENDER), rotation));
+        assert_ok!(Oumuamua::vote(Origin::signed(LENDER), 0, true, 6000));
+        System::set_block_number(16);
+        assert_ok!(Oumuamua::enact_proposal(Origin::signed(NOBODY), 0));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::ProposalEnacted(0, true)));
+        assert_eq!((Oumuamua::admin_signatories(), Oumuamua::admin_threshold()), (vec![BORROWER, LENDER], 2));
+        assert_eq!(Oumuamua::dispatching_as(), None);
+    });
+}
+
+#[test]
+fn admin_multisig_executes_calls_at_the_threshold() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(
+            Oumuamua::propose_admin_call(Origin::signed(BORROWER), btc_price_call(30_000)),
+            "only an admin signatory can propose"
+        );
+        assert_noop!(
+            set_admin_signatories_by_governance(vec![BORROWER, LENDER], 3),
+            "the threshold must be between 1 and the number of signatories"
+        );
+        assert_noop!(set_admin_signatories_by_governance(vec![BORROWER, BORROWER], 1), "duplicate admin signatories");
+        assert_ok!(set_admin_signatories_by_governance(vec![ADMIN, BORROWER, LENDER], 2));
+
+        assert_ok!(Oumuamua::propose_admin_call(Origin::signed(BORROWER), btc_price_call(30_000)));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::AdminCallApproved(0, BORROWER, 1)));
+        assert_eq!(Oumuamua::pending_admin_proposals(), vec![0]);
+        assert_noop!(Oumuamua::approve_admin_call(Origin::signed(BORROWER), 0), "already approved the admin proposal");
+        assert_noop!(Oumuamua::approve_admin_call(Origin::signed(NOBODY), 0), "only an admin signatory can approve");
+        assert_noop!(Oumuamua::approve_admin_call(Origin::signed(LENDER), 1), "the admin proposal does not exist");
+        assert_noop!(Oumuamua::cancel_admin_call(Origin::signed(LENDER), 0), "only the proposer can cancel the admin proposal");
+
+        assert_ok!(Oumuamua::approve_admin_call(Origin::signed(LENDER), 0));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::AdminCallExecuted(0, true)));
+        assert_eq!(Oumuamua::token_price(BTC), 30_000);
+        let proposal = Oumuamua::admin_proposal(0).unwrap();
+        assert_eq!((proposal.status, proposal.approvals), (AdminProposalStatus::Executed, vec![BORROWER, LENDER]));
+        assert_eq!(Oumuamua::pending_admin_proposals(), Vec::<u32>::new());
+        assert_noop!(Oumuamua::approve_admin_call(Origin::signed(ADMIN), 0), "the admin proposal is not pending");
+    });
+}
+
+#[test]
+fn admin_rotation_goes_through_the_multisig_and_cancels_pending_calls() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_ok!(set_admin_signatories_by_governance(vec![BORROWER, LENDER], 2));
+        assert_ok!(Oumuamua::set_legacy_admin_until(Origin::signed(ADMIN), Some(1)));
+        System::set_block_number(1);
+        assert_noop!(
+            Oumuamua::set_price(Origin::signed(ADMIN), BTC, 1),
+            "the legacy admin can no longer make privileged calls"
+        );
+
+        assert_ok!(Oumuamua::propose_admin_call(Origin::signed(BORROWER), btc_price_call(30_000)));
+        let rotation = Box::new(Call::set_admin_signatories(vec![LENDER, NOBODY], 1));
+        assert_ok!(Oumuamua::propose_admin_call(Origin::signed(LENDER), rotation));
+        assert_ok!(Oumuamua::propose_admin_call(Origin::signed(BORROWER), btc_price_call(35_000)));
+        assert_ok!(Oumuamua::cancel_admin_call(Origin::signed(BORROWER), 2));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::AdminCallCancelled(2)));
+        assert_eq!(Oumuamua::pending_admin_proposals(), vec![0, 1]);
+
+        // 更换签名人后，等待中的提案全部作废
+        assert_ok!(Oumuamua::approve_admin_call(Origin::signed(BORROWER), 1));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::AdminCallExecuted(1, true)));
+        assert_eq!((Oumuamua::admin_signatories(), Oumuamua::admin_threshold()), (vec![LENDER, NOBODY], 1));
+        assert_eq!(Oumuamua::pending_admin_proposals(), Vec::<u32>::new());
+        assert_eq!(Oumuamua::admin_proposal(0).unwrap().status, AdminProposalStatus::Cancelled);
+        assert_eq!(Oumuamua::admin_proposal(2).unwrap().status, AdminProposalStatus::Cancelled);
+        assert_noop!(Oumuamua::approve_admin_call(Origin::signed(LENDER), 0), "the admin proposal is not pending");
+
+        // 门槛为 1 时提案立即执行
+        assert_ok!(Oumuamua::propose_admin_call(Origin::signed(NOBODY), btc_price_call(40_000)));
+        assert_eq!(last_event(), TestEvent::oumuamua(RawEvent::AdminCallExecuted(3, true)));
+        assert_eq!(Oumuamua::token_price(BTC), 40_000);
+    });
+}
+
+#[test]
+fn history_records_both_sides_of_a_loan() {
+    with_externalities(&mut new_market_ext(), || {
+        System::set_block_number(5);
+        create_default_borrow();
+        let id = first_borrow_order();
+        assert_ok!(Oumuamua::take_borrow(Origin::signed(LENDER), id));
+
+        assert_eq!(Oumuamua::history_count(BORROWER), 2);
+        let page = Oumuamua::history_page(&BORROWER, 0, 10);
+        assert_eq!(page.len(), 2);
+        assert_eq!(page[0].kind, HistoryKind::OrderCreated);
+        assert_eq!(page[0].block, 5);
+        assert_eq!(page[1].kind, HistoryKind::OrderFilled);
+        assert_eq!(page[1].counterparty, Some(LENDER));
+
+        let lender = Oumuamua::history_page(&LENDER, 0, 10);
+        assert_eq!(lender.len(), 1);
+        assert_eq!((lender[0].order_id, lender[0].asset_id, lender[0].amount), (id, USDT, 100));
+    });
+}
+
+#[test]
+fn history_cap_prunes_oldest_entries() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_ok!(Oumuamua::set_history_cap(Origin::signed(ADMIN), 2));
+        for _ in 0..3 {
+            create_default_borrow();
+        }
+
+        assert_eq!(Oumuamua::history_count(BORROWER), 3);
+        assert_eq!(Oumuamua::history_start(BORROWER), 1);
+        assert!(Oumuamua::history_of((BORROWER, 0)).is_none());
+        assert_eq!(Oumuamua::history_page(&BORROWER, 0, 10).len(), 2);
+        assert_eq!(Oumuamua::history_page(&BORROWER, 2, 10).len(), 1);
+    });
+}
+
+fn pool_history(who: u64) -> Vec<(HistoryKind, Option<u64>, u32, u64)> {
+    Oumuamua::history_page(&who, 0, 10).into_iter()
+        .inspect(|entry| assert_eq!(entry.order_id, H256::default()))
+        .map(|entry| (entry.kind, entry.counterparty, entry.asset_id, entry.amount))
+        .collect()
+}
+
+#[test]
+fn history_records_pool_loans_and_flash_loans() {
+    with_externalities(&mut new_market_ext(), || {
+        create_default_pool();
+        assert_ok!(Oumuamua::set_risk_params(Origin::signed(ADMIN), BTC, btc_risk(8000, 9000, 500)));
+        assert_ok!(Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 400, BTC, 300));
+        // 只追加抵押物时不记录
+        assert_ok!(Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 0, BTC, 100));
+        assert_ok!(Oumuamua::pool_repay(Origin::signed(BORROWER), USDT, 1000));
+        assert_ok!(Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 400, BTC, 300));
+
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, NOBODY, 400));
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), BTC, 14_000));
+        assert_ok!(Oumuamua::liquidate_pool_loan(Origin::signed(NOBODY), USDT, BORROWER));
+        assert_eq!(pool_history(BORROWER), vec![
+            (HistoryKind::PoolBorrowed, None, USDT, 400),
+            (HistoryKind::PoolRepaid, None, USDT, 400),
+            (HistoryKind::PoolBorrowed, None, USDT, 400),
+            (HistoryKind::PoolLiquidated, Some(NOBODY), BTC, 300),
+        ]);
+        assert_eq!(pool_history(NOBODY), vec![(HistoryKind::PoolLiquidated, Some(BORROWER), USDT, 400)]);
+
+        assert_ok!(Oumuamua::flash_loan(Origin::signed(LENDER), USDT, 500, vec![]));
+        assert_eq!(pool_history(LENDER), vec![(HistoryKind::FlashLoan, None, USDT, 500)]);
+        // 失败的闪电贷不留下记录
+        assert_noop!(
+            Oumuamua::flash_loan(Origin::signed(NOBODY), USDT, 500, vec![Call::transfer(USDT, LENDER, 500)]),
+            "Not enough free balance."
+        );
+    });
+}
+
+fn new_fee_ext() -> runtime_io::TestExternalities<primitives::Blake2Hasher> {
+    build(GenesisConfig::<Test> { fee_per_weight: 1, ..market_config() })
+}
+
+#[test]
+fn calls_pay_fees_by_weight() {
+    with_externalities(&mut new_fee_ext(), || {
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, NOBODY, 400));
+        // 13 reads and 5 writes
+        assert_eq!(Balances::free_balance(LENDER), NATIVE_BALANCE - 825);
+
+        assert_ok!(Oumuamua::create_Supply(Origin::signed(LENDER), 1000, USDT, vec![BTC], 5000, 30, 300));
+        // 28 reads and 17 writes, the bytes are paid through the balances transaction fee
+        assert_eq!(Balances::free_balance(LENDER), NATIVE_BALANCE - 825 - 2400);
+    });
+}
+
+#[test]
+fn pool_validation_checks_the_call_fee() {
+    with_externalities(&mut new_fee_ext(), || {
+        let call = Call::<Test>::create_Supply(1000, USDT, vec![BTC], 5000, 30, 300);
+        assert_ok!(Oumuamua::can_pay(&LENDER, &call, NATIVE_BALANCE - 2400));
+        assert_eq!(Oumuamua::can_pay(&LENDER, &call, NATIVE_BALANCE - 2399), Err("not enough balance to pay the call fee"));
+        assert_eq!(Oumuamua::can_pay(&NOBODY, &Call::<Test>::transfer(USDT, LENDER, 1), 0), Err("not enough balance to pay the call fee"));
+    });
+}
+
+// 在计数的 externalities 中执行 setup 返回的调用，读写的不同存储项不能超过它的权重，
+// 加上它在内部执行的调用的权重。历史记录上限为 1，第二条记录就要删掉一条旧的
+fn assert_within_weight(setup: impl FnOnce() -> (Origin, Call<Test>, Vec<Call<Test>>)) {
+    let mut ext = build(GenesisConfig::<Test> { history_cap: 1, insurance_fee_share: 2000, ..market_config() });
+    let (origin, call, dispatched) = with_externalities(&mut ext, setup);
+    let name = format!("{:?}", call);
+    let (reads, writes) = with_externalities(&mut ext, || {
+        dispatched.iter().map(weights::of).fold((0, 0), |(reads, writes), weight| (reads + weight.reads, writes + weight.writes))
+    });
+    let weight = with_externalities(&mut ext, || weights::of(&call));
+    let (result, used) = count_accesses(&mut ext, || call.dispatch(origin));
+    assert_ok!(result);
+    assert!(
+        used.reads <= weight.reads + reads && used.writes <= weight.writes + writes,
+        "{} read {} and wrote {} storage entries", name, used.reads, used.writes
+    );
+}
+
+#[test]
+fn calls_stay_within_their_weights() {
+    let signed = Origin::signed;
+    assert_within_weight(|| (signed(ADMIN), Call::init(ADMIN, b"Ether".to_vec(), b"ETH".to_vec(), 4, 10_000), vec![]));
+    assert_within_weight(|| (signed(ADMIN), Call::issue(USDT, LENDER, 100), vec![]));
+    assert_within_weight(|| (signed(ADMIN), Call::destroy(USDT, LENDER, 100), vec![]));
+    assert_within_weight(|| (signed(ADMIN), Call::set_price(BTC, 30_000), vec![]));
+    assert_within_weight(|| (signed(ADMIN), Call::set_risk_params(BTC, btc_risk(8000, 9000, 500)), vec![]));
+    assert_within_weight(|| (signed(ADMIN), Call::set_history_cap(2), vec![]));
+    assert_within_weight(|| (signed(LENDER), Call::transfer(USDT, BORROWER, 10), vec![]));
+    assert_within_weight(|| (signed(BORROWER), Call::approve(BTC, LENDER, 25), vec![]));
+    assert_within_weight(|| {
+        assert_ok!(Oumuamua::approve(Origin::signed(BORROWER), BTC, LENDER, 25));
+        (signed(LENDER), Call::transfer_from(BTC, BORROWER, LENDER, 10), vec![])
+    });
+    assert_within_weight(|| (signed(ADMIN), Call::set_allow_assets(BTC, false), vec![]));
+
+    // 订单
+    assert_within_weight(|| (signed(BORROWER), Call::create_borrow(100, USDT, 30, 60, BTC, 500), vec![]));
+    assert_within_weight(|| {
+        create_default_borrow();
+        (signed(BORROWER), Call::cancel_borrow(first_borrow_order()), vec![])
+    });
+    assert_within_weight(|| {
+        create_default_borrow();
+        (signed(LENDER), Call::take_borrow(first_borrow_order()), vec![])
+    });
+    assert_within_weight(|| (signed(LENDER), Call::create_Supply(1000, USDT, vec![BTC], 5000, 30, 300), vec![]));
+    assert_within_weight(|| {
+        create_default_supply();
+        (signed(LENDER), Call::cance_supply(first_supply_order()), vec![])
+    });
+    assert_within_weight(|| {
+        create_default_supply();
+        (signed(BORROWER), Call::take_supply(first_supply_order(), BTC), vec![])
+    });
+    assert_within_weight(|| {
+        let id = take_interest_bearing_borrow();
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, NOBODY, 1000));
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), BTC, 16_000));
+        (signed(NOBODY), Call::liquidate(id), vec![])
+    });
+    assert_within_weight(|| {
+        let id = take_interest_bearing_borrow();
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, BORROWER, 6));
+        System::set_block_number(5);
+        (signed(BORROWER), Call::repay(id), vec![])
+    });
+
+    // 资金池
+    assert_within_weight(|| (signed(ADMIN), Call::create_pool(USDT, rate_model()), vec![]));
+    assert_within_weight(|| {
+        create_default_pool();
+        (signed(ADMIN), Call::set_rate_model(USDT, RateModel { slope2: 20_000, ..rate_model() }), vec![])
+    });
+    assert_within_weight(|| {
+        create_default_pool();
+        (signed(LENDER), Call::deposit(USDT, 10), vec![])
+    });
+    assert_within_weight(|| {
+        create_default_pool();
+        (signed(LENDER), Call::withdraw(USDT, 10), vec![])
+    });
+    assert_within_weight(|| {
+        create_default_pool();
+        (signed(BORROWER), Call::pool_borrow(USDT, 400, BTC, 300), vec![])
+    });
+    assert_within_weight(|| {
+        create_default_pool();
+        assert_ok!(Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 400, BTC, 300));
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, BORROWER, 3));
+        System::set_block_number(10);
+        (signed(BORROWER), Call::pool_repay(USDT, 1000), vec![])
+    });
+    assert_within_weight(|| {
+        create_default_pool();
+        assert_ok!(Oumuamua::set_risk_params(Origin::signed(ADMIN), BTC, btc_risk(8000, 9000, 500)));
+        assert_ok!(Oumuamua::pool_borrow(Origin::signed(BORROWER), USDT, 400, BTC, 300));
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, NOBODY, 400));
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), BTC, 14_000));
+        (signed(NOBODY), Call::liquidate_pool_loan(USDT, BORROWER), vec![])
+    });
+
+    // 贷款条款、抵押物和债权
+    assert_within_weight(|| {
+        create_default_borrow();
+        (signed(BORROWER), Call::set_prepayment_fee(first_borrow_order(), 100), vec![])
+    });
+    assert_within_weight(|| {
+        let id = take_interest_bearing_borrow();
+        System::set_block_number(10);
+        (signed(BORROWER), Call::request_extension(id, 20, 2000), vec![])
+    });
+    assert_within_weight(|| {
+        let id = take_interest_bearing_borrow();
+        System::set_block_number(10);
+        assert_ok!(Oumuamua::request_extension(Origin::signed(BORROWER), id, 20, 2000));
+        (signed(LENDER), Call::accept_extension(id), vec![])
+    });
+    assert_within_weight(|| {
+        let id = take_interest_bearing_borrow();
+        (signed(LENDER), Call::set_rollover(id, true), vec![])
+    });
+    assert_within_weight(|| {
+        let id = take_interest_bearing_borrow();
+        assert_ok!(Oumuamua::set_rollover(Origin::signed(BORROWER), id, true));
+        assert_ok!(Oumuamua::set_rollover(Origin::signed(LENDER), id, true));
+        assert_ok!(Oumuamua::set_risk_params(Origin::signed(ADMIN), BTC, btc_risk(9000, 9500, 500)));
+        System::set_block_number(30);
+        (signed(NOBODY), Call::roll_over(id), vec![])
+    });
+    assert_within_weight(|| {
+        let id = take_interest_bearing_borrow();
+        (signed(BORROWER), Call::add_collateral(id, 100), vec![])
+    });
+    assert_within_weight(|| {
+        let id = take_interest_bearing_borrow();
+        (signed(BORROWER), Call::withdraw_collateral(id, 10), vec![])
+    });
+    assert_within_weight(|| {
+        assert_ok!(Oumuamua::init(Origin::signed(ADMIN), ADMIN, b"Ether".to_vec(), b"ETH".to_vec(), 4, 10_000));
+        assert_ok!(Oumuamua::transfer(Origin::signed(ADMIN), 3, BORROWER, 5000));
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), 3, 30_000));
+        assert_ok!(Oumuamua::set_allow_assets(Origin::signed(ADMIN), 3, true));
+        assert_ok!(Oumuamua::create_Supply(Origin::signed(LENDER), 1000, USDT, vec![BTC, 3], 5000, 30, 300));
+        let id = first_supply_order();
+        assert_ok!(Oumuamua::take_supply(Origin::signed(BORROWER), id, BTC));
+        (signed(BORROWER), Call::substitute_collateral(id, 3, 667), vec![])
+    });
+    assert_within_weight(|| {
+        let id = take_interest_bearing_borrow();
+        (signed(LENDER), Call::transfer_loan_claim(id, NOBODY), vec![])
+    });
+    assert_within_weight(|| {
+        let id = take_interest_bearing_borrow();
+        (signed(LENDER), Call::list_loan_claim(id, BTC, 500), vec![])
+    });
+    assert_within_weight(|| {
+        let id = take_interest_bearing_borrow();
+        assert_ok!(Oumuamua::list_loan_claim(Origin::signed(LENDER), id, BTC, 500));
+        (signed(LENDER), Call::unlist_loan_claim(id), vec![])
+    });
+    assert_within_weight(|| {
+        let id = take_interest_bearing_borrow();
+        assert_ok!(Oumuamua::list_loan_claim(Origin::signed(LENDER), id, BTC, 500));
+        assert_ok!(Oumuamua::transfer(Origin::signed(BORROWER), BTC, NOBODY, 500));
+        (signed(NOBODY), Call::buy_loan_claim(id, BTC, 500), vec![])
+    });
+    assert_within_weight(|| {
+        let id = take_interest_bearing_borrow();
+        assert_ok!(Oumuamua::deposit_insurance(Origin::signed(LENDER), USDT, 30));
+        assert_ok!(Oumuamua::set_price(Origin::signed(ADMIN), BTC, 16_000));
+        System::set_block_number(30);
+        (signed(LENDER), Call::claim_default(id), vec![])
+    });
+
+    // 保险基金和闪电贷，闪电贷内部的调用各自付手续费
+    assert_within_weight(|| (signed(ADMIN), Call::set_insurance_fee_share(3000), vec![]));
+    assert_within_weight(|| (signed(LENDER), Call::deposit_insurance(USDT, 150), vec![]));
+    assert_within_weight(|| (signed(ADMIN), Call::set_flash_loan_fee(9), vec![]));
+    assert_within_weight(|| {
+        create_default_pool();
+        assert_ok!(Oumuamua::set_flash_loan_fee(Origin::signed(ADMIN), 9));
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, BORROWER, 10));
+        let calls = vec![Call::deposit(USDT, 1000), Call::withdraw(USDT, 1000)];
+        (signed(BORROWER), Call::flash_loan(USDT, 1000, calls.clone()), calls)
+    });
+    assert_within_weight(|| {
+        create_default_pool();
+        let id = take_interest_bearing_borrow();
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, BORROWER, 1000));
+        System::set_block_number(5);
+        let calls = vec![Call::repay(id)];
+        (signed(BORROWER), Call::flash_loan(USDT, 100, calls.clone()), calls)
+    });
+
+    // 治理，通过的提案以 root 身份执行
+    assert_within_weight(|| (signed(ADMIN), Call::set_governance(Some(USDT), governance_params()), vec![]));
+    assert_within_weight(|| (signed(ADMIN), Call::set_legacy_admin_until(Some(12)), vec![]));
+    assert_within_weight(|| (signed(ADMIN), Call::set_proposal_deposit(100), vec![]));
+    assert_within_weight(|| {
+        System::set_block_number(1);
+        assert_ok!(Oumuamua::set_governance(Origin::signed(ADMIN), Some(USDT), governance_params()));
+        assert_ok!(Oumuamua::set_proposal_deposit(Origin::signed(ADMIN), 100));
+        (signed(LENDER), Call::propose(btc_price_call(30_000)), vec![])
+    });
+    assert_within_weight(|| {
+        System::set_block_number(1);
+        assert_ok!(Oumuamua::set_governance(Origin::signed(ADMIN), Some(USDT), governance_params()));
+        propose_btc_price(30_000);
+        (signed(LENDER), Call::vote(0, true, 6000), vec![])
+    });
+    assert_within_weight(|| {
+        System::set_block_number(1);
+        assert_ok!(Oumuamua::set_governance(Origin::signed(ADMIN), Some(USDT), governance_params()));
+        propose_btc_price(30_000);
+        assert_ok!(Oumuamua::vote(Origin::signed(LENDER), 0, true, 6000));
+        System::set_block_number(16);
+        assert_ok!(Oumuamua::enact_proposal(Origin::signed(NOBODY), 0));
+        (signed(LENDER), Call::unlock_vote(0), vec![])
+    });
+    assert_within_weight(|| {
+        System::set_block_number(1);
+        assert_ok!(Oumuamua::set_governance(Origin::signed(ADMIN), Some(USDT), governance_params()));
+        assert_ok!(Oumuamua::set_proposal_deposit(Origin::signed(ADMIN), 100));
+        propose_btc_price(30_000);
+        assert_ok!(Oumuamua::vote(Origin::signed(LENDER), 0, true, 6000));
+        System::set_block_number(16);
+        (signed(NOBODY), Call::enact_proposal(0), vec![*btc_price_call(30_000)])
+    });
+
+    // 多签管理员，达到门槛时提案以 root 身份执行
+    assert_within_weight(|| {
+        assert_ok!(set_admin_signatories_by_governance(vec![BORROWER, LENDER], 2));
+        assert_ok!(Oumuamua::propose_admin_call(Origin::signed(BORROWER), btc_price_call(30_000)));
+        // 相当于治理提案正在执行
+        <DispatchingAs<Test>>::put(AdminOrigin::Governance);
+        (system::RawOrigin::Root.into(), Call::set_admin_signatories(vec![LENDER, NOBODY], 1), vec![])
+    });
+    assert_within_weight(|| {
+        assert_ok!(set_admin_signatories_by_governance(vec![BORROWER, LENDER], 2));
+        (signed(BORROWER), Call::propose_admin_call(btc_price_call(30_000)), vec![])
+    });
+    assert_within_weight(|| {
+        assert_ok!(set_admin_signatories_by_governance(vec![BORROWER], 1));
+        (signed(BORROWER), Call::propose_admin_call(btc_price_call(30_000)), vec![*btc_price_call(30_000)])
+    });
+    assert_within_weight(|| {
+        assert_ok!(set_admin_signatories_by_governance(vec![BORROWER, LENDER], 2));
+        assert_ok!(Oumuamua::propose_admin_call(Origin::signed(BORROWER), btc_price_call(30_000)));
+        (signed(LENDER), Call::approve_admin_call(0), vec![*btc_price_call(30_000)])
+    });
+    assert_within_weight(|| {
+        assert_ok!(set_admin_signatories_by_governance(vec![BORROWER, LENDER], 2));
+        assert_ok!(Oumuamua::propose_admin_call(Origin::signed(BORROWER), btc_price_call(30_000)));
+        (signed(BORROWER), Call::cancel_admin_call(0), vec![])
+    });
+}
+
+#[test]
+fn failed_calls_keep_their_fee() {
+    with_externalities(&mut new_fee_ext(), || {
+        assert!(Oumuamua::transfer(Origin::signed(LENDER), USDT, NOBODY, 20_000).is_err());
+        assert_eq!(Balances::free_balance(LENDER), NATIVE_BALANCE - 825);
+        assert_eq!(Oumuamua::balance_of((USDT, LENDER)), 10_000);
+    });
+}
+
+#[test]
+fn calls_need_funds_for_the_fee() {
+    with_externalities(&mut new_fee_ext(), || {
+        assert_ok!(Oumuamua::transfer(Origin::signed(LENDER), USDT, NOBODY, 400));
+        assert!(Oumuamua::transfer(Origin::signed(NOBODY), USDT, LENDER, 400).is_err());
+        assert_eq!(Oumuamua::balance_of((USDT, NOBODY)), 400);
+    });
+}
+
+#[test]
+fn supply_orders_accept_a_bounded_number_of_tokens() {
+    with_externalities(&mut new_market_ext(), || {
+        assert_noop!(
+            Oumuamua::create_Supply(Origin::signed(LENDER), 1000, USDT, vec![BTC; 9], 5000, 30, 300),
+            "too many collateral tokens"
+        );
+        assert_ok!(Oumuamua::create_Supply(Origin::signed(LENDER), 1000, USDT, vec![BTC; 8], 5000, 30, 300));
+    });
+}
+
+// 把当前状态导出成创世配置，新链上的余额、冻结、授权和订单应与旧链一致
+#[test]
+fn genesis_restores_exported_state() {
+    let mut old = new_market_ext();
+    let config = with_externalities(&mut old, || {
+        create_default_borrow();
+        assert_ok!(Oumuamua::take_borrow(Origin::signed(LENDER), first_borrow_order()));
+        create_default_supply();
+        assert_ok!(Oumuamua::set_prepayment_fee(Origin::signed(LENDER), first_supply_order(), 50));
+        assert_ok!(Oumuamua::approve(Origin::signed(BORROWER), BTC, LENDER, 25));
+        assert_ok!(Oumuamua::set_risk_params(Origin::signed(ADMIN), BTC, btc_risk(8000, 9000, 500)));
+
+        let mut config = market_config();
+        config.tokens = (1..Oumuamua::token_id()).map(|token_id| {
+            let token = Oumuamua::token_details(token_id);
+            let holders = invariants::ACCOUNTS.iter()
+                .map(|&who| (who, Oumuamua::balance_of((token_id, who))))
+                .collect();
+            (token.name, token.ticker, token.decimals, token.total_supply, holders)
+        }).collect();
+        config.allowances = vec![(BTC, BORROWER, LENDER, Oumuamua::allowance((BTC, BORROWER, LENDER)))];
+        config.borrow_orders = (0..Oumuamua::borrow_order_count())
+            .map(|i| Oumuamua::borrow_order_detail(Oumuamua::borrow_by_index(i)))
+            .collect();
+        config.supply_orders = (0..Oumuamua::supply_order_count())
+            .map(|i| Oumuamua::supply_order_detail(Oumuamua::supply_by_index(i)))
+            .collect();
+        config.risk_params = vec![(BTC, Oumuamua::risk_params(BTC))];
+        let id = first_borrow_order();
+        config.loan_indexes = vec![(id, Oumuamua::loan_index(id).unwrap())];
+        config.loan_terms = vec![(first_supply_order(), Oumuamua::loan_terms(first_supply_order()))];
+        config
+    });
+    let snapshot = |ext: &mut runtime_io::TestExternalities<primitives::Blake2Hasher>| with_externalities(ext, || {
+        let balances: Vec<_> = [USDT, BTC].iter()
+            .flat_map(|&token_id| invariants::ACCOUNTS.iter().map(move |&who| {
+                (Oumuamua::balance_of((token_id, who)), free_and_reserved(token_id, who))
+            }))
+            .collect();
+        invariants::assert_ledger_invariants(&invariants::ACCOUNTS);
+        (
+            balances,
+            Oumuamua::allowance((BTC, BORROWER, LENDER)),
+            Oumuamua::allowance_count(),
+            Oumuamua::borrow_order_detail(first_borrow_order()),
+            Oumuamua::supply_order_detail(first_supply_order()),
+            Oumuamua::owned_supply_count(LENDER),
+            Oumuamua::risk_params(BTC),
+            Oumuamua::loan_index(first_borrow_order()),
+            Oumuamua::loan_terms(first_supply_order()),
+            (Oumuamua::total_supplied(USDT), Oumuamua::total_borrowed(USDT)),
+        )
+    });
+
+    assert_eq!(snapshot(&mut build(config)), snapshot(&mut old));
+}
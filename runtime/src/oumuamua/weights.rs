@@ -0,0 +1,196 @@
+//! 各调用的权重，用来按调用实际产生的工作量收取手续费。
+//!
+//! 权重是调用读写的不同存储项个数，按实现的最坏情况统计：余额还在旧前缀下时多读一次，
+//! 历史记录满了时删掉一条，事件也算在内；付手续费本身的读写不算。tests.rs 的
+//! `calls_stay_within_their_weights`（迁移调用在 migration.rs 中）在计数的 externalities 中执行每个调用，
+//! 检查实际的读写不超过这里的数字。
+//! 交易的字节数由 balances 模块的 transaction_byte_fee 收取，这里不再另计。出块前交易池用 `of`
+//! 查出同一个权重，检查发送者付得起手续费。
+
+use runtime_primitives::traits::As;
+
+use super::{Call, Module, Trait};
+
+/// Storage reads and writes of a call.
+pub struct Weight {
+    pub reads: u64,
+    pub writes: u64,
+}
+
+/// Weight of a single storage read.
+pub const WEIGHT_PER_READ: u64 = 25;
+/// Weight of a single storage write.
+pub const WEIGHT_PER_WRITE: u64 = 100;
+
+/// Upper bound of the collateral tokens a supply order accepts.
+pub const MAX_COLLATERAL_TOKENS: usize = 8;
+/// Upper bound of the calls dispatched inside a flash loan.
+pub const MAX_FLASH_LOAN_CALLS: usize = 16;
+/// Upper bound of the signatories of the admin multisig.
+pub const MAX_ADMIN_SIGNATORIES: usize = 16;
+/// Upper bound of the admin multisig proposals waiting for approvals.
+pub const MAX_PENDING_ADMIN_PROPOSALS: usize = 16;
+/// Upper bound of the accounts and allowances of one `migrate_v0_accounts` call.
+pub const MAX_MIGRATE_V0_ENTRIES: usize = 64;
+
+pub const INIT: Weight = Weight { reads: 8, writes: 6 };
+pub const ISSUE: Weight = Weight { reads: 12, writes: 4 };
+pub const DESTROY: Weight = Weight { reads: 12, writes: 4 };
+pub const SET_PRICE: Weight = Weight { reads: 8, writes: 2 };
+pub const SET_RISK_PARAMS: Weight = Weight { reads: 8, writes: 2 };
+pub const SET_HISTORY_CAP: Weight = Weight { reads: 7, writes: 2 };
+pub const TRANSFER: Weight = Weight { reads: 13, writes: 5 };
+pub const APPROVE: Weight = Weight { reads: 9, writes: 4 };
+pub const TRANSFER_FROM: Weight = Weight { reads: 16, writes: 8 };
+pub const SET_ALLOW_ASSETS: Weight = Weight { reads: 8, writes: 2 };
+pub const CREATE_BORROW: Weight = Weight { reads: 26, writes: 16 };
+pub const CANCEL_BORROW: Weight = Weight { reads: 17, writes: 9 };
+pub const TAKE_BORROW: Weight = Weight { reads: 29, writes: 17 };
+/// Plus `CREATE_SUPPLY_PER_TOKEN` for every accepted collateral token.
+pub const CREATE_SUPPLY: Weight = Weight { reads: 26, writes: 17 };
+pub const CREATE_SUPPLY_PER_TOKEN: Weight = Weight { reads: 2, writes: 0 };
+pub const CANCEL_SUPPLY: Weight = Weight { reads: 18, writes: 10 };
+pub const TAKE_SUPPLY: Weight = Weight { reads: 38, writes: 19 };
+pub const LIQUIDATE: Weight = Weight { reads: 40, writes: 27 };
+pub const REPAY: Weight = Weight { reads: 34, writes: 27 };
+pub const CREATE_POOL: Weight = Weight { reads: 11, writes: 8 };
+pub const SET_RATE_MODEL: Weight = Weight { reads: 12, writes: 3 };
+pub const DEPOSIT: Weight = Weight { reads: 22, writes: 8 };
+pub const WITHDRAW: Weight = Weight { reads: 19, writes: 8 };
+pub const POOL_BORROW: Weight = Weight { reads: 31, writes: 12 };
+pub const POOL_REPAY: Weight = Weight { reads: 24, writes: 12 };
+pub const LIQUIDATE_POOL_LOAN: Weight = Weight { reads: 34, writes: 20 };
+pub const SET_PREPAYMENT_FEE: Weight = Weight { reads: 7, writes: 2 };
+pub const REQUEST_EXTENSION: Weight = Weight { reads: 10, writes: 2 };
+pub const ACCEPT_EXTENSION: Weight = Weight { reads: 11, writes: 4 };
+pub const SET_ROLLOVER: Weight = Weight { reads: 10, writes: 2 };
+pub const ROLL_OVER: Weight = Weight { reads: 13, writes: 2 };
+pub const ADD_COLLATERAL: Weight = Weight { reads: 16, writes: 4 };
+pub const WITHDRAW_COLLATERAL: Weight = Weight { reads: 19, writes: 4 };
+pub const SUBSTITUTE_COLLATERAL: Weight = Weight { reads: 27, writes: 6 };
+pub const TRANSFER_LOAN_CLAIM: Weight = Weight { reads: 9, writes: 3 };
+pub const LIST_LOAN_CLAIM: Weight = Weight { reads: 11, writes: 2 };
+pub const UNLIST_LOAN_CLAIM: Weight = Weight { reads: 10, writes: 2 };
+pub const BUY_LOAN_CLAIM: Weight = Weight { reads: 24, writes: 15 };
+pub const CLAIM_DEFAULT: Weight = Weight { reads: 37, writes: 25 };
+pub const SET_INSURANCE_FEE_SHARE: Weight = Weight { reads: 7, writes: 2 };
+pub const DEPOSIT_INSURANCE: Weight = Weight { reads: 11, writes: 4 };
+pub const SET_FLASH_LOAN_FEE: Weight = Weight { reads: 7, writes: 2 };
+/// Plus `FLASH_LOAN_PER_CALL` for recording and undoing the writes of every call, which still
+/// pays its own fee; a call of `journal::allowed` journals at most 30 storage entries.
+pub const FLASH_LOAN: Weight = Weight { reads: 19, writes: 10 };
+pub const FLASH_LOAN_PER_CALL: Weight = Weight { reads: 30, writes: 30 };
+pub const SET_GOVERNANCE: Weight = Weight { reads: 8, writes: 3 };
+pub const SET_LEGACY_ADMIN_UNTIL: Weight = Weight { reads: 7, writes: 2 };
+pub const SET_PROPOSAL_DEPOSIT: Weight = Weight { reads: 7, writes: 2 };
+pub const PROPOSE: Weight = Weight { reads: 16, writes: 6 };
+pub const VOTE: Weight = Weight { reads: 14, writes: 5 };
+pub const UNLOCK_VOTE: Weight = Weight { reads: 14, writes: 4 };
+/// The enacted call runs as root and pays no fee of its own.
+pub const ENACT_PROPOSAL: Weight = Weight { reads: 16, writes: 6 };
+/// Includes cancelling up to `MAX_PENDING_ADMIN_PROPOSALS` pending proposals.
+pub const SET_ADMIN_SIGNATORIES: Weight = Weight { reads: 21, writes: 20 };
+/// A call executed once the threshold is reached runs as root and pays no fee of its own.
+pub const PROPOSE_ADMIN_CALL: Weight = Weight { reads: 10, writes: 5 };
+pub const APPROVE_ADMIN_CALL: Weight = Weight { reads: 9, writes: 4 };
+pub const CANCEL_ADMIN_CALL: Weight = Weight { reads: 6, writes: 3 };
+/// Plus `MIGRATE_V0_ENTRY` for every balance (account and token) and allowance.
+pub const MIGRATE_V0_ACCOUNTS: Weight = Weight { reads: 5, writes: 1 };
+pub const MIGRATE_V0_ENTRY: Weight = Weight { reads: 6, writes: 6 };
+
+impl Weight {
+    /// The weight in fee units.
+    pub fn total(&self) -> u64 {
+        self.reads.saturating_mul(WEIGHT_PER_READ)
+            .saturating_add(self.writes.saturating_mul(WEIGHT_PER_WRITE))
+    }
+}
+
+/// Weight of a supply order accepting `tokens` collateral tokens.
+pub fn create_supply(tokens: usize) -> Weight {
+    let tokens = tokens as u64;
+    Weight {
+        reads: CREATE_SUPPLY.reads + tokens * CREATE_SUPPLY_PER_TOKEN.reads,
+        writes: CREATE_SUPPLY.writes + tokens * CREATE_SUPPLY_PER_TOKEN.writes,
+    }
+}
+
+/// Weight of a flash loan dispatching `calls` calls.
+pub fn flash_loan(calls: usize) -> Weight {
+    let calls = calls as u64;
+    Weight {
+        reads: FLASH_LOAN.reads.saturating_add(calls.saturating_mul(FLASH_LOAN_PER_CALL.reads)),
+        writes: FLASH_LOAN.writes.saturating_add(calls.saturating_mul(FLASH_LOAN_PER_CALL.writes)),
+    }
+}
+
+/// Weight of migrating the balances of `accounts` in every token plus `allowances`.
+pub fn migrate_v0_accounts<T: Trait>(accounts: usize, allowances: usize) -> Weight {
+    let tokens = <T::AssetId as As<u64>>::as_(<Module<T>>::token_id());
+    let entries = (accounts as u64).saturating_mul(tokens).saturating_add(allowances as u64);
+    Weight {
+        reads: MIGRATE_V0_ACCOUNTS.reads.saturating_add(entries.saturating_mul(MIGRATE_V0_ENTRY.reads)),
+        writes: MIGRATE_V0_ACCOUNTS.writes.saturating_add(entries.saturating_mul(MIGRATE_V0_ENTRY.writes)),
+    }
+}
+
+/// Weight charged for a signed `call`, the single table both dispatch and pool validation read.
+pub fn of<T: Trait>(call: &Call<T>) -> Weight {
+    match call {
+        Call::init(..) => INIT,
+        Call::issue(..) => ISSUE,
+        Call::destroy(..) => DESTROY,
+        Call::set_price(..) => SET_PRICE,
+        Call::set_risk_params(..) => SET_RISK_PARAMS,
+        Call::set_history_cap(..) => SET_HISTORY_CAP,
+        Call::migrate_v0_accounts(accounts, allowances) => migrate_v0_accounts::<T>(accounts.len(), allowances.len()),
+        Call::transfer(..) => TRANSFER,
+        Call::approve(..) => APPROVE,
+        Call::transfer_from(..) => TRANSFER_FROM,
+        Call::set_allow_assets(..) => SET_ALLOW_ASSETS,
+        Call::create_borrow(..) => CREATE_BORROW,
+        Call::cancel_borrow(..) => CANCEL_BORROW,
+        Call::take_borrow(..) => TAKE_BORROW,
+        Call::create_Supply(_, _, btokenids, ..) => create_supply(btokenids.len()),
+        Call::cance_supply(..) => CANCEL_SUPPLY,
+        Call::take_supply(..) => TAKE_SUPPLY,
+        Call::liquidate(..) => LIQUIDATE,
+        Call::create_pool(..) => CREATE_POOL,
+        Call::set_rate_model(..) => SET_RATE_MODEL,
+        Call::deposit(..) => DEPOSIT,
+        Call::withdraw(..) => WITHDRAW,
+        Call::pool_borrow(..) => POOL_BORROW,
+        Call::pool_repay(..) => POOL_REPAY,
+        Call::liquidate_pool_loan(..) => LIQUIDATE_POOL_LOAN,
+        Call::repay(..) => REPAY,
+        Call::set_prepayment_fee(..) => SET_PREPAYMENT_FEE,
+        Call::request_extension(..) => REQUEST_EXTENSION,
+        Call::accept_extension(..) => ACCEPT_EXTENSION,
+        Call::set_rollover(..) => SET_ROLLOVER,
+        Call::roll_over(..) => ROLL_OVER,
+        Call::add_collateral(..) => ADD_COLLATERAL,
+        Call::withdraw_collateral(..) => WITHDRAW_COLLATERAL,
+        Call::substitute_collateral(..) => SUBSTITUTE_COLLATERAL,
+        Call::transfer_loan_claim(..) => TRANSFER_LOAN_CLAIM,
+        Call::list_loan_claim(..) => LIST_LOAN_CLAIM,
+        Call::unlist_loan_claim(..) => UNLIST_LOAN_CLAIM,
+        Call::buy_loan_claim(..) => BUY_LOAN_CLAIM,
+        Call::claim_default(..) => CLAIM_DEFAULT,
+        Call::set_insurance_fee_share(..) => SET_INSURANCE_FEE_SHARE,
+        Call::deposit_insurance(..) => DEPOSIT_INSURANCE,
+        Call::set_flash_loan_fee(..) => SET_FLASH_LOAN_FEE,
+        Call::flash_loan(_, _, calls) => flash_loan(calls.len()),
+        Call::set_governance(..) => SET_GOVERNANCE,
+        Call::set_legacy_admin_until(..) => SET_LEGACY_ADMIN_UNTIL,
+        Call::set_proposal_deposit(..) => SET_PROPOSAL_DEPOSIT,
+        Call::propose(..) => PROPOSE,
+        Call::vote(..) => VOTE,
+        Call::unlock_vote(..) => UNLOCK_VOTE,
+        Call::enact_proposal(..) => ENACT_PROPOSAL,
+        Call::set_admin_signatories(..) => SET_ADMIN_SIGNATORIES,
+        Call::propose_admin_call(..) => PROPOSE_ADMIN_CALL,
+        Call::approve_admin_call(..) => APPROVE_ADMIN_CALL,
+        Call::cancel_admin_call(..) => CANCEL_ADMIN_CALL,
+        Call::__PhantomItem(..) => Weight { reads: 0, writes: 0 },
+    }
+}
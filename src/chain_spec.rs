@@ -1,12 +1,20 @@
-use primitives::{ed25519, sr25519, Pair};
+use primitives::{ed25519, sr25519, Pair, crypto::Ss58Codec};
 use project_runtime::{
 	AccountId, GenesisConfig, ConsensusConfig, TimestampConfig, BalancesConfig,
-	SudoConfig, IndicesConfig, OumuamuaModuleConfig,
+	SudoConfig, IndicesConfig, OumuamuaModuleConfig, oumuamua,
 };
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use substrate_service;
 
+use crate::snapshot::Snapshot;
+
 use ed25519::Public as AuthorityId;
 
+/// Block at which the legacy oumuamua admin account retires unless a spec says otherwise,
+/// about 30 days of 10 second blocks. Privileged calls then go through the admin multisig or governance.
+pub const DEFAULT_LEGACY_ADMIN_UNTIL: u64 = 259_200;
+
 // Note this is the URL for the telemetry server
 //const STAGING_TELEMETRY_URL: &str = "wss://telemetry.polkadot.io/submit/";
 
@@ -22,6 +30,146 @@ pub enum Alternative {
 	Development,
 	/// Whatever the current runtime is, with simple Alice/Bob auths.
 	LocalTestnet,
+	/// Multi-operator testnet. It has no built-in keys; its spec comes from `generate-spec`.
+	Staging,
+}
+
+/// Keys and accounts of a chain spec, as read from a `generate-spec` config file.
+///
+/// Keys are SS58 addresses. Secret URIs starting with `//` are accepted as well so that
+/// development keys can be written down by name, but they must not be used on a real network.
+/// Missing fields take their default, misspelled ones are rejected.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields, default)]
+pub struct SpecConfig {
+	/// Human readable name of the chain.
+	pub name: String,
+	/// Chain id, also used for the database directory.
+	pub id: String,
+	/// Aura authorities (ed25519).
+	pub authorities: Vec<String>,
+	/// Accounts endowed with balances and oumuamua tokens (sr25519).
+	pub endowed_accounts: Vec<String>,
+	/// Sudo key (sr25519).
+	pub sudo: String,
+	/// Admin of the oumuamua module (sr25519).
+	pub oumuamua_admin: String,
+	/// Block at which `oumuamuaAdmin` stops making privileged calls, `DEFAULT_LEGACY_ADMIN_UNTIL` if missing.
+	pub legacy_admin_until: Option<u64>,
+	/// Signatories of the oumuamua admin multisig (sr25519).
+	pub admin_signatories: Vec<String>,
+	/// Approvals an admin multisig call needs, between 1 and the number of signatories.
+	pub admin_threshold: u32,
+	/// Boot node multiaddresses.
+	pub boot_nodes: Vec<String>,
+	/// Dump written by `export-state` to start the oumuamua module from. Tokens, balances and
+	/// orders then come from the dump instead of the endowed accounts; the admin stays `oumuamuaAdmin`.
+	/// The admin multisig and the legacy admin deadline of the spec apply when the dump has none.
+	pub oumuamua_state: Option<PathBuf>,
+}
+
+impl SpecConfig {
+	/// Name, id and admin threshold of the staging network, without keys: the authorities, the
+	/// sudo key, the oumuamua admin and the admin signatories have to come from the operators.
+	pub fn staging() -> Self {
+		SpecConfig {
+			name: "Oumuamua Staging Testnet".into(),
+			id: "oumuamua_staging".into(),
+			admin_threshold: 2,
+			..SpecConfig::default()
+		}
+	}
+
+	/// Build the genesis config described by this spec.
+	pub fn genesis(&self) -> Result<GenesisConfig, String> {
+		if self.authorities.is_empty() {
+			return Err("A chain spec needs at least one authority".into());
+		}
+		if self.sudo.is_empty() {
+			return Err("A chain spec needs a sudo key".into());
+		}
+		if self.oumuamua_admin.is_empty() {
+			return Err("A chain spec needs an oumuamua admin".into());
+		}
+		let authorities = self.authorities.iter()
+			.map(|k| parse_public::<ed25519::Pair>(k))
+			.collect::<Result<Vec<_>, _>>()?;
+		let endowed_accounts = self.endowed_accounts.iter()
+			.map(|k| parse_public::<sr25519::Pair>(k))
+			.collect::<Result<Vec<_>, _>>()?;
+		let oumuamua_admin = parse_public::<sr25519::Pair>(&self.oumuamua_admin)?;
+		let admin_signatories = self.admin_signatories.iter()
+			.map(|k| parse_public::<sr25519::Pair>(k))
+			.collect::<Result<Vec<_>, _>>()?;
+		if admin_signatories.is_empty() {
+			return Err("A chain spec needs at least one admin multisig signatory".into());
+		}
+		if self.admin_threshold == 0 || self.admin_threshold as usize > admin_signatories.len() {
+			return Err(format!(
+				"The admin threshold must be between 1 and the number of signatories ({}), found {}",
+				admin_signatories.len(), self.admin_threshold,
+			));
+		}
+		let legacy_admin_until = self.legacy_admin_until.unwrap_or(DEFAULT_LEGACY_ADMIN_UNTIL);
+		let mut genesis = testnet_genesis(
+			authorities,
+			endowed_accounts,
+			parse_public::<sr25519::Pair>(&self.sudo)?,
+			oumuamua_admin.clone(),
+			admin_signatories.clone(),
+			self.admin_threshold,
+			legacy_admin_until,
+		);
+		if let Some(ref path) = self.oumuamua_state {
+			let mut config = Snapshot::from_file(path)?.genesis_config(oumuamua_admin)?;
+			if config.admin_signatories.is_empty() {
+				config.admin_signatories = admin_signatories;
+				config.admin_threshold = self.admin_threshold;
+			}
+			if config.legacy_admin_until.is_none() {
+				config.legacy_admin_until = Some(legacy_admin_until);
+			}
+			genesis.oumuamua = Some(config);
+		}
+		Ok(genesis)
+	}
+
+	/// Render the chain spec as JSON, with the genesis state as runtime config.
+	///
+	/// `ChainSpec::from_genesis` only accepts a plain `fn`, so the spec is first built around the
+	/// development genesis and its runtime section is then replaced by this config.
+	pub fn to_json(&self) -> Result<String, String> {
+		let genesis = serde_json::to_value(self.genesis()?).map_err(|e| e.to_string())?;
+		let template = ChainSpec::from_genesis(
+			&self.name,
+			&self.id,
+			development_genesis,
+			self.boot_nodes.clone(),
+			None,
+			None,
+			None,
+			None,
+		);
+		let mut json: serde_json::Value = serde_json::from_str(&template.to_json(false)?)
+			.map_err(|e| e.to_string())?;
+		json["genesis"]["runtime"] = genesis;
+		serde_json::to_string_pretty(&json).map_err(|e| e.to_string())
+	}
+}
+
+/// Render the chain spec in the file `path` with the genesis state as raw storage.
+pub fn to_raw_json(path: &Path) -> Result<String, String> {
+	ChainSpec::from_json_file(path.to_path_buf())?.to_json(true)
+}
+
+fn parse_public<P: Pair>(s: &str) -> Result<P::Public, String> where P::Public: Ss58Codec {
+	if s.starts_with("//") {
+		P::from_string(s, None)
+			.map(|pair| pair.public())
+			.map_err(|e| format!("Invalid secret URI {}: {:?}", s, e))
+	} else {
+		P::Public::from_ss58check(s).map_err(|e| format!("Invalid SS58 address {}: {:?}", s, e))
+	}
 }
 
 fn authority_key(s: &str) -> AuthorityId {
@@ -43,13 +191,7 @@ impl Alternative {
 			Alternative::Development => ChainSpec::from_genesis(
 				"Development",
 				"dev",
-				|| testnet_genesis(vec![
-					authority_key("Alice")
-				], vec![
-					account_key("Alice")
-				],
-					account_key("Alice")
-				),
+				development_genesis,
 				vec![],
 				None,
 				None,
@@ -71,6 +213,10 @@ impl Alternative {
 					account_key("Ferdie"),
 				],
 					account_key("Alice"),
+					account_key("Alice"),
+					vec![account_key("Alice"), account_key("Bob"), account_key("Charlie")],
+					2,
+					DEFAULT_LEGACY_ADMIN_UNTIL,
 				),
 				vec![],
 				None,
@@ -78,6 +224,10 @@ impl Alternative {
 				None,
 				None
 			),
+			Alternative::Staging => return Err(
+				"The staging chain has no built-in keys; write them to a config file, run \
+				`generate-spec --config <FILE> --output <SPEC>` and start the node with `--chain <SPEC>`".into()
+			),
 		})
 	}
 
@@ -85,12 +235,45 @@ impl Alternative {
 		match s {
 			"dev" => Some(Alternative::Development),
 			"" | "local" => Some(Alternative::LocalTestnet),
+			"staging" => Some(Alternative::Staging),
 			_ => None,
 		}
 	}
 }
 
-fn testnet_genesis(initial_authorities: Vec<AuthorityId>, endowed_accounts: Vec<AccountId>, root_key: AccountId) -> GenesisConfig {
+fn development_genesis() -> GenesisConfig {
+	testnet_genesis(vec![
+		authority_key("Alice")
+	], vec![
+		account_key("Alice")
+	],
+		account_key("Alice"),
+		account_key("Alice"),
+		vec![account_key("Alice")],
+		1,
+		DEFAULT_LEGACY_ADMIN_UNTIL,
+	)
+}
+
+/// Give every endowed account `share` of a token, but never more than half of `total_supply`
+/// altogether, so that long account lists still fit; the admin keeps the rest.
+fn token_holders(endowed_accounts: &[AccountId], total_supply: u64, share: u64) -> Vec<(AccountId, u64)> {
+	let share = match endowed_accounts.len() as u64 {
+		0 => 0,
+		n => ::std::cmp::min(share, total_supply / 2 / n),
+	};
+	endowed_accounts.iter().cloned().map(|k| (k, share)).collect()
+}
+
+fn testnet_genesis(
+	initial_authorities: Vec<AuthorityId>,
+	endowed_accounts: Vec<AccountId>,
+	root_key: AccountId,
+	oumuamua_admin: AccountId,
+	admin_signatories: Vec<AccountId>,
+	admin_threshold: u32,
+	legacy_admin_until: u64,
+) -> GenesisConfig {
 	GenesisConfig {
 		consensus: Some(ConsensusConfig {
 			code: include_bytes!("../runtime/wasm/target/wasm32-unknown-unknown/release/project_runtime_wasm.compact.wasm").to_vec(),
@@ -105,7 +288,7 @@ fn testnet_genesis(initial_authorities: Vec<AuthorityId>, endowed_accounts: Vec<
 		}),
 		balances: Some(BalancesConfig {
 			transaction_base_fee: 1,
-			transaction_byte_fee: 0,
+			transaction_byte_fee: 1,
 			existential_deposit: 500,
 			transfer_fee: 0,
 			creation_fee: 0,
@@ -116,8 +299,94 @@ fn testnet_genesis(initial_authorities: Vec<AuthorityId>, endowed_accounts: Vec<
 			key: root_key,
 		}),
 		oumuamua: Some(OumuamuaModuleConfig {
-		    admin: account_key("Alice"),
-                    token_id: 1,
+			admin: oumuamua_admin,
+			token_id: 1,
+			history_cap: 1024,
+			// a transfer weighs 825, creating a borrow order 2250, see oumuamua/weights.rs
+			fee_per_weight: 1,
+			insurance_fee_share: 0,
+			flash_loan_fee: 0,
+			governance_asset: None,
+			governance_params: Default::default(),
+			legacy_admin_until: Some(legacy_admin_until),
+			proposal_deposit: 0,
+			admin_signatories,
+			admin_threshold,
+			// 10 second blocks
+			blocks_per_year: 3_153_600,
+			// ids 1, 2, 3; every endowed account holds a share of each token
+			tokens: vec![
+				(b"Tether USD".to_vec(), b"USDT".to_vec(), 2, 10_000_000_000, token_holders(&endowed_accounts, 10_000_000_000, 100_000_000)),
+				(b"Bitcoin".to_vec(), b"BTC".to_vec(), 4, 210_000_000, token_holders(&endowed_accounts, 210_000_000, 1_000_000)),
+				(b"Ether".to_vec(), b"ETH".to_vec(), 4, 10_000_000_000, token_holders(&endowed_accounts, 10_000_000_000, 10_000_000)),
+			],
+			// price of one smallest unit, in 1/10000 USDT
+			prices: vec![(1, 100), (2, 8_000), (3, 200)],
+			allowed_assets: vec![1, 2, 3],
+			allowances: vec![],
+			borrow_orders: vec![],
+			supply_orders: vec![],
+			// (max LTV, liquidation threshold, liquidation bonus) in 1/10000, no caps
+			risk_params: vec![
+				(1, oumuamua::RiskParams { max_ltv: 8_000, liquidation_threshold: 8_500, liquidation_bonus: 400, borrow_cap: 0, supply_cap: 0 }),
+				(2, oumuamua::RiskParams { max_ltv: 7_000, liquidation_threshold: 8_000, liquidation_bonus: 500, borrow_cap: 0, supply_cap: 0 }),
+				(3, oumuamua::RiskParams { max_ltv: 7_500, liquidation_threshold: 8_250, liquidation_bonus: 500, borrow_cap: 0, supply_cap: 0 }),
+			],
+			loan_indexes: vec![],
+			loan_terms: vec![],
+			loan_claims: vec![],
+			claim_listings: vec![],
+			insurance_funds: vec![],
+			bad_debts: vec![],
+			// pools and their share tokens are created by the admin with `create_pool`
+			pools: vec![],
+			pool_loans: vec![],
 		}),
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::testing::account;
+
+	// the staging template with the keys of three operators, written as development URIs
+	fn operator_config() -> SpecConfig {
+		let dev = |names: &[&str]| names.iter().map(|n| format!("//{}", n)).collect::<Vec<_>>();
+		SpecConfig {
+			authorities: dev(&["Alice", "Bob", "Charlie"]),
+			endowed_accounts: dev(&["Alice", "Bob", "Charlie"]),
+			sudo: "//Alice".into(),
+			oumuamua_admin: "//Alice".into(),
+			admin_signatories: dev(&["Alice", "Bob", "Charlie"]),
+			..SpecConfig::staging()
+		}
+	}
+
+	#[test]
+	fn staging_specs_need_the_operator_keys() {
+		assert!(Alternative::Staging.load().is_err());
+		assert!(SpecConfig::staging().genesis().is_err());
+		assert!(SpecConfig { authorities: vec![], ..operator_config() }.genesis().is_err());
+		assert!(SpecConfig { sudo: String::new(), ..operator_config() }.genesis().is_err());
+		assert!(SpecConfig { oumuamua_admin: String::new(), ..operator_config() }.genesis().is_err());
+		assert!(operator_config().genesis().is_ok());
+	}
+
+	#[test]
+	fn spec_configs_set_up_the_admin_multisig() {
+		let genesis = operator_config().genesis().expect("the operator spec is valid");
+		let config = genesis.oumuamua.expect("the spec configures the oumuamua module");
+		let signatories = ["Alice", "Bob", "Charlie"].iter().map(|name| account(name).public()).collect::<Vec<_>>();
+		assert_eq!((config.admin_signatories, config.admin_threshold), (signatories, 2));
+		assert_eq!(config.legacy_admin_until, Some(DEFAULT_LEGACY_ADMIN_UNTIL));
+
+		let config = SpecConfig { legacy_admin_until: Some(100), ..operator_config() };
+		assert_eq!(config.genesis().unwrap().oumuamua.unwrap().legacy_admin_until, Some(100));
+
+		// the admin account must not be the only way to make privileged calls
+		assert!(SpecConfig { admin_signatories: vec![], ..operator_config() }.genesis().is_err());
+		assert!(SpecConfig { admin_threshold: 0, ..operator_config() }.genesis().is_err());
+		assert!(SpecConfig { admin_threshold: 4, ..operator_config() }.genesis().is_err());
+	}
+}
@@ -3,11 +3,184 @@ use futures::{future, Future, sync::oneshot};
 use std::cell::RefCell;
 use tokio::runtime::Runtime;
 pub use substrate_cli::{VersionInfo, IntoExit, error};
-use substrate_cli::{informant, parse_and_execute, NoCustom};
+use substrate_cli::{informant, parse_and_execute, impl_augment_clap, GetLogFilter};
 use substrate_service::{ServiceFactory, Roles as ServiceRoles};
-use crate::chain_spec;
+use crate::chain_spec::{self, SpecConfig};
+use crate::indexer::IndexerConfig;
+use crate::sealing::{SealConfig, SealMode};
+use crate::snapshot::ExportStateCmd;
+use crate::tx::TxCmd;
+use std::{env, fs, process};
 use std::ops::Deref;
-use log::info;
+use std::path::{Path, PathBuf};
+use log::{info, warn};
+use structopt::StructOpt;
+
+/// Default port of the sealing JSON-RPC server.
+const DEFAULT_SEALING_RPC_PORT: u16 = 9956;
+
+/// Extra flags of the `run` command.
+#[derive(Debug, StructOpt, Clone)]
+pub struct CustomArgs {
+	/// Run the embedded oumuamua indexer next to the node and add its `indexer_*` queries to the
+	/// node's RPC
+	#[structopt(long = "indexer")]
+	indexer: bool,
+
+	/// Seal blocks without Aura slots: `instant` seals as soon as a transaction enters the pool,
+	/// `manual` only on the `engine_createBlock` RPC call. All nodes of the chain need the same flag
+	#[structopt(long = "sealing", value_name = "MODE")]
+	sealing: Option<SealMode>,
+
+	/// Specify the sealing JSON-RPC server TCP port
+	#[structopt(long = "sealing-rpc-port", value_name = "PORT")]
+	sealing_rpc_port: Option<u16>,
+}
+
+impl_augment_clap!(CustomArgs);
+
+/// Subcommands added on top of the standard substrate ones.
+#[derive(Debug, StructOpt, Clone)]
+pub enum CustomSubcommands {
+	/// Generate a chain spec from operator supplied keys
+	#[structopt(name = "generate-spec")]
+	GenerateSpec(GenerateSpecCmd),
+
+	/// Build and sign an oumuamua extrinsic offline
+	#[structopt(name = "tx")]
+	Tx(TxCmd),
+
+	/// Dump the oumuamua state of a stopped node at a block as JSON
+	#[structopt(name = "export-state")]
+	ExportState(ExportStateCmd),
+}
+
+impl GetLogFilter for CustomSubcommands {
+	fn get_log_filter(&self) -> Option<String> {
+		None
+	}
+}
+
+/// The `generate-spec` command. Flags override the values of the config file.
+#[derive(Debug, StructOpt, Clone)]
+pub struct GenerateSpecCmd {
+	/// JSON file with the keys and accounts of the chain, see `chain_spec::SpecConfig`. Without
+	/// it the chain is named after the staging network and every key has to be given as a flag
+	#[structopt(long = "config", value_name = "FILE", parse(from_os_str))]
+	config: Option<PathBuf>,
+
+	/// Chain name
+	#[structopt(long = "name")]
+	name: Option<String>,
+
+	/// Chain id
+	#[structopt(long = "id")]
+	id: Option<String>,
+
+	/// Aura authority key (SS58), may be repeated
+	#[structopt(long = "authority", value_name = "KEY")]
+	authorities: Vec<String>,
+
+	/// Endowed account (SS58), may be repeated
+	#[structopt(long = "endowed", value_name = "ACCOUNT")]
+	endowed_accounts: Vec<String>,
+
+	/// Sudo key (SS58)
+	#[structopt(long = "sudo", value_name = "ACCOUNT")]
+	sudo: Option<String>,
+
+	/// Admin of the oumuamua module (SS58)
+	#[structopt(long = "oumuamua-admin", value_name = "ACCOUNT")]
+	oumuamua_admin: Option<String>,
+
+	/// Block at which the oumuamua admin stops making privileged calls
+	#[structopt(long = "legacy-admin-until", value_name = "BLOCK")]
+	legacy_admin_until: Option<u64>,
+
+	/// Signatory of the oumuamua admin multisig (SS58), may be repeated
+	#[structopt(long = "admin-signatory", value_name = "ACCOUNT")]
+	admin_signatories: Vec<String>,
+
+	/// Approvals an admin multisig call needs
+	#[structopt(long = "admin-threshold", value_name = "N")]
+	admin_threshold: Option<u32>,
+
+	/// Start the oumuamua module from a dump written by `export-state`
+	#[structopt(long = "oumuamua-state", value_name = "FILE", parse(from_os_str))]
+	oumuamua_state: Option<PathBuf>,
+
+	/// Boot node multiaddress, may be repeated
+	#[structopt(long = "boot-node", value_name = "ADDR")]
+	boot_nodes: Vec<String>,
+
+	/// Write the genesis state as raw storage
+	#[structopt(long = "raw")]
+	raw: bool,
+
+	/// Write the chain spec to this file instead of stdout
+	#[structopt(long = "output", short = "o", value_name = "FILE", parse(from_os_str))]
+	output: Option<PathBuf>,
+}
+
+impl GenerateSpecCmd {
+	fn spec_config(&self) -> Result<SpecConfig, String> {
+		let mut config = match self.config {
+			Some(ref path) => {
+				let file = fs::File::open(path).map_err(|e| format!("Error opening {}: {}", path.display(), e))?;
+				serde_json::from_reader(file).map_err(|e| format!("Error parsing {}: {}", path.display(), e))?
+			}
+			None => SpecConfig::staging(),
+		};
+
+		if let Some(ref name) = self.name { config.name = name.clone(); }
+		if let Some(ref id) = self.id { config.id = id.clone(); }
+		if !self.authorities.is_empty() { config.authorities = self.authorities.clone(); }
+		if !self.endowed_accounts.is_empty() { config.endowed_accounts = self.endowed_accounts.clone(); }
+		if let Some(ref sudo) = self.sudo { config.sudo = sudo.clone(); }
+		if let Some(ref admin) = self.oumuamua_admin { config.oumuamua_admin = admin.clone(); }
+		if let Some(until) = self.legacy_admin_until { config.legacy_admin_until = Some(until); }
+		if !self.admin_signatories.is_empty() { config.admin_signatories = self.admin_signatories.clone(); }
+		if let Some(threshold) = self.admin_threshold { config.admin_threshold = threshold; }
+		if let Some(ref path) = self.oumuamua_state { config.oumuamua_state = Some(path.clone()); }
+		if !self.boot_nodes.is_empty() { config.boot_nodes = self.boot_nodes.clone(); }
+
+		if config.name.is_empty() || config.id.is_empty() {
+			return Err("The chain spec needs a name and an id".into());
+		}
+		Ok(config)
+	}
+
+	fn run(&self) -> Result<(), String> {
+		let config = self.spec_config()?;
+		let json = config.to_json()?;
+		if !self.raw {
+			return self.write(&json);
+		}
+
+		// a spec is read back from a file to convert it to raw storage: the output file, or a
+		// temporary one when printing to stdout
+		let path = match self.output {
+			Some(ref path) => path.clone(),
+			None => env::temp_dir().join(format!("{}-{}.json", config.id, process::id())),
+		};
+		fs::write(&path, json).map_err(|e| format!("Error writing {}: {}", path.display(), e))?;
+		let raw = chain_spec::to_raw_json(&path);
+		if self.output.is_none() {
+			let _ = fs::remove_file(&path);
+		}
+		self.write(&raw?)
+	}
+
+	fn write(&self, json: &str) -> Result<(), String> {
+		match self.output {
+			Some(ref path) => fs::write(path, json).map_err(|e| format!("Error writing {}: {}", path.display(), e)),
+			None => {
+				println!("{}", json);
+				Ok(())
+			}
+		}
+	}
+}
 
 /// Parse command line arguments into service configuration.
 pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()> where
@@ -15,15 +188,39 @@ pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()>
 	T: Into<std::ffi::OsString> + Clone,
 	E: IntoExit,
 {
-	parse_and_execute::<service::Factory, NoCustom, NoCustom, _, _, _, _, _>(
+	let custom = parse_and_execute::<service::Factory, CustomSubcommands, CustomArgs, _, _, _, _, _>(
 		load_spec, &version, "substrate-node", args, exit,
-	 	|exit, _custom_args, config| {
+	 	|exit, custom_args, mut config| {
 			info!("{}", version.name);
 			info!("  version {}", config.full_version());
 			info!("  by {}, 2017, 2018", version.author);
 			info!("Chain specification: {}", config.chain_spec.name());
 			info!("Node name: {}", config.name);
 			info!("Roles: {:?}", config.roles);
+			if custom_args.indexer {
+				if config.roles == ServiceRoles::LIGHT {
+					warn!("The indexer needs full block state and is disabled on light clients");
+				} else {
+					let path = Path::new(&config.database_path).with_file_name("indexer");
+					info!("Indexer database: {}", path.display());
+					// the node starts its RPC servers itself, with the indexer methods added
+					config.custom.indexer = Some(IndexerConfig {
+						path,
+						rpc_http: config.rpc_http.take(),
+						rpc_ws: config.rpc_ws.take(),
+						rpc_cors: config.rpc_cors.clone(),
+					});
+				}
+			}
+			if let Some(mode) = custom_args.sealing {
+				if config.roles == ServiceRoles::LIGHT {
+					return Err("Light clients cannot follow an instantly sealed chain".into());
+				}
+				config.custom.sealing = Some(SealConfig {
+					mode,
+					rpc_port: Some(custom_args.sealing_rpc_port.unwrap_or(DEFAULT_SEALING_RPC_PORT)),
+				});
+			}
 			let runtime = Runtime::new().map_err(|e| format!("{:?}", e))?;
 			let executor = runtime.executor();
 			match config.roles {
@@ -39,12 +236,20 @@ pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()>
 				),
 			}.map_err(|e| format!("{:?}", e))
 		}
-	).map_err(Into::into).map(|_| ())
+	)?;
+
+	match custom {
+		Some(CustomSubcommands::GenerateSpec(cmd)) => cmd.run().map_err(Into::into),
+		Some(CustomSubcommands::Tx(cmd)) => cmd.run().map_err(Into::into),
+		Some(CustomSubcommands::ExportState(cmd)) => cmd.run().map_err(Into::into),
+		None => Ok(()),
+	}
 }
 
-fn load_spec(id: &str) -> Result<Option<chain_spec::ChainSpec>, String> {
+pub(crate) fn load_spec(id: &str) -> Result<Option<chain_spec::ChainSpec>, String> {
 	Ok(match chain_spec::Alternative::from(id) {
 		Some(spec) => Some(spec.load()?),
+		None if Path::new(id).is_file() => Some(chain_spec::ChainSpec::from_json_file(PathBuf::from(id))?),
 		None => None,
 	})
 }
@@ -0,0 +1,765 @@
+//! Embedded indexer for oumuamua orders, loans, transfers and prices.
+//!
+//! The indexer follows the best chain of the local client, decodes the oumuamua events of every
+//! block, reconciles the orders they touch with the oumuamua storage of that block and keeps the
+//! resulting records in its own key-value database. Every indexed block keeps an undo journal so
+//! that a reorg can be rolled back; journals are dropped once the block is finalized. Queries are
+//! added to the node's own JSON-RPC handler as `indexer_*` methods, see `extend_rpc`.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use futures::{Future, Stream};
+use jsonrpc_core::{Error as RpcError, Params, Value};
+use kvdb::{DBTransaction, KeyValueDB};
+use kvdb_rocksdb::{Database, DatabaseConfig};
+use log::{info, warn};
+use parity_codec::{Decode, Encode};
+use parking_lot::Mutex;
+use primitives::{blake2_256, crypto::Ss58Codec, storage::StorageKey, twox_128, Blake2Hasher, H256};
+use project_runtime::{
+	oumuamua::{self, BorrowOrder, RawEvent, SupplyOrder},
+	opaque::Block,
+	AccountId,
+	Event,
+};
+use runtime_primitives::generic::BlockId;
+use runtime_primitives::traits::Header as HeaderT;
+use serde_json::json;
+use substrate_client::{self as client, BlockchainEvents, Client};
+use substrate_rpc_servers::RpcHandler;
+use substrate_service::TaskExecutor;
+
+const COL_META: Option<u32> = Some(0);
+const COL_CANON: Option<u32> = Some(1);
+const COL_JOURNAL: Option<u32> = Some(2);
+const COL_ORDERS: Option<u32> = Some(3);
+const COL_ACCOUNT_ORDERS: Option<u32> = Some(4);
+const COL_TRANSFERS: Option<u32> = Some(5);
+const COL_PRICES: Option<u32> = Some(6);
+const NUM_COLUMNS: u32 = 7;
+
+const META_BEST: &[u8] = b"best";
+const META_FINALIZED: &[u8] = b"finalized";
+
+/// Largest page returned by list queries.
+const MAX_PAGE: usize = 256;
+
+/// Indexer settings, filled in from the command line.
+#[derive(Clone, Debug)]
+pub struct IndexerConfig {
+	/// Directory of the indexer database.
+	pub path: PathBuf,
+	/// The node's HTTP RPC address. The service leaves it to the node, which serves the standard
+	/// methods together with the `indexer_*` ones there.
+	pub rpc_http: Option<SocketAddr>,
+	/// The node's WebSocket RPC address, taken over like `rpc_http`.
+	pub rpc_ws: Option<SocketAddr>,
+	/// CORS origins allowed on both RPC servers.
+	pub rpc_cors: Option<Vec<String>>,
+}
+
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Debug)]
+pub enum OrderKind {
+	Borrow,
+	Supply,
+}
+
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Debug)]
+pub enum OrderStatus {
+	Open,
+	Cancelled,
+	Filled,
+	Liquidated,
+	Repaid,
+	Defaulted,
+}
+
+/// An order as seen by the indexer. Once filled, the order is a loan between `owner` and `taker`.
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct OrderRecord {
+	pub id: H256,
+	pub kind: OrderKind,
+	pub owner: AccountId,
+	pub asset_id: u32,
+	pub amount: u64,
+	pub collateral_ids: Vec<u32>,
+	pub collateral: u64,
+	pub amortgage: u32,
+	pub duration: u64,
+	pub interest: u32,
+	pub status: OrderStatus,
+	pub taker: Option<AccountId>,
+	pub created_at: u64,
+	pub updated_at: u64,
+}
+
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct TransferRecord {
+	pub asset_id: u32,
+	pub from: AccountId,
+	pub to: AccountId,
+	pub value: u64,
+	pub block: u64,
+}
+
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct PriceRecord {
+	pub price: u64,
+	pub block: u64,
+}
+
+/// One entry of a block's undo journal: the column, the key and the value it had before the block.
+type JournalEntry = (u32, Vec<u8>, Option<Vec<u8>>);
+
+fn col_id(col: Option<u32>) -> u32 {
+	col.expect("all indexer columns are numbered; qed")
+}
+
+fn number_key(n: u64) -> [u8; 8] {
+	n.to_be_bytes()
+}
+
+/// Writes of a single block. Reads see earlier writes of the same block, and the first write of
+/// every key records the previous value in the journal.
+struct BlockWriter<'a> {
+	db: &'a Database,
+	overlay: HashMap<(u32, Vec<u8>), Option<Vec<u8>>>,
+	journal: Vec<JournalEntry>,
+}
+
+impl<'a> BlockWriter<'a> {
+	fn new(db: &'a Database) -> Self {
+		BlockWriter { db, overlay: HashMap::new(), journal: Vec::new() }
+	}
+
+	fn get(&self, col: Option<u32>, key: &[u8]) -> Result<Option<Vec<u8>>, String> {
+		if let Some(value) = self.overlay.get(&(col_id(col), key.to_vec())) {
+			return Ok(value.clone());
+		}
+		self.db.get(col, key).map(|v| v.map(|v| v.to_vec())).map_err(|e| format!("{:?}", e))
+	}
+
+	fn get_decoded<T: Decode>(&self, col: Option<u32>, key: &[u8]) -> Result<Option<T>, String> {
+		Ok(self.get(col, key)?.and_then(|v| T::decode(&mut &v[..])))
+	}
+
+	fn set(&mut self, col: Option<u32>, key: &[u8], value: Option<Vec<u8>>) -> Result<(), String> {
+		let slot = (col_id(col), key.to_vec());
+		if !self.overlay.contains_key(&slot) {
+			let old = self.db.get(col, key).map_err(|e| format!("{:?}", e))?.map(|v| v.to_vec());
+			self.journal.push((slot.0, slot.1.clone(), old));
+		}
+		self.overlay.insert(slot, value);
+		Ok(())
+	}
+
+	fn put<T: Encode>(&mut self, col: Option<u32>, key: &[u8], value: &T) -> Result<(), String> {
+		self.set(col, key, Some(value.encode()))
+	}
+
+	fn into_transaction(self, tx: &mut DBTransaction) -> Vec<JournalEntry> {
+		for ((col, key), value) in self.overlay {
+			match value {
+				Some(value) => tx.put_vec(Some(col), &key, value),
+				None => tx.delete(Some(col), &key),
+			}
+		}
+		self.journal
+	}
+}
+
+/// The indexer database together with the bookkeeping of which blocks it has applied.
+pub struct Indexer {
+	db: Database,
+	// serializes block application, reorgs and finalization
+	write_lock: Mutex<()>,
+}
+
+impl Indexer {
+	/// Open (or create) the indexer database in `path`.
+	pub fn open(path: &PathBuf) -> Result<Self, String> {
+		let config = DatabaseConfig::with_columns(Some(NUM_COLUMNS));
+		let path = path.to_str().ok_or_else(|| "Invalid indexer database path".to_string())?;
+		let db = Database::open(&config, path).map_err(|e| format!("Error opening indexer database: {:?}", e))?;
+		Ok(Indexer { db, write_lock: Mutex::new(()) })
+	}
+
+	fn get_decoded<T: Decode>(&self, col: Option<u32>, key: &[u8]) -> Result<Option<T>, String> {
+		let value = self.db.get(col, key).map_err(|e| format!("{:?}", e))?;
+		Ok(value.and_then(|v| T::decode(&mut &v[..])))
+	}
+
+	fn prefixed<T: Decode>(&self, col: Option<u32>, prefix: &[u8]) -> Vec<(Vec<u8>, T)> {
+		KeyValueDB::iter_from_prefix(&self.db, col, prefix)
+			.take_while(|(k, _)| k.starts_with(prefix))
+			.filter_map(|(k, v)| T::decode(&mut &v[..]).map(|v| (k.to_vec(), v)))
+			.collect()
+	}
+
+	/// Number and hash of the last indexed block.
+	pub fn best(&self) -> Result<Option<(u64, H256)>, String> {
+		self.get_decoded(COL_META, META_BEST)
+	}
+
+	/// Number and hash of the last finalized block seen by the indexer.
+	pub fn finalized(&self) -> Result<Option<(u64, H256)>, String> {
+		self.get_decoded(COL_META, META_FINALIZED)
+	}
+
+	fn canon_hash(&self, n: u64) -> Result<Option<H256>, String> {
+		self.get_decoded(COL_CANON, &number_key(n))
+	}
+
+	pub fn order(&self, id: &H256) -> Result<Option<OrderRecord>, String> {
+		self.get_decoded(COL_ORDERS, id.as_bytes())
+	}
+
+	/// Orders created or taken by `who`, and loans whose claim `who` received.
+	pub fn orders_of(&self, who: &AccountId) -> Result<Vec<OrderRecord>, String> {
+		let ids = self.prefixed::<H256>(COL_ACCOUNT_ORDERS, who.as_ref());
+		let mut orders = Vec::with_capacity(ids.len());
+		for (_, id) in ids {
+			if let Some(order) = self.order(&id)? {
+				orders.push(order);
+			}
+		}
+		Ok(orders)
+	}
+
+	/// Transfers from or to `who`, oldest first.
+	pub fn transfers_of(&self, who: &AccountId, skip: usize, limit: usize) -> Vec<TransferRecord> {
+		let prefix = who.as_ref();
+		KeyValueDB::iter_from_prefix(&self.db, COL_TRANSFERS, prefix)
+			.take_while(|(k, _)| k.starts_with(prefix))
+			.skip(skip)
+			.take(limit)
+			.filter_map(|(_, v)| TransferRecord::decode(&mut &v[..]))
+			.collect()
+	}
+
+	pub fn price(&self, asset_id: u32) -> Result<Option<PriceRecord>, String> {
+		self.get_decoded(COL_PRICES, &asset_id.to_be_bytes())
+	}
+
+	/// Bring the index to `target`, reverting blocks that are no longer on the best chain.
+	pub fn sync_to<B, E, RA>(&self, client: &Client<B, E, Block, RA>, target: H256) -> Result<(), String> where
+		B: client::backend::Backend<Block, Blake2Hasher>,
+		E: client::CallExecutor<Block, Blake2Hasher>,
+	{
+		let _lock = self.write_lock.lock();
+
+		// walk back from the target until we meet a block we already have on our canonical chain
+		let mut enacted = Vec::new();
+		let mut hash = target;
+		let common = loop {
+			let header = client.header(&BlockId::Hash(hash))
+				.map_err(|e| format!("{:?}", e))?
+				.ok_or_else(|| format!("Unknown block {:?}", hash))?;
+			let number = *header.number();
+			if self.canon_hash(number)? == Some(hash) {
+				break Some(number);
+			}
+			enacted.push((number, hash));
+			if number == 0 {
+				break None;
+			}
+			hash = *header.parent_hash();
+		};
+
+		while let Some((number, hash)) = self.best()? {
+			if common.map_or(false, |c| number <= c) {
+				break;
+			}
+			self.revert_block(number, hash)?;
+		}
+
+		for (number, hash) in enacted.into_iter().rev() {
+			self.apply_block(client, number, hash)?;
+		}
+
+		Ok(())
+	}
+
+	fn apply_block<B, E, RA>(&self, client: &Client<B, E, Block, RA>, number: u64, hash: H256) -> Result<(), String> where
+		B: client::backend::Backend<Block, Blake2Hasher>,
+		E: client::CallExecutor<Block, Blake2Hasher>,
+	{
+		let key = StorageKey(twox_128(b"System Events").to_vec());
+		let records: Vec<system::EventRecord<Event>> = client.storage(&BlockId::Hash(hash), &key)
+			.map_err(|e| format!("{:?}", e))?
+			.and_then(|data| Decode::decode(&mut &data.0[..]))
+			.unwrap_or_default();
+
+		let mut writer = BlockWriter::new(&self.db);
+		for (index, record) in records.into_iter().enumerate() {
+			if let Event::oumuamua(event) = record.event {
+				apply_event(&mut writer, number, index as u32, event)?;
+			}
+		}
+
+		// 事件只带有变化的字段，被这个区块改动过的订单再按链上存储校正一遍
+		let touched: Vec<H256> = writer.overlay.keys()
+			.filter(|(col, key)| Some(*col) == COL_ORDERS && key.len() == 32)
+			.map(|(_, key)| H256::from_slice(key))
+			.collect();
+		for id in touched {
+			reconcile_order(&mut writer, client, hash, number, id)?;
+		}
+		if number == 0 {
+			index_genesis_prices(&mut writer, client, hash)?;
+		}
+
+		let mut tx = self.db.transaction();
+		let journal = writer.into_transaction(&mut tx);
+		tx.put_vec(COL_JOURNAL, hash.as_bytes(), journal.encode());
+		tx.put_vec(COL_CANON, &number_key(number), hash.encode());
+		tx.put_vec(COL_META, META_BEST, (number, hash).encode());
+		self.db.write(tx).map_err(|e| format!("{:?}", e))
+	}
+
+	fn revert_block(&self, number: u64, hash: H256) -> Result<(), String> {
+		let journal: Vec<JournalEntry> = self.get_decoded(COL_JOURNAL, hash.as_bytes())?
+			.ok_or_else(|| format!("Cannot revert finalized block #{} ({:?})", number, hash))?;
+
+		let mut tx = self.db.transaction();
+		for (col, key, old) in journal.into_iter().rev() {
+			match old {
+				Some(value) => tx.put_vec(Some(col), &key, value),
+				None => tx.delete(Some(col), &key),
+			}
+		}
+		tx.delete(COL_JOURNAL, hash.as_bytes());
+		tx.delete(COL_CANON, &number_key(number));
+		match number.checked_sub(1) {
+			Some(parent) => {
+				let parent_hash = self.canon_hash(parent)?
+					.ok_or_else(|| format!("Missing indexed parent of block #{}", number))?;
+				tx.put_vec(COL_META, META_BEST, (parent, parent_hash).encode());
+			}
+			None => tx.delete(COL_META, META_BEST),
+		}
+		self.db.write(tx).map_err(|e| format!("{:?}", e))?;
+
+		info!(target: "indexer", "Reverted block #{} ({:?})", number, hash);
+		Ok(())
+	}
+
+	/// Drop the undo journals of every indexed block up to and including `number`, or up to the
+	/// best indexed block if the indexer is still behind.
+	pub fn finalize(&self, number: u64, hash: H256) -> Result<(), String> {
+		let _lock = self.write_lock.lock();
+
+		let from = self.finalized()?.map_or(0, |(n, _)| n + 1);
+		let to = match self.best()? {
+			Some((best, _)) => ::std::cmp::min(best, number),
+			None => return Ok(()),
+		};
+
+		// blocks above the best indexed one are finalized again by a later notification
+		let to_hash = match self.canon_hash(to)? {
+			Some(h) => h,
+			None => return Ok(()),
+		};
+		if to == number && to_hash != hash {
+			return Err(format!("Indexed block #{} is {:?}, not the finalized {:?}", number, to_hash, hash));
+		}
+
+		let mut tx = self.db.transaction();
+		for n in from..=to {
+			if let Some(h) = self.canon_hash(n)? {
+				tx.delete(COL_JOURNAL, h.as_bytes());
+			}
+		}
+		if from <= to {
+			tx.put_vec(COL_META, META_FINALIZED, (to, to_hash).encode());
+		}
+		self.db.write(tx).map_err(|e| format!("{:?}", e))
+	}
+}
+
+/// Decode the storage entry `key` of block `hash`.
+fn storage<T: Decode, B, E, RA>(client: &Client<B, E, Block, RA>, hash: H256, key: &[u8]) -> Result<Option<T>, String> where
+	B: client::backend::Backend<Block, Blake2Hasher>,
+	E: client::CallExecutor<Block, Blake2Hasher>,
+{
+	match client.storage(&BlockId::Hash(hash), &StorageKey(key.to_vec())).map_err(|e| format!("{:?}", e))? {
+		Some(data) => Decode::decode(&mut &data.0[..])
+			.map(Some)
+			.ok_or_else(|| format!("Undecodable storage entry {:?} in block {:?}", key, hash)),
+		None => Ok(None),
+	}
+}
+
+/// Key of the oumuamua storage map entry `name`, e.g. `b"Oumuamua BorrowOrderDetail"`.
+fn map_key<K: Encode>(name: &[u8], key: &K) -> [u8; 32] {
+	let mut raw = name.to_vec();
+	key.encode_to(&mut raw);
+	blake2_256(&raw)
+}
+
+fn indexed_status(status: &oumuamua::OrderStatus, current: OrderStatus) -> OrderStatus {
+	match status {
+		oumuamua::OrderStatus::Open => OrderStatus::Open,
+		oumuamua::OrderStatus::Cancelled => OrderStatus::Cancelled,
+		oumuamua::OrderStatus::Taken => OrderStatus::Filled,
+		oumuamua::OrderStatus::Liquidated => OrderStatus::Liquidated,
+		oumuamua::OrderStatus::Repaid => OrderStatus::Repaid,
+		oumuamua::OrderStatus::Defaulted => OrderStatus::Defaulted,
+		// migrated orders do not say how they were closed, keep what the events told us
+		oumuamua::OrderStatus::Closed => current,
+	}
+}
+
+/// Overwrite the fields of order `id` with the order stored on chain at block `hash`.
+fn reconcile_order<B, E, RA>(
+	writer: &mut BlockWriter,
+	client: &Client<B, E, Block, RA>,
+	hash: H256,
+	number: u64,
+	id: H256,
+) -> Result<(), String> where
+	B: client::backend::Backend<Block, Blake2Hasher>,
+	E: client::CallExecutor<Block, Blake2Hasher>,
+{
+	let mut order = match writer.get_decoded::<OrderRecord>(COL_ORDERS, id.as_bytes())? {
+		Some(order) => order,
+		None => return Ok(()),
+	};
+	match order.kind {
+		OrderKind::Borrow => {
+			let key = map_key(b"Oumuamua BorrowOrderDetail", &id);
+			let stored: BorrowOrder<u64, AccountId, u32, H256, u64> = match storage(client, hash, &key)? {
+				Some(stored) => stored,
+				None => return Ok(()),
+			};
+			order.amount = stored.btotal;
+			order.collateral_ids = vec![stored.stoken_id];
+			order.collateral = stored.stotal;
+			order.duration = stored.duration;
+			order.interest = stored.interest;
+			order.status = indexed_status(&stored.status, order.status);
+			order.taker = stored.taker;
+		}
+		OrderKind::Supply => {
+			let key = map_key(b"Oumuamua SupplyOrderDetail", &id);
+			let stored: SupplyOrder<u64, AccountId, u32, H256, u64> = match storage(client, hash, &key)? {
+				Some(stored) => stored,
+				None => return Ok(()),
+			};
+			order.amount = stored.total;
+			if stored.taker.is_some() {
+				order.collateral_ids = vec![stored.btoken];
+				order.collateral = stored.btotal;
+			}
+			order.amortgage = stored.amortgage;
+			order.duration = stored.duration;
+			order.interest = stored.interest;
+			order.status = indexed_status(&stored.status, order.status);
+			order.taker = stored.taker;
+		}
+	}
+	order.updated_at = number;
+	writer.put(COL_ORDERS, id.as_bytes(), &order)?;
+	if let Some(taker) = order.taker.clone() {
+		writer.put(COL_ACCOUNT_ORDERS, &account_order_key(&taker, &id), &id)?;
+	}
+	Ok(())
+}
+
+/// Prices configured at genesis never show up as `PriceSet` events, read them from storage.
+fn index_genesis_prices<B, E, RA>(
+	writer: &mut BlockWriter,
+	client: &Client<B, E, Block, RA>,
+	hash: H256,
+) -> Result<(), String> where
+	B: client::backend::Backend<Block, Blake2Hasher>,
+	E: client::CallExecutor<Block, Blake2Hasher>,
+{
+	let next_id: u32 = storage(client, hash, &twox_128(b"Oumuamua TokenId"))?.unwrap_or_default();
+	for asset_id in 0..next_id {
+		let price: Option<u64> = storage(client, hash, &map_key(b"Oumuamua TokenPrice", &asset_id))?;
+		if let Some(price) = price {
+			writer.put(COL_PRICES, &asset_id.to_be_bytes(), &PriceRecord { price, block: 0 })?;
+		}
+	}
+	Ok(())
+}
+
+fn account_order_key(who: &AccountId, id: &H256) -> Vec<u8> {
+	let mut key = who.as_ref().to_vec();
+	key.extend_from_slice(id.as_bytes());
+	key
+}
+
+fn transfer_key(who: &AccountId, number: u64, index: u32) -> Vec<u8> {
+	let mut key = who.as_ref().to_vec();
+	key.extend_from_slice(&number_key(number));
+	key.extend_from_slice(&index.to_be_bytes());
+	key
+}
+
+fn update_order<F: FnOnce(&mut OrderRecord)>(
+	writer: &mut BlockWriter,
+	number: u64,
+	id: H256,
+	f: F,
+) -> Result<(), String> {
+	match writer.get_decoded::<OrderRecord>(COL_ORDERS, id.as_bytes())? {
+		Some(mut order) => {
+			f(&mut order);
+			order.updated_at = number;
+			writer.put(COL_ORDERS, id.as_bytes(), &order)?;
+			if let Some(taker) = order.taker.clone() {
+				writer.put(COL_ACCOUNT_ORDERS, &account_order_key(&taker, &id), &id)?;
+			}
+			Ok(())
+		}
+		None => {
+			warn!(target: "indexer", "Event for unknown order {:?} in block #{}", id, number);
+			Ok(())
+		}
+	}
+}
+
+type OumuamuaEvent = RawEvent<AccountId, H256, u64, u32, u64>;
+
+fn apply_event(writer: &mut BlockWriter, number: u64, index: u32, event: OumuamuaEvent) -> Result<(), String> {
+	match event {
+		RawEvent::CreateBorrow(owner, id, btoken_id, btotal, stoken_id, stotal, duration, interest) => {
+			let order = OrderRecord {
+				id,
+				kind: OrderKind::Borrow,
+				owner: owner.clone(),
+				asset_id: btoken_id,
+				amount: btotal,
+				collateral_ids: vec![stoken_id],
+				collateral: stotal,
+				amortgage: 0,
+				duration,
+				interest,
+				status: OrderStatus::Open,
+				taker: None,
+				created_at: number,
+				updated_at: number,
+			};
+			writer.put(COL_ORDERS, id.as_bytes(), &order)?;
+			writer.put(COL_ACCOUNT_ORDERS, &account_order_key(&owner, &id), &id)
+		}
+		RawEvent::CreateSupply(owner, id, stoken_id, stotal, btoken_ids, amortgage, duration, interest) => {
+			let order = OrderRecord {
+				id,
+				kind: OrderKind::Supply,
+				owner: owner.clone(),
+				asset_id: stoken_id,
+				amount: stotal,
+				collateral_ids: btoken_ids,
+				collateral: 0,
+				amortgage,
+				duration,
+				interest,
+				status: OrderStatus::Open,
+				taker: None,
+				created_at: number,
+				updated_at: number,
+			};
+			writer.put(COL_ORDERS, id.as_bytes(), &order)?;
+			writer.put(COL_ACCOUNT_ORDERS, &account_order_key(&owner, &id), &id)
+		}
+		RawEvent::CancelBorrow(_, id, ..) | RawEvent::CancelSupply(_, id, ..) => {
+			update_order(writer, number, id, |order| order.status = OrderStatus::Cancelled)
+		}
+		RawEvent::TakeBorrow(id, lender, _, _, _) => {
+			update_order(writer, number, id, |order| {
+				order.status = OrderStatus::Filled;
+				order.taker = Some(lender);
+			})
+		}
+		RawEvent::TakeSupply(id, _, borrower, _, _, btoken_id, btotal) => {
+			update_order(writer, number, id, |order| {
+				order.status = OrderStatus::Filled;
+				order.taker = Some(borrower);
+				order.collateral_ids = vec![btoken_id];
+				order.collateral = btotal;
+			})
+		}
+		RawEvent::Liquidated(id, ..) => {
+			update_order(writer, number, id, |order| order.status = OrderStatus::Liquidated)
+		}
+		RawEvent::Repaid(id, ..) => {
+			update_order(writer, number, id, |order| order.status = OrderStatus::Repaid)
+		}
+		RawEvent::Defaulted(id, ..) => {
+			update_order(writer, number, id, |order| order.status = OrderStatus::Defaulted)
+		}
+		RawEvent::ExtensionAccepted(id, _, interest, _) => {
+			update_order(writer, number, id, |order| order.interest = interest)
+		}
+		RawEvent::CollateralAdded(id, _, _, collateral) | RawEvent::CollateralWithdrawn(id, _, _, collateral) => {
+			update_order(writer, number, id, |order| order.collateral = collateral)
+		}
+		RawEvent::CollateralSubstituted(id, _, _, collateral_id, collateral) => {
+			update_order(writer, number, id, |order| {
+				order.collateral_ids = vec![collateral_id];
+				order.collateral = collateral;
+			})
+		}
+		RawEvent::LoanClaimTransferred(id, _, holder) | RawEvent::LoanClaimSold(id, _, holder, ..) => {
+			writer.put(COL_ACCOUNT_ORDERS, &account_order_key(&holder, &id), &id)
+		}
+		RawEvent::Transfer(asset_id, from, to, value) => {
+			let transfer = TransferRecord { asset_id, from: from.clone(), to: to.clone(), value, block: number };
+			writer.put(COL_TRANSFERS, &transfer_key(&from, number, index), &transfer)?;
+			if from != to {
+				writer.put(COL_TRANSFERS, &transfer_key(&to, number, index), &transfer)?;
+			}
+			Ok(())
+		}
+		RawEvent::PriceSet(asset_id, price) => {
+			writer.put(COL_PRICES, &asset_id.to_be_bytes(), &PriceRecord { price, block: number })
+		}
+		_ => Ok(()),
+	}
+}
+
+fn order_json(order: &OrderRecord) -> Value {
+	json!({
+		"id": format!("{:?}", order.id),
+		"kind": format!("{:?}", order.kind),
+		"owner": order.owner.to_ss58check(),
+		"assetId": order.asset_id,
+		"amount": order.amount,
+		"collateralIds": order.collateral_ids,
+		"collateral": order.collateral,
+		"amortgage": order.amortgage,
+		"duration": order.duration,
+		"interest": order.interest,
+		"status": format!("{:?}", order.status),
+		"taker": order.taker.as_ref().map(|t| t.to_ss58check()),
+		"createdAt": order.created_at,
+		"updatedAt": order.updated_at,
+	})
+}
+
+fn transfer_json(transfer: &TransferRecord) -> Value {
+	json!({
+		"assetId": transfer.asset_id,
+		"from": transfer.from.to_ss58check(),
+		"to": transfer.to.to_ss58check(),
+		"value": transfer.value,
+		"block": transfer.block,
+	})
+}
+
+fn parse_account(s: &str) -> Result<AccountId, RpcError> {
+	AccountId::from_ss58check(s).map_err(|_| RpcError::invalid_params("invalid ss58 account"))
+}
+
+fn parse_hash(s: &str) -> Result<H256, RpcError> {
+	H256::from_str(s.trim_start_matches("0x")).map_err(|_| RpcError::invalid_params("invalid hash"))
+}
+
+fn internal(e: String) -> RpcError {
+	let mut error = RpcError::internal_error();
+	error.data = Some(Value::String(e));
+	error
+}
+
+/// Add the `indexer_*` query methods to the node's JSON-RPC handler `io`.
+pub fn extend_rpc(io: &mut RpcHandler, indexer: Arc<Indexer>) {
+	let idx = indexer.clone();
+	io.add_method("indexer_status", move |_: Params| {
+		let best = idx.best().map_err(internal)?;
+		let finalized = idx.finalized().map_err(internal)?;
+		Ok(json!({
+			"best": best.map(|(n, h)| json!({ "number": n, "hash": format!("{:?}", h) })),
+			"finalized": finalized.map(|(n, h)| json!({ "number": n, "hash": format!("{:?}", h) })),
+		}))
+	});
+
+	let idx = indexer.clone();
+	io.add_method("indexer_order", move |params: Params| {
+		let (id,): (String,) = params.parse()?;
+		let order = idx.order(&parse_hash(&id)?).map_err(internal)?;
+		Ok(order.as_ref().map(order_json).unwrap_or(Value::Null))
+	});
+
+	let idx = indexer.clone();
+	io.add_method("indexer_ordersOf", move |params: Params| {
+		let (who,): (String,) = params.parse()?;
+		let orders = idx.orders_of(&parse_account(&who)?).map_err(internal)?;
+		Ok(Value::Array(orders.iter().map(order_json).collect()))
+	});
+
+	let idx = indexer.clone();
+	io.add_method("indexer_loansOf", move |params: Params| {
+		let (who,): (String,) = params.parse()?;
+		let orders = idx.orders_of(&parse_account(&who)?).map_err(internal)?;
+		Ok(Value::Array(orders.iter().filter(|o| o.status == OrderStatus::Filled).map(order_json).collect()))
+	});
+
+	let idx = indexer.clone();
+	io.add_method("indexer_transfersOf", move |params: Params| {
+		let (who, skip, limit): (String, usize, usize) = params.parse()?;
+		let transfers = idx.transfers_of(&parse_account(&who)?, skip, ::std::cmp::min(limit, MAX_PAGE));
+		Ok(Value::Array(transfers.iter().map(transfer_json).collect()))
+	});
+
+	let idx = indexer;
+	io.add_method("indexer_price", move |params: Params| {
+		let (asset_id,): (u32,) = params.parse()?;
+		let price = idx.price(asset_id).map_err(internal)?;
+		Ok(price.map(|p| json!({ "price": p.price, "block": p.block })).unwrap_or(Value::Null))
+	});
+}
+
+/// Open the indexer database, catch up with the client and follow block imports and finality
+/// until the node exits. The returned indexer answers the queries of `extend_rpc`.
+pub fn start<B, E, RA>(
+	config: IndexerConfig,
+	client: Arc<Client<B, E, Block, RA>>,
+	executor: &TaskExecutor,
+	on_exit: exit_future::Exit,
+) -> Result<Arc<Indexer>, String> where
+	B: client::backend::Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+	E: client::CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+{
+	let indexer = Arc::new(Indexer::open(&config.path)?);
+
+	let best_hash = client.info().map_err(|e| format!("{:?}", e))?.chain.best_hash;
+	indexer.sync_to(&*client, best_hash)?;
+	info!(target: "indexer", "Indexer ready at {:?}", indexer.best()?);
+
+	let imports = {
+		let indexer = indexer.clone();
+		let client = client.clone();
+		client.import_notification_stream()
+			.filter(|notification| notification.is_new_best)
+			.for_each(move |notification| {
+				if let Err(e) = indexer.sync_to(&*client, notification.hash) {
+					warn!(target: "indexer", "Failed to index block {:?}: {}", notification.hash, e);
+				}
+				Ok(())
+			})
+	};
+	executor.spawn(imports.select(on_exit.clone()).then(|_| Ok(())));
+
+	let finality = {
+		let indexer = indexer.clone();
+		client.finality_notification_stream()
+			.for_each(move |notification| {
+				let number = *notification.header.number();
+				if let Err(e) = indexer.finalize(number, notification.hash) {
+					warn!(target: "indexer", "Failed to finalize block #{}: {}", number, e);
+				}
+				Ok(())
+			})
+	};
+	executor.spawn(finality.select(on_exit).then(|_| Ok(())));
+
+	Ok(indexer)
+}
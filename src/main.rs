@@ -6,6 +6,12 @@
 mod chain_spec;
 mod service;
 mod cli;
+mod indexer;
+mod sealing;
+mod snapshot;
+mod tx;
+#[cfg(test)]
+mod testing;
 
 pub use substrate_cli::{VersionInfo, IntoExit, error};
 
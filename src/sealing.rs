@@ -0,0 +1,241 @@
+//! Instant and manual sealing for development chains.
+//!
+//! Instead of waiting for Aura slots, the node authors a block as soon as a transaction enters the
+//! pool (`instant`) or when the `engine_createBlock` JSON-RPC method is called (`manual`, also
+//! available in instant mode). Blocks carry no seal, so every node of such a chain has to import
+//! them through `import_queue` rather than the Aura one.
+//!
+//! The runtime accepts a single block per Aura slot, so a block is stamped with the wall-clock time
+//! or the start of the slot after its parent, whichever is later. Sealed faster than one block per
+//! slot, the chain's clock runs ahead of the wall clock by a slot duration for every extra block
+//! and only catches up while blocks are sealed more slowly. The oumuamua module counts blocks, not
+//! time, so only the timestamp itself is affected.
+
+use std::cmp;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use consensus::SlotDuration;
+use consensus_common::{
+	import_queue::{BasicQueue, Verifier},
+	BlockImport, BlockOrigin, Environment, ForkChoiceStrategy, ImportBlock, Proposer,
+};
+use futures::{future, sync::{mpsc, oneshot}, Future, IntoFuture, Stream};
+use inherents::InherentData;
+use jsonrpc_core::{Error as RpcError, IoHandler, Value};
+use log::{info, warn};
+use parity_codec::Decode;
+use primitives::{storage::StorageKey, twox_128, H256};
+use project_runtime::opaque::Block;
+use runtime_primitives::generic::BlockId;
+use runtime_primitives::traits::{AuthorityIdFor, Block as BlockT, Header as HeaderT};
+use runtime_primitives::Justification;
+use substrate_service::{FullClient, TaskExecutor};
+
+use crate::service::Factory;
+
+/// When the node seals blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SealMode {
+	/// As soon as a transaction enters the pool.
+	Instant,
+	/// Only when requested through the RPC.
+	Manual,
+}
+
+impl FromStr for SealMode {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, String> {
+		match s {
+			"instant" => Ok(SealMode::Instant),
+			"manual" => Ok(SealMode::Manual),
+			_ => Err(format!("Unknown sealing mode {}, expected instant or manual", s)),
+		}
+	}
+}
+
+/// Settings of the sealing mode.
+#[derive(Clone, Debug)]
+pub struct SealConfig {
+	/// When to seal.
+	pub mode: SealMode,
+	/// Port of the `engine_createBlock` JSON-RPC server, `None` to not serve it.
+	pub rpc_port: Option<u16>,
+}
+
+/// Accepts every block as it is; there is no seal to check.
+pub struct InstantSealVerifier;
+
+impl Verifier<Block> for InstantSealVerifier {
+	fn verify(
+		&self,
+		origin: BlockOrigin,
+		header: <Block as BlockT>::Header,
+		justification: Option<Justification>,
+		body: Option<Vec<<Block as BlockT>::Extrinsic>>,
+	) -> Result<(ImportBlock<Block>, Option<Vec<AuthorityIdFor<Block>>>), String> {
+		let import = ImportBlock {
+			origin,
+			header,
+			justification,
+			post_digests: Vec::new(),
+			body,
+			finalized: false,
+			auxiliary: Vec::new(),
+			fork_choice: ForkChoiceStrategy::LongestChain,
+		};
+		Ok((import, None))
+	}
+}
+
+/// Import queue for chains sealed by this module.
+pub fn import_queue(client: Arc<FullClient<Factory>>) -> BasicQueue<Block> {
+	BasicQueue::new(Arc::new(InstantSealVerifier), client, None)
+}
+
+// `None` comes from the transaction pool, `Some` from the RPC and wants the result back.
+type SealRequest = Option<oneshot::Sender<Result<H256, String>>>;
+
+/// Seal blocks on top of the best block of `client` until the node exits.
+pub fn start<E>(
+	config: SealConfig,
+	client: Arc<FullClient<Factory>>,
+	environment: Arc<E>,
+	pool_ready: impl Fn() -> usize + Send + 'static,
+	pool_imports: mpsc::UnboundedReceiver<()>,
+	executor: &TaskExecutor,
+	on_exit: exit_future::Exit,
+) -> Result<(), String> where
+	E: Environment<Block> + Send + Sync + 'static,
+	E::Error: std::fmt::Debug,
+	<E::Proposer as Proposer<Block>>::Error: std::fmt::Debug,
+	<<E::Proposer as Proposer<Block>>::Create as IntoFuture>::Future: Send + 'static,
+{
+	let slot_duration = SlotDuration::get_or_compute(&*client).map_err(|e| format!("{:?}", e))?.get();
+	let (requests, seal_requests) = mpsc::unbounded::<SealRequest>();
+
+	if config.mode == SealMode::Instant {
+		let requests = requests.clone();
+		let imports = pool_imports.for_each(move |_| {
+			let _ = requests.unbounded_send(None);
+			Ok(())
+		});
+		executor.spawn(imports.select(on_exit.clone()).then(|_| Ok(())));
+	}
+
+	if let Some(port) = config.rpc_port {
+		let addr = SocketAddr::from(([127, 0, 0, 1], port));
+		let server = jsonrpc_http_server::ServerBuilder::new(rpc_handler(requests))
+			.threads(1)
+			.start_http(&addr)
+			.map_err(|e| format!("Error starting the sealing RPC server on {}: {}", addr, e))?;
+		info!("Sealing RPC server listening on {}", addr);
+		executor.spawn(on_exit.clone().then(move |_| {
+			server.close();
+			Ok(())
+		}));
+	}
+
+	let sealing = seal_requests.for_each(move |request| {
+		// several transactions can arrive before the first block is sealed
+		if request.is_none() && pool_ready() == 0 {
+			return future::Either::A(future::ok(()));
+		}
+		let sealed = seal(client.clone(), &*environment, slot_duration);
+		future::Either::B(sealed.then(move |result| {
+			match request {
+				Some(reply) => {
+					let _ = reply.send(result);
+				},
+				None => if let Err(e) = result {
+					warn!("Failed to seal a block: {}", e);
+				},
+			}
+			Ok(())
+		}))
+	});
+	executor.spawn(sealing.select(on_exit).then(|_| Ok(())));
+
+	info!("Sealing blocks in {:?} mode", config.mode);
+	Ok(())
+}
+
+fn seal<E>(client: Arc<FullClient<Factory>>, environment: &E, slot_duration: u64) -> impl Future<Item = H256, Error = String> where
+	E: Environment<Block>,
+	E::Error: std::fmt::Debug,
+	<E::Proposer as Proposer<Block>>::Error: std::fmt::Debug,
+{
+	future::result(propose(&client, environment, slot_duration))
+		.flatten()
+		.and_then(move |block| import(&client, block))
+}
+
+// Start building a block on top of the best block; the returned future completes it without
+// blocking the executor.
+fn propose<E>(
+	client: &FullClient<Factory>,
+	environment: &E,
+	slot_duration: u64,
+) -> Result<impl Future<Item = Block, Error = String>, String> where
+	E: Environment<Block>,
+	E::Error: std::fmt::Debug,
+	<E::Proposer as Proposer<Block>>::Error: std::fmt::Debug,
+{
+	let parent = client.best_block_header().map_err(|e| format!("{:?}", e))?;
+
+	// the runtime accepts a single block per Aura slot, so the timestamp has to move to the next
+	// slot even if blocks are sealed faster than that; see the module docs for the drift
+	let key = StorageKey(twox_128(b"Timestamp Now").to_vec());
+	let last: u64 = client.storage(&BlockId::Hash(parent.hash()), &key)
+		.map_err(|e| format!("{:?}", e))?
+		.and_then(|data| Decode::decode(&mut &data.0[..]))
+		.unwrap_or(0);
+	let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| format!("{:?}", e))?.as_secs();
+	let timestamp = cmp::max(now, last + slot_duration);
+
+	let mut inherent_data = InherentData::new();
+	inherent_data.put_data(timestamp::INHERENT_IDENTIFIER, &timestamp).map_err(|e| format!("{:?}", e))?;
+
+	let proposer = environment.init(&parent, &[]).map_err(|e| format!("{:?}", e))?;
+	Ok(proposer.propose(inherent_data, Duration::from_secs(slot_duration))
+		.into_future()
+		.map_err(|e| format!("{:?}", e)))
+}
+
+fn import(client: &FullClient<Factory>, block: Block) -> Result<H256, String> {
+	let (header, body) = block.deconstruct();
+	let (number, hash) = (*header.number(), header.hash());
+	let import = ImportBlock {
+		origin: BlockOrigin::Own,
+		header,
+		justification: None,
+		post_digests: Vec::new(),
+		body: Some(body),
+		finalized: false,
+		auxiliary: Vec::new(),
+		fork_choice: ForkChoiceStrategy::LongestChain,
+	};
+	client.import_block(import, None).map_err(|e| format!("{:?}", e))?;
+
+	info!("Sealed block #{} ({})", number, hash);
+	Ok(hash)
+}
+
+fn rpc_handler(requests: mpsc::UnboundedSender<SealRequest>) -> IoHandler {
+	let mut io = IoHandler::new();
+	io.add_method("engine_createBlock", move |_| {
+		let (reply, result) = oneshot::channel();
+		if requests.unbounded_send(Some(reply)).is_err() {
+			return future::Either::A(future::err(RpcError::internal_error()));
+		}
+		future::Either::B(result.then(|result| match result {
+			Ok(Ok(hash)) => Ok(Value::String(format!("{:?}", hash))),
+			Ok(Err(e)) => Err(RpcError { message: e, ..RpcError::internal_error() }),
+			Err(_) => Err(RpcError::internal_error()),
+		}))
+	});
+	io
+}
@@ -3,14 +3,16 @@
 #![warn(unused_extern_crates)]
 
 use std::sync::Arc;
+use futures::Future;
 use log::info;
 use transaction_pool::{self, txpool::{Pool as TransactionPool}};
 use project_runtime::{self, GenesisConfig, opaque::Block, RuntimeApi};
 use substrate_service::{
 	FactoryFullConfiguration, LightComponents, FullComponents, FullBackend,
 	FullClient, LightClient, LightBackend, FullExecutor, LightExecutor,
-	TaskExecutor,
+	TaskExecutor, ServiceFactory, Roles,
 };
+use substrate_rpc_servers as rpc;
 use basic_authorship::ProposerFactory;
 use consensus::{import_queue, start_aura, AuraImportQueue, SlotDuration, NothingExtra};
 use substrate_client as client;
@@ -19,6 +21,8 @@ use inherents::InherentDataProviders;
 use network::construct_simple_protocol;
 use substrate_executor::native_executor_instance;
 use substrate_service::construct_service_factory;
+use crate::indexer::{self, Indexer, IndexerConfig};
+use crate::sealing::{self, SealConfig};
 
 pub use substrate_executor::NativeExecutor;
 // Our native executor instance.
@@ -32,6 +36,58 @@ native_executor_instance!(
 #[derive(Default)]
 pub struct NodeConfig {
 	inherent_data_providers: InherentDataProviders,
+	/// Settings of the embedded indexer, `None` when it is disabled.
+	pub indexer: Option<IndexerConfig>,
+	/// Instant or manual sealing instead of Aura, `None` to use Aura.
+	pub sealing: Option<SealConfig>,
+}
+
+type FullPool = TransactionPool<<Factory as ServiceFactory>::FullTransactionPoolApi>;
+type FullExHash = <<Factory as ServiceFactory>::FullTransactionPoolApi as transaction_pool::txpool::ChainApi>::Hash;
+
+/// Start the node's HTTP and WebSocket RPC servers with the `indexer_*` methods added to the
+/// standard ones. The service does not start its own servers when the indexer is enabled.
+#[allow(clippy::too_many_arguments)]
+fn start_rpc(
+	config: &IndexerConfig,
+	system_info: rpc::apis::system::SystemInfo,
+	should_have_peers: bool,
+	client: Arc<FullClient<Factory>>,
+	network: Arc<network::SyncProvider<Block>>,
+	transaction_pool: Arc<FullPool>,
+	indexer: Arc<Indexer>,
+	executor: TaskExecutor,
+) -> Result<(Option<rpc::HttpServer>, Option<rpc::WsServer>), String> {
+	let handler = || {
+		let subscriptions = rpc::apis::Subscriptions::new(executor.clone());
+		let chain = rpc::apis::chain::Chain::new(client.clone(), subscriptions.clone());
+		let state = rpc::apis::state::State::new(client.clone(), subscriptions.clone());
+		let author = rpc::apis::author::Author::new(client.clone(), transaction_pool.clone(), subscriptions);
+		let system = rpc::apis::system::System::new(system_info.clone(), network.clone(), should_have_peers);
+		let mut io = rpc::rpc_handler::<Block, FullExHash, _, _, _, _>(state, chain, author, system);
+		indexer::extend_rpc(&mut io, indexer.clone());
+		io
+	};
+
+	let http = match config.rpc_http {
+		Some(addr) => {
+			let server = rpc::start_http(&addr, config.rpc_cors.as_ref(), handler())
+				.map_err(|e| format!("Error starting HTTP RPC on {}: {:?}", addr, e))?;
+			info!("HTTP RPC with indexer queries listening on {}", addr);
+			Some(server)
+		}
+		None => None,
+	};
+	let ws = match config.rpc_ws {
+		Some(addr) => {
+			let server = rpc::start_ws(&addr, config.rpc_cors.as_ref(), handler())
+				.map_err(|e| format!("Error starting WebSocket RPC on {}: {:?}", addr, e))?;
+			info!("WebSocket RPC with indexer queries listening on {}", addr);
+			Some(server)
+		}
+		None => None,
+	};
+	Ok((http, ws))
 }
 
 construct_simple_protocol! {
@@ -57,6 +113,55 @@ construct_service_factory! {
 			},
 		AuthoritySetup = {
 			|service: Self::FullService, executor: TaskExecutor, key: Option<Arc<Pair>>| {
+				if let Some(indexer_config) = service.config.custom.indexer.clone() {
+					let indexer = indexer::start(indexer_config.clone(), service.client(), &executor, service.on_exit())?;
+					let system_info = rpc::apis::system::SystemInfo {
+						impl_name: service.config.impl_name.into(),
+						impl_version: service.config.impl_version.into(),
+						chain_name: service.config.chain_spec.name().into(),
+						properties: service.config.chain_spec.properties(),
+					};
+					let servers = start_rpc(
+						&indexer_config,
+						system_info,
+						!service.config.network.boot_nodes.is_empty(),
+						service.client(),
+						service.network(),
+						service.transaction_pool(),
+						indexer,
+						executor.clone(),
+					)?;
+					// the servers shut down when dropped
+					executor.spawn(service.on_exit().then(move |_| {
+						drop(servers);
+						Ok(())
+					}));
+				}
+
+				// sealing needs no authority key, but only authorities seal; the others import
+				if let Some(seal_config) = service.config.custom.sealing.clone() {
+					if service.config.roles != Roles::AUTHORITY {
+						info!("Following a {:?} sealed chain without sealing blocks", seal_config.mode);
+						return Ok(service);
+					}
+					let proposer = Arc::new(ProposerFactory {
+						client: service.client(),
+						transaction_pool: service.transaction_pool(),
+						inherents_pool: service.inherents_pool(),
+					});
+					let pool = service.transaction_pool();
+					sealing::start(
+						seal_config,
+						service.client(),
+						proposer,
+						move || pool.status().ready,
+						service.transaction_pool().import_notification_stream(),
+						&executor,
+						service.on_exit(),
+					)?;
+					return Ok(service);
+				}
+
 				if let Some(key) = key {
 					info!("Using authority key {}", key.public());
 					let proposer = Arc::new(ProposerFactory {
@@ -87,6 +192,9 @@ construct_service_factory! {
 			Self::Block,
 		>
 			{ |config: &mut FactoryFullConfiguration<Self> , client: Arc<FullClient<Self>>| {
+					if config.custom.sealing.is_some() {
+						return Ok(sealing::import_queue(client));
+					}
 					import_queue::<_, _, _, Pair>(
 						SlotDuration::get_or_compute(&*client)?,
 						client.clone(),
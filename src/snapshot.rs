@@ -0,0 +1,836 @@
+//! Dump of the oumuamua state at a block, and its conversion back into genesis config.
+//!
+//! The `export-state` command opens the database of a stopped node and writes tokens, balances,
+//! allowances, prices, allowed assets and orders as JSON. `generate-spec --oumuamua-state` turns
+//! such a dump into the oumuamua genesis of a new chain. Account history is not part of the dump.
+//!
+//! Interest is accrued up to the exported block. Pool loans are dumped with their debt, which the
+//! new chain takes as principal at a borrow index of one; running orders keep their principal and
+//! carry their loan index, restarted from the genesis block of the new chain. The maturity of a
+//! running order is dumped as the blocks it has left, which the new chain counts from genesis.
+//!
+//! Balances are maps over accounts, which storage cannot enumerate. Candidate accounts are
+//! collected from the indices module, the orders, the oumuamua events of every block whose state
+//! is still available and `--account`; the export fails if the balances found do not add up to the
+//! total supply of a token. Allowances are listed by `AllowanceByIndex`, which starts with the
+//! migration to storage version 1. Older ones are only known from the `Approval` events before
+//! `AllowancesIndexedFrom`, so the export fails if the state of those blocks is pruned. Balances
+//! and allowances the migration has not moved are read from the `KittyStorage` prefix.
+//!
+//! Governance and admin multisig proposals are not part of the dump. A dump taken while any of
+//! them is open cannot become a genesis config; balances locked in votes come back free.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+use parity_codec::{Decode, Encode};
+use primitives::{blake2_256, crypto::Ss58Codec, hexdisplay::HexDisplay, storage::StorageKey, twox_128, H256};
+use project_runtime::{oumuamua, AccountId, Event, OumuamuaModuleConfig};
+use runtime_primitives::generic::BlockId;
+use runtime_primitives::traits::Header as HeaderT;
+use serde::{Deserialize, Serialize};
+use structopt::StructOpt;
+use substrate_service::{Configuration, FactoryFullConfiguration, FullClient, PruningMode};
+
+use crate::service::Factory;
+
+type AssetId = u32;
+type TokenBalance = u64;
+type BorrowOrder = oumuamua::BorrowOrder<TokenBalance, AccountId, AssetId, H256, u64>;
+type SupplyOrder = oumuamua::SupplyOrder<TokenBalance, AccountId, AssetId, H256, u64>;
+
+/// The oumuamua state at one block.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Snapshot {
+	/// Number of the exported block.
+	pub block_number: u64,
+	/// Hash of the exported block.
+	pub block_hash: H256,
+	/// Admin of the module.
+	pub admin: AccountId,
+	/// History entries kept per account.
+	pub history_cap: u64,
+	/// Call fee per unit of weight, a decimal string as it does not fit every JSON parser.
+	pub fee_per_weight: String,
+	/// Blocks per year, the unit of interest accrual.
+	#[serde(default = "default_blocks_per_year")]
+	pub blocks_per_year: u64,
+	/// Share of loan interest paid into the insurance fund, in 1/10000.
+	#[serde(default)]
+	pub insurance_fee_share: u32,
+	/// Flash loan fee, in 1/10000 of the borrowed amount.
+	#[serde(default)]
+	pub flash_loan_fee: u32,
+	/// Token whose holders vote on governance proposals, none while governance is disabled.
+	#[serde(default)]
+	pub governance_asset: Option<u32>,
+	/// Voting period, enactment delay, quorum and threshold of new proposals.
+	#[serde(default)]
+	pub governance_params: oumuamua::GovernanceParams<u64>,
+	/// Block from which the admin account can no longer make privileged calls directly.
+	#[serde(default)]
+	pub legacy_admin_until: Option<u64>,
+	/// Governance tokens a proposer locks until the proposal closes.
+	#[serde(default)]
+	pub proposal_deposit: TokenBalance,
+	/// Signatories of the admin multisig, empty while it is not set up.
+	#[serde(default)]
+	pub admin_signatories: Vec<AccountId>,
+	/// Approvals an admin multisig proposal needs.
+	#[serde(default)]
+	pub admin_threshold: u32,
+	/// Tokens by id, starting at 1.
+	pub tokens: Vec<TokenState>,
+	/// Non-zero allowances, sorted by token, owner and spender.
+	pub allowances: Vec<AllowanceState>,
+	/// Borrow orders in creation order.
+	pub borrow_orders: Vec<BorrowOrder>,
+	/// Supply orders in creation order.
+	pub supply_orders: Vec<SupplyOrder>,
+	/// Lending pools by token id.
+	#[serde(default)]
+	pub pools: Vec<PoolState>,
+	/// Interest indexes of the running orders, sorted by order id. Taken orders without one
+	/// accrue interest from the genesis block.
+	#[serde(default)]
+	pub loan_indexes: Vec<LoanIndexState>,
+	/// Repayment terms of the open and running orders that set any, sorted by order id.
+	#[serde(default)]
+	pub loan_terms: Vec<LoanTermsState>,
+	/// Holders and listings of the loan claims that changed hands or are for sale, sorted by order id.
+	#[serde(default)]
+	pub loan_claims: Vec<LoanClaimState>,
+	/// Ids of the governance proposals still voting or waiting for enactment.
+	#[serde(default)]
+	pub open_proposals: Vec<u32>,
+	/// Ids of the admin multisig proposals waiting for approvals.
+	#[serde(default)]
+	pub pending_admin_proposals: Vec<u32>,
+}
+
+/// Dumps of chains before interest accrual: 10 second blocks, as the storage migration.
+fn default_blocks_per_year() -> u64 {
+	3_153_600
+}
+
+/// A token and its holders.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TokenState {
+	pub id: AssetId,
+	pub name: String,
+	pub ticker: String,
+	pub decimals: u8,
+	pub total_supply: TokenBalance,
+	/// Price of one smallest unit in 1/10000 USDT, if set.
+	pub price: Option<u64>,
+	/// Whether orders accept the token.
+	pub allowed: bool,
+	/// Risk parameters, if set. Dumps of older chains have none.
+	#[serde(default)]
+	pub risk_params: Option<oumuamua::RiskParams<TokenBalance>>,
+	/// Insurance fund of the token, held by no account and so not part of the holders.
+	#[serde(default)]
+	pub insurance_fund: TokenBalance,
+	/// Shortfalls of loans in the token so far.
+	#[serde(default)]
+	pub bad_debt: oumuamua::BadDebt<TokenBalance>,
+	/// Accounts with a non-zero balance, sorted by account.
+	pub holders: Vec<HolderState>,
+}
+
+/// Balance of one account in one token.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct HolderState {
+	pub account: AccountId,
+	pub free: TokenBalance,
+	/// Locked by orders, see `borrowOrders` and `supplyOrders`.
+	pub reserved: TokenBalance,
+}
+
+/// A lending pool and the loans drawn from it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PoolState {
+	pub token_id: AssetId,
+	pub share_token: AssetId,
+	/// Funds in the pool, not held by any account and so not part of the token holders.
+	pub cash: TokenBalance,
+	/// Sum of the debts of `loans`.
+	pub borrowed: TokenBalance,
+	pub rate_model: oumuamua::RateModel,
+	/// Interest set aside for the insurance fund and not yet paid into it from `cash`.
+	#[serde(default)]
+	pub reserves: TokenBalance,
+	/// Borrowers, sorted by account.
+	pub loans: Vec<PoolLoanState>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PoolLoanState {
+	pub account: AccountId,
+	/// Principal plus interest up to the exported block.
+	pub principal: TokenBalance,
+	pub collateral_asset: AssetId,
+	/// Reserved from the account, see `reserved` of its holder entry.
+	pub collateral: TokenBalance,
+}
+
+/// Interest index of a running order at the exported block.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LoanIndexState {
+	pub order_id: H256,
+	/// Fixed-point, `oumuamua::interest::INDEX_ONE` being one; decimal strings like `feePerWeight`.
+	pub index: String,
+	pub rate_per_block: String,
+}
+
+/// Repayment terms of an order. `maturity` is the number of blocks a running order has left.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LoanTermsState {
+	pub order_id: H256,
+	pub terms: oumuamua::LoanTerms<u64>,
+}
+
+/// Claim on a running loan. `holder` is absent while the lender of the order holds it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LoanClaimState {
+	pub order_id: H256,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub holder: Option<AccountId>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub listing: Option<oumuamua::ClaimListing<AssetId, TokenBalance>>,
+}
+
+/// What `spender` may still transfer out of the `owner` balance.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AllowanceState {
+	pub token_id: AssetId,
+	pub owner: AccountId,
+	pub spender: AccountId,
+	pub amount: TokenBalance,
+}
+
+impl Snapshot {
+	/// Read a dump written by `export-state`.
+	pub fn from_file(path: &Path) -> Result<Self, String> {
+		let file = fs::File::open(path).map_err(|e| format!("Error opening {}: {}", path.display(), e))?;
+		serde_json::from_reader(file).map_err(|e| format!("Error parsing {}: {}", path.display(), e))
+	}
+
+	/// Genesis config of a chain starting from this state, with `admin` as module admin.
+	///
+	/// Reserved balances are not part of the config, the genesis build reserves them again from
+	/// the orders. Proposals cannot be carried over, so a dump with open ones is refused; tokens
+	/// locked in votes come back free.
+	pub fn genesis_config(&self, admin: AccountId) -> Result<OumuamuaModuleConfig, String> {
+		if !self.open_proposals.is_empty() || !self.pending_admin_proposals.is_empty() {
+			return Err(format!(
+				"The dump has open governance proposals {:?} and pending admin multisig proposals {:?}, \
+				which a genesis config cannot carry; export a block after they are closed",
+				self.open_proposals, self.pending_admin_proposals,
+			));
+		}
+		for (index, token) in self.tokens.iter().enumerate() {
+			if token.id as usize != index + 1 {
+				return Err(format!("Token ids must be consecutive from 1, found {} at position {}", token.id, index));
+			}
+		}
+		let fee_per_weight = self.fee_per_weight.parse()
+			.map_err(|_| format!("Invalid fee per weight {}", self.fee_per_weight))?;
+		let fixed = |s: &str| s.parse::<u128>().map_err(|_| format!("Invalid interest index {}", s));
+		let mut loan_indexes = Vec::new();
+		for loan in &self.loan_indexes {
+			loan_indexes.push((loan.order_id, oumuamua::LoanIndex {
+				index: fixed(&loan.index)?,
+				rate_per_block: fixed(&loan.rate_per_block)?,
+				accrued_at: 0,
+			}));
+		}
+
+		Ok(OumuamuaModuleConfig {
+			admin,
+			token_id: 1,
+			history_cap: self.history_cap,
+			fee_per_weight,
+			blocks_per_year: self.blocks_per_year,
+			insurance_fee_share: self.insurance_fee_share,
+			flash_loan_fee: self.flash_loan_fee,
+			governance_asset: self.governance_asset,
+			governance_params: self.governance_params,
+			legacy_admin_until: self.legacy_admin_until,
+			proposal_deposit: self.proposal_deposit,
+			admin_signatories: self.admin_signatories.clone(),
+			admin_threshold: self.admin_threshold,
+			tokens: self.tokens.iter().map(|token| (
+				token.name.clone().into_bytes(),
+				token.ticker.clone().into_bytes(),
+				token.decimals,
+				token.total_supply,
+				token.holders.iter().map(|h| (h.account.clone(), h.free + h.reserved)).collect(),
+			)).collect(),
+			prices: self.tokens.iter().filter_map(|token| token.price.map(|price| (token.id, price))).collect(),
+			allowed_assets: self.tokens.iter().filter(|token| token.allowed).map(|token| token.id).collect(),
+			allowances: self.allowances.iter()
+				.map(|a| (a.token_id, a.owner.clone(), a.spender.clone(), a.amount))
+				.collect(),
+			borrow_orders: self.borrow_orders.clone(),
+			supply_orders: self.supply_orders.clone(),
+			risk_params: self.tokens.iter().filter_map(|token| token.risk_params.map(|params| (token.id, params))).collect(),
+			insurance_funds: self.tokens.iter()
+				.filter(|token| token.insurance_fund > 0)
+				.map(|token| (token.id, token.insurance_fund))
+				.collect(),
+			bad_debts: self.tokens.iter()
+				.filter(|token| token.bad_debt != Default::default())
+				.map(|token| (token.id, token.bad_debt))
+				.collect(),
+			loan_indexes,
+			loan_terms: self.loan_terms.iter().map(|loan| (loan.order_id, loan.terms.clone())).collect(),
+			loan_claims: self.loan_claims.iter()
+				.filter_map(|claim| claim.holder.clone().map(|holder| (claim.order_id, holder)))
+				.collect(),
+			claim_listings: self.loan_claims.iter()
+				.filter_map(|claim| claim.listing.map(|listing| (claim.order_id, listing)))
+				.collect(),
+			pools: self.pools.iter().map(|pool| (pool.token_id, oumuamua::Pool {
+				share_token: pool.share_token,
+				cash: pool.cash,
+				scaled_borrowed: pool.borrowed,
+				rate_model: pool.rate_model,
+				borrow_index: oumuamua::interest::INDEX_ONE,
+				accrued_at: 0,
+				reserves: pool.reserves,
+			})).collect(),
+			pool_loans: self.pools.iter()
+				.flat_map(|pool| pool.loans.iter().map(move |loan| (pool.token_id, loan.account.clone(), oumuamua::PoolLoan {
+					scaled_debt: loan.principal,
+					collateral_asset: loan.collateral_asset,
+					collateral: loan.collateral,
+				})))
+				.collect(),
+		})
+	}
+}
+
+/// Storage of one block.
+struct State<'a> {
+	client: &'a FullClient<Factory>,
+	at: BlockId<project_runtime::opaque::Block>,
+}
+
+impl<'a> State<'a> {
+	fn raw<T: Decode>(&self, key: &[u8]) -> Result<Option<T>, String> {
+		let data = self.client.storage(&self.at, &StorageKey(key.to_vec())).map_err(|e| format!("{:?}", e))?;
+		match data {
+			Some(data) => Decode::decode(&mut &data.0[..])
+				.map(Some)
+				.ok_or_else(|| format!("Undecodable storage entry 0x{}", HexDisplay::from(&key.to_vec()))),
+			None => Ok(None),
+		}
+	}
+
+	/// A storage value such as `b"Oumuamua TokenId"`.
+	fn value<T: Decode>(&self, name: &[u8]) -> Result<Option<T>, String> {
+		self.raw(&twox_128(name))
+	}
+
+	/// A storage map entry such as `b"Oumuamua BalanceOf"`.
+	fn map<K: Encode, T: Decode>(&self, name: &[u8], key: &K) -> Result<Option<T>, String> {
+		let mut raw = name.to_vec();
+		key.encode_to(&mut raw);
+		self.raw(&blake2_256(&raw))
+	}
+
+	/// A balance or allowance map entry such as `b"BalanceOf"`, under the version 0 prefix if the
+	/// storage migration has not moved it.
+	fn account_map<K: Encode, T: Decode>(&self, name: &[u8], key: &K) -> Result<Option<T>, String> {
+		match self.map(&[&b"Oumuamua "[..], name].concat(), key)? {
+			Some(value) => Ok(Some(value)),
+			None => self.map(&[&b"KittyStorage "[..], name].concat(), key),
+		}
+	}
+}
+
+/// Accounts and approvals seen in the oumuamua events of `hash` and its ancestors.
+#[derive(Default)]
+struct Seen {
+	accounts: BTreeMap<Vec<u8>, AccountId>,
+	approvals: BTreeSet<(AssetId, Vec<u8>, Vec<u8>)>,
+}
+
+impl Seen {
+	fn account(&mut self, who: &AccountId) {
+		self.accounts.insert(who.encode(), who.clone());
+	}
+
+	fn approval(&mut self, token_id: AssetId, owner: &AccountId, spender: &AccountId) {
+		self.approvals.insert((token_id, owner.encode(), spender.encode()));
+		self.account(owner);
+		self.account(spender);
+	}
+
+	/// Scan the events of `hash` and its ancestors; those of every block before `needed_before`
+	/// must be available.
+	fn scan_events(&mut self, client: &FullClient<Factory>, mut hash: H256, needed_before: u64) -> Result<(), String> {
+		let key = StorageKey(twox_128(b"System Events").to_vec());
+		loop {
+			let header = client.header(&BlockId::Hash(hash))
+				.map_err(|e| format!("{:?}", e))?
+				.ok_or_else(|| format!("Unknown block {:?}", hash))?;
+			// pruned state cannot be read; the blocks before it are pruned as well
+			let events = match client.storage(&BlockId::Hash(hash), &key) {
+				Ok(events) => events,
+				Err(_) if *header.number() < needed_before => return Err(format!(
+					"Allowances granted before block #{} are only known from events and the state of block #{} is pruned; \
+					export from an archive node",
+					needed_before, header.number(),
+				)),
+				Err(_) => {
+					warn!("State of blocks up to #{} is pruned, their events are not scanned", header.number());
+					return Ok(());
+				},
+			};
+			let records: Vec<system::EventRecord<Event>> = events
+				.and_then(|data| Decode::decode(&mut &data.0[..]))
+				.unwrap_or_default();
+			for record in records {
+				if let Event::oumuamua(event) = record.event {
+					self.event(event);
+				}
+			}
+			if *header.number() == 0 {
+				return Ok(());
+			}
+			hash = *header.parent_hash();
+		}
+	}
+
+	fn event(&mut self, event: oumuamua::Event<project_runtime::Runtime>) {
+		use oumuamua::RawEvent::*;
+		match event {
+			Approval(token_id, owner, spender, _) => self.approval(token_id, &owner, &spender),
+			Transfer(_, from, to, _) => {
+				self.account(&from);
+				self.account(&to);
+			},
+			TakeBorrow(_, a, b, ..) | TakeSupply(_, a, b, ..) | Liquidated(_, a, b, ..) | PoolLiquidated(_, a, b, ..)
+			| Repaid(_, a, b, ..) | LoanClaimTransferred(_, a, b) | LoanClaimSold(_, a, b, ..) | Defaulted(_, a, b, ..) => {
+				self.account(&a);
+				self.account(&b);
+			},
+			CreateBorrow(who, ..) | CancelBorrow(who, ..) | CreateSupply(who, ..) | CancelSupply(who, ..)
+			| NewToken(_, who, _) | Issue(_, who, _) | Destroyed(_, who, _)
+			| Reserve(_, who, _) | UnReserve(_, who, _) | FinishBorrow(who, _) | FinishSupply(who, _)
+			| Deposited(_, who, ..) | Withdrawn(_, who, ..) | PoolBorrowed(_, who, ..) | PoolRepaid(_, who, _)
+			| RolloverSet(_, who, _) | LoanClaimListed(_, who, ..) | InsuranceDeposited(_, who, _)
+			| ShortfallCovered(_, who, ..) => self.account(&who),
+			_ => {},
+		}
+	}
+}
+
+/// Dump the oumuamua state at `at`, looking at `extra_accounts` besides the discovered ones.
+pub fn export(client: &FullClient<Factory>, at: BlockId<project_runtime::opaque::Block>, extra_accounts: &[AccountId]) -> Result<Snapshot, String> {
+	let header = client.header(&at)
+		.map_err(|e| format!("{:?}", e))?
+		.ok_or_else(|| format!("Unknown block {:?}", at))?;
+	let state = State { client, at: BlockId::Hash(header.hash()) };
+
+	let version: u32 = state.value(b"Oumuamua StorageVersion")?.unwrap_or_default();
+	if version != oumuamua::CURRENT_STORAGE_VERSION {
+		return Err(format!(
+			"Oumuamua storage is at version {}, expected {}; export a block after the runtime upgrade",
+			version, oumuamua::CURRENT_STORAGE_VERSION,
+		));
+	}
+
+	let admin: AccountId = state.value(b"Oumuamua Admin")?.unwrap_or_default();
+	let token_id: AssetId = state.value(b"Oumuamua TokenId")?.unwrap_or_default();
+	let fee_per_weight: u128 = state.value(b"Oumuamua FeePerWeight")?.unwrap_or_default();
+	let blocks_per_year: u64 = state.value(b"Oumuamua BlocksPerYear")?.unwrap_or_default();
+	let block_number = *header.number();
+
+	let mut borrow_orders = Vec::new();
+	for index in 0..state.value::<u64>(b"Oumuamua AllBorrowOrderCount")?.unwrap_or_default() {
+		let id: H256 = state.map(b"Oumuamua AllBorrowOrder", &index)?.unwrap_or_default();
+		borrow_orders.push(state.map(b"Oumuamua BorrowOrderDetail", &id)?.unwrap_or_default());
+	}
+	let mut supply_orders = Vec::new();
+	for index in 0..state.value::<u64>(b"Oumuamua AllSupplyOrderCount")?.unwrap_or_default() {
+		let id: H256 = state.map(b"Oumuamua AllSupplyOrder", &index)?.unwrap_or_default();
+		supply_orders.push(state.map(b"Oumuamua SupplyOrderDetail", &id)?.unwrap_or_default());
+	}
+
+	let mut loan_indexes = Vec::new();
+	let taken = borrow_orders.iter().filter(|order| order.status == oumuamua::OrderStatus::Taken).map(|order| order.id)
+		.chain(supply_orders.iter().filter(|order| order.status == oumuamua::OrderStatus::Taken).map(|order| order.id));
+	for order_id in taken {
+		let index: Option<oumuamua::LoanIndex<u64>> = state.map(b"Oumuamua LoanIndexes", &order_id)?;
+		if let Some(index) = index {
+			loan_indexes.push(LoanIndexState {
+				order_id,
+				index: index.at(block_number).to_string(),
+				rate_per_block: index.rate_per_block.to_string(),
+			});
+		}
+	}
+	loan_indexes.sort_by_key(|loan| loan.order_id);
+
+	let mut loan_terms = Vec::new();
+	let running = |status: oumuamua::OrderStatus| status == oumuamua::OrderStatus::Open || status == oumuamua::OrderStatus::Taken;
+	let orders = borrow_orders.iter().filter(|order| running(order.status)).map(|order| (order.id, order.status, order.updated_at + order.duration))
+		.chain(supply_orders.iter().filter(|order| running(order.status)).map(|order| (order.id, order.status, order.updated_at + order.duration)));
+	for (order_id, status, default_maturity) in orders {
+		let terms: Option<oumuamua::LoanTerms<u64>> = state.map(b"Oumuamua OrderTerms", &order_id)?;
+		let mut terms = match terms {
+			Some(terms) => terms,
+			None if status == oumuamua::OrderStatus::Taken => Default::default(),
+			None => continue,
+		};
+		if status == oumuamua::OrderStatus::Taken {
+			let maturity = terms.maturity.unwrap_or(default_maturity);
+			terms.maturity = Some(maturity.saturating_sub(block_number));
+		}
+		loan_terms.push(LoanTermsState { order_id, terms });
+	}
+	loan_terms.sort_by_key(|loan| loan.order_id);
+
+	let mut loan_claims = Vec::new();
+	let taken = borrow_orders.iter().filter(|order| order.status == oumuamua::OrderStatus::Taken).map(|order| order.id)
+		.chain(supply_orders.iter().filter(|order| order.status == oumuamua::OrderStatus::Taken).map(|order| order.id));
+	for order_id in taken {
+		let holder: Option<AccountId> = state.map(b"Oumuamua LoanClaims", &order_id)?;
+		let listing: Option<oumuamua::ClaimListing<AssetId, TokenBalance>> = state.map(b"Oumuamua ClaimListings", &order_id)?;
+		if holder.is_some() || listing.is_some() {
+			loan_claims.push(LoanClaimState { order_id, holder, listing });
+		}
+	}
+	loan_claims.sort_by_key(|claim| claim.order_id);
+
+	let mut seen = Seen::default();
+	seen.account(&admin);
+	extra_accounts.iter().for_each(|who| seen.account(who));
+	for order in &borrow_orders {
+		seen.account(&order.owner);
+		order.taker.iter().for_each(|who| seen.account(who));
+	}
+	for order in &supply_orders {
+		seen.account(&order.owner);
+		order.taker.iter().for_each(|who| seen.account(who));
+	}
+	loan_claims.iter().filter_map(|claim| claim.holder.as_ref()).for_each(|who| seen.account(who));
+	let next_enum_set: u32 = state.value(b"Indices NextEnumSet")?.unwrap_or_default();
+	for set in 0..=next_enum_set {
+		let accounts: Vec<AccountId> = state.map(b"Indices EnumSet", &set)?.unwrap_or_default();
+		accounts.iter().for_each(|who| seen.account(who));
+	}
+	for index in 0..state.value::<u64>(b"Oumuamua AllowanceCount")?.unwrap_or_default() {
+		let (token_id, owner, spender): (AssetId, AccountId, AccountId) = state.map(b"Oumuamua AllowanceByIndex", &index)?
+			.ok_or_else(|| format!("Allowance {} is missing", index))?;
+		seen.approval(token_id, &owner, &spender);
+	}
+	let indexed_from: u64 = state.value(b"Oumuamua AllowancesIndexedFrom")?.unwrap_or_default();
+	seen.scan_events(client, header.hash(), indexed_from)?;
+	info!("Looking up balances of {} accounts", seen.accounts.len());
+
+	let insurance_fee_share: u32 = state.value(b"Oumuamua InsuranceFeeShare")?.unwrap_or_default();
+	let mut pools = Vec::new();
+	for id in 1..token_id {
+		let mut pool: oumuamua::Pool<AssetId, TokenBalance, u64> = match state.map(b"Oumuamua Pools", &id)? {
+			Some(pool) => pool,
+			None => continue,
+		};
+		pool.accrue(block_number, blocks_per_year, insurance_fee_share);
+		let mut loans = Vec::new();
+		let mut scaled = 0;
+		for who in seen.accounts.values() {
+			let loan: Option<oumuamua::PoolLoan<AssetId, TokenBalance>> = state.map(b"Oumuamua PoolLoans", &(id, who.clone()))?;
+			if let Some(loan) = loan {
+				scaled += loan.scaled_debt;
+				loans.push(PoolLoanState {
+					account: who.clone(),
+					principal: pool.debt(loan.scaled_debt),
+					collateral_asset: loan.collateral_asset,
+					collateral: loan.collateral,
+				});
+			}
+		}
+		if scaled != pool.scaled_borrowed {
+			return Err(format!(
+				"Loans found for the pool of token {} do not add up to {} borrowed; pass the missing borrowers with --account",
+				id, pool.borrowed(),
+			));
+		}
+		pools.push(PoolState {
+			token_id: id,
+			share_token: pool.share_token,
+			cash: pool.cash,
+			borrowed: loans.iter().map(|loan| loan.principal).sum(),
+			rate_model: pool.rate_model,
+			reserves: pool.reserves,
+			loans,
+		});
+	}
+
+	let mut tokens = Vec::new();
+	for id in 1..token_id {
+		let token: oumuamua::Erc20Token<TokenBalance> = state.map(b"Oumuamua Tokens", &id)?
+			.ok_or_else(|| format!("Token {} is missing", id))?;
+		let mut holders = Vec::new();
+		let mut sum = 0u64;
+		for who in seen.accounts.values() {
+			let key = (id, who.clone());
+			let balance: TokenBalance = state.account_map(b"BalanceOf", &key)?.unwrap_or_default();
+			if balance == 0 {
+				continue;
+			}
+			let free: TokenBalance = state.account_map(b"FreeBalanceOf", &key)?.unwrap_or_default();
+			let reserved: TokenBalance = state.account_map(b"ReserveBalanceOf", &key)?.unwrap_or_default();
+			if free + reserved != balance {
+				return Err(format!("Balance of {} in token {} is not free plus reserved", who.to_ss58check(), id));
+			}
+			sum += balance;
+			holders.push(HolderState { account: who.clone(), free, reserved });
+		}
+		// pool cash and the insurance fund are held by no account
+		sum += pools.iter().filter(|pool| pool.token_id == id).map(|pool| pool.cash).sum::<TokenBalance>();
+		let insurance_fund: TokenBalance = state.map(b"Oumuamua InsuranceFunds", &id)?.unwrap_or_default();
+		sum += insurance_fund;
+		if sum != token.total_supply {
+			return Err(format!(
+				"Balances found for token {} add up to {} of {}; pass the missing holders with --account",
+				id, sum, token.total_supply,
+			));
+		}
+
+		let text = |bytes: Vec<u8>| String::from_utf8(bytes).map_err(|_| format!("Name or ticker of token {} is not UTF-8", id));
+		tokens.push(TokenState {
+			id,
+			name: text(token.name)?,
+			ticker: text(token.ticker)?,
+			decimals: token.decimals,
+			total_supply: token.total_supply,
+			price: state.map(b"Oumuamua TokenPrice", &id)?,
+			allowed: state.map(b"Oumuamua AllowAssets", &id)?.unwrap_or(false),
+			risk_params: state.map(b"Oumuamua AssetRiskParams", &id)?,
+			insurance_fund,
+			bad_debt: state.map(b"Oumuamua BadDebts", &id)?.unwrap_or_default(),
+			holders,
+		});
+	}
+
+	let mut allowances = Vec::new();
+	for (token_id, owner, spender) in &seen.approvals {
+		let (owner, spender) = (&seen.accounts[owner], &seen.accounts[spender]);
+		let amount: TokenBalance = state.account_map(b"Allowance", &(*token_id, owner.clone(), spender.clone()))?.unwrap_or_default();
+		if amount > 0 {
+			allowances.push(AllowanceState { token_id: *token_id, owner: owner.clone(), spender: spender.clone(), amount });
+		}
+	}
+
+	let mut open_proposals = Vec::new();
+	for id in 0..state.value::<u32>(b"Oumuamua ProposalCount")?.unwrap_or_default() {
+		let proposal: Option<oumuamua::GovernanceProposal<AccountId, oumuamua::Call<project_runtime::Runtime>, AssetId, u64, TokenBalance>> =
+			state.map(b"Oumuamua Proposals", &id)?;
+		if proposal.map_or(false, |proposal| proposal.status == oumuamua::ProposalStatus::Voting) {
+			open_proposals.push(id);
+		}
+	}
+
+	Ok(Snapshot {
+		block_number,
+		block_hash: header.hash(),
+		admin,
+		history_cap: state.value(b"Oumuamua HistoryCap")?.unwrap_or_default(),
+		fee_per_weight: fee_per_weight.to_string(),
+		blocks_per_year,
+		insurance_fee_share,
+		flash_loan_fee: state.value(b"Oumuamua FlashLoanFee")?.unwrap_or_default(),
+		governance_asset: state.value(b"Oumuamua GovernanceAsset")?,
+		governance_params: state.value(b"Oumuamua GovernanceParameters")?.unwrap_or_default(),
+		legacy_admin_until: state.value(b"Oumuamua LegacyAdminUntil")?,
+		proposal_deposit: state.value(b"Oumuamua ProposalDeposit")?.unwrap_or_default(),
+		admin_signatories: state.value(b"Oumuamua AdminSignatories")?.unwrap_or_default(),
+		admin_threshold: state.value(b"Oumuamua AdminThreshold")?.unwrap_or_default(),
+		tokens,
+		allowances,
+		borrow_orders,
+		supply_orders,
+		pools,
+		loan_indexes,
+		loan_terms,
+		loan_claims,
+		open_proposals,
+		pending_admin_proposals: state.value(b"Oumuamua PendingAdminProposals")?.unwrap_or_default(),
+	})
+}
+
+/// The `export-state` command. The node must not be running. The client opens its database for
+/// writing, so the pruning mode has to be given explicitly; the state database refuses to open with
+/// a mode other than the one it was created with.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ExportStateCmd {
+	/// Chain spec of the database: dev, local or a JSON file
+	#[structopt(long = "chain", value_name = "CHAIN_SPEC", default_value = "")]
+	chain: String,
+
+	/// Base path of the node, as given to `--base-path` when running it
+	#[structopt(long = "base-path", short = "d", value_name = "PATH", parse(from_os_str))]
+	base_path: PathBuf,
+
+	/// Pruning mode of the database, as given to `--pruning` when running the node: archive or a
+	/// number of blocks (256 if the node ran without `--pruning`)
+	#[structopt(long = "pruning", value_name = "PRUNING_MODE")]
+	pruning: String,
+
+	/// Block to export, by number or 0x prefixed hash. Defaults to the best block
+	#[structopt(long = "block", value_name = "BLOCK")]
+	block: Option<String>,
+
+	/// Also look up the balances of this account (SS58), may be repeated
+	#[structopt(long = "account", value_name = "ACCOUNT", parse(try_from_str = "parse_account"))]
+	accounts: Vec<AccountId>,
+
+	/// Write the dump to this file instead of stdout
+	#[structopt(long = "output", short = "o", value_name = "FILE", parse(from_os_str))]
+	output: Option<PathBuf>,
+}
+
+impl ExportStateCmd {
+	fn config(&self) -> Result<FactoryFullConfiguration<Factory>, String> {
+		let spec = crate::cli::load_spec(&self.chain)?
+			.ok_or_else(|| format!("Unknown chain spec {}", self.chain))?;
+		let mut config = Configuration::default_with_spec(spec);
+
+		// same layout as the node, see `substrate_cli`
+		let path = self.base_path.join("chains").join(config.chain_spec.id()).join("db");
+		if !path.is_dir() {
+			return Err(format!("No database at {}", path.display()));
+		}
+		config.database_path = path.to_string_lossy().into();
+		config.pruning = match self.pruning.as_str() {
+			"archive" => PruningMode::ArchiveAll,
+			blocks => PruningMode::keep_blocks(blocks.parse().map_err(|_| format!("Invalid pruning mode {}", blocks))?),
+		};
+		Ok(config)
+	}
+
+	fn block(&self, client: &FullClient<Factory>) -> Result<BlockId<project_runtime::opaque::Block>, String> {
+		Ok(match self.block {
+			None => BlockId::Hash(client.info().map_err(|e| format!("{:?}", e))?.chain.best_hash),
+			Some(ref s) if s.starts_with("0x") => BlockId::Hash(s[2..].parse().map_err(|_| format!("Invalid block hash {}", s))?),
+			Some(ref s) => BlockId::Number(s.parse().map_err(|_| format!("Invalid block number {}", s))?),
+		})
+	}
+
+	/// Write the dump of the requested block.
+	pub fn run(&self) -> Result<(), String> {
+		let client = substrate_service::new_client::<Factory>(&self.config()?)
+			.map_err(|e| format!("Cannot open the database with pruning mode {}: {:?}", self.pruning, e))?;
+		let snapshot = export(&client, self.block(&client)?, &self.accounts)?;
+		let json = serde_json::to_string_pretty(&snapshot).map_err(|e| e.to_string())?;
+		match self.output {
+			Some(ref path) => fs::write(path, json).map_err(|e| format!("Error writing {}: {}", path.display(), e)),
+			None => {
+				println!("{}", json);
+				Ok(())
+			}
+		}
+	}
+}
+
+fn parse_account(s: &str) -> Result<AccountId, String> {
+	AccountId::from_ss58check(s).map_err(|e| format!("Invalid SS58 address {}: {:?}", s, e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use primitives::Pair;
+
+	use crate::testing::account;
+
+	// Alice holds 500 USDT, 100 of them reserved, Bob 1000 and may spend 500 of Alice's.
+	fn dump() -> Snapshot {
+		let (alice, bob) = (account("Alice").public(), account("Bob").public());
+		Snapshot {
+			block_number: 10,
+			block_hash: H256::repeat_byte(1),
+			admin: alice.clone(),
+			history_cap: 1024,
+			fee_per_weight: "1".into(),
+			blocks_per_year: default_blocks_per_year(),
+			insurance_fee_share: 0,
+			flash_loan_fee: 0,
+			governance_asset: None,
+			governance_params: Default::default(),
+			legacy_admin_until: Some(100),
+			proposal_deposit: 0,
+			admin_signatories: vec![alice.clone()],
+			admin_threshold: 1,
+			tokens: vec![TokenState {
+				id: 1,
+				name: "Tether USD".into(),
+				ticker: "USDT".into(),
+				decimals: 2,
+				total_supply: 1_500,
+				price: Some(10_000),
+				allowed: true,
+				risk_params: None,
+				insurance_fund: 0,
+				bad_debt: Default::default(),
+				holders: vec![
+					HolderState { account: alice.clone(), free: 400, reserved: 100 },
+					HolderState { account: bob.clone(), free: 1_000, reserved: 0 },
+				],
+			}],
+			allowances: vec![AllowanceState { token_id: 1, owner: alice, spender: bob, amount: 500 }],
+			borrow_orders: vec![],
+			supply_orders: vec![],
+			pools: vec![],
+			loan_indexes: vec![],
+			loan_terms: vec![],
+			loan_claims: vec![],
+			open_proposals: vec![],
+			pending_admin_proposals: vec![],
+		}
+	}
+
+	#[test]
+	fn dumps_survive_json_and_become_genesis_config() {
+		let (alice, bob) = (account("Alice").public(), account("Bob").public());
+		let json = serde_json::to_string(&dump()).expect("a dump serializes");
+		let dump: Snapshot = serde_json::from_str(&json).expect("a dump deserializes");
+
+		let config = dump.genesis_config(bob.clone()).expect("the dump is a valid genesis");
+		assert_eq!(config.admin, bob);
+		assert_eq!(config.tokens.len(), 1);
+		assert_eq!(config.tokens[0].4, vec![(alice.clone(), 500), (bob.clone(), 1_000)]);
+		assert_eq!((config.prices.clone(), config.allowed_assets.clone()), (vec![(1, 10_000)], vec![1]));
+		assert_eq!(config.allowances, vec![(1, alice.clone(), bob, 500)]);
+		assert_eq!((config.admin_signatories, config.admin_threshold), (vec![alice], 1));
+		assert_eq!((config.fee_per_weight, config.legacy_admin_until), (1, Some(100)));
+	}
+
+	#[test]
+	fn dumps_with_open_proposals_or_gaps_in_token_ids_are_refused() {
+		let admin = account("Alice").public();
+		assert!(Snapshot { open_proposals: vec![0], ..dump() }.genesis_config(admin.clone()).is_err());
+		assert!(Snapshot { pending_admin_proposals: vec![0], ..dump() }.genesis_config(admin.clone()).is_err());
+
+		let mut gap = dump();
+		gap.tokens[0].id = 2;
+		assert!(gap.genesis_config(admin.clone()).is_err());
+		assert!(Snapshot { fee_per_weight: "one".into(), ..dump() }.genesis_config(admin).is_err());
+	}
+}
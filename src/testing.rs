@@ -0,0 +1,301 @@
+//! In-process test network.
+//!
+//! Builds full nodes from `service::Factory` with temporary databases, connects them over local
+//! ports using the development chain spec and offers helpers to submit signed oumuamua extrinsics,
+//! wait for their inclusion and read storage and events of the resulting blocks.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use futures::Future;
+use parity_codec::{Decode, Encode};
+use primitives::{blake2_256, sr25519, twox_128, Pair, H256};
+use project_runtime::{
+	opaque, oumuamua, AccountId, Call, Event, Nonce, UncheckedExtrinsic,
+};
+use runtime_primitives::generic::BlockId;
+use substrate_service::{Configuration, FactoryFullConfiguration, Roles, ServiceFactory};
+use tempdir::TempDir;
+use tokio::runtime::Runtime;
+
+use crate::chain_spec::{Alternative, ChainSpec, DEFAULT_LEGACY_ADMIN_UNTIL};
+use crate::sealing::{SealConfig, SealMode};
+use crate::service::Factory;
+use crate::snapshot;
+use crate::tx::sign;
+
+/// First TCP port used by the test nodes. Every node of every test network gets its own port so
+/// that tests can run in parallel.
+const BASE_PORT: usize = 30500;
+
+static NEXT_PORT: AtomicUsize = AtomicUsize::new(BASE_PORT);
+
+/// How long to wait for a block or an extrinsic before failing the test.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+type FullService = <Factory as ServiceFactory>::FullService;
+
+/// The development keypair of `name`, e.g. `"Alice"`.
+pub fn account(name: &str) -> sr25519::Pair {
+	sr25519::Pair::from_string(&format!("//{}", name), None).expect("static values are valid; qed")
+}
+
+/// A full node of the test network.
+pub struct TestNode {
+	service: FullService,
+}
+
+impl TestNode {
+	/// Number and hash of the best block.
+	pub fn best(&self) -> (u64, H256) {
+		let info = self.service.client().info().expect("client info is available; qed");
+		(info.chain.best_number, info.chain.best_hash)
+	}
+
+	/// Read and decode a raw storage entry at block `at`.
+	pub fn storage<T: Decode>(&self, at: H256, key: &[u8]) -> Option<T> {
+		self.service.client()
+			.storage(&BlockId::Hash(at), &primitives::storage::StorageKey(key.to_vec()))
+			.expect("storage is readable; qed")
+			.and_then(|data| Decode::decode(&mut &data.0[..]))
+	}
+
+	/// Read a storage value such as `b"Oumuamua AllSupplyOrderCount"` at block `at`.
+	pub fn storage_value<T: Decode>(&self, at: H256, name: &[u8]) -> Option<T> {
+		self.storage(at, &twox_128(name))
+	}
+
+	/// Read a storage map entry such as `b"Oumuamua BalanceOf"` at block `at`.
+	pub fn storage_map<K: Encode, T: Decode>(&self, at: H256, name: &[u8], key: &K) -> Option<T> {
+		let mut raw = name.to_vec();
+		key.encode_to(&mut raw);
+		self.storage(at, &blake2_256(&raw))
+	}
+
+	/// All events deposited in block `at`.
+	pub fn events(&self, at: H256) -> Vec<Event> {
+		self.storage_value::<Vec<system::EventRecord<Event>>>(at, b"System Events")
+			.unwrap_or_default()
+			.into_iter()
+			.map(|record| record.event)
+			.collect()
+	}
+
+	/// The oumuamua events deposited in block `at`.
+	pub fn oumuamua_events(&self, at: H256) -> Vec<oumuamua::Event<project_runtime::Runtime>> {
+		self.events(at)
+			.into_iter()
+			.filter_map(|event| match event {
+				Event::oumuamua(event) => Some(event),
+				_ => None,
+			})
+			.collect()
+	}
+
+	/// The next nonce of `who` according to the best block.
+	pub fn nonce(&self, who: &AccountId) -> Nonce {
+		self.storage_map(self.best().1, b"System AccountNonce", who).unwrap_or_default()
+	}
+
+	/// Submit a signed extrinsic to the transaction pool of this node.
+	pub fn submit(&self, xt: UncheckedExtrinsic) -> opaque::UncheckedExtrinsic {
+		let encoded = xt.encode();
+		let xt = opaque::UncheckedExtrinsic(Decode::decode(&mut &encoded[..]).expect("an extrinsic is length prefixed; qed"));
+		let best = BlockId::Hash(self.best().1);
+		self.service.transaction_pool()
+			.submit_one(&best, xt.clone())
+			.expect("the extrinsic is accepted by the pool");
+		xt
+	}
+
+	/// Sign `call` as `signer` with its next nonce, submit it here and wait until a block of this
+	/// node contains it. Returns the hash of that block.
+	pub fn submit_and_wait(&self, signer: &sr25519::Pair, call: Call) -> H256 {
+		let genesis_hash = self.service.client().genesis_hash();
+		let from = self.best().0;
+		let xt = self.submit(sign(signer, self.nonce(&signer.public()), call, genesis_hash));
+		self.wait_for_extrinsic(from, &xt)
+	}
+
+	/// Wait until a block after `from` on the best chain contains `xt`.
+	pub fn wait_for_extrinsic(&self, from: u64, xt: &opaque::UncheckedExtrinsic) -> H256 {
+		let client = self.service.client();
+		let mut next = from + 1;
+		wait_until(|| {
+			while next <= self.best().0 {
+				let hash = client.block_hash(next).expect("block hashes are readable; qed")?;
+				let body = client.body(&BlockId::Hash(hash)).expect("block bodies are readable; qed")?;
+				if body.contains(xt) {
+					return Some(hash);
+				}
+				next += 1;
+			}
+			None
+		})
+	}
+
+	/// Wait until the best block of this node is at least `number`.
+	pub fn wait_for_block(&self, number: u64) -> H256 {
+		wait_until(|| {
+			let (best, hash) = self.best();
+			if best >= number { Some(hash) } else { None }
+		})
+	}
+}
+
+/// One or more full nodes of the development chain, running in this process.
+///
+/// The chain is sealed instantly: node 0 (Alice) authors a block as soon as a transaction enters
+/// its pool, the others sync from it.
+pub struct TestNet {
+	nodes: Vec<TestNode>,
+	runtime: Option<Runtime>,
+	_dir: TempDir,
+}
+
+impl TestNet {
+	/// Start `count` connected nodes.
+	pub fn new(count: usize) -> Self {
+		let spec = Alternative::Development.load().expect("the development spec is valid; qed");
+		let dir = TempDir::new("oumuamua-testnet").expect("a temporary directory can be created");
+		let runtime = Runtime::new().expect("a tokio runtime can be created");
+
+		let mut nodes: Vec<TestNode> = Vec::with_capacity(count);
+		for index in 0..count {
+			let mut config = node_config(index, &spec, &dir);
+			if let Some(first) = nodes.first() {
+				let boot_node = first.service.network().node_id().expect("the first node is listening");
+				config.network.boot_nodes.push(boot_node);
+			}
+			let service = Factory::new_full(config, runtime.executor()).expect("the service starts");
+			nodes.push(TestNode { service });
+		}
+
+		TestNet { nodes, runtime: Some(runtime), _dir: dir }
+	}
+
+	/// The node with the given index.
+	pub fn node(&self, index: usize) -> &TestNode {
+		&self.nodes[index]
+	}
+
+	/// Wait until every node has imported block `number`.
+	pub fn wait_for_block(&self, number: u64) {
+		for node in &self.nodes {
+			node.wait_for_block(number);
+		}
+	}
+}
+
+impl Drop for TestNet {
+	fn drop(&mut self) {
+		// the services have to go before the runtime that drives them
+		self.nodes.clear();
+		if let Some(runtime) = self.runtime.take() {
+			let _ = runtime.shutdown_now().wait();
+		}
+	}
+}
+
+fn node_config(index: usize, spec: &ChainSpec, dir: &TempDir) -> FactoryFullConfiguration<Factory> {
+	let root = dir.path().join(format!("node-{}", index));
+	let mut config = Configuration::default_with_spec(spec.clone());
+
+	config.name = format!("Node {}", index);
+	config.database_path = root.join("db").to_string_lossy().into();
+	config.keystore_path = root.join("keys").to_string_lossy().into();
+	config.network.config_path = Some(root.join("network").to_string_lossy().into());
+	config.network.net_config_path = config.network.config_path.clone();
+	config.network.listen_addresses = vec![
+		format!("/ip4/127.0.0.1/tcp/{}", NEXT_PORT.fetch_add(1, Ordering::SeqCst)).parse().expect("a valid multiaddr; qed"),
+	];
+	config.network.boot_nodes = vec![];
+	config.custom.sealing = Some(SealConfig { mode: SealMode::Instant, rpc_port: None });
+
+	if index == 0 {
+		config.roles = Roles::AUTHORITY;
+		config.keys = vec!["//Alice".into()];
+	} else {
+		config.roles = Roles::FULL;
+	}
+
+	config
+}
+
+fn wait_until<T, F: FnMut() -> Option<T>>(mut f: F) -> T {
+	let start = Instant::now();
+	loop {
+		if let Some(result) = f() {
+			return result;
+		}
+		assert!(start.elapsed() < TIMEOUT, "timed out waiting for the test network");
+		thread::sleep(Duration::from_millis(100));
+	}
+}
+
+#[test]
+fn single_node_includes_oumuamua_transfer() {
+	let net = TestNet::new(1);
+	let node = net.node(0);
+	let (alice, bob) = (account("Alice"), account("Bob"));
+
+	let call = Call::OumuamuaModule(oumuamua::Call::transfer(1, bob.public(), 1_000));
+	let block = node.submit_and_wait(&alice, call);
+
+	assert!(node.oumuamua_events(block).contains(
+		&oumuamua::RawEvent::Transfer(1, alice.public(), bob.public(), 1_000)
+	));
+	assert_eq!(node.storage_map::<_, u64>(block, b"Oumuamua BalanceOf", &(1u32, bob.public())), Some(1_000));
+	assert_eq!(node.nonce(&alice.public()), 1);
+}
+
+#[test]
+fn nodes_sync_oumuamua_orders() {
+	let net = TestNet::new(2);
+	let alice = account("Alice");
+
+	// submitted to the non-authority node and gossiped to the authority
+	let call = Call::OumuamuaModule(oumuamua::Call::create_Supply(1_000, 1, vec![2], 5_000, 30, 300));
+	let block = net.node(1).submit_and_wait(&alice, call);
+	let (number, _) = net.node(1).best();
+	net.wait_for_block(number);
+
+	for index in 0..2 {
+		let node = net.node(index);
+		assert_eq!(node.storage_value::<u64>(block, b"Oumuamua AllSupplyOrderCount"), Some(1));
+		assert!(node.oumuamua_events(block).iter().any(|event| match event {
+			oumuamua::RawEvent::CreateSupply(who, ..) => *who == alice.public(),
+			_ => false,
+		}));
+	}
+}
+
+#[test]
+fn exported_state_becomes_genesis_config() {
+	let net = TestNet::new(1);
+	let node = net.node(0);
+	let (alice, bob) = (account("Alice"), account("Bob"));
+
+	let call = Call::OumuamuaModule(oumuamua::Call::approve(2, bob.public(), 500));
+	node.submit_and_wait(&alice, call);
+	let call = Call::OumuamuaModule(oumuamua::Call::transfer(1, bob.public(), 1_000));
+	let block = node.submit_and_wait(&alice, call);
+
+	let dump = snapshot::export(&node.service.client(), BlockId::Hash(block), &[]).expect("the state is exported");
+	assert_eq!(dump.block_hash, block);
+	assert_eq!(dump.tokens.len(), 3);
+	let bob_usdt = dump.tokens[0].holders.iter().find(|h| h.account == bob.public()).expect("Bob holds USDT");
+	assert_eq!((bob_usdt.free, bob_usdt.reserved), (1_000, 0));
+	assert_eq!(dump.allowances.len(), 1);
+	assert_eq!((dump.allowances[0].token_id, dump.allowances[0].amount), (2, 500));
+
+	// the genesis config keeps every balance and the parameters of the dev chain
+	let config = dump.genesis_config(alice.public()).expect("the dump is a valid genesis");
+	assert_eq!(config.tokens[0].3, config.tokens[0].4.iter().map(|(_, amount)| amount).sum::<u64>());
+	assert!(config.tokens[0].4.contains(&(bob.public(), 1_000)));
+	assert_eq!(config.allowances, vec![(2, alice.public(), bob.public(), 500)]);
+	assert_eq!(config.risk_params.len(), 3);
+	assert_eq!((config.admin_signatories.clone(), config.admin_threshold), (vec![alice.public()], 1));
+	assert_eq!(config.legacy_admin_until, Some(DEFAULT_LEGACY_ADMIN_UNTIL));
+}
@@ -0,0 +1,683 @@
+//! The `tx` command: build and sign oumuamua extrinsics offline.
+//!
+//! The signed extrinsic is printed as hex so that it can be submitted by any client; with
+//! `--submit` it is also sent to a node through `author_submitExtrinsic`.
+
+use std::fs;
+use std::path::PathBuf;
+
+use futures::{Future, Stream};
+use parity_codec::{Compact, Decode, Encode};
+use primitives::{blake2_256, crypto::Ss58Codec, hexdisplay::HexDisplay, sr25519, Pair, H256};
+use project_runtime::{oumuamua, AccountId, Call, Nonce, UncheckedExtrinsic};
+use runtime_primitives::generic::Era;
+use serde_json::{json, Value};
+use structopt::StructOpt;
+use tokio::runtime::Runtime;
+
+/// Sign `call` as `signer` with the given nonce, immortal era.
+pub fn sign(signer: &sr25519::Pair, nonce: Nonce, call: Call, genesis_hash: H256) -> UncheckedExtrinsic {
+	let era = Era::immortal();
+	let payload = (Compact(nonce), call, era, genesis_hash);
+	// long payloads are signed by their hash, as checked by the runtime
+	let signature = payload.using_encoded(|b| {
+		if b.len() > 256 {
+			signer.sign(&blake2_256(b))
+		} else {
+			signer.sign(b)
+		}
+	});
+	let (_, call, era, _) = payload;
+	UncheckedExtrinsic::new_signed(nonce, call, signer.public().into(), signature.into(), era)
+}
+
+/// The `tx` command.
+#[derive(Debug, StructOpt, Clone)]
+pub struct TxCmd {
+	/// Secret URI of the signer: a mnemonic, a 0x prefixed seed or a derivation such as //Alice
+	#[structopt(long = "seed", value_name = "URI")]
+	seed: Option<String>,
+
+	/// File containing the secret URI of the signer
+	#[structopt(long = "key-file", value_name = "FILE", parse(from_os_str))]
+	key_file: Option<PathBuf>,
+
+	/// Nonce of the signer
+	#[structopt(long = "nonce")]
+	nonce: Nonce,
+
+	/// Genesis hash of the chain, hex encoded
+	#[structopt(long = "genesis-hash", value_name = "HASH", parse(try_from_str = "parse_hash"))]
+	genesis_hash: H256,
+
+	/// Submit the extrinsic to the node RPC at this URL, e.g. http://127.0.0.1:9933
+	#[structopt(long = "submit", value_name = "URL")]
+	submit: Option<String>,
+
+	#[structopt(subcommand)]
+	call: CallCmd,
+}
+
+/// The oumuamua calls, one subcommand each. Accounts are SS58 addresses, order ids hex hashes.
+#[derive(Debug, StructOpt, Clone)]
+pub enum CallCmd {
+	/// Create a token owned by the given account
+	#[structopt(name = "init")]
+	Init {
+		#[structopt(parse(try_from_str = "parse_account"))]
+		owner: AccountId,
+		name: String,
+		ticker: String,
+		decimals: u8,
+		total_supply: u64,
+	},
+	/// Mint tokens to an account
+	#[structopt(name = "issue")]
+	Issue {
+		token_id: u32,
+		#[structopt(parse(try_from_str = "parse_account"))]
+		to: AccountId,
+		amount: u64,
+	},
+	/// Burn tokens of an account
+	#[structopt(name = "destroy")]
+	Destroy {
+		token_id: u32,
+		#[structopt(parse(try_from_str = "parse_account"))]
+		from: AccountId,
+		amount: u64,
+	},
+	/// Set the price of a token
+	#[structopt(name = "set-price")]
+	SetPrice { token_id: u32, price: u64 },
+	/// Set the risk parameters of a token
+	#[structopt(name = "set-risk-params")]
+	SetRiskParams {
+		token_id: u32,
+		/// Max loan-to-value as collateral, in 1/10000
+		max_ltv: u32,
+		/// Liquidation threshold, in 1/10000
+		liquidation_threshold: u32,
+		/// Liquidation bonus, in 1/10000
+		liquidation_bonus: u32,
+		/// Borrow cap, 0 for none
+		borrow_cap: u64,
+		/// Supply cap, 0 for none
+		supply_cap: u64,
+	},
+	/// Set how many history entries are kept per account
+	#[structopt(name = "set-history-cap")]
+	SetHistoryCap { cap: u64 },
+	/// Allow or forbid a token in orders
+	#[structopt(name = "set-allow-assets")]
+	SetAllowAssets {
+		token_id: u32,
+		#[structopt(parse(try_from_str))]
+		allowed: bool,
+	},
+	/// Transfer tokens
+	#[structopt(name = "transfer")]
+	Transfer {
+		token_id: u32,
+		#[structopt(parse(try_from_str = "parse_account"))]
+		to: AccountId,
+		amount: u64,
+	},
+	/// Allow a spender to transfer tokens of the signer
+	#[structopt(name = "approve")]
+	Approve {
+		token_id: u32,
+		#[structopt(parse(try_from_str = "parse_account"))]
+		spender: AccountId,
+		amount: u64,
+	},
+	/// Transfer approved tokens
+	#[structopt(name = "transfer-from")]
+	TransferFrom {
+		token_id: u32,
+		#[structopt(parse(try_from_str = "parse_account"))]
+		from: AccountId,
+		#[structopt(parse(try_from_str = "parse_account"))]
+		to: AccountId,
+		amount: u64,
+	},
+	/// Ask for a loan against collateral
+	#[structopt(name = "create-borrow")]
+	CreateBorrow {
+		/// Amount to borrow
+		btotal: u64,
+		/// Token to borrow
+		btoken_id: u32,
+		/// Duration in blocks
+		duration: u64,
+		/// Collateral amount
+		stotal: u64,
+		/// Collateral token
+		stoken_id: u32,
+		/// Interest in 1/10000
+		interest: u32,
+	},
+	/// Cancel an open borrow order
+	#[structopt(name = "cancel-borrow")]
+	CancelBorrow {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+	},
+	/// Lend to a borrow order
+	#[structopt(name = "take-borrow")]
+	TakeBorrow {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+	},
+	/// Offer a loan
+	#[structopt(name = "create-supply")]
+	CreateSupply {
+		/// Amount to lend
+		stotal: u64,
+		/// Token to lend
+		stoken_id: u32,
+		/// Accepted collateral tokens, comma separated
+		#[structopt(parse(try_from_str = "parse_tokens"))]
+		btoken_ids: TokenList,
+		/// Amortgage in 1/10000
+		amortgage: u32,
+		/// Duration in blocks
+		duration: u64,
+		/// Interest in 1/10000
+		interest: u32,
+	},
+	/// Cancel an open supply order
+	#[structopt(name = "cancel-supply")]
+	CancelSupply {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+	},
+	/// Borrow from a supply order
+	#[structopt(name = "take-supply")]
+	TakeSupply {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+		/// Collateral token
+		btoken_id: u32,
+	},
+	/// Repay a loan with the interest accrued so far, as its borrower
+	#[structopt(name = "repay")]
+	Repay {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+	},
+	/// Liquidate a loan below the liquidation threshold
+	#[structopt(name = "liquidate")]
+	Liquidate {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+	},
+	/// Open a lending pool for a token, with its rates in 1/10000 a year
+	#[structopt(name = "create-pool")]
+	CreatePool {
+		token_id: u32,
+		base_rate: u32,
+		/// Rate added up to the kink
+		slope1: u32,
+		/// Optimal utilization, in 1/10000
+		kink: u32,
+		/// Rate added from the kink to full utilization
+		slope2: u32,
+	},
+	/// Change the rates of a lending pool
+	#[structopt(name = "set-rate-model")]
+	SetRateModel { token_id: u32, base_rate: u32, slope1: u32, kink: u32, slope2: u32 },
+	/// Deposit into a lending pool for share tokens
+	#[structopt(name = "deposit")]
+	Deposit { token_id: u32, amount: u64 },
+	/// Redeem share tokens of a lending pool
+	#[structopt(name = "withdraw")]
+	Withdraw { token_id: u32, shares: u64 },
+	/// Borrow from a lending pool, adding collateral
+	#[structopt(name = "pool-borrow")]
+	PoolBorrow { token_id: u32, amount: u64, collateral_id: u32, collateral: u64 },
+	/// Repay a loan from a lending pool
+	#[structopt(name = "pool-repay")]
+	PoolRepay { token_id: u32, amount: u64 },
+	/// Liquidate a loan from a lending pool below the liquidation threshold
+	#[structopt(name = "liquidate-pool-loan")]
+	LiquidatePoolLoan {
+		token_id: u32,
+		#[structopt(parse(try_from_str = "parse_account"))]
+		borrower: AccountId,
+	},
+	/// Set the fee, in 1/10000 of the principal, for repaying before maturity, as owner of an open order
+	#[structopt(name = "set-prepayment-fee")]
+	SetPrepaymentFee {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+		fee: u32,
+	},
+	/// Ask the lender to extend a loan by some blocks at a new rate in 1/10000 a year
+	#[structopt(name = "request-extension")]
+	RequestExtension {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+		blocks: u64,
+		interest: u32,
+	},
+	/// Accept the pending extension of a loan, as its lender
+	#[structopt(name = "accept-extension")]
+	AcceptExtension {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+	},
+	/// Opt in or out of rolling a loan over at maturity
+	#[structopt(name = "set-rollover")]
+	SetRollover {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+		#[structopt(parse(try_from_str))]
+		opt_in: bool,
+	},
+	/// Roll a matured loan over for another duration, once both parties opted in
+	#[structopt(name = "roll-over")]
+	RollOver {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+	},
+	/// Add collateral to a loan, as its borrower
+	#[structopt(name = "add-collateral")]
+	AddCollateral {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+		amount: u64,
+	},
+	/// Take back collateral the loan does not need at current prices, as its borrower
+	#[structopt(name = "withdraw-collateral")]
+	WithdrawCollateral {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+		amount: u64,
+	},
+	/// Replace all the collateral of a loan with another token, as its borrower
+	#[structopt(name = "substitute-collateral")]
+	SubstituteCollateral {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+		collateral_id: u32,
+		collateral: u64,
+	},
+	/// Give the claim on a loan, and its repayments, to another account
+	#[structopt(name = "transfer-loan-claim")]
+	TransferLoanClaim {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+		#[structopt(parse(try_from_str = "parse_account"))]
+		to: AccountId,
+	},
+	/// Offer the claim on a loan for sale, priced in any allowed token
+	#[structopt(name = "list-loan-claim")]
+	ListLoanClaim {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+		price_asset: u32,
+		price: u64,
+	},
+	/// Withdraw the claim on a loan from sale
+	#[structopt(name = "unlist-loan-claim")]
+	UnlistLoanClaim {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+	},
+	/// Buy a listed loan claim; fails if the listing no longer has this price
+	#[structopt(name = "buy-loan-claim")]
+	BuyLoanClaim {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+		price_asset: u32,
+		price: u64,
+	},
+	/// Take the collateral of a matured loan that was not repaid, as its claim holder
+	#[structopt(name = "claim-default")]
+	ClaimDefault {
+		#[structopt(parse(try_from_str = "parse_hash"))]
+		order_id: H256,
+	},
+	/// Set the share of loan interest, in 1/10000, paid into the insurance fund
+	#[structopt(name = "set-insurance-fee-share")]
+	SetInsuranceFeeShare { share: u32 },
+	/// Donate tokens to the insurance fund of the token
+	#[structopt(name = "deposit-insurance")]
+	DepositInsurance { token_id: u32, amount: u64 },
+	/// Set the flash loan fee, in 1/10000 of the borrowed amount
+	#[structopt(name = "set-flash-loan-fee")]
+	SetFlashLoanFee { fee: u32 },
+	/// Borrow from the pool of the token, dispatch the calls and pay the amount plus the fee back
+	#[structopt(name = "flash-loan")]
+	FlashLoan {
+		token_id: u32,
+		amount: u64,
+		/// Hex SCALE encoded oumuamua calls, dispatched in order as the signer; only transfers and
+		/// pool deposits, withdrawals, repayments and liquidations are allowed
+		#[structopt(parse(try_from_str = "parse_oumuamua_call"))]
+		calls: Vec<oumuamua::Call<project_runtime::Runtime>>,
+	},
+	/// Set the governance asset and the parameters of new proposals, in blocks and 1/10000
+	#[structopt(name = "set-governance")]
+	SetGovernance {
+		/// Token whose holders vote; without it no new proposals are accepted
+		#[structopt(long = "asset")]
+		asset: Option<u32>,
+		voting_period: u64,
+		enactment_delay: u64,
+		quorum: u32,
+		threshold: u32,
+	},
+	/// Set the block from which the admin account can only act through governance
+	#[structopt(name = "set-legacy-admin-until")]
+	SetLegacyAdminUntil {
+		/// Without it the admin account keeps its rights
+		#[structopt(long = "until")]
+		until: Option<u64>,
+	},
+	/// Set the governance tokens a proposer locks until the proposal closes
+	#[structopt(name = "set-proposal-deposit")]
+	SetProposalDeposit { deposit: u64 },
+	/// Propose a privileged oumuamua call, executed as governance if the vote passes
+	#[structopt(name = "propose")]
+	Propose {
+		/// Hex SCALE encoded oumuamua call
+		#[structopt(parse(try_from_str = "parse_oumuamua_call"))]
+		call: oumuamua::Call<project_runtime::Runtime>,
+	},
+	/// Vote on a proposal, locking the balance of the governance asset until the vote ends
+	#[structopt(name = "vote")]
+	Vote {
+		proposal_id: u32,
+		#[structopt(parse(try_from_str))]
+		aye: bool,
+		balance: u64,
+	},
+	/// Unlock the balance voted on a proposal after its vote ended
+	#[structopt(name = "unlock-vote")]
+	UnlockVote { proposal_id: u32 },
+	/// Close a proposal after its vote, executing it once the enactment delay has passed
+	#[structopt(name = "enact-proposal")]
+	EnactProposal { proposal_id: u32 },
+	/// Replace the admin multisig, cancelling its pending proposals. Only works inside
+	/// `propose-admin-call` or `propose`
+	#[structopt(name = "set-admin-signatories")]
+	SetAdminSignatories {
+		threshold: u32,
+		#[structopt(parse(try_from_str = "parse_account"))]
+		signatories: Vec<AccountId>,
+	},
+	/// Propose a privileged oumuamua call to the admin multisig, approving it as the proposer
+	#[structopt(name = "propose-admin-call")]
+	ProposeAdminCall {
+		/// Hex SCALE encoded oumuamua call
+		#[structopt(parse(try_from_str = "parse_oumuamua_call"))]
+		call: oumuamua::Call<project_runtime::Runtime>,
+	},
+	/// Approve a pending admin multisig proposal, executing it once the threshold is reached
+	#[structopt(name = "approve-admin-call")]
+	ApproveAdminCall { proposal_id: u32 },
+	/// Cancel a pending admin multisig proposal, as its proposer
+	#[structopt(name = "cancel-admin-call")]
+	CancelAdminCall { proposal_id: u32 },
+}
+
+/// Comma separated token ids.
+#[derive(Debug, Clone)]
+pub struct TokenList(Vec<u32>);
+
+impl CallCmd {
+	fn call(&self) -> oumuamua::Call<project_runtime::Runtime> {
+		use oumuamua::Call as C;
+		match self.clone() {
+			CallCmd::Init { owner, name, ticker, decimals, total_supply } =>
+				C::init(owner, name.into_bytes(), ticker.into_bytes(), decimals, total_supply),
+			CallCmd::Issue { token_id, to, amount } => C::issue(token_id, to, amount),
+			CallCmd::Destroy { token_id, from, amount } => C::destroy(token_id, from, amount),
+			CallCmd::SetPrice { token_id, price } => C::set_price(token_id, price),
+			CallCmd::SetRiskParams { token_id, max_ltv, liquidation_threshold, liquidation_bonus, borrow_cap, supply_cap } =>
+				C::set_risk_params(token_id, oumuamua::RiskParams { max_ltv, liquidation_threshold, liquidation_bonus, borrow_cap, supply_cap }),
+			CallCmd::SetHistoryCap { cap } => C::set_history_cap(cap),
+			CallCmd::SetAllowAssets { token_id, allowed } => C::set_allow_assets(token_id, allowed),
+			CallCmd::Transfer { token_id, to, amount } => C::transfer(token_id, to, amount),
+			CallCmd::Approve { token_id, spender, amount } => C::approve(token_id, spender, amount),
+			CallCmd::TransferFrom { token_id, from, to, amount } => C::transfer_from(token_id, from, to, amount),
+			CallCmd::CreateBorrow { btotal, btoken_id, duration, stotal, stoken_id, interest } =>
+				C::create_borrow(btotal, btoken_id, duration, stotal, stoken_id, interest),
+			CallCmd::CancelBorrow { order_id } => C::cancel_borrow(order_id),
+			CallCmd::TakeBorrow { order_id } => C::take_borrow(order_id),
+			CallCmd::CreateSupply { stotal, stoken_id, btoken_ids, amortgage, duration, interest } =>
+				C::create_Supply(stotal, stoken_id, btoken_ids.0, amortgage, duration, interest),
+			CallCmd::CancelSupply { order_id } => C::cance_supply(order_id),
+			CallCmd::TakeSupply { order_id, btoken_id } => C::take_supply(order_id, btoken_id),
+			CallCmd::Repay { order_id } => C::repay(order_id),
+			CallCmd::Liquidate { order_id } => C::liquidate(order_id),
+			CallCmd::CreatePool { token_id, base_rate, slope1, kink, slope2 } =>
+				C::create_pool(token_id, oumuamua::RateModel { base_rate, slope1, kink, slope2 }),
+			CallCmd::SetRateModel { token_id, base_rate, slope1, kink, slope2 } =>
+				C::set_rate_model(token_id, oumuamua::RateModel { base_rate, slope1, kink, slope2 }),
+			CallCmd::Deposit { token_id, amount } => C::deposit(token_id, amount),
+			CallCmd::Withdraw { token_id, shares } => C::withdraw(token_id, shares),
+			CallCmd::PoolBorrow { token_id, amount, collateral_id, collateral } =>
+				C::pool_borrow(token_id, amount, collateral_id, collateral),
+			CallCmd::PoolRepay { token_id, amount } => C::pool_repay(token_id, amount),
+			CallCmd::LiquidatePoolLoan { token_id, borrower } => C::liquidate_pool_loan(token_id, borrower),
+			CallCmd::SetPrepaymentFee { order_id, fee } => C::set_prepayment_fee(order_id, fee),
+			CallCmd::RequestExtension { order_id, blocks, interest } => C::request_extension(order_id, blocks, interest),
+			CallCmd::AcceptExtension { order_id } => C::accept_extension(order_id),
+			CallCmd::SetRollover { order_id, opt_in } => C::set_rollover(order_id, opt_in),
+			CallCmd::RollOver { order_id } => C::roll_over(order_id),
+			CallCmd::AddCollateral { order_id, amount } => C::add_collateral(order_id, amount),
+			CallCmd::WithdrawCollateral { order_id, amount } => C::withdraw_collateral(order_id, amount),
+			CallCmd::SubstituteCollateral { order_id, collateral_id, collateral } =>
+				C::substitute_collateral(order_id, collateral_id, collateral),
+			CallCmd::TransferLoanClaim { order_id, to } => C::transfer_loan_claim(order_id, to),
+			CallCmd::ListLoanClaim { order_id, price_asset, price } => C::list_loan_claim(order_id, price_asset, price),
+			CallCmd::UnlistLoanClaim { order_id } => C::unlist_loan_claim(order_id),
+			CallCmd::BuyLoanClaim { order_id, price_asset, price } => C::buy_loan_claim(order_id, price_asset, price),
+			CallCmd::ClaimDefault { order_id } => C::claim_default(order_id),
+			CallCmd::SetInsuranceFeeShare { share } => C::set_insurance_fee_share(share),
+			CallCmd::DepositInsurance { token_id, amount } => C::deposit_insurance(token_id, amount),
+			CallCmd::SetFlashLoanFee { fee } => C::set_flash_loan_fee(fee),
+			CallCmd::FlashLoan { token_id, amount, calls } => C::flash_loan(token_id, amount, calls),
+			CallCmd::SetGovernance { asset, voting_period, enactment_delay, quorum, threshold } =>
+				C::set_governance(asset, oumuamua::GovernanceParams { voting_period, enactment_delay, quorum, threshold }),
+			CallCmd::SetLegacyAdminUntil { until } => C::set_legacy_admin_until(until),
+			CallCmd::SetProposalDeposit { deposit } => C::set_proposal_deposit(deposit),
+			CallCmd::Propose { call } => C::propose(Box::new(call)),
+			CallCmd::Vote { proposal_id, aye, balance } => C::vote(proposal_id, aye, balance),
+			CallCmd::UnlockVote { proposal_id } => C::unlock_vote(proposal_id),
+			CallCmd::EnactProposal { proposal_id } => C::enact_proposal(proposal_id),
+			CallCmd::SetAdminSignatories { threshold, signatories } => C::set_admin_signatories(signatories, threshold),
+			CallCmd::ProposeAdminCall { call } => C::propose_admin_call(Box::new(call)),
+			CallCmd::ApproveAdminCall { proposal_id } => C::approve_admin_call(proposal_id),
+			CallCmd::CancelAdminCall { proposal_id } => C::cancel_admin_call(proposal_id),
+		}
+	}
+}
+
+impl TxCmd {
+	fn signer(&self) -> Result<sr25519::Pair, String> {
+		let uri = match (&self.seed, &self.key_file) {
+			(Some(seed), None) => seed.clone(),
+			(None, Some(path)) => fs::read_to_string(path)
+				.map_err(|e| format!("Error reading {}: {}", path.display(), e))?
+				.trim()
+				.to_string(),
+			_ => return Err("Exactly one of --seed and --key-file is needed".into()),
+		};
+		sr25519::Pair::from_string(&uri, None).map_err(|e| format!("Invalid secret URI: {:?}", e))
+	}
+
+	/// The signed extrinsic of the command.
+	pub fn extrinsic(&self) -> Result<UncheckedExtrinsic, String> {
+		let call = Call::OumuamuaModule(self.call.call());
+		Ok(sign(&self.signer()?, self.nonce, call, self.genesis_hash))
+	}
+
+	/// Print the signed extrinsic and submit it if asked to.
+	pub fn run(&self) -> Result<(), String> {
+		let hex = format!("0x{}", HexDisplay::from(&self.extrinsic()?.encode()));
+		println!("{}", hex);
+
+		if let Some(ref url) = self.submit {
+			let hash = submit(url, &hex)?;
+			println!("Submitted, extrinsic hash {}", hash);
+		}
+		Ok(())
+	}
+}
+
+fn submit(url: &str, hex: &str) -> Result<Value, String> {
+	let body = json!({
+		"jsonrpc": "2.0",
+		"id": 1,
+		"method": "author_submitExtrinsic",
+		"params": [hex],
+	});
+	let request = hyper::Request::post(url)
+		.header("Content-Type", "application/json")
+		.body(hyper::Body::from(body.to_string()))
+		.map_err(|e| format!("Invalid node URL {}: {}", url, e))?;
+
+	let response = hyper::Client::new()
+		.request(request)
+		.and_then(|response| response.into_body().concat2())
+		.map_err(|e| format!("Error talking to the node: {}", e));
+	let bytes = Runtime::new().map_err(|e| format!("{:?}", e))?.block_on(response)?;
+
+	let mut response: Value = serde_json::from_slice(&bytes).map_err(|e| format!("Invalid node response: {}", e))?;
+	match response.get("error") {
+		Some(error) => Err(format!("The node rejected the extrinsic: {}", error)),
+		None => Ok(response["result"].take()),
+	}
+}
+
+fn parse_account(s: &str) -> Result<AccountId, String> {
+	AccountId::from_ss58check(s).map_err(|e| format!("Invalid SS58 address {}: {:?}", s, e))
+}
+
+fn parse_hash(s: &str) -> Result<H256, String> {
+	s.trim_start_matches("0x").parse().map_err(|_| format!("Invalid hash {}", s))
+}
+
+fn decode_hex<T: Decode>(s: &str) -> Result<T, String> {
+	let hex = s.trim_start_matches("0x");
+	let bytes = (0..hex.len())
+		.step_by(2)
+		.map(|i| hex.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+		.collect::<Option<Vec<u8>>>()
+		.ok_or_else(|| format!("Invalid hex {}", s))?;
+	T::decode(&mut &bytes[..]).ok_or_else(|| format!("Invalid encoded call {}", s))
+}
+
+fn parse_oumuamua_call(s: &str) -> Result<oumuamua::Call<project_runtime::Runtime>, String> {
+	decode_hex(s)
+}
+
+fn parse_tokens(s: &str) -> Result<TokenList, String> {
+	s.split(',')
+		.map(|t| t.trim().parse().map_err(|_| format!("Invalid token id {}", t)))
+		.collect::<Result<_, _>>()
+		.map(TokenList)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use runtime_primitives::traits::Verify;
+
+	use crate::testing::account;
+
+	const TX_GENESIS: [u8; 32] = [7; 32];
+
+	/// Sign a `tx` command line as Alice with nonce 5 and decode the extrinsic from its encoding.
+	fn signed_tx(args: &[&str]) -> UncheckedExtrinsic {
+		let genesis = format!("0x{}", HexDisplay::from(&TX_GENESIS.to_vec()));
+		let cmd = TxCmd::from_iter_safe(
+			["tx", "--seed", "//Alice", "--nonce", "5", "--genesis-hash", genesis.as_str()].iter().chain(args)
+		).expect("the command line parses");
+		let xt = cmd.extrinsic().expect("the extrinsic is signed");
+		UncheckedExtrinsic::decode(&mut &xt.encode()[..]).expect("the extrinsic decodes")
+	}
+
+	/// Check that `args` sign `call` as Alice, with a signature the runtime accepts.
+	fn assert_tx(args: &[&str], call: oumuamua::Call<project_runtime::Runtime>) {
+		let alice = account("Alice");
+		let xt = signed_tx(args);
+		let call = Call::OumuamuaModule(call);
+		assert_eq!(xt.function, call);
+
+		let (_, signature, _, _) = xt.signature.clone().expect("the extrinsic is signed");
+		assert_eq!(xt, UncheckedExtrinsic::new_signed(5, call.clone(), alice.public().into(), signature.clone(), Era::immortal()));
+		let payload = (Compact(5 as Nonce), call, Era::immortal(), H256::from(TX_GENESIS)).encode();
+		let signed = if payload.len() > 256 { blake2_256(&payload).to_vec() } else { payload };
+		assert!(signature.verify(&signed[..], &alice.public()));
+	}
+
+	#[test]
+	fn tx_commands_encode_runtime_calls() {
+		let bob = account("Bob").public();
+		let order = H256::repeat_byte(1);
+		let order_hex = format!("0x{}", HexDisplay::from(&order.0.to_vec()));
+		let bob_ss58 = bob.to_ss58check();
+
+		assert_tx(&["transfer", "1", &bob_ss58, "400"], oumuamua::Call::transfer(1, bob.clone(), 400));
+		assert_tx(
+			&["create-supply", "1000", "1", "2,3", "5000", "30", "300"],
+			oumuamua::Call::create_Supply(1000, 1, vec![2, 3], 5000, 30, 300),
+		);
+		assert_tx(&["cancel-supply", &order_hex], oumuamua::Call::cance_supply(order));
+		assert_tx(&["issue", "2", &bob_ss58, "500"], oumuamua::Call::issue(2, bob.clone(), 500));
+		assert_tx(&["destroy", "2", &bob_ss58, "300"], oumuamua::Call::destroy(2, bob.clone(), 300));
+		assert_tx(&["set-proposal-deposit", "50"], oumuamua::Call::set_proposal_deposit(50));
+		assert_tx(
+			&["set-risk-params", "2", "6000", "8000", "500", "0", "100"],
+			oumuamua::Call::set_risk_params(2, oumuamua::RiskParams {
+				max_ltv: 6000,
+				liquidation_threshold: 8000,
+				liquidation_bonus: 500,
+				borrow_cap: 0,
+				supply_cap: 100,
+			}),
+		);
+		assert_tx(&["set-legacy-admin-until"], oumuamua::Call::set_legacy_admin_until(None));
+		assert_tx(&["set-legacy-admin-until", "--until", "100"], oumuamua::Call::set_legacy_admin_until(Some(100)));
+		assert_tx(
+			&["set-admin-signatories", "1", &bob_ss58],
+			oumuamua::Call::set_admin_signatories(vec![bob], 1),
+		);
+	}
+
+	#[test]
+	fn tx_commands_take_hex_encoded_calls() {
+		let bob = account("Bob").public();
+		let inner = oumuamua::Call::transfer(1, bob, 400);
+		let hex = format!("0x{}", HexDisplay::from(&inner.encode()));
+		assert_tx(&["flash-loan", "1", "1000", &hex], oumuamua::Call::flash_loan(1, 1000, vec![inner.clone()]));
+		assert_tx(&["propose", &hex], oumuamua::Call::propose(Box::new(inner.clone())));
+		assert_tx(&["propose-admin-call", &hex], oumuamua::Call::propose_admin_call(Box::new(inner)));
+	}
+
+	#[test]
+	fn tx_signs_long_payloads_by_hash() {
+		let bob = account("Bob").public();
+		let name = "n".repeat(300);
+		assert_tx(
+			&["init", &bob.to_ss58check(), &name, "LONG", "8", "1000"],
+			oumuamua::Call::init(bob, name.clone().into_bytes(), b"LONG".to_vec(), 8, 1000),
+		);
+	}
+
+	#[test]
+	fn tx_rejects_bad_arguments() {
+		let genesis = format!("0x{}", HexDisplay::from(&TX_GENESIS.to_vec()));
+		let parse = |args: &[&str]| TxCmd::from_iter_safe(
+			["tx", "--seed", "//Alice", "--nonce", "5", "--genesis-hash", genesis.as_str()].iter().chain(args)
+		);
+		assert!(parse(&["transfer", "1", "not-an-address", "400"]).is_err());
+		assert!(parse(&["create-supply", "1000", "1", "2,x", "5000", "30", "300"]).is_err());
+		assert!(parse(&["propose", "0xzz"]).is_err());
+	}
+}